@@ -0,0 +1,295 @@
+use std::path::PathBuf;
+
+use chrono::Duration;
+use rocket::fairing::AdHoc;
+use rocket::figment::Figment;
+use serde::Deserialize;
+use tracing::error;
+
+use crate::methods::ShippingCarrier;
+use crate::tokens::Revocations;
+
+/// Application configuration, read from `Rocket.toml` (or the matching
+/// `ROCKET_*` environment variables) and validated when the server
+/// ignites, i.e.
+///
+/// ```toml
+/// [global]
+/// demo = false
+///
+/// [global.cors]
+/// allowed_origins = ["https://pos.example.com"]
+///
+/// [global.sessions]
+/// access_ttl_minutes = 10
+/// refresh_ttl_days = 7
+/// ```
+///
+/// The values are managed state and can be requested as `&State<AppConfig>`.
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+pub struct AppConfig {
+    pub cors: CorsConfig,
+    pub sessions: SessionConfig,
+    pub gc: GcConfig,
+    pub ingress: IngressConfig,
+    pub shipping: ShippingConfig,
+    /// Enables the unauthenticated `/helpers/generate` route.
+    pub demo: bool,
+    /// The tenant operating the deployment, whose employees holding
+    /// `SuperUserDo` may administer every tenant.
+    pub operator_tenant: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+pub struct CorsConfig {
+    /// Origins permitted to make credentialed requests, `"*"` permits any.
+    pub allowed_origins: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SessionConfig {
+    pub access_ttl_minutes: i64,
+    pub refresh_ttl_days: i64,
+    /// Issues access tokens signed with Rocket's `secret_key` alongside the
+    /// session cookie, which are verified without querying the database.
+    pub stateless: bool,
+    /// How often the in-memory revocation list of signed tokens is
+    /// refreshed from the database, bounding how long a revoked token
+    /// remains usable.
+    pub revocation_sync_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GcConfig {
+    pub interval_secs: u64,
+    /// How long a `Saved` transaction is kept before it is culled.
+    pub saved_transaction_ttl_secs: i64,
+    /// How long after a purge is requested a suspended tenant's data is
+    /// deleted, during which reactivating the tenant cancels the purge.
+    pub tenant_purge_grace_secs: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct IngressConfig {
+    pub directory: PathBuf,
+    pub interval_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ShippingConfig {
+    /// The provider labels are purchased from, `none` disables shipping.
+    pub provider: ShippingProvider,
+    /// The base URL of the Karrio API, i.e. `http://localhost:5002`.
+    pub karrio_url: String,
+    pub karrio_api_key: String,
+    /// How often the tracking of orders in transit is polled.
+    pub poll_interval_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ShippingProvider {
+    #[default]
+    None,
+    Karrio,
+    /// An in-memory carrier, for development and testing.
+    Mock,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        SessionConfig {
+            access_ttl_minutes: 10,
+            refresh_ttl_days: 7,
+            stateless: false,
+            revocation_sync_secs: 5,
+        }
+    }
+}
+
+impl Default for GcConfig {
+    fn default() -> Self {
+        GcConfig {
+            interval_secs: 5,
+            saved_transaction_ttl_secs: 3600,
+            tenant_purge_grace_secs: 604800,
+        }
+    }
+}
+
+impl Default for IngressConfig {
+    fn default() -> Self {
+        IngressConfig {
+            directory: PathBuf::from("./ingress/"),
+            interval_secs: 5,
+        }
+    }
+}
+
+impl Default for ShippingConfig {
+    fn default() -> Self {
+        ShippingConfig {
+            provider: ShippingProvider::default(),
+            karrio_url: String::new(),
+            karrio_api_key: String::new(),
+            poll_interval_secs: 300,
+        }
+    }
+}
+
+impl SessionConfig {
+    pub fn access_ttl(&self) -> Duration {
+        Duration::minutes(self.access_ttl_minutes)
+    }
+
+    pub fn refresh_ttl(&self) -> Duration {
+        Duration::days(self.refresh_ttl_days)
+    }
+
+    pub fn revocation_sync_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.revocation_sync_secs)
+    }
+}
+
+impl GcConfig {
+    pub fn saved_transaction_ttl(&self) -> Duration {
+        Duration::seconds(self.saved_transaction_ttl_secs)
+    }
+
+    pub fn tenant_purge_grace(&self) -> Duration {
+        Duration::seconds(self.tenant_purge_grace_secs)
+    }
+}
+
+impl CorsConfig {
+    /// Returns the value for `Access-Control-Allow-Origin` given the
+    /// request's `Origin`, if that origin is permitted.
+    pub fn allow_origin<'a>(&self, origin: Option<&'a str>) -> Option<&'a str> {
+        let origin = origin?;
+
+        self.allowed_origins
+            .iter()
+            .any(|allowed| allowed == "*" || allowed == origin)
+            .then_some(origin)
+    }
+}
+
+impl AppConfig {
+    /// Extracts the configuration, falling back to the legacy
+    /// `ACCESS_ORIGIN` and `DEMO` environment variables when the
+    /// respective values are not configured.
+    pub fn from_figment(figment: &Figment) -> Result<Self, String> {
+        let mut config: AppConfig = figment.extract().map_err(|e| e.to_string())?;
+
+        if config.cors.allowed_origins.is_empty() {
+            if let Ok(origins) = dotenv::var("ACCESS_ORIGIN") {
+                config.cors.allowed_origins = origins
+                    .split(',')
+                    .map(|origin| origin.trim().to_string())
+                    .filter(|origin| !origin.is_empty())
+                    .collect();
+            }
+        }
+
+        if figment.find_value("demo").is_err() {
+            if let Ok(demo) = dotenv::var("DEMO") {
+                config.demo = !matches!(demo.as_str(), "" | "0" | "false");
+            }
+        }
+
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+
+        for origin in &self.cors.allowed_origins {
+            if origin != "*" && !(origin.starts_with("http://") || origin.starts_with("https://")) {
+                errors.push(format!(
+                    "cors.allowed_origins: `{}` must be `*` or an http(s) origin",
+                    origin
+                ));
+            } else if origin.ends_with('/') {
+                errors.push(format!(
+                    "cors.allowed_origins: `{}` must not have a trailing slash",
+                    origin
+                ));
+            }
+        }
+
+        if self.sessions.access_ttl_minutes <= 0 {
+            errors.push("sessions.access_ttl_minutes must be positive".to_string());
+        }
+
+        if self.sessions.refresh_ttl_days <= 0 {
+            errors.push("sessions.refresh_ttl_days must be positive".to_string());
+        }
+
+        if self.gc.interval_secs == 0 {
+            errors.push("gc.interval_secs must be positive".to_string());
+        }
+
+        if self.gc.saved_transaction_ttl_secs <= 0 {
+            errors.push("gc.saved_transaction_ttl_secs must be positive".to_string());
+        }
+
+        if self.ingress.interval_secs == 0 {
+            errors.push("ingress.interval_secs must be positive".to_string());
+        }
+
+        if self.ingress.directory.as_os_str().is_empty() {
+            errors.push("ingress.directory must not be empty".to_string());
+        }
+
+        if self.shipping.poll_interval_secs == 0 {
+            errors.push("shipping.poll_interval_secs must be positive".to_string());
+        }
+
+        if self.shipping.provider == ShippingProvider::Karrio
+            && !(self.shipping.karrio_url.starts_with("http://")
+                || self.shipping.karrio_url.starts_with("https://"))
+        {
+            errors.push("shipping.karrio_url must be an http(s) URL".to_string());
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Loads and validates the configuration on ignition, aborting the
+    /// launch if it is invalid. The revocation list of signed tokens, and
+    /// the shipping carrier, are managed alongside it.
+    pub fn fairing() -> AdHoc {
+        AdHoc::try_on_ignite("Application configuration", |rocket| async {
+            let config = match AppConfig::from_figment(rocket.figment()) {
+                Ok(config) => config,
+                Err(reason) => {
+                    error!(%reason, "unable to read configuration");
+                    return Err(rocket);
+                }
+            };
+
+            if let Err(errors) = config.validate() {
+                for reason in errors {
+                    error!(%reason, "invalid configuration");
+                }
+
+                return Err(rocket);
+            }
+
+            let revocations = Revocations::new(config.sessions.revocation_sync_interval());
+            let carrier = ShippingCarrier::from_config(&config.shipping);
+
+            Ok(rocket.manage(config).manage(revocations).manage(carrier))
+        })
+    }
+}
@@ -2,13 +2,20 @@
 
 pub mod prelude;
 
+pub mod api_keys;
 pub mod authrecord;
 pub mod customer;
 pub mod employee;
+pub mod employee_mfa;
+pub mod external_references;
+pub mod import_jobs;
 pub mod kiosk;
+pub mod manager_overrides;
 pub mod products;
 pub mod promotion;
+pub mod revoked_tokens;
 pub mod sea_orm_active_enums;
+pub mod serials;
 pub mod session;
 pub mod store;
 pub mod supplier;
@@ -1,10 +1,17 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.10.3
 
+pub use super::api_keys::Entity as ApiKeys;
 pub use super::customer::Entity as Customer;
 pub use super::employee::Entity as Employee;
+pub use super::employee_mfa::Entity as EmployeeMfa;
+pub use super::external_references::Entity as ExternalReferences;
+pub use super::import_jobs::Entity as ImportJobs;
 pub use super::kiosk::Entity as Kiosk;
+pub use super::manager_overrides::Entity as ManagerOverrides;
 pub use super::products::Entity as Products;
 pub use super::promotion::Entity as Promotion;
+pub use super::revoked_tokens::Entity as RevokedTokens;
+pub use super::serials::Entity as Serials;
 pub use super::session::Entity as Session;
 pub use super::store::Entity as Store;
 pub use super::supplier::Entity as Supplier;
@@ -13,6 +13,15 @@ pub struct Model {
     pub employee_id: String,
     pub expiry: DateTime,
     pub variant: Json,
+    pub kiosk_id: Option<String>,
+    pub created_at: Option<DateTime>,
+    /// The access token's id, shared by the refresh tokens derived from it.
+    pub family_id: Option<String>,
+    /// Set once a refresh token has been exchanged, a second exchange is
+    /// treated as token theft.
+    pub consumed_at: Option<DateTime>,
+    /// Actions withheld from the session as MFA was not satisfied.
+    pub restricted_actions: Option<Json>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -11,6 +11,9 @@ pub struct Model {
     pub settings: Json,
     pub created_at: DateTime,
     pub updated_at: DateTime,
+    pub suspended_at: Option<DateTime>,
+    pub purge_after: Option<DateTime>,
+    pub quote_sequence: i64,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -19,6 +19,9 @@ pub struct Model {
     pub salesperson: String,
     #[sea_orm(column_type = "Text")]
     pub kiosk: String,
+    pub void: Option<Json>,
+    pub quote: Option<Json>,
+    pub in_transit: bool,
     pub tenant_id: String,
     pub created_at: DateTime,
     pub updated_at: DateTime,
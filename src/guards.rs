@@ -1,5 +1,7 @@
+use crate::config::AppConfig;
 use crate::methods::common::Error;
-use crate::{cookie_status_wrapper, Db, ErrorResponse, Session};
+use crate::tokens::Revocations;
+use crate::{cookie_status_wrapper, ApiKey, Db, ErrorResponse, Session, OVERRIDE_HEADER};
 use futures::TryStreamExt;
 use okapi::openapi3::{MediaType, RefOr, Response, Responses};
 use rocket::request::{FromRequest, Outcome};
@@ -17,12 +19,103 @@ use rocket_okapi::gen::OpenApiGenerator;
 use rocket_okapi::request::{OpenApiFromRequest, RequestHeaderInput};
 use rocket_okapi::response::OpenApiResponderInner;
 use schemars::JsonSchema;
+use sea_orm::DbConn;
 use serde::{Deserialize, Serialize};
+use tracing::Span;
 use uuid::Uuid;
 
+/// The id assigned to the request by the `RequestTracer` fairing.
 #[derive(Copy, Clone, Debug)]
 pub struct RequestId(pub Option<Uuid>);
 
+/// The span opened for the request by the `RequestTracer` fairing,
+/// handlers may record further fields (such as `kiosk_id`) onto it.
+#[derive(Clone, Debug)]
+pub struct RequestSpan(pub Span);
+
+impl RequestSpan {
+    pub fn none() -> Self {
+        RequestSpan(Span::none())
+    }
+
+    pub fn record_session(&self, session: &Session) {
+        self.0.record("tenant_id", session.tenant_id.as_str());
+        self.0.record("employee_id", session.employee.id.as_str());
+    }
+
+    pub fn record_kiosk(&self, kiosk_id: &str) {
+        self.0.record("kiosk_id", kiosk_id);
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RequestId {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(*request.local_cache(|| RequestId(None)))
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RequestSpan {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(request.local_cache(RequestSpan::none).clone())
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for RequestId {
+    fn from_request_input(
+        _gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(RequestHeaderInput::None)
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for RequestSpan {
+    fn from_request_input(
+        _gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(RequestHeaderInput::None)
+    }
+}
+
+/// The manager override tokens presented with the request, see
+/// `ManagerOverride::authorise`.
+#[derive(Clone, Debug, Default)]
+pub struct OverrideTokens(pub Vec<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for OverrideTokens {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(OverrideTokens(
+            request
+                .headers()
+                .get(OVERRIDE_HEADER)
+                .map(|token| token.trim().to_string())
+                .collect(),
+        ))
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for OverrideTokens {
+    fn from_request_input(
+        _gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(RequestHeaderInput::None)
+    }
+}
+
 #[derive(Debug)]
 pub struct JsonValidation<T>(pub T);
 
@@ -72,6 +165,19 @@ impl<'r> OpenApiFromRequest<'r> for Session {
     }
 }
 
+/// In stateless mode, a valid signed token stands in for the session
+/// lookup. Any other token is ignored in favour of the session cookie.
+async fn signed_session(request: &Request<'_>, db: &DbConn) -> Option<Session> {
+    let rocket = request.rocket();
+
+    match (rocket.state::<AppConfig>(), rocket.state::<Revocations>()) {
+        (Some(config), Some(revocations)) if config.sessions.stateless => {
+            revocations.verify(request.cookies(), db).await
+        }
+        _ => None,
+    }
+}
+
 #[rocket::async_trait]
 impl<'r> FromRequest<'r> for Session {
     // &'r
@@ -93,8 +199,28 @@ impl<'r> FromRequest<'r> for Session {
             Outcome::Forward(f) => return Outcome::Forward(f),
         };
 
-        match cookie_status_wrapper(&db, cookies).await {
-            Ok(session) => Outcome::Success(session),
+        // Integrations present an API key as `Authorization: Bearer <key>`,
+        // otherwise the session cookie is used.
+        let bearer = request
+            .headers()
+            .get_one("Authorization")
+            .and_then(|value| value.strip_prefix("Bearer "));
+
+        let session = match bearer {
+            Some(key) => ApiKey::authenticate(key.trim(), &db).await,
+            None => match signed_session(request, &db).await {
+                Some(session) => Ok(session),
+                None => cookie_status_wrapper(&db, cookies).await,
+            },
+        };
+
+        match session {
+            Ok(session) => {
+                request
+                    .local_cache(RequestSpan::none)
+                    .record_session(&session);
+                Outcome::Success(session)
+            }
             Err(_) => Outcome::Forward(Status::Unauthorized),
         }
     }
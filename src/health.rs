@@ -0,0 +1,221 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use photon_geocoding::PhotonApiClient;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{get, routes, Route, State};
+use sea_orm::DbConn;
+use sea_orm_migration::MigratorTrait;
+use serde::Serialize;
+
+use crate::config::AppConfig;
+use crate::metrics::{BACKGROUND_TASK_LAST_RUN, SESSION_GARBAGE_COLLECTOR, SESSION_INGRESS_WORKER};
+use crate::migrator::Migrator;
+use crate::pool::InternalDb;
+
+/// A background task which has not completed an iteration within this
+/// many of its intervals is considered stalled.
+const WORKER_STALE_INTERVALS: u64 = 12;
+
+const GEOCODER_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How long a geocoder lookup is reported before it is repeated, such
+/// that probes do not each make a request to Photon.
+const GEOCODER_TTL: Duration = Duration::from_secs(300);
+
+/// The last geocoder lookup, and whether another is underway.
+struct GeocoderStatus {
+    checked_at: Option<Instant>,
+    refreshing: bool,
+    last: Option<Check>,
+}
+
+static GEOCODER_STATUS: Mutex<GeocoderStatus> = Mutex::new(GeocoderStatus {
+    checked_at: None,
+    refreshing: false,
+    last: None,
+});
+
+pub fn routes() -> Vec<Route> {
+    routes![live, ready]
+}
+
+#[derive(Serialize, Clone)]
+pub struct Check {
+    pub healthy: bool,
+    /// Checks which are not critical are reported, but do not
+    /// influence readiness.
+    pub critical: bool,
+    pub detail: String,
+}
+
+impl Check {
+    fn pass(detail: impl Into<String>) -> Self {
+        Check {
+            healthy: true,
+            critical: true,
+            detail: detail.into(),
+        }
+    }
+
+    fn fail(detail: impl Into<String>) -> Self {
+        Check {
+            healthy: false,
+            critical: true,
+            detail: detail.into(),
+        }
+    }
+
+    fn non_critical(mut self) -> Self {
+        self.critical = false;
+        self
+    }
+}
+
+#[derive(Serialize)]
+pub struct Readiness {
+    pub ready: bool,
+    pub database: Check,
+    pub migrations: Check,
+    pub session_garbage_collector: Check,
+    pub session_ingress_worker: Check,
+    pub geocoder: Check,
+}
+
+/// Liveness only asserts the process is able to serve requests.
+#[get("/live")]
+pub async fn live() -> Status {
+    Status::Ok
+}
+
+/// Readiness reports every dependency, responding `503` while any
+/// critical check is failing.
+#[get("/ready")]
+pub async fn ready(db: InternalDb, config: &State<AppConfig>) -> (Status, Json<Readiness>) {
+    let database = check_database(&db.0).await;
+    let migrations = check_migrations(&db.0).await;
+    let session_garbage_collector =
+        check_worker(SESSION_GARBAGE_COLLECTOR, config.gc.interval_secs);
+    let session_ingress_worker = check_worker(SESSION_INGRESS_WORKER, config.ingress.interval_secs);
+    let geocoder = check_geocoder().non_critical();
+
+    let ready = [
+        &database,
+        &migrations,
+        &session_garbage_collector,
+        &session_ingress_worker,
+        &geocoder,
+    ]
+    .iter()
+    .all(|check| check.healthy || !check.critical);
+
+    let status = if ready {
+        Status::Ok
+    } else {
+        Status::ServiceUnavailable
+    };
+
+    (
+        status,
+        Json(Readiness {
+            ready,
+            database,
+            migrations,
+            session_garbage_collector,
+            session_ingress_worker,
+            geocoder,
+        }),
+    )
+}
+
+async fn check_database(db: &DbConn) -> Check {
+    match db.ping().await {
+        Ok(_) => Check::pass("connected"),
+        Err(error) => Check::fail(error.to_string()),
+    }
+}
+
+async fn check_migrations(db: &DbConn) -> Check {
+    match Migrator::get_pending_migrations(db).await {
+        Ok(pending) if pending.is_empty() => Check::pass("up to date"),
+        Ok(pending) => Check::fail(format!(
+            "pending: {}",
+            pending
+                .iter()
+                .map(|migration| migration.name().to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        )),
+        Err(error) => Check::fail(error.to_string()),
+    }
+}
+
+fn check_worker(task: &str, interval_secs: u64) -> Check {
+    let last_run = BACKGROUND_TASK_LAST_RUN.with_label_values(&[task]).get();
+
+    if last_run == 0 {
+        return Check::fail("not yet run");
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default();
+
+    let age = now - last_run;
+
+    if age <= (interval_secs * WORKER_STALE_INTERVALS) as i64 {
+        Check::pass(format!("last ran {}s ago", age))
+    } else {
+        Check::fail(format!("stalled, last ran {}s ago", age))
+    }
+}
+
+/// Reports the last geocoder lookup without waiting on Photon, starting
+/// another in the background once it is older than [`GEOCODER_TTL`].
+fn check_geocoder() -> Check {
+    let mut status = GEOCODER_STATUS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let stale = status
+        .checked_at
+        .is_none_or(|checked_at| checked_at.elapsed() >= GEOCODER_TTL);
+
+    if stale && !status.refreshing {
+        status.refreshing = true;
+
+        rocket::tokio::spawn(async {
+            let check = lookup_geocoder().await;
+
+            let mut status = GEOCODER_STATUS
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            status.checked_at = Some(Instant::now());
+            status.refreshing = false;
+            status.last = Some(check);
+        });
+    }
+
+    status
+        .last
+        .clone()
+        .unwrap_or_else(|| Check::fail("not yet checked"))
+}
+
+async fn lookup_geocoder() -> Check {
+    let lookup = rocket::tokio::task::spawn_blocking(|| {
+        PhotonApiClient::default()
+            .forward_search("Berlin", None)
+            .map(|_| ())
+            .map_err(|error| format!("{:?}", error))
+    });
+
+    match rocket::tokio::time::timeout(GEOCODER_TIMEOUT, lookup).await {
+        Ok(Ok(Ok(_))) => Check::pass("reachable"),
+        Ok(Ok(Err(error))) => Check::fail(error),
+        Ok(Err(error)) => Check::fail(error.to_string()),
+        Err(_) => Check::fail("timed out"),
+    }
+}
@@ -7,12 +7,24 @@ use std::thread;
 
 pub mod catchers;
 #[cfg(feature = "process")]
+pub mod config;
+#[cfg(feature = "process")]
 pub mod entities;
 pub mod guards;
+#[cfg(feature = "process")]
+pub mod health;
+#[cfg(feature = "process")]
+pub mod logging;
+#[cfg(feature = "process")]
+pub mod metrics;
 pub mod methods;
 #[cfg(feature = "process")]
 pub mod migrator;
 pub mod pool;
+#[cfg(feature = "process")]
+pub mod server;
+#[cfg(feature = "process")]
+pub mod tokens;
 
 #[cfg(feature = "process")]
 pub use self::entities::*;
@@ -76,11 +88,11 @@ impl Worker {
             let message = receiver.lock().unwrap().recv().unwrap();
             match message {
                 Message::NewJob(job) => {
-                    println!("Worker {} got a job; executing.", id);
+                    tracing::trace!(worker = id, "worker got a job, executing");
                     job.call_box();
                 }
                 Message::Terminate => {
-                    println!("Worker{} was told to terminate.", id);
+                    tracing::trace!(worker = id, "worker was told to terminate");
                     break;
                 }
             }
@@ -94,13 +106,12 @@ impl Worker {
 
 impl Drop for ThreadPool {
     fn drop(&mut self) {
-        println!("Sending terminate message to all workers.");
+        tracing::debug!("sending terminate message to all workers");
         for _ in &mut self.workers {
             self.sender.send(Message::Terminate).unwrap();
         }
-        println!("Shutting down all workers.");
         for worker in &mut self.workers {
-            println!("Shutting down worker {}", worker.id);
+            tracing::trace!(worker = worker.id, "shutting down worker");
             if let Some(thread) = worker.thread.take() {
                 thread.join().unwrap();
             }
@@ -0,0 +1,56 @@
+use crate::catchers::Validated;
+use crate::guards::Convert;
+use crate::methods::{Action, Error, ErrorResponse};
+use crate::pool::InternalDb;
+use crate::{check_permissions, ApiKey, ApiKeyInput, NewApiKey, Session, SessionVariant};
+use okapi::openapi3::OpenApi;
+use rocket::serde::json::Json;
+use rocket::{get, post};
+use rocket_okapi::settings::OpenApiSettings;
+use rocket_okapi::{openapi, openapi_get_routes_spec};
+
+pub fn documented_routes(settings: &OpenApiSettings) -> (Vec<rocket::Route>, OpenApi) {
+    openapi_get_routes_spec![settings: get_all, get, create, revoke]
+}
+
+#[openapi(tag = "ApiKey")]
+#[get("/")]
+pub async fn get_all(db: InternalDb, session: Session) -> Convert<Vec<ApiKey>> {
+    check_permissions!(session.clone(), Action::AccessAdminPanel);
+    ApiKey::fetch_all(session, &db.0).await.into()
+}
+
+#[openapi(tag = "ApiKey")]
+#[get("/<id>")]
+pub async fn get(db: InternalDb, session: Session, id: &str) -> Convert<ApiKey> {
+    check_permissions!(session.clone(), Action::AccessAdminPanel);
+    ApiKey::fetch_by_id(id, session, &db.0).await.into()
+}
+
+/// Keys may only be issued from an employee's session, not by another key.
+#[openapi(tag = "ApiKey")]
+#[post("/", data = "<input_data>")]
+pub async fn create(
+    db: InternalDb,
+    session: Session,
+    input_data: Validated<Json<ApiKeyInput>>,
+) -> Result<Json<NewApiKey>, Error> {
+    check_permissions!(session.clone(), Action::AccessAdminPanel);
+
+    if let SessionVariant::ApiKey(_) = session.variant {
+        return Err(ErrorResponse::custom_unauthorized(
+            "API keys cannot be issued using an API key.",
+        ));
+    }
+
+    ApiKey::insert(input_data.data(), session, &db.0)
+        .await
+        .map(Json)
+}
+
+#[openapi(tag = "ApiKey")]
+#[post("/revoke/<id>")]
+pub async fn revoke(db: InternalDb, session: Session, id: &str) -> Convert<ApiKey> {
+    check_permissions!(session.clone(), Action::AccessAdminPanel);
+    ApiKey::revoke(id, session, &db.0).await.into()
+}
@@ -0,0 +1,259 @@
+#[cfg(feature = "process")]
+use crate::entities::api_keys;
+#[cfg(feature = "process")]
+use crate::entities::prelude::ApiKeys;
+use crate::methods::{Access, Action, Error, ErrorResponse, Id};
+#[cfg(feature = "process")]
+use crate::{Employee, Session, SessionVariant, Tenant};
+use chrono::{DateTime, Days, Duration, Utc};
+#[cfg(feature = "process")]
+use rand::distributions::{Alphanumeric, DistString};
+use schemars::JsonSchema;
+#[cfg(feature = "process")]
+use sea_orm::{ActiveModelTrait, ColumnTrait, DbConn, DbErr, EntityTrait, QueryFilter, Set};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+#[cfg(feature = "process")]
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use validator::Validate;
+
+/// Keys are presented as `osk_<prefix>.<secret>`, the prefix is stored in
+/// the clear to look the key up, whilst only a hash of the whole key is kept.
+pub const API_KEY_PREFIX: &str = "osk_";
+
+/// `last_used_at` is only written when it is older than this, so that a
+/// busy integration does not write on every request.
+const LAST_USED_RESOLUTION: i64 = 60;
+
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema, Validate)]
+pub struct ApiKey {
+    pub id: Id,
+    pub name: String,
+    /// The non-secret leading portion of the key, i.e. `osk_1a2B3c4D`,
+    /// allowing a key to be recognised without revealing it.
+    pub prefix: String,
+    /// The actions the key may perform, further limited to those its
+    /// creator currently holds.
+    pub scopes: Vec<Action>,
+    pub created_by: Id,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema, Validate)]
+pub struct ApiKeyInput {
+    #[validate(length(min = 1, max = 128))]
+    pub name: String,
+    pub scopes: Vec<Action>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct NewApiKey {
+    pub api_key: ApiKey,
+    /// The full key, this is only ever returned upon creation.
+    pub key: String,
+}
+
+#[cfg(feature = "process")]
+impl From<api_keys::Model> for ApiKey {
+    fn from(val: api_keys::Model) -> Self {
+        ApiKey {
+            id: val.id,
+            name: val.name,
+            prefix: val.prefix,
+            scopes: serde_json::from_value::<Vec<Action>>(val.scopes).unwrap_or_default(),
+            created_by: val.created_by,
+            created_at: DateTime::from_naive_utc_and_offset(val.created_at, Utc),
+            last_used_at: val
+                .last_used_at
+                .map(|v| DateTime::from_naive_utc_and_offset(v, Utc)),
+            expires_at: val
+                .expires_at
+                .map(|v| DateTime::from_naive_utc_and_offset(v, Utc)),
+            revoked_at: val
+                .revoked_at
+                .map(|v| DateTime::from_naive_utc_and_offset(v, Utc)),
+        }
+    }
+}
+
+#[cfg(feature = "process")]
+fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+#[cfg(feature = "process")]
+fn hashes_match(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+#[cfg(feature = "methods")]
+impl ApiKey {
+    pub fn is_active(&self) -> bool {
+        self.revoked_at.is_none() && self.expires_at.is_none_or(|expiry| expiry > Utc::now())
+    }
+
+    /// Creates a key for the session's tenant. A key cannot be granted
+    /// any action the creating employee does not hold themselves.
+    pub async fn insert(
+        input: ApiKeyInput,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<NewApiKey, Error> {
+        if let Some(action) = input
+            .scopes
+            .iter()
+            .find(|action| !session.clone().has_permission((*action).clone()))
+        {
+            return Err(ErrorResponse::unauthorized(action.clone()));
+        }
+
+        let prefix = format!(
+            "{}{}",
+            API_KEY_PREFIX,
+            Alphanumeric.sample_string(&mut rand::thread_rng(), 8)
+        );
+        let key = format!(
+            "{}.{}",
+            prefix,
+            Alphanumeric.sample_string(&mut rand::thread_rng(), 40)
+        );
+        let id = Uuid::new_v4().to_string();
+
+        api_keys::ActiveModel {
+            id: Set(id.clone()),
+            tenant_id: Set(session.tenant_id.clone()),
+            name: Set(input.name),
+            prefix: Set(prefix),
+            key_hash: Set(hash_key(&key)),
+            scopes: Set(json!(input.scopes)),
+            created_by: Set(session.employee.id.clone()),
+            created_at: Set(Utc::now().naive_utc()),
+            last_used_at: Set(None),
+            expires_at: Set(input.expires_at.map(|v| v.naive_utc())),
+            revoked_at: Set(None),
+        }
+        .insert(db)
+        .await?;
+
+        Ok(NewApiKey {
+            api_key: ApiKey::fetch_by_id(&id, session, db).await?,
+            key,
+        })
+    }
+
+    pub async fn fetch_by_id(id: &str, session: Session, db: &DbConn) -> Result<ApiKey, Error> {
+        let key = ApiKeys::find_by_id(id.to_string())
+            .filter(api_keys::Column::TenantId.eq(session.tenant_id))
+            .one(db)
+            .await?;
+
+        match key {
+            Some(k) => Ok(k.into()),
+            None => Err(DbErr::RecordNotFound(id.to_string()).into()),
+        }
+    }
+
+    pub async fn fetch_all(session: Session, db: &DbConn) -> Result<Vec<ApiKey>, Error> {
+        let keys = ApiKeys::find()
+            .filter(api_keys::Column::TenantId.eq(session.tenant_id))
+            .all(db)
+            .await?;
+
+        Ok(keys.into_iter().map(|k| k.into()).collect())
+    }
+
+    pub async fn revoke(id: &str, session: Session, db: &DbConn) -> Result<ApiKey, Error> {
+        let key = ApiKey::fetch_by_id(id, session.clone(), db).await?;
+
+        if key.revoked_at.is_none() {
+            api_keys::ActiveModel {
+                id: Set(key.id.clone()),
+                revoked_at: Set(Some(Utc::now().naive_utc())),
+                ..Default::default()
+            }
+            .update(db)
+            .await?;
+        }
+
+        ApiKey::fetch_by_id(id, session, db).await
+    }
+
+    /// Resolves a presented key to a session acting as the key's creator,
+    /// holding only the key's scopes.
+    pub async fn authenticate(key: &str, db: &DbConn) -> Result<Session, Error> {
+        let invalid = || ErrorResponse::custom_unauthorized("Invalid API key.");
+
+        let (prefix, _) = key.split_once('.').ok_or_else(invalid)?;
+
+        let model = ApiKeys::find()
+            .filter(api_keys::Column::Prefix.eq(prefix))
+            .one(db)
+            .await?
+            .ok_or_else(invalid)?;
+
+        if !hashes_match(&model.key_hash, &hash_key(key)) {
+            return Err(invalid());
+        }
+
+        let api_key: ApiKey = model.clone().into();
+
+        if !api_key.is_active() {
+            return Err(invalid());
+        }
+
+        if Tenant::fetch_by_id(&model.tenant_id, db)
+            .await?
+            .is_suspended()
+        {
+            return Err(ErrorResponse::custom_unauthorized("Tenant is suspended."));
+        }
+
+        let tenant_session = Session::default_with_tenant(model.tenant_id.clone());
+        let mut employee = Employee::fetch_by_id(&api_key.created_by, tenant_session, db)
+            .await
+            .map_err(|_| invalid())?;
+
+        employee.level = employee
+            .level
+            .into_iter()
+            .filter(|access| api_key.scopes.contains(&access.action))
+            .collect::<Vec<Access<Action>>>();
+
+        let stale = api_key
+            .last_used_at
+            .is_none_or(|used| Utc::now() - used > Duration::seconds(LAST_USED_RESOLUTION));
+
+        if stale {
+            api_keys::ActiveModel {
+                id: Set(api_key.id.clone()),
+                last_used_at: Set(Some(Utc::now().naive_utc())),
+                ..Default::default()
+            }
+            .update(db)
+            .await?;
+        }
+
+        Ok(Session {
+            id: api_key.id.clone(),
+            key: String::new(),
+            employee,
+            expiry: api_key
+                .expires_at
+                .unwrap_or_else(|| Utc::now().checked_add_days(Days::new(1)).unwrap()),
+            tenant_id: model.tenant_id,
+            variant: SessionVariant::ApiKey(api_key.id),
+        })
+    }
+}
@@ -1,6 +1,6 @@
-use std::fmt::Display;
+use std::fmt::{self, Display};
 
-use super::{Employee as EmployeeObj, ProductExchange};
+use super::{Employee as EmployeeObj, PriceDiff, ProductExchange};
 #[cfg(feature = "process")]
 use crate::entities::employee::Entity as Employee;
 #[cfg(feature = "process")]
@@ -10,7 +10,11 @@ use crate::{example_employee, session, AccountType, Employee as EmployeeStruct,
 
 #[cfg(feature = "process")]
 use crate::entities;
-use crate::methods::{stml::Order, Access, Action, Attendance, EmployeeAuth};
+#[cfg(feature = "process")]
+use crate::tokens;
+use crate::methods::{
+    stml::Order, Access, Action, Attendance, EmployeeAuth, OrderStatus, OrderType,
+};
 use chrono::{DateTime, Days, Utc};
 use lazy_static::lazy_static;
 use okapi::openapi3::Responses;
@@ -18,7 +22,14 @@ use regex::Regex;
 use rocket::http::{Cookie, SameSite};
 use rocket::time::OffsetDateTime;
 #[cfg(feature = "process")]
-use rocket::{http::CookieJar, serde::json::Json, Responder};
+use rocket::{
+    http::{CookieJar, Status},
+    response::{self, Responder},
+    serde::json::Json,
+    Request, Response,
+};
+#[cfg(feature = "process")]
+use crate::metrics::ERRORS;
 use rocket_okapi::gen::OpenApiGenerator;
 
 use crate::session::{ActiveModel, Model};
@@ -26,9 +37,10 @@ use rocket_okapi::response::OpenApiResponderInner;
 use schemars::JsonSchema;
 use sea_orm::ActiveValue::Set;
 #[cfg(feature = "process")]
-use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QuerySelect};
+use sea_orm::{ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use tracing::warn;
 use uuid::Uuid;
 use validator::Validate;
 
@@ -60,6 +72,28 @@ pub struct ContactInformation {
     pub address: Address,
 }
 
+impl ContactInformation {
+    /// Contact information identifying no one, which replaces that of an
+    /// anonymised customer.
+    pub fn anonymised() -> Self {
+        ContactInformation {
+            name: String::new(),
+            mobile: MobileNumber::from(String::new()),
+            email: Email::from(String::new()),
+            landline: String::new(),
+            address: Address {
+                street: String::new(),
+                street2: String::new(),
+                city: String::new(),
+                country: String::new(),
+                po_code: String::new(),
+                lat: 0.0,
+                lon: 0.0,
+            },
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema, Validate)]
 pub struct ContactInformationInput {
     pub name: String,
@@ -212,7 +246,7 @@ pub type TagList = Vec<Tag>;
 pub type Tag = String;
 pub type Id = String;
 
-#[derive(Debug, JsonSchema, Validate)]
+#[derive(JsonSchema, Validate)]
 pub struct SessionRaw {
     pub id: String,
     pub key: String,
@@ -222,14 +256,29 @@ pub struct SessionRaw {
     pub tenant_id: String,
 }
 
+impl fmt::Debug for SessionRaw {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionRaw")
+            .field("id", &self.id)
+            .field("key", &"<redacted>")
+            .field("employee_id", &self.employee_id)
+            .field("expiry", &self.expiry)
+            .field("variant", &self.variant)
+            .field("tenant_id", &self.tenant_id)
+            .finish()
+    }
+}
+
 #[derive(Debug, Clone, JsonSchema, Serialize, Deserialize)]
 pub enum SessionVariant {
     // Stores ID of AT.
     RefreshToken(String),
     AccessToken,
+    // Stores ID of the API key, such sessions are never persisted.
+    ApiKey(String),
 }
 
-#[derive(Debug, Clone, JsonSchema, Validate, Serialize, Deserialize)]
+#[derive(Clone, JsonSchema, Validate, Serialize, Deserialize)]
 pub struct Session {
     pub id: String,
     pub key: String,
@@ -239,6 +288,20 @@ pub struct Session {
     pub variant: SessionVariant,
 }
 
+impl fmt::Debug for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The key is a bearer credential, so it is never written to logs.
+        f.debug_struct("Session")
+            .field("id", &self.id)
+            .field("key", &"<redacted>")
+            .field("employee", &self.employee.id)
+            .field("expiry", &self.expiry)
+            .field("tenant_id", &self.tenant_id)
+            .field("variant", &self.variant)
+            .finish()
+    }
+}
+
 impl Session {
     pub fn default_with_tenant(tenant_id: String) -> Self {
         let default_employee = example_employee();
@@ -257,13 +320,157 @@ impl Session {
 impl From<Session> for session::ActiveModel {
     fn from(val: Session) -> Self {
         ActiveModel {
-            id: Set(val.id),
+            id: Set(val.id.clone()),
             key: Set(val.key),
             tenant_id: Set(val.tenant_id),
             employee_id: Set(val.employee.id),
             expiry: Set(val.expiry.naive_utc()),
+            family_id: Set(Some(val.id)),
             variant: Set(json!(val.variant)),
+            kiosk_id: Set(None),
+            created_at: Set(Some(Utc::now().naive_utc())),
+            consumed_at: Set(None),
+            restricted_actions: Set(None),
+        }
+    }
+}
+
+/// An active session as presented to the employee it belongs to, the key
+/// is never returned.
+#[derive(Debug, Clone, JsonSchema, Serialize, Deserialize)]
+pub struct SessionSummary {
+    pub id: String,
+    pub variant: SessionVariant,
+    pub kiosk_id: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub expiry: DateTime<Utc>,
+    /// Whether this is the session making the request.
+    pub current: bool,
+}
+
+#[cfg(feature = "process")]
+impl Session {
+    /// Lists the unexpired, unconsumed sessions held by an employee.
+    pub async fn fetch_active(
+        employee_id: &str,
+        session: Session,
+        db: &DatabaseConnection,
+    ) -> Result<Vec<SessionSummary>, Error> {
+        let sessions = SessionEntity::find()
+            .filter(entities::session::Column::EmployeeId.eq(employee_id))
+            .filter(entities::session::Column::TenantId.eq(session.tenant_id.clone()))
+            .filter(entities::session::Column::Expiry.gt(Utc::now().naive_utc()))
+            .filter(entities::session::Column::ConsumedAt.is_null())
+            .all(db)
+            .await?;
+
+        // An access token roots its own family, so its refresh tokens are
+        // marked as current alongside it.
+        let current = session.id;
+
+        Ok(sessions
+            .into_iter()
+            .map(|model| SessionSummary {
+                current: model.family_id.as_deref().unwrap_or(&model.id) == current,
+                variant: serde_json::from_value::<SessionVariant>(model.variant)
+                    .unwrap_or(SessionVariant::AccessToken),
+                kiosk_id: model.kiosk_id,
+                created_at: model
+                    .created_at
+                    .map(|created| DateTime::from_naive_utc_and_offset(created, Utc)),
+                expiry: DateTime::from_naive_utc_and_offset(model.expiry, Utc),
+                id: model.id,
+            })
+            .collect())
+    }
+
+    /// The kiosk the session was issued at, if it was issued at one.
+    pub async fn kiosk_id(&self, db: &DatabaseConnection) -> Result<Option<String>, Error> {
+        Ok(SessionEntity::find_by_id(self.id.clone())
+            .one(db)
+            .await?
+            .and_then(|model| model.kiosk_id))
+    }
+
+    /// Revokes a session along with every token in its family. When
+    /// `employee_id` is given, only that employee's sessions may be revoked.
+    pub async fn revoke(
+        id: &str,
+        employee_id: Option<&str>,
+        session: Session,
+        db: &DatabaseConnection,
+    ) -> Result<u64, Error> {
+        let mut query = SessionEntity::find_by_id(id)
+            .filter(entities::session::Column::TenantId.eq(session.tenant_id));
+
+        if let Some(employee_id) = employee_id {
+            query = query.filter(entities::session::Column::EmployeeId.eq(employee_id));
+        }
+
+        match query.one(db).await? {
+            Some(model) => {
+                let family_id = model.family_id.unwrap_or(model.id);
+                Session::revoke_family(&family_id, db).await
+            }
+            None => Err(ErrorResponse::db_err(DbErr::RecordNotFound(format!(
+                "Session {} does not exist.",
+                id
+            )))),
+        }
+    }
+
+    /// Revokes every session held by an employee, i.e. logging out everywhere.
+    pub async fn revoke_all(
+        employee_id: &str,
+        session: Session,
+        db: &DatabaseConnection,
+    ) -> Result<u64, Error> {
+        let condition = Condition::all()
+            .add(entities::session::Column::EmployeeId.eq(employee_id))
+            .add(entities::session::Column::TenantId.eq(session.tenant_id));
+
+        Session::revoke_matching(employee_id, condition, db).await
+    }
+
+    pub async fn revoke_family(family_id: &str, db: &DatabaseConnection) -> Result<u64, Error> {
+        let condition = Condition::any()
+            .add(entities::session::Column::FamilyId.eq(family_id))
+            .add(entities::session::Column::Id.eq(family_id));
+
+        Session::revoke_matching(family_id, condition, db).await
+    }
+
+    /// Ends every session of the tenant, i.e. when it is suspended.
+    pub async fn revoke_tenant(tenant_id: &str, db: &DatabaseConnection) -> Result<u64, Error> {
+        let condition = Condition::all().add(entities::session::Column::TenantId.eq(tenant_id));
+
+        Session::revoke_matching(tenant_id, condition, db).await
+    }
+
+    /// Deletes the matching sessions and, as signed tokens issued for them
+    /// remain valid until they expire, records `subject` as revoked.
+    async fn revoke_matching(
+        subject: &str,
+        condition: Condition,
+        db: &DatabaseConnection,
+    ) -> Result<u64, Error> {
+        let sessions = SessionEntity::find()
+            .filter(condition.clone())
+            .all(db)
+            .await?;
+
+        let latest = sessions.iter().max_by_key(|model| model.expiry);
+
+        if let Some(latest) = latest {
+            tokens::revoke_subject(subject, &latest.tenant_id, latest.expiry, db).await?;
         }
+
+        let result = SessionEntity::delete_many()
+            .filter(condition)
+            .exec(db)
+            .await?;
+
+        Ok(result.rows_affected)
     }
 }
 
@@ -281,6 +488,16 @@ impl From<Model> for SessionRaw {
 }
 
 impl Session {
+    /// The greatest discount the employee may give, as a percentage of the
+    /// price it applies to. Without `ApplyDiscount`, none may be given.
+    pub fn discount_ceiling(&self) -> u32 {
+        self.employee
+            .level
+            .iter()
+            .find(|access| access.action == Action::ApplyDiscount)
+            .map_or(0, |access| access.authority.clamp(0, 100) as u32)
+    }
+
     pub fn has_permission(self, permission: Action) -> bool {
         let action = self
             .employee
@@ -325,20 +542,46 @@ pub fn get_key_cookie(cookies: &CookieJar<'_>) -> Option<String> {
         .map(|crumb| crumb.value().to_string())
 }
 
+/// The actions withheld from a session when it was issued, as the
+/// employee had not satisfied MFA.
+#[cfg(feature = "process")]
+pub fn restricted_actions(model: &Model) -> Vec<Action> {
+    model
+        .restricted_actions
+        .clone()
+        .and_then(|actions| serde_json::from_value::<Vec<Action>>(actions).ok())
+        .unwrap_or_default()
+}
+
 #[cfg(feature = "process")]
 pub async fn verify_cookie(key: String, db: &DatabaseConnection) -> Result<Session, DbErr> {
     let session = SessionEntity::find()
-        .having(entities::session::Column::Key.eq(key.clone()))
+        .filter(entities::session::Column::Key.eq(key.clone()))
         .find_also_related(Employee)
         .one(db)
         .await?;
 
+    // Only a live access token may authenticate a request, refresh tokens
+    // are exchanged through `/helpers/refresh_token/<token>` instead.
+    if let Some((val, _)) = &session {
+        let variant = serde_json::from_value::<SessionVariant>(val.variant.clone())
+            .map_err(|e| DbErr::Custom(e.to_string()))?;
+
+        if !matches!(variant, SessionVariant::AccessToken) {
+            return Err(DbErr::Custom(
+                "Session is not an access token.".to_string(),
+            ));
+        }
+
+        if val.expiry < Utc::now().naive_utc() {
+            return Err(DbErr::Custom("Session has expired.".to_string()));
+        }
+    }
+
     match session {
         Some((val, Some(e))) => Ok(Session {
-            id: val.id,
-            key: val.key,
-            tenant_id: val.tenant_id,
-            employee: EmployeeObj {
+            employee: {
+                let mut employee = EmployeeObj {
                 id: e.id.clone(),
                 rid: e.rid.clone(),
                 name: serde_json::from_value::<Name>(e.name.clone()).unwrap(),
@@ -352,7 +595,14 @@ pub async fn verify_cookie(key: String, db: &DatabaseConnection) -> Result<Sessi
                 level: serde_json::from_value::<Vec<Access<Action>>>(e.level).unwrap(),
                 created_at: Default::default(),
                 updated_at: Default::default(),
+                };
+
+                employee.restrict(&restricted_actions(&val));
+                employee
             },
+            id: val.id,
+            key: val.key,
+            tenant_id: val.tenant_id,
             expiry: DateTime::from_naive_utc_and_offset(val.expiry, Utc),
             variant: SessionVariant::AccessToken,
         }),
@@ -368,11 +618,9 @@ pub async fn verify_cookie(key: String, db: &DatabaseConnection) -> Result<Sessi
 }
 
 #[cfg(feature = "process")]
-pub fn create_cookie(api_key: String) -> Cookie<'static> {
-    use std::time::Duration;
-
+pub fn create_cookie(api_key: String, expires_in: chrono::Duration) -> Cookie<'static> {
     let now = OffsetDateTime::now_utc();
-    let expiry = now + Duration::from_secs(10 * 60);
+    let expiry = now + rocket::time::Duration::seconds(expires_in.num_seconds());
 
     Cookie::build(("os-stock-key", api_key.clone()))
         .expires(expiry)
@@ -403,7 +651,7 @@ pub async fn cookie_status_wrapper(
         Some(val) => match verify_cookie(val, db).await {
             Ok(v) => Ok(v),
             Err(err) => {
-                println!("[err]: {}", err);
+                warn!(error = %err, "unable to validate session cookie");
                 Err(ErrorResponse::custom_unauthorized(
                     "Unable to validate cookie, user does not have valid session.",
                 ))
@@ -421,6 +669,26 @@ pub struct ErrorResponse {
     code: String,
 }
 
+/// Returned when a sale's prices differ from those of the catalogue, or
+/// its discounts exceed what the employee may give.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct PriceMismatchResponse {
+    pub message: String,
+    pub code: String,
+    pub differences: Vec<PriceDiff>,
+}
+
+/// Returned when an order is moved to a status its type does not permit
+/// from the one it holds.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct TransitionErrorResponse {
+    pub message: String,
+    pub code: String,
+    pub order_type: OrderType,
+    pub from: OrderStatus,
+    pub to: OrderStatus,
+}
+
 #[cfg(feature = "process")]
 impl ErrorResponse {
     pub fn create_error(message: &str) -> Error {
@@ -451,6 +719,36 @@ impl ErrorResponse {
         }))
     }
 
+    pub fn price_mismatch(differences: Vec<PriceDiff>) -> Error {
+        Error::PriceMismatch(Json(PriceMismatchResponse {
+            message: format!(
+                "Transaction pricing differs from the catalogue in {} place(s).",
+                differences.len()
+            ),
+            code: "error.transaction.price_mismatch".to_string(),
+            differences,
+        }))
+    }
+
+    pub fn invalid_transition(
+        order_type: &OrderType,
+        from: &OrderStatus,
+        to: &OrderStatus,
+    ) -> Error {
+        Error::InvalidTransition(Json(TransitionErrorResponse {
+            message: format!(
+                "A {:?} order cannot move from {} to {}.",
+                order_type,
+                from.to_string().trim_end_matches(':'),
+                to.to_string().trim_end_matches(':')
+            ),
+            code: "error.order.invalid_transition".to_string(),
+            order_type: order_type.clone(),
+            from: from.clone(),
+            to: to.clone(),
+        }))
+    }
+
     pub fn db_err(message: DbErr) -> Error {
         Error::DbError(Json(ErrorResponse {
             message: format!("SQL error, reason: {}", message),
@@ -485,18 +783,63 @@ impl<T: JsonSchema> Into<Json<T>> for Wrapper<T> {
 }
 
 #[cfg(feature = "process")]
-#[derive(Debug, Responder)]
+#[derive(Debug)]
 pub enum Error {
-    #[response(status = 500, content_type = "json")]
     StandardError(Json<ErrorResponse>),
-    #[response(status = 400, content_type = "json")]
     InputError(Json<ErrorResponse>),
-    #[response(status = 401, content_type = "json")]
     Unauthorized(Json<ErrorResponse>),
-    #[response(status = 500, content_type = "json")]
     DbError(Json<ErrorResponse>),
-    #[response(status = 500, content_type = "text")]
     DemoDisabled(String),
+    PriceMismatch(Json<PriceMismatchResponse>),
+    InvalidTransition(Json<TransitionErrorResponse>),
+}
+
+#[cfg(feature = "process")]
+impl Error {
+    pub fn variant_name(&self) -> &'static str {
+        match self {
+            Error::StandardError(_) => "StandardError",
+            Error::InputError(_) => "InputError",
+            Error::Unauthorized(_) => "Unauthorized",
+            Error::DbError(_) => "DbError",
+            Error::DemoDisabled(_) => "DemoDisabled",
+            Error::PriceMismatch(_) => "PriceMismatch",
+            Error::InvalidTransition(_) => "InvalidTransition",
+        }
+    }
+
+    /// The message returned to the client, for recording elsewhere.
+    pub fn message(&self) -> String {
+        match self {
+            Error::StandardError(body)
+            | Error::InputError(body)
+            | Error::Unauthorized(body)
+            | Error::DbError(body) => body.message.clone(),
+            Error::DemoDisabled(message) => message.clone(),
+            Error::PriceMismatch(body) => body.message.clone(),
+            Error::InvalidTransition(body) => body.message.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "process")]
+impl<'r> Responder<'r, 'static> for Error {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        ERRORS.with_label_values(&[self.variant_name()]).inc();
+
+        let (status, response) = match self {
+            Error::StandardError(body) | Error::DbError(body) => {
+                (Status::InternalServerError, body.respond_to(request)?)
+            }
+            Error::InputError(body) => (Status::BadRequest, body.respond_to(request)?),
+            Error::Unauthorized(body) => (Status::Unauthorized, body.respond_to(request)?),
+            Error::DemoDisabled(body) => (Status::InternalServerError, body.respond_to(request)?),
+            Error::PriceMismatch(body) => (Status::Conflict, body.respond_to(request)?),
+            Error::InvalidTransition(body) => (Status::Conflict, body.respond_to(request)?),
+        };
+
+        Response::build_from(response).status(status).ok()
+    }
 }
 
 impl OpenApiResponderInner for Error {
@@ -1,4 +1,4 @@
-use super::{Customer, CustomerInput};
+use super::{Customer, CustomerExport, CustomerInput};
 use crate::catchers::Validated;
 use crate::guards::Convert;
 use crate::methods::{Action, ContactInformation, CustomerWithTransactionsOut, Error, Transaction};
@@ -10,6 +10,7 @@ use rocket::post;
 use rocket::serde::json::Json;
 use rocket_okapi::settings::OpenApiSettings;
 use rocket_okapi::{openapi, openapi_get_routes_spec};
+use tracing::info;
 
 pub fn documented_routes(settings: &OpenApiSettings) -> (Vec<rocket::Route>, OpenApi) {
     openapi_get_routes_spec![
@@ -26,7 +27,9 @@ pub fn documented_routes(settings: &OpenApiSettings) -> (Vec<rocket::Route>, Ope
         search_query,
         update_contact_info,
         update_by_input,
-        find_related_transactions
+        find_related_transactions,
+        export,
+        anonymise
     ]
 }
 
@@ -161,3 +164,19 @@ pub async fn create(
         .into();
     converted.0
 }
+
+#[openapi(tag = "Customer")]
+#[get("/export/<id>")]
+pub async fn export(db: InternalDb, session: Session, id: &str) -> Convert<CustomerExport> {
+    check_permissions!(session.clone(), Action::FetchCustomer);
+    Customer::export(id, session, &db.0).await.into()
+}
+
+/// Honours a customer's request to be forgotten, see `Customer::anonymise`.
+#[openapi(tag = "Customer")]
+#[post("/anonymise/<id>")]
+pub async fn anonymise(db: InternalDb, session: Session, id: &str) -> Convert<Customer> {
+    check_permissions!(session.clone(), Action::DeleteCustomer);
+    info!(customer_id = %id, anonymised_by = %session.employee.id, "anonymising customer");
+    Customer::anonymise(id, session, &db.0).await.into()
+}
@@ -9,7 +9,7 @@ use crate::entities::customer::ActiveModel;
 use crate::entities::prelude::Customer as Cust;
 #[cfg(feature = "process")]
 use crate::methods::convert_addr_to_geo;
-use crate::methods::{Address, ContactInformation, Id, NoteList};
+use crate::methods::{Address, ContactInformation, Id, NoteList, Transaction, EXPORT_VERSION};
 use crate::{methods::Error, ContactInformationInput, Session};
 #[cfg(feature = "process")]
 use sea_orm::QueryFilter;
@@ -80,6 +80,16 @@ pub struct CustomerWithTransactionsOut {
     pub transactions: Option<String>,
 }
 
+/// Everything held about a customer, as returned to them on request.
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
+pub struct CustomerExport {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub customer: Customer,
+    pub transactions: Vec<Transaction>,
+}
+
 #[cfg(feature = "types")]
 #[derive(Serialize, Deserialize, Clone, JsonSchema, Validate)]
 pub struct CustomerInput {
@@ -186,7 +196,7 @@ impl Customer {
     ) -> Result<Vec<Customer>, Error> {
         let res = customer::Entity::find()
             .filter(customer::Column::TenantId.eq(session.tenant_id))
-            .having(
+            .filter(
                 Expr::expr(Func::lower(Expr::col(customer::Column::Name)))
                     .like(format!("%{}%", name)),
             )
@@ -214,7 +224,7 @@ impl Customer {
     ) -> Result<Vec<Customer>, Error> {
         let res = customer::Entity::find()
             .filter(customer::Column::TenantId.eq(session.tenant_id))
-            .having(customer::Column::Contact.contains(value))
+            .filter(customer::Column::Contact.contains(value))
             .limit(25)
             .all(db)
             .await?;
@@ -253,6 +263,63 @@ impl Customer {
         Ok(mapped)
     }
 
+    pub async fn export(id: &str, session: Session, db: &DbConn) -> Result<CustomerExport, Error> {
+        let customer = Self::fetch_by_id(id, session.clone(), db).await?;
+        let transactions = Transaction::fetch_by_client_id(id, session, db)
+            .await?
+            .into_iter()
+            .filter(|transaction| transaction.customer.customer_id == id)
+            .collect();
+
+        Ok(CustomerExport {
+            version: EXPORT_VERSION,
+            exported_at: Utc::now(),
+            customer,
+            transactions,
+        })
+    }
+
+    /// Scrubs the customer's personal details, along with their contact
+    /// information wherever it was recorded on their orders. The customer
+    /// and their transactions are kept, so balances and totals still hold.
+    pub async fn anonymise(id: &str, session: Session, db: &DbConn) -> Result<Customer, Error> {
+        let customer = Self::fetch_by_id(id, session.clone(), db).await?;
+
+        for mut transaction in Transaction::fetch_by_client_id(id, session.clone(), db).await? {
+            if transaction.customer.customer_id != id {
+                continue;
+            }
+
+            // Deliveries to the customer are recorded against a location
+            // which is not one of the tenant's stores.
+            for order in transaction.products.iter_mut() {
+                for location in [&mut order.origin, &mut order.destination] {
+                    if location.store_id.is_empty() || location.contact == customer.contact {
+                        location.contact = ContactInformation::anonymised();
+                    }
+                }
+            }
+
+            let transaction_id = transaction.id.clone();
+            Transaction::update_value(transaction, session.clone(), &transaction_id, db).await?;
+        }
+
+        Customer {
+            name: String::new(),
+            contact: ContactInformation::anonymised(),
+            customer_notes: vec![],
+            special_pricing: String::new(),
+            accepts_marketing: false,
+            updated_at: Utc::now(),
+            ..customer
+        }
+        .into_active(session.tenant_id.clone())
+        .update(db)
+        .await?;
+
+        Self::fetch_by_id(id, session, db).await
+    }
+
     /// Generate and insert a default customer.
     pub async fn generate(session: Session, db: &DbConn) -> Result<Customer, Error> {
         let cust = example_customer();
@@ -299,8 +366,6 @@ impl Customer {
                     ..cust.contact
                 }));
 
-                println!("Have active model: {:?}", model);
-
                 model.update(db).await?;
 
                 Self::fetch_by_id(id, session, db).await
@@ -337,8 +402,6 @@ impl Customer {
                     ..cust.contact
                 }));
 
-                println!("Have active model: {:?}", model);
-
                 model.update(db).await?;
 
                 Self::fetch_by_id(id, session, db).await
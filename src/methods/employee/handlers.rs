@@ -1,19 +1,23 @@
 use crate::catchers::Validated;
+use crate::config::AppConfig;
 use crate::entities::session;
-use crate::guards::Convert;
+use crate::guards::{Convert, RequestSpan};
+use crate::metrics::LOGIN_ATTEMPTS;
 use crate::methods::{cookie_status_wrapper, Error, ErrorResponse, History, Name};
 use crate::pool::{Db, InternalDb};
+use crate::tokens::SignedSession;
 use crate::SessionVariant;
 use crate::{
     check_permissions, create_cookie, example_employee, tenants, Auth, AuthenticationLog, Customer,
-    Kiosk, LogRequest, Session,
+    Kiosk, LogRequest, Mfa, Session, SessionSummary, Tenant,
 };
-use chrono::{Days, Duration as ChronoDuration, Utc};
+use chrono::{Days, Utc};
 use okapi::openapi3::OpenApi;
 use rocket::get;
 use rocket::http::CookieJar;
 use rocket::post;
 use rocket::serde::json::Json;
+use rocket::State;
 use rocket_db_pools::Connection;
 use rocket_okapi::settings::OpenApiSettings;
 use rocket_okapi::{openapi, openapi_get_routes_spec};
@@ -41,27 +45,30 @@ pub fn documented_routes(settings: &OpenApiSettings) -> (Vec<rocket::Route>, Ope
         generate,
         auth,
         get_status,
-        logout
+        logout,
+        get_sessions,
+        get_sessions_for,
+        revoke_session,
+        revoke_all_sessions,
+        revoke_all_sessions_for
     ]
 }
 
 #[openapi(tag = "Employee")]
 #[get("/")]
-pub async fn whoami(session: Session) -> Convert<Employee> {
+pub async fn whoami(db: InternalDb, session: Session) -> Convert<Employee> {
     check_permissions!(session.clone(), Action::FetchEmployee);
-    Ok(session.employee).into()
+
+    // A signed session only carries a snapshot of the employee.
+    let id = session.employee.id.clone();
+    Employee::fetch_by_id(&id, session, &db.0).await.into()
 }
 
 #[openapi(tag = "Employee")]
 #[get("/<id>")]
 pub async fn get(db: InternalDb, id: &str, session: Session) -> Convert<Employee> {
     check_permissions!(session.clone(), Action::FetchEmployee);
-
-    if session.employee.id == id {
-        Ok(session.employee).into()
-    } else {
-        Employee::fetch_by_id(id, session, &db.0).await.into()
-    }
+    Employee::fetch_by_id(id, session, &db.0).await.into()
 }
 
 #[openapi(tag = "Employee")]
@@ -148,60 +155,180 @@ async fn update_by_input(
 #[openapi(tag = "Employee")]
 #[post("/logout")]
 pub async fn logout(cookies: &CookieJar<'_>,) -> Result<(), Error> {
+    SignedSession::remove(cookies);
+
     match cookies.get("os-stock-key") {
         Some(cookie) => Ok(cookies.remove(cookie.clone())),
         None => Err(ErrorResponse::create_error("Cookie not found."))
     }
 }
 
+/// Lists the active sessions of the current employee.
+#[openapi(tag = "Employee")]
+#[get("/sessions")]
+pub async fn get_sessions(db: InternalDb, session: Session) -> Convert<Vec<SessionSummary>> {
+    let employee_id = session.employee.id.clone();
+    Session::fetch_active(&employee_id, session, &db.0)
+        .await
+        .into()
+}
+
+#[openapi(tag = "Employee")]
+#[get("/sessions/<employee_id>")]
+pub async fn get_sessions_for(
+    db: InternalDb,
+    employee_id: &str,
+    session: Session,
+) -> Convert<Vec<SessionSummary>> {
+    check_permissions!(session.clone(), Action::AccessAdminPanel);
+    Session::fetch_active(employee_id, session, &db.0)
+        .await
+        .into()
+}
+
+/// Revokes a session (and the tokens refreshed from it), employees may
+/// revoke their own sessions whilst administrators may revoke any.
+#[openapi(tag = "Employee")]
+#[post("/sessions/revoke/<id>")]
+pub async fn revoke_session(db: InternalDb, id: &str, session: Session) -> Convert<u64> {
+    let employee_id = session.employee.id.clone();
+
+    let owner = if session.clone().has_permission(Action::AccessAdminPanel) {
+        None
+    } else {
+        Some(employee_id.as_str())
+    };
+
+    Session::revoke(id, owner, session, &db.0).await.into()
+}
+
+/// Logs the current employee out of every device.
+#[openapi(tag = "Employee")]
+#[post("/sessions/revoke_all")]
+pub async fn revoke_all_sessions(
+    db: InternalDb,
+    session: Session,
+    cookies: &CookieJar<'_>,
+) -> Convert<u64> {
+    let employee_id = session.employee.id.clone();
+    cookies.remove("os-stock-key");
+    SignedSession::remove(cookies);
+
+    Session::revoke_all(&employee_id, session, &db.0)
+        .await
+        .into()
+}
+
+#[openapi(tag = "Employee")]
+#[post("/sessions/revoke_all/<employee_id>")]
+pub async fn revoke_all_sessions_for(
+    db: InternalDb,
+    employee_id: &str,
+    session: Session,
+) -> Convert<u64> {
+    check_permissions!(session.clone(), Action::AccessAdminPanel);
+    Session::revoke_all(employee_id, session, &db.0)
+        .await
+        .into()
+}
+
+/// Completes a login once the password has been verified. Enrolled
+/// employees must also present a valid `mfa_code`, and employees whom the
+/// tenant's MFA policy covers but who have not enrolled are issued a
+/// session without the protected actions.
+async fn start_session(
+    db: &InternalDb,
+    cookies: &CookieJar<'_>,
+    config: &AppConfig,
+    input: &Auth,
+    mut employee: Employee,
+) -> Result<String, Error> {
+    let tenant_data: Option<tenants::Model> =
+        tenants::Entity::find_by_id(input.tenant_id.clone())
+            .one(&db.0)
+            .await?;
+
+    let tenant: Tenant = match tenant_data {
+        Some(tenant) => tenant.into(),
+        None => return Err(ErrorResponse::create_error("Tenant does not exist.")),
+    };
+
+    if tenant.is_suspended() {
+        return Err(ErrorResponse::custom_unauthorized("Tenant is suspended."));
+    }
+
+    let access_ttl = tenant
+        .settings
+        .sessions
+        .access_ttl(config.sessions.access_ttl());
+
+    let restricted =
+        Mfa::authorise(&employee, &tenant.tenant_id, input.mfa_code.as_deref(), &db.0).await?;
+    employee.restrict(&restricted);
+
+    // User is authenticated, lets give them an API key to work with...
+    let api_key = Uuid::new_v4().to_string();
+    let session_id = Uuid::new_v4().to_string();
+    let exp = Utc::now().checked_add_signed(access_ttl).unwrap();
+
+    session::Entity::insert(session::ActiveModel {
+        id: Set(session_id.to_string()),
+        key: Set(api_key.clone()),
+        employee_id: Set(employee.id.to_string()),
+        expiry: Set(exp.naive_utc()),
+        tenant_id: Set(tenant.tenant_id.clone()),
+        variant: Set(json!(SessionVariant::AccessToken)),
+        kiosk_id: Set(Some(input.kiosk_id.clone())),
+        created_at: Set(Some(Utc::now().naive_utc())),
+        family_id: Set(Some(session_id.to_string())),
+        consumed_at: Set(None),
+        restricted_actions: Set((!restricted.is_empty()).then(|| json!(restricted))),
+    })
+    .exec(&db.0)
+    .await?;
+
+    if config.sessions.stateless {
+        SignedSession::new(&session_id, &tenant.tenant_id, employee, exp).assign(cookies);
+    }
+
+    cookies.add(create_cookie(api_key.clone(), access_ttl));
+    Ok(api_key)
+}
+
 #[openapi(tag = "Employee")]
 #[post("/auth/<id>", data = "<input_data>")]
 pub async fn auth(
     db: InternalDb,
     input_data: Validated<Json<Auth>>,
     cookies: &CookieJar<'_>,
+    span: RequestSpan,
+    config: &State<AppConfig>,
     id: &str,
 ) -> Result<Json<String>, Error> {
     let input = input_data.data();
+    span.record_kiosk(&input.kiosk_id);
     let default_session = Session::default_with_tenant(input.tenant_id.clone());
 
-    let verified = Employee::verify(id, default_session, &input.pass, &db.0).await?;
+    let verified = Employee::verify(id, default_session.clone(), &input.pass, &db.0)
+        .await
+        .inspect_err(|_| LOGIN_ATTEMPTS.with_label_values(&["failure"]).inc())?;
 
     match verified {
-        false => Err(ErrorResponse::custom_unauthorized(
-            "Invalid password or id.",
-        )),
+        false => {
+            LOGIN_ATTEMPTS.with_label_values(&["failure"]).inc();
+            Err(ErrorResponse::custom_unauthorized(
+                "Invalid password or id.",
+            ))
+        }
         true => {
-            // User is authenticated, lets give them an API key to work with...
-            let api_key = Uuid::new_v4().to_string();
-            let session_id = Uuid::new_v4().to_string();
-            let exp = Utc::now()
-                .checked_add_signed(ChronoDuration::minutes(10))
-                .unwrap();
-
-            let tenant_data: Option<tenants::Model> =
-                tenants::Entity::find_by_id(input.tenant_id.clone())
-                    .one(&db.0)
-                    .await?;
-
-            match tenant_data {
-                Some(data) => {
-                    session::Entity::insert(session::ActiveModel {
-                        id: Set(session_id.to_string()),
-                        key: Set(api_key.clone()),
-                        employee_id: Set(id.to_string()),
-                        expiry: Set(exp.naive_utc()),
-                        tenant_id: Set(data.tenant_id),
-                        variant: Set(json!(SessionVariant::AccessToken)),
-                    })
-                    .exec(&db.0)
-                    .await?;
-
-                    cookies.add(create_cookie(api_key.clone()));
-                    Ok(Json(api_key))
-                }
-                None => Err(ErrorResponse::create_error("Tenant does not exist.")),
-            }
+            let employee = Employee::fetch_by_id(id, default_session, &db.0).await?;
+
+            let api_key = start_session(&db, cookies, config, &input, employee)
+                .await
+                .inspect_err(|_| LOGIN_ATTEMPTS.with_label_values(&["failure"]).inc())?;
+
+            LOGIN_ATTEMPTS.with_label_values(&["success"]).inc();
+            Ok(Json(api_key))
         }
     }
 }
@@ -212,53 +339,38 @@ pub async fn auth_rid(
     db: InternalDb,
     input_data: Validated<Json<Auth>>,
     cookies: &CookieJar<'_>,
+    span: RequestSpan,
+    config: &State<AppConfig>,
     rid: &str,
 ) -> Result<Json<String>, Error> {
     let input = input_data.data();
+    span.record_kiosk(&input.kiosk_id);
     let session = Session::default_with_tenant(input.tenant_id.clone());
 
     match Employee::verify_with_rid(rid, session.clone(), &input.pass, &db.0).await {
         Ok(data) => {
+            let employee_id = data.id.to_string();
+            let result = start_session(&db, cookies, config, &input, data).await;
+
             let auth_log = AuthenticationLog {
-                employee_id: data.id.to_string(),
-                successful: true,
+                employee_id,
+                successful: result.is_ok(),
+                override_of: None,
             };
             Kiosk::auth_log(&input.kiosk_id, session.clone(), auth_log, &db.0).await?;
 
-            let api_key = Uuid::new_v4().to_string();
-            let session_id = Uuid::new_v4().to_string();
-            let exp = Utc::now()
-                .checked_add_signed(ChronoDuration::minutes(10))
-                .unwrap();
-
-            let tenant_data: Option<tenants::Model> =
-                tenants::Entity::find_by_id(input.tenant_id.clone())
-                    .one(&db.0)
-                    .await?;
-
-            match tenant_data {
-                Some(tenant) => {
-                    session::Entity::insert(session::ActiveModel {
-                        id: Set(session_id.to_string()),
-                        key: Set(api_key.clone()),
-                        employee_id: Set(data.id.to_string()),
-                        expiry: Set(exp.naive_utc()),
-                        tenant_id: Set(tenant.tenant_id),
-                        variant: Set(json!(SessionVariant::AccessToken)),
-                    })
-                    .exec(&db.0)
-                    .await?;
-
-                    cookies.add(create_cookie(api_key.clone()));
-                    Ok(Json(api_key))
-                }
-                None => Err(ErrorResponse::create_error("Tenant does not exist.")),
-            }
+            let outcome = if result.is_ok() { "success" } else { "failure" };
+            LOGIN_ATTEMPTS.with_label_values(&[outcome]).inc();
+
+            result.map(Json)
         }
         Err(err) => {
+            LOGIN_ATTEMPTS.with_label_values(&["failure"]).inc();
+
             let auth_log = AuthenticationLog {
                 employee_id: rid.to_string(),
                 successful: false,
+                override_of: None,
             };
             Kiosk::auth_log(&input.kiosk_id, session.clone(), auth_log, &db.0).await?;
 
@@ -25,6 +25,10 @@ pub struct Auth {
     pub pass: String,
     pub kiosk_id: String,
     pub tenant_id: String,
+    /// Required once the employee has enrolled in MFA, either a current
+    /// TOTP code or a recovery code.
+    #[serde(default)]
+    pub mfa_code: Option<String>,
 }
 
 #[cfg(feature = "types")]
@@ -82,9 +86,15 @@ pub enum Action {
     FetchEmployee,
 
     CreateTransaction,
+    /// Void a transaction, which is kept rather than deleted.
     DeleteTransaction,
     ModifyTransaction,
     FetchTransaction,
+    /// Approve a sensitive till action on another employee's session.
+    AuthoriseOverride,
+    /// Give discounts, the authority being the greatest discount permitted
+    /// as a percentage of the price it applies to.
+    ApplyDiscount,
 
     CreateProduct,
     DeleteProduct,
@@ -120,8 +130,11 @@ pub enum Action {
 pub fn all_actions() -> Vec<Access<Action>> {
     all::<Action>()
         .map(|x| Access {
+            authority: match x {
+                Action::ApplyDiscount => 100,
+                _ => 1,
+            },
             action: x,
-            authority: 1,
         })
         .collect::<Vec<_>>()
 }
@@ -130,11 +143,19 @@ pub fn all_actions() -> Vec<Access<Action>> {
 /// Stores a password hash, signed as a key using the users login ID.
 /// Upon logging in using a client portal, the pre-sign object is signed using the provided ID -
 /// if the hash matches that which is given, authentication can be approved.
-#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema, Validate)]
+#[derive(Serialize, Deserialize, Clone, JsonSchema, Validate)]
 pub struct EmployeeAuth {
     pub hash: String,
 }
 
+impl fmt::Debug for EmployeeAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EmployeeAuth")
+            .field("hash", &"<redacted>")
+            .finish()
+    }
+}
+
 #[cfg(feature = "types")]
 #[derive(Serialize, Deserialize, Clone, JsonSchema, Validate)]
 pub struct EmployeeInput {
@@ -188,8 +209,16 @@ use argon2::{self, Config};
 use rand::Rng;
 use schemars::JsonSchema;
 use sea_orm::QueryOrder;
+use tracing::{debug, warn};
 use validator::Validate;
 
+impl Employee {
+    /// Withholds the given actions from the employee's permissions.
+    pub fn restrict(&mut self, actions: &[Action]) {
+        self.level.retain(|access| !actions.contains(&access.action));
+    }
+}
+
 #[cfg(feature = "methods")]
 impl Employee {
     pub async fn insert(
@@ -248,20 +277,18 @@ impl Employee {
         let mut valid_user: Option<Employee> = None;
 
         for employee in employee {
-            println!("Validating employee");
-
             let is_valid = argon2::verify_encoded(&employee.auth.hash, pass.as_bytes())
                 .map_err(|e| DbErr::RecordNotFound(e.to_string()))?;
 
-            println!(
-                "Found employee is {}",
-                if is_valid { "Valid" } else { "Invalid" }
-            );
+            debug!(employee_id = %employee.id, is_valid, "validated employee credentials");
 
             if is_valid && valid_user.is_none() {
                 valid_user = Some(employee);
             } else if is_valid {
-                println!("User with same rid and password exists. Unsure which one to pass - insufficient information.")
+                warn!(
+                    rid,
+                    "user with same rid and password exists, unsure which one to pass"
+                )
             }
         }
 
@@ -306,7 +333,7 @@ impl Employee {
         db: &DbConn,
     ) -> Result<Vec<Employee>, Error> {
         let res = employee::Entity::find()
-            .having(employee::Column::Rid.contains(rid))
+            .filter(employee::Column::Rid.contains(rid))
             .filter(employee::Column::TenantId.eq(session.tenant_id))
             .limit(25)
             .all(db)
@@ -323,7 +350,7 @@ impl Employee {
         db: &DbConn,
     ) -> Result<Vec<Employee>, Error> {
         let res = employee::Entity::find()
-            .having(employee::Column::Name.contains(name))
+            .filter(employee::Column::Name.contains(name))
             .filter(employee::Column::TenantId.eq(session.tenant_id))
             .limit(25)
             .all(db)
@@ -340,7 +367,7 @@ impl Employee {
         db: &DbConn,
     ) -> Result<Vec<Employee>, Error> {
         let res = employee::Entity::find()
-            .having(employee::Column::Name.eq(name))
+            .filter(employee::Column::Name.eq(name))
             .filter(employee::Column::TenantId.eq(session.tenant_id))
             .limit(25)
             .all(db)
@@ -357,7 +384,7 @@ impl Employee {
         db: &DbConn,
     ) -> Result<Vec<Employee>, Error> {
         let res = employee::Entity::find()
-            .having(employee::Column::Level.eq(level))
+            .filter(employee::Column::Level.eq(level))
             .filter(employee::Column::TenantId.eq(session.tenant_id))
             .limit(25)
             .all(db)
@@ -630,6 +657,10 @@ pub fn example_employee() -> EmployeeInput {
                 action: Action::CreateTransaction,
                 authority: 1,
             },
+            Access {
+                action: Action::ApplyDiscount,
+                authority: 10,
+            },
             Access {
                 action: Action::CreateStore,
                 authority: 0,
@@ -1,19 +1,21 @@
 use crate::catchers::Validated;
+use crate::config::AppConfig;
 use crate::guards::Convert;
 use crate::pool::InternalDb;
-use crate::session::ActiveModel;
+use crate::tokens::SignedSession;
 use crate::ContactInformationInput;
 use crate::{
     all_actions, check_permissions, create_cookie, example_employee,
     methods::{
-        cookie_status_wrapper, Action, Address, Customer, Employee, Error, ErrorResponse, Product,
+        cookie_status_wrapper, restricted_actions, Action, Address, Customer, Employee, Error, ErrorResponse, Product,
         Promotion, Session, Store, Transaction,
     },
     pool::Db,
-    session, AccountType, All, Distance, EmployeeInput, Kiosk, NewTenantInput, NewTenantResponse,
-    SessionRaw, SessionVariant, Tenant, TenantSettings,
+    session, AccountType, All, ApiKey, ApiKeyInput, Distance, EmployeeInput, Kiosk, NewTenantInput, NewTenantResponse,
+    RefreshedSession, SessionRaw, SessionVariant, Tenant, TenantSettings,
 };
-use chrono::{Days, Duration, Utc};
+use chrono::{DateTime, Days, Utc};
+use enum_iterator::all;
 use geo::point;
 use geo::VincentyDistance;
 use okapi::openapi3::OpenApi;
@@ -21,13 +23,14 @@ use photon_geocoding::{
     filter::{ForwardFilter, PhotonLayer},
     LatLon, PhotonApiClient, PhotonFeature,
 };
-use rocket::{get, http::CookieJar, post, serde::json::Json};
+use rocket::{get, http::CookieJar, post, serde::json::Json, State};
 use rocket_db_pools::Connection;
 use rocket_okapi::settings::OpenApiSettings;
 use rocket_okapi::{openapi, openapi_get_routes_spec};
-use sea_orm::{ActiveModelTrait, ColumnTrait, DbErr, EntityTrait, QueryFilter, QuerySelect, Set};
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, DbConn, EntityTrait, QueryFilter, Set};
 use serde_json::json;
-use std::env;
+use tracing::{debug, warn};
 use uuid::Uuid;
 
 pub fn documented_routes(_settings: &OpenApiSettings) -> (Vec<rocket::Route>, OpenApi) {
@@ -47,8 +50,11 @@ pub fn documented_routes(_settings: &OpenApiSettings) -> (Vec<rocket::Route>, Op
 /// This route does not require authentication, but is not enabled in release mode.
 #[openapi(tag = "Helpers")]
 #[post("/generate")]
-pub async fn generate_template(db: InternalDb) -> Result<Json<All>, Error> {
-    if env::var("DEMO").is_err() || env::var("DEMO").unwrap() == "0" {
+pub async fn generate_template(
+    db: InternalDb,
+    config: &State<AppConfig>,
+) -> Result<Json<All>, Error> {
+    if !config.demo {
         return Err(Error::DemoDisabled(
             "OpenStock is not in DEMO mode.".to_string(),
         ));
@@ -149,6 +155,8 @@ pub async fn new_tenant(
         registration_date: Utc::now(),
         created_at: Utc::now(),
         updated_at: Utc::now(),
+        suspended_at: None,
+        purge_after: None,
     };
 
     Tenant::insert(tenant, &db.0).await?;
@@ -182,21 +190,33 @@ pub async fn new_tenant(
     let employee_insert_result =
         Employee::insert(employee, &db.0, session.clone(), None, Some(employee_id)).await?;
 
-    session::Entity::insert::<ActiveModel>(session.clone().into())
-        .exec(&db.0)
-        .await?;
+    // Issue the tenant's first API key, on behalf of its primary employee
+    let api_key = ApiKey::insert(
+        ApiKeyInput {
+            name: "Primary".to_string(),
+            scopes: all::<Action>().collect(),
+            expires_at: None,
+        },
+        session,
+        &db.0,
+    )
+    .await?;
 
     Ok(Json(NewTenantResponse {
         tenant_id,
-        api_key: session.key,
+        api_key: api_key.key,
         employee_id: employee_insert_result.last_insert_id,
     }))
 }
 
 #[openapi(tag = "Helpers")]
 #[get("/session/<key>")]
-pub async fn assign_session_cookie(key: &str, cookies: &CookieJar<'_>) -> Result<(), Error> {
-    cookies.add(create_cookie(key.to_string()));
+pub async fn assign_session_cookie(
+    key: &str,
+    cookies: &CookieJar<'_>,
+    config: &State<AppConfig>,
+) -> Result<(), Error> {
+    cookies.add(create_cookie(key.to_string(), config.sessions.access_ttl()));
     Ok(())
 }
 
@@ -213,7 +233,7 @@ pub fn convert_addr_to_geo(address: &str) -> Result<Address, Error> {
 
     match result.get(0) {
         Some(loc) => {
-            println!("{:?}", loc);
+            debug!(lat = loc.coords.lat, lon = loc.coords.lon, "geocoded address");
 
             Ok(Address {
                 street: format!(
@@ -356,23 +376,41 @@ pub async fn distance_to_stores_from_store(
 pub async fn refresh_token_create(
     conn: Connection<Db>,
     cookies: &CookieJar<'_>,
+    config: &State<AppConfig>,
 ) -> Result<Json<String>, Error> {
     let db = conn.into_inner();
     let session = cookie_status_wrapper(&db, cookies).await?;
 
     let token_key = Uuid::new_v4().to_string();
     let token_id = Uuid::new_v4().to_string();
+    let lifetimes = Tenant::fetch_by_id(&session.tenant_id, &db)
+        .await?
+        .settings
+        .sessions;
+
+    // Any actions withheld from the access token are withheld from those
+    // it is refreshed into, which remain bound to the same kiosk.
+    let access = session::Entity::find_by_id(session.id.clone())
+        .one(&db)
+        .await?;
+    let kiosk_id = access.as_ref().and_then(|access| access.kiosk_id.clone());
+    let restricted = access.and_then(|access| access.restricted_actions);
 
     match session::Entity::insert(session::ActiveModel {
         id: Set(token_id),
         key: Set(token_key.clone()),
-        variant: Set(json!(SessionVariant::RefreshToken(session.id))),
+        variant: Set(json!(SessionVariant::RefreshToken(session.id.clone()))),
         employee_id: Set(session.employee.id),
         tenant_id: Set(session.tenant_id),
         expiry: Set(Utc::now()
-            .checked_add_signed(Duration::days(7))
+            .checked_add_signed(lifetimes.refresh_ttl(config.sessions.refresh_ttl()))
             .unwrap()
             .naive_utc()),
+        kiosk_id: Set(kiosk_id),
+        created_at: Set(Some(Utc::now().naive_utc())),
+        family_id: Set(Some(session.id)),
+        consumed_at: Set(None),
+        restricted_actions: Set(restricted),
     })
     .exec(&db)
     .await
@@ -384,104 +422,204 @@ pub async fn refresh_token_create(
     }
 }
 
+/// Revokes the family of a refresh token presented once already consumed.
+async fn reject_reuse(session_id: &str, family_id: &str, db: &DbConn) -> Error {
+    warn!(
+        session_id = %session_id,
+        family_id = %family_id,
+        "refresh token reused, revoking session family"
+    );
+
+    match Session::revoke_family(family_id, db).await {
+        Ok(_) => ErrorResponse::custom_unauthorized("Refresh token has already been used."),
+        Err(error) => error,
+    }
+}
+
 /// Sending a refresh token through to this route,
 /// will create a new access token and assign it
 /// as server-assigned current cookie.
 ///
-///
+/// Refresh tokens are single use, the token presented is consumed and
+/// replaced by the returned `refresh_key`. Presenting a consumed token
+/// again is treated as theft, revoking every session in its family.
 #[openapi(tag = "Helpers")]
 #[get("/refresh_token/<token>")]
 pub async fn refresh_token_refresh(
     conn: Connection<Db>,
     token: &str,
     cookies: &CookieJar<'_>,
-) -> Result<Json<String>, Error> {
+    config: &State<AppConfig>,
+) -> Result<Json<RefreshedSession>, Error> {
     let db = conn.into_inner();
 
-    let found_token = crate::entities::session::Entity::find()
-        .having(session::Column::Key.eq(token))
+    let found_token = session::Entity::find()
+        .filter(session::Column::Key.eq(token))
         .one(&db)
         .await?;
 
-    match found_token {
-        Some(e) => {
-            let decoded_token: SessionRaw = e.into();
-            println!("GOT TOKEN: {:?}", decoded_token);
-
-            match decoded_token.variant {
-                SessionVariant::AccessToken => Err(ErrorResponse::create_error(
-                    "Expected a Refresh Token, got an Access Token",
-                )),
-                SessionVariant::RefreshToken(access_reference) => {
-                    let access_token = crate::entities::session::Entity::find()
-                        .having(session::Column::Key.eq(access_reference.clone()))
-                        .one(&db)
-                        .await?;
-
-                    match access_token {
-                        Some(token) => {
-                            println!("THEN GOT ACCESS TOKEN: {:?}", token);
-
-                            let decoded_token_2: SessionRaw = token.into();
-                            let api_key = Uuid::new_v4().to_string();
-
-                            // Return an updated access token (we update
-                            // to optimize the avoidance of a dangling token)
-                            match session::Entity::update(session::ActiveModel {
-                                id: Set(decoded_token_2.id.to_string()),
-                                key: Set(api_key.clone()),
-                                variant: Set(json!(SessionVariant::AccessToken)),
-                                ..Default::default()
-                            })
-                            .exec(&db)
-                            .await
-                            {
-                                Ok(_) => {
-                                    // Assign the cookie
-                                    cookies.add(create_cookie(api_key.clone()));
-
-                                    Ok(Json(api_key))
-                                }
-                                Err(reason) => Err(ErrorResponse::db_err(reason)),
-                            }
-                        }
-                        None => {
-                            println!("ACCESS TOKEN EXPIRED! LET'S GENERATE ONE! {:?}", token);
-
-                            let new_access_key = Uuid::new_v4().to_string();
-
-                            let exp = Utc::now()
-                                .checked_add_signed(Duration::minutes(10))
-                                .unwrap();
-
-                            let access_token_to_insert = session::ActiveModel {
-                                id: Set(access_reference),
-                                key: Set(new_access_key.clone()),
-                                employee_id: Set(decoded_token.employee_id),
-                                expiry: Set(exp.naive_utc()),
-                                tenant_id: Set(decoded_token.tenant_id),
-                                variant: Set(json!(SessionVariant::AccessToken)),
-                            };
-
-                            match session::Entity::insert(access_token_to_insert)
-                                .exec(&db)
-                                .await
-                            {
-                                Ok(_) => {
-                                    // Assign the cookie
-                                    cookies.add(create_cookie(new_access_key.clone()));
-
-                                    Ok(Json(new_access_key))
-                                }
-                                Err(reason) => Err(ErrorResponse::db_err(reason)),
-                            }
-                        }
-                    }
-                }
-            }
+    let found_token = match found_token {
+        Some(found) => found,
+        None => {
+            return Err(ErrorResponse::custom_unauthorized(
+                "Refresh token does not exist.",
+            ))
         }
-        None => Err(ErrorResponse::db_err(DbErr::RecordNotFound(
-            token.to_string(),
+    };
+
+    let family_id = found_token.family_id.clone();
+    let consumed = found_token.consumed_at.is_some();
+    let restricted = restricted_actions(&found_token);
+    let restricted_json = found_token.restricted_actions.clone();
+    let kiosk_id = found_token.kiosk_id.clone();
+    let decoded_token: SessionRaw = found_token.into();
+
+    let access_reference = match decoded_token.variant {
+        SessionVariant::AccessToken | SessionVariant::ApiKey(_) => {
+            return Err(ErrorResponse::create_error(
+                "Expected a Refresh Token, got an Access Token",
+            ))
+        }
+        SessionVariant::RefreshToken(access_reference) => access_reference,
+    };
+
+    let family_id = family_id.unwrap_or_else(|| access_reference.clone());
+
+    if consumed {
+        return Err(reject_reuse(&decoded_token.id, &family_id, &db).await);
+    }
+
+    if decoded_token.expiry < Utc::now() {
+        return Err(ErrorResponse::custom_unauthorized(
+            "Refresh token has expired.",
+        ));
+    }
+
+    let tenant = Tenant::fetch_by_id(&decoded_token.tenant_id, &db).await?;
+
+    if tenant.is_suspended() {
+        return Err(ErrorResponse::custom_unauthorized("Tenant is suspended."));
+    }
+
+    debug!(session_id = %decoded_token.id, "refreshing access token");
+
+    let now = Utc::now();
+    let access_key = Uuid::new_v4().to_string();
+    let refresh_key = Uuid::new_v4().to_string();
+    let access_ttl = tenant
+        .settings
+        .sessions
+        .access_ttl(config.sessions.access_ttl());
+    let access_expiry = now.checked_add_signed(access_ttl).unwrap().naive_utc();
+
+    // Consume the presented token, the replacement inherits its expiry so
+    // rotation does not extend the lifetime of the family. Only one of any
+    // concurrent refreshes can consume it, the others are treated as reuse.
+    let consumption = session::Entity::update_many()
+        .col_expr(
+            session::Column::ConsumedAt,
+            Expr::value(Some(now.naive_utc())),
+        )
+        .filter(session::Column::Id.eq(decoded_token.id.clone()))
+        .filter(session::Column::ConsumedAt.is_null())
+        .exec(&db)
+        .await?;
+
+    if consumption.rows_affected == 0 {
+        return Err(reject_reuse(&decoded_token.id, &family_id, &db).await);
+    }
+
+    session::Entity::insert(session::ActiveModel {
+        id: Set(Uuid::new_v4().to_string()),
+        key: Set(refresh_key.clone()),
+        employee_id: Set(decoded_token.employee_id.clone()),
+        expiry: Set(decoded_token.expiry.naive_utc()),
+        tenant_id: Set(decoded_token.tenant_id.clone()),
+        variant: Set(json!(SessionVariant::RefreshToken(
+            access_reference.clone()
         ))),
+        kiosk_id: Set(kiosk_id.clone()),
+        created_at: Set(Some(now.naive_utc())),
+        family_id: Set(Some(family_id.clone())),
+        consumed_at: Set(None),
+        restricted_actions: Set(restricted_json.clone()),
+    })
+    .exec(&db)
+    .await?;
+
+    let access_token = session::Entity::find_by_id(access_reference.clone())
+        .one(&db)
+        .await?;
+
+    match access_token {
+        Some(_) => {
+            // Return an updated access token (we update
+            // to optimize the avoidance of a dangling token)
+            let renewal = session::Entity::update_many()
+                .set(session::ActiveModel {
+                    key: Set(access_key.clone()),
+                    expiry: Set(access_expiry),
+                    variant: Set(json!(SessionVariant::AccessToken)),
+                    ..Default::default()
+                })
+                .filter(session::Column::Id.eq(access_reference))
+                .exec(&db)
+                .await?;
+
+            // The family was revoked by a concurrent reuse of the token.
+            if renewal.rows_affected == 0 {
+                return Err(ErrorResponse::custom_unauthorized(
+                    "Refresh token has already been used.",
+                ));
+            }
+        }
+        None => {
+            debug!(
+                session_id = %access_reference,
+                "access token expired, issuing a new one"
+            );
+
+            session::Entity::insert(session::ActiveModel {
+                id: Set(access_reference),
+                key: Set(access_key.clone()),
+                employee_id: Set(decoded_token.employee_id.clone()),
+                expiry: Set(access_expiry),
+                tenant_id: Set(decoded_token.tenant_id.clone()),
+                variant: Set(json!(SessionVariant::AccessToken)),
+                kiosk_id: Set(kiosk_id),
+                created_at: Set(Some(now.naive_utc())),
+                family_id: Set(Some(family_id.clone())),
+                consumed_at: Set(None),
+                restricted_actions: Set(restricted_json),
+            })
+            .exec(&db)
+            .await?;
+        }
+    }
+
+    // Assign the cookie
+    cookies.add(create_cookie(access_key.clone(), access_ttl));
+
+    if config.sessions.stateless {
+        let mut employee = Employee::fetch_by_id(
+            &decoded_token.employee_id,
+            Session::default_with_tenant(decoded_token.tenant_id.clone()),
+            &db,
+        )
+        .await?;
+        employee.restrict(&restricted);
+
+        SignedSession::new(
+            &family_id,
+            &decoded_token.tenant_id,
+            employee,
+            DateTime::from_naive_utc_and_offset(access_expiry, Utc),
+        )
+        .assign(cookies);
     }
+
+    Ok(Json(RefreshedSession {
+        access_key,
+        refresh_key,
+    }))
 }
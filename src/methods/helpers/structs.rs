@@ -37,6 +37,16 @@ pub struct NewTenantInput {
 #[derive(Serialize, Deserialize, Clone, JsonSchema, Validate)]
 pub struct NewTenantResponse {
     pub tenant_id: String,
+    /// A full API key, holding every action, for the primary employee.
+    /// It is only ever returned here.
     pub api_key: String,
     pub employee_id: String,
 }
+
+/// Returned when a refresh token is exchanged, the refresh token presented
+/// is consumed and `refresh_key` must be used for the next exchange.
+#[derive(Serialize, Deserialize, Clone, JsonSchema, Validate)]
+pub struct RefreshedSession {
+    pub access_key: String,
+    pub refresh_key: String,
+}
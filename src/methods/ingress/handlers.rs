@@ -1,55 +1,114 @@
-use crate::{
-    check_permissions, cookie_status_wrapper, methods::Action, methods::Error, Db, ErrorResponse,
-    Session,
-};
-use chrono::Utc;
+use super::{ImportJob, ShopifyExport, ShopifyImport};
+use crate::config::AppConfig;
+use crate::guards::Convert;
+use crate::pool::InternalDb;
+use crate::{check_permissions, methods::Action, methods::Error, ErrorResponse, Session};
 use okapi::openapi3::OpenApi;
+use rocket::serde::json::Json;
+use std::path::Path;
 
-use rocket::{fs::TempFile, http::CookieJar, post};
-use rocket_db_pools::Connection;
+use rocket::{fs::TempFile, get, post, State};
 use rocket_okapi::settings::OpenApiSettings;
 use rocket_okapi::{openapi, openapi_get_routes_spec};
+use tracing::info;
 
 pub fn documented_routes(settings: &OpenApiSettings) -> (Vec<rocket::Route>, OpenApi) {
-    openapi_get_routes_spec![settings: upload]
+    openapi_get_routes_spec![settings: upload, get_job, shopify]
 }
 
-/// Usage is as follows
+/// Accepts an [`ImportManifest`](super::ImportManifest), returning the job
+/// which tracks its import. Usage is as follows
 ///
 /// curl -X POST -H "Content-Type: text/plain" -d "@/to/file/location/" http://127.0.0.1:8000/api/ingress/upload
 #[openapi(tag = "Ingress")]
 #[post("/upload", format = "plain", data = "<file>")]
-async fn upload(session: Session, file: TempFile<'_>) -> Result<(), Error> {
+async fn upload(
+    db: InternalDb,
+    session: Session,
+    file: TempFile<'_>,
+    config: &State<AppConfig>,
+) -> Result<Json<ImportJob>, Error> {
     check_permissions!(session.clone(), Action::AccessAdminPanel);
-    receive_file(file, session.tenant_id).await
+
+    let job = ImportJob::create(session, &db.0).await?;
+
+    if let Err(error) = receive_file(file, &job.id, &config.ingress.directory).await {
+        ImportJob::abandon(&job.id, error.message(), &db.0).await?;
+        return Err(error);
+    }
+    info!(job_id = %job.id, "queued import job");
+
+    Ok(Json(job))
 }
 
-async fn receive_file(mut file: TempFile<'_>, tenant_id: String) -> Result<(), Error> {
-    let current_date = Utc::now().to_rfc3339();
-    let path = "./ingress/".to_string();
-    if let Err(error) = std::fs::create_dir_all(path.clone()) {
+#[openapi(tag = "Ingress")]
+#[get("/jobs/<id>")]
+async fn get_job(db: InternalDb, session: Session, id: &str) -> Convert<ImportJob> {
+    check_permissions!(session.clone(), Action::AccessAdminPanel);
+    ImportJob::fetch_by_id(id, session, &db.0).await.into()
+}
+
+/// Imports the products, customers and orders of a Shopify export. Stock
+/// levels are recorded against, and orders placed at, the store with the
+/// code `store` where one is given. Records imported before are updated
+/// rather than duplicated.
+#[openapi(tag = "Ingress")]
+#[post("/shopify?<store>", data = "<export>")]
+async fn shopify(
+    db: InternalDb,
+    session: Session,
+    store: Option<&str>,
+    export: Json<ShopifyExport>,
+) -> Result<Json<ShopifyImport>, Error> {
+    check_permissions!(session.clone(), Action::AccessAdminPanel);
+
+    let result = export.import(store, session.clone(), &db.0).await?;
+    info!(
+        tenant_id = %session.tenant_id,
+        products = result.products.created + result.products.updated,
+        customers = result.customers.created + result.customers.updated,
+        orders = result.orders.created + result.orders.updated,
+        failed = result.errors.len(),
+        "imported shopify export"
+    );
+
+    Ok(Json(result))
+}
+
+async fn receive_file(mut file: TempFile<'_>, job_id: &str, path: &Path) -> Result<(), Error> {
+    if let Err(error) = std::fs::create_dir_all(path) {
         return Err(ErrorResponse::create_error(&format!(
             "Unable to create file path, {}",
             error
         )));
     }
 
+    // The file is only given the `.os` extension the ingress worker looks
+    // for once it has been written in full.
+    let partial = path.join(format!("{}.part", job_id));
+
     match file
         // We must use `copy_to` due to:
         // https://github.com/SergioBenitez/Rocket/issues/1600
         // Where a cross-device link is made using `link`, for the persistence
         // of the file to a new location, which occurs cross-mount and thus
         // will not work.
-        .copy_to(format!("{}/{}_{}.os", path, tenant_id, current_date))
+        .copy_to(&partial)
         .await
+        .and_then(|_| std::fs::rename(&partial, path.join(format!("{}.os", job_id))))
     {
         Ok(_) => {
             //... Now we let ingress worker take over
             Ok(())
         }
-        Err(error) => Err(ErrorResponse::create_error(&format!(
-            "Unable to write file, {}",
-            error,
-        ))),
+        Err(error) => {
+            // A partial file is never picked up, so need not be kept.
+            let _ = std::fs::remove_file(&partial);
+
+            Err(ErrorResponse::create_error(&format!(
+                "Unable to write file, {}",
+                error,
+            )))
+        }
     }
 }
@@ -1,5 +1,9 @@
 #[cfg(feature = "process")]
 pub(crate) mod handlers;
+mod shopify;
+mod structs;
 
+pub use self::shopify::*;
+pub use self::structs::*;
 #[cfg(feature = "process")]
 pub use handlers::*;
@@ -0,0 +1,967 @@
+//! Imports the products, customers and orders of a Shopify store, as
+//! returned by the Shopify Admin REST API (`products.json`,
+//! `customers.json` and `orders.json`).
+//!
+//! The id of each imported record is kept as an [`ExternalReference`], so
+//! an export may be imported any number of times: records seen before are
+//! updated in place, and only new records are created.
+
+use crate::methods::ImportRowError;
+#[cfg(feature = "process")]
+use crate::methods::{Error, ErrorResponse, ExternalReference, History};
+#[cfg(feature = "process")]
+use crate::{
+    entities::{prelude::Products, products},
+    new_product, new_variant, Address, ContactInformation, Customer, CustomerType, DiscountValue,
+    Email, FulfillmentStatus, Location, MobileNumber, Note, Order, OrderStatus,
+    OrderStatusAssignment, OrderType, Payment, PaymentAction, PaymentMethod, PaymentProcessor,
+    PaymentStatus, PickStatus, Price, Processable, Product, ProductInstance, ProductPurchase,
+    ProductVisibility, Quantity, Session, Stock, Store, Transaction, TransactionCustomer,
+    TransactionType, Variant, VariantCategory,
+};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+#[cfg(feature = "process")]
+use sea_orm::{ActiveModelTrait, ColumnTrait, DbConn, EntityTrait, QueryFilter};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+#[cfg(feature = "process")]
+use std::collections::HashMap;
+#[cfg(feature = "process")]
+use uuid::Uuid;
+
+/// The `source` of every [`ExternalReference`] made by the importer.
+pub const SHOPIFY_SOURCE: &str = "shopify";
+
+/// The origin given to imported payments and their processor.
+pub const SHOPIFY_TRANSACTION: &str = "Shopify Imported Transaction";
+
+/// Shopify ids are numbers in the REST API, and strings elsewhere.
+fn shopify_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::String(id) => Ok(id),
+        Value::Number(id) => Ok(id.to_string()),
+        other => Err(serde::de::Error::custom(format!(
+            "expected a Shopify id, found {}",
+            other
+        ))),
+    }
+}
+
+fn optional_shopify_id<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::Null => Ok(None),
+        Value::String(id) => Ok(Some(id)),
+        Value::Number(id) => Ok(Some(id.to_string())),
+        other => Err(serde::de::Error::custom(format!(
+            "expected a Shopify id, found {}",
+            other
+        ))),
+    }
+}
+
+/// Shopify gives amounts as decimal strings, i.e. `"19.99"`.
+fn shopify_amount<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::Null => Ok(0.0),
+        Value::Number(amount) => Ok(amount.as_f64().unwrap_or_default() as f32),
+        Value::String(amount) if amount.is_empty() => Ok(0.0),
+        Value::String(amount) => amount
+            .parse::<f32>()
+            .map_err(|_| serde::de::Error::custom(format!("invalid amount `{}`", amount))),
+        other => Err(serde::de::Error::custom(format!(
+            "expected an amount, found {}",
+            other
+        ))),
+    }
+}
+
+/// The contents of a Shopify export. Each collection is read as returned
+/// by the Admin API, such that the responses of `products.json`,
+/// `customers.json` and `orders.json` may be merged into one document.
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
+pub struct ShopifyExport {
+    #[serde(default)]
+    pub products: Vec<ShopifyProduct>,
+    #[serde(default)]
+    pub customers: Vec<ShopifyCustomer>,
+    #[serde(default)]
+    pub orders: Vec<ShopifyOrder>,
+}
+
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct ShopifyProduct {
+    #[serde(deserialize_with = "shopify_id")]
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub body_html: Option<String>,
+    #[serde(default)]
+    pub vendor: Option<String>,
+    #[serde(default)]
+    pub handle: Option<String>,
+    /// Comma separated.
+    #[serde(default)]
+    pub tags: Option<String>,
+    /// One of `active`, `draft` or `archived`.
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub options: Vec<ShopifyOption>,
+    #[serde(default)]
+    pub variants: Vec<ShopifyVariant>,
+    #[serde(default)]
+    pub images: Vec<ShopifyImage>,
+}
+
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct ShopifyOption {
+    pub name: String,
+    #[serde(default)]
+    pub values: Vec<String>,
+}
+
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct ShopifyImage {
+    pub src: String,
+}
+
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct ShopifyVariant {
+    #[serde(deserialize_with = "shopify_id")]
+    pub id: String,
+    pub title: String,
+    #[serde(deserialize_with = "shopify_amount")]
+    pub price: f32,
+    #[serde(default)]
+    pub sku: Option<String>,
+    #[serde(default)]
+    pub barcode: Option<String>,
+    #[serde(default)]
+    pub option1: Option<String>,
+    #[serde(default)]
+    pub option2: Option<String>,
+    #[serde(default)]
+    pub option3: Option<String>,
+    #[serde(default)]
+    pub inventory_quantity: Option<f32>,
+    #[serde(default)]
+    pub weight: Option<f64>,
+    #[serde(default)]
+    pub weight_unit: Option<String>,
+}
+
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct ShopifyCustomer {
+    #[serde(deserialize_with = "shopify_id")]
+    pub id: String,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub first_name: Option<String>,
+    #[serde(default)]
+    pub last_name: Option<String>,
+    #[serde(default)]
+    pub phone: Option<String>,
+    #[serde(default)]
+    pub note: Option<String>,
+    /// Absent from the customers given within orders.
+    #[serde(default)]
+    pub accepts_marketing: Option<bool>,
+    #[serde(default)]
+    pub default_address: Option<ShopifyAddress>,
+}
+
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
+pub struct ShopifyAddress {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub address1: Option<String>,
+    #[serde(default)]
+    pub address2: Option<String>,
+    #[serde(default)]
+    pub city: Option<String>,
+    #[serde(default)]
+    pub country: Option<String>,
+    #[serde(default)]
+    pub zip: Option<String>,
+    #[serde(default)]
+    pub phone: Option<String>,
+    #[serde(default)]
+    pub latitude: Option<f64>,
+    #[serde(default)]
+    pub longitude: Option<f64>,
+}
+
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct ShopifyOrder {
+    #[serde(deserialize_with = "shopify_id")]
+    pub id: String,
+    /// The order's name as shown to the customer, i.e. `#1001`.
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub processed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub closed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub cancelled_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub cancel_reason: Option<String>,
+    pub currency: String,
+    #[serde(deserialize_with = "shopify_amount")]
+    pub total_price: f32,
+    #[serde(default)]
+    pub financial_status: Option<String>,
+    #[serde(default)]
+    pub fulfillment_status: Option<String>,
+    #[serde(default)]
+    pub note: Option<String>,
+    #[serde(default)]
+    pub payment_gateway_names: Vec<String>,
+    #[serde(default)]
+    pub customer: Option<ShopifyCustomer>,
+    #[serde(default)]
+    pub shipping_address: Option<ShopifyAddress>,
+    #[serde(default)]
+    pub line_items: Vec<ShopifyLineItem>,
+}
+
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct ShopifyLineItem {
+    #[serde(deserialize_with = "shopify_id")]
+    pub id: String,
+    #[serde(default, deserialize_with = "optional_shopify_id")]
+    pub product_id: Option<String>,
+    #[serde(default, deserialize_with = "optional_shopify_id")]
+    pub variant_id: Option<String>,
+    pub title: String,
+    #[serde(default)]
+    pub variant_title: Option<String>,
+    #[serde(default)]
+    pub sku: Option<String>,
+    pub quantity: f32,
+    #[serde(deserialize_with = "shopify_amount")]
+    pub price: f32,
+    #[serde(default, deserialize_with = "shopify_amount")]
+    pub total_discount: f32,
+}
+
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct ImportCount {
+    pub created: usize,
+    pub updated: usize,
+}
+
+/// The outcome of a Shopify import.
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct ShopifyImport {
+    pub products: ImportCount,
+    pub customers: ImportCount,
+    pub orders: ImportCount,
+    /// Records which could not be imported, by their Shopify id.
+    pub errors: Vec<ImportRowError>,
+}
+
+#[cfg(feature = "process")]
+const PRODUCT: &str = "product";
+#[cfg(feature = "process")]
+const VARIANT: &str = "variant";
+#[cfg(feature = "process")]
+const CUSTOMER: &str = "customer";
+#[cfg(feature = "process")]
+const ORDER: &str = "order";
+
+/// Shopify names the variant of a product without options `Default Title`.
+#[cfg(feature = "process")]
+const DEFAULT_TITLE: &str = "Default Title";
+
+#[cfg(feature = "process")]
+fn text(value: &Option<String>) -> String {
+    value.clone().unwrap_or_default()
+}
+
+/// Strips the markup from a product's `body_html`.
+#[cfg(feature = "process")]
+fn plain_text(html: &str) -> String {
+    let mut output = String::new();
+    let mut in_tag = false;
+
+    for character in html.chars() {
+        match character {
+            '<' => in_tag = true,
+            '>' => {
+                in_tag = false;
+                output.push(' ');
+            }
+            _ if !in_tag => output.push(character),
+            _ => {}
+        }
+    }
+
+    output.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Options are coded by position, the second value of the first option
+/// being `1-02`.
+#[cfg(feature = "process")]
+fn option_code(option: usize, value: usize) -> String {
+    format!("{}-{:02}", option + 1, value + 1)
+}
+
+#[cfg(feature = "process")]
+fn contact(
+    name: String,
+    email: &Option<String>,
+    address: &Option<ShopifyAddress>,
+) -> ContactInformation {
+    let address = address.clone().unwrap_or_default();
+
+    ContactInformation {
+        name: match name.trim().is_empty() {
+            true => text(&address.name),
+            false => name.trim().to_string(),
+        },
+        mobile: MobileNumber::from(text(&address.phone)),
+        email: Email::from(text(email)),
+        landline: String::new(),
+        address: Address {
+            street: text(&address.address1),
+            street2: text(&address.address2),
+            city: text(&address.city),
+            country: text(&address.country),
+            po_code: text(&address.zip),
+            lat: address.latitude.unwrap_or_default(),
+            lon: address.longitude.unwrap_or_default(),
+        },
+    }
+}
+
+#[cfg(feature = "process")]
+fn row_error(collection: &str, index: usize, id: &str, error: Error) -> ImportRowError {
+    ImportRowError {
+        collection: collection.to_string(),
+        index,
+        id: Some(id.to_string()),
+        message: error.message(),
+    }
+}
+
+#[cfg(feature = "process")]
+async fn fetch_product(sku: &str, session: Session, db: &DbConn) -> Result<Option<Product>, Error> {
+    let product = Products::find_by_id(sku.to_string())
+        .filter(products::Column::TenantId.eq(session.tenant_id))
+        .one(db)
+        .await?;
+
+    Ok(product.map(|p| p.into()))
+}
+
+#[cfg(feature = "process")]
+async fn local_id(
+    kind: &str,
+    external_id: &str,
+    session: Session,
+    db: &DbConn,
+) -> Result<Option<String>, Error> {
+    ExternalReference::fetch(SHOPIFY_SOURCE, kind, external_id, session, db)
+        .await
+        .map(|reference| reference.map(|r| r.local_id))
+}
+
+#[cfg(feature = "methods")]
+impl ShopifyProduct {
+    /// Creates or updates the product, yielding whether it was created.
+    /// Variants are matched by their Shopify id, and any stock is held at
+    /// `store`.
+    pub async fn import(
+        &self,
+        store: Option<&Location>,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<bool, Error> {
+        let existing = match local_id(PRODUCT, &self.id, session.clone(), db).await? {
+            Some(sku) => fetch_product(&sku, session.clone(), db).await?,
+            None => None,
+        };
+        let created = existing.is_none();
+        let mut product = existing.unwrap_or_else(|| new_product(&self.id));
+
+        product.name = self.title.clone();
+        product.name_long = self.title.clone();
+        product.company = text(&self.vendor);
+        product.description_long = text(&self.body_html);
+        product.description = plain_text(&product.description_long);
+        product.tags = text(&self.tags)
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect();
+        product.images = self.images.iter().map(|image| image.src.clone()).collect();
+        product.visible = match self.status.as_deref() {
+            Some("draft") | Some("archived") => ProductVisibility::AlwaysHidden,
+            _ => ProductVisibility::ShowWhenInStock,
+        };
+
+        // A product without options has the single option `Title`.
+        let options = match self.variants.iter().all(|v| v.title == DEFAULT_TITLE) {
+            true => vec![],
+            false => self.options.clone(),
+        };
+        product.variant_groups = options
+            .iter()
+            .enumerate()
+            .map(|(o, option)| VariantCategory {
+                category: option.name.clone(),
+                variants: option
+                    .values
+                    .iter()
+                    .enumerate()
+                    .map(|(v, value)| Variant {
+                        name: value.clone(),
+                        images: vec![],
+                        marginal_price: 0.0,
+                        variant_code: option_code(o, v),
+                        order_history: vec![],
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let mut references = vec![];
+
+        for shopify_variant in &self.variants {
+            let id = local_id(VARIANT, &shopify_variant.id, session.clone(), db)
+                .await?
+                .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+            let variant = match product.variants.iter().position(|v| v.id == id) {
+                Some(position) => &mut product.variants[position],
+                None => {
+                    let mut variant = new_variant(id.clone());
+                    variant.marginal_price = shopify_variant.price;
+                    product.variants.push(variant);
+                    product.variants.last_mut().unwrap()
+                }
+            };
+
+            variant.name = match shopify_variant.title.as_str() {
+                DEFAULT_TITLE => self.title.clone(),
+                title => title.to_string(),
+            };
+            variant.barcode = text(&shopify_variant.barcode);
+            variant.identification.sku = text(&shopify_variant.sku);
+            variant.retail_price = shopify_variant.price;
+            variant.variant_code = [
+                &shopify_variant.option1,
+                &shopify_variant.option2,
+                &shopify_variant.option3,
+            ]
+            .iter()
+            .zip(options.iter())
+            .enumerate()
+            .filter_map(|(o, (value, option))| {
+                value
+                    .as_ref()
+                    .and_then(|value| option.values.iter().position(|v| v == value))
+                    .map(|v| option_code(o, v))
+            })
+            .collect();
+
+            if let Some(weight) = shopify_variant.weight {
+                variant.stock_information.weight =
+                    format!("{}{}", weight, text(&shopify_variant.weight_unit));
+            }
+
+            if let (Some(store), Some(quantity)) = (store, shopify_variant.inventory_quantity) {
+                match variant
+                    .stock
+                    .iter_mut()
+                    .find(|stock| stock.store.store_id == store.store_id)
+                {
+                    Some(stock) => stock.quantity.quantity_sellable = quantity,
+                    None => variant.stock.push(Stock {
+                        store: store.clone(),
+                        quantity: Quantity {
+                            quantity_sellable: quantity,
+                            quantity_unsellable: 0.0,
+                            quantity_on_order: 0.0,
+                            quantity_allocated: 0.0,
+                        },
+                        lots: vec![],
+                    }),
+                }
+            }
+
+            references.push((shopify_variant.id.clone(), id));
+        }
+
+        let sku = product.sku.clone();
+        match created {
+            true => {
+                Product::insert(product, session.clone(), db).await?;
+            }
+            false => {
+                product.updated_at = Utc::now();
+                Product::update(product, session.clone(), &sku, db).await?;
+            }
+        }
+
+        ExternalReference::record(SHOPIFY_SOURCE, PRODUCT, &self.id, &sku, session.clone(), db)
+            .await?;
+        for (external_id, id) in references {
+            ExternalReference::record(
+                SHOPIFY_SOURCE,
+                VARIANT,
+                &external_id,
+                &id,
+                session.clone(),
+                db,
+            )
+            .await?;
+        }
+
+        Ok(created)
+    }
+}
+
+#[cfg(feature = "methods")]
+impl ShopifyCustomer {
+    /// Creates or updates the customer, yielding their id and whether they
+    /// were created. Of an existing customer, only the details given are
+    /// updated, and their notes, balance and pricing are kept.
+    pub async fn import(&self, session: Session, db: &DbConn) -> Result<(String, bool), Error> {
+        let name = format!("{} {}", text(&self.first_name), text(&self.last_name));
+        let mut contact = contact(name, &self.email, &self.default_address);
+        if let Some(phone) = &self.phone {
+            contact.mobile = MobileNumber::from(phone.clone());
+        }
+
+        let note = self.note.clone().filter(|note| !note.trim().is_empty());
+
+        match local_id(CUSTOMER, &self.id, session.clone(), db).await? {
+            Some(id) => {
+                let mut customer = Customer::fetch_by_id(&id, session.clone(), db).await?;
+
+                if !contact.name.is_empty() {
+                    customer.name = contact.name.clone();
+                    customer.contact.name = contact.name;
+                }
+                if self.email.is_some() {
+                    customer.contact.email = contact.email;
+                }
+                if self.phone.is_some() || self.default_address.is_some() {
+                    customer.contact.mobile = contact.mobile;
+                }
+                if self.default_address.is_some() {
+                    customer.contact.address = contact.address;
+                }
+                if let Some(accepts_marketing) = self.accepts_marketing {
+                    customer.accepts_marketing = accepts_marketing;
+                }
+                customer.updated_at = Utc::now();
+                if let Some(note) = note {
+                    if !customer.customer_notes.iter().any(|n| n.message == note) {
+                        customer.customer_notes.push(Note {
+                            message: note,
+                            author: SHOPIFY_SOURCE.to_string(),
+                            timestamp: Utc::now(),
+                        });
+                    }
+                }
+
+                customer
+                    .into_active(session.tenant_id.clone())
+                    .update(db)
+                    .await?;
+                ExternalReference::record(SHOPIFY_SOURCE, CUSTOMER, &self.id, &id, session, db)
+                    .await?;
+
+                Ok((id, false))
+            }
+            None => {
+                let id = Uuid::new_v4().to_string();
+
+                let customer = Customer {
+                    id: id.clone(),
+                    name: contact.name.clone(),
+                    contact,
+                    customer_notes: note
+                        .map(|message| Note {
+                            message,
+                            author: SHOPIFY_SOURCE.to_string(),
+                            timestamp: Utc::now(),
+                        })
+                        .into_iter()
+                        .collect(),
+                    balance: 0,
+                    special_pricing: String::new(),
+                    accepts_marketing: self.accepts_marketing.unwrap_or(false),
+                    created_at: Utc::now(),
+                    updated_at: Utc::now(),
+                };
+
+                Customer::insert_raw(customer, session.clone(), db).await?;
+                ExternalReference::record(SHOPIFY_SOURCE, CUSTOMER, &self.id, &id, session, db)
+                    .await?;
+
+                Ok((id, true))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "process")]
+impl ShopifyOrder {
+    fn status(&self) -> OrderStatus {
+        match (&self.cancelled_at, self.fulfillment_status.as_deref()) {
+            (Some(_), _) => OrderStatus::Failed(format!(
+                "Cancelled in Shopify ({})",
+                self.cancel_reason.as_deref().unwrap_or("no reason given")
+            )),
+            (None, Some("fulfilled")) => {
+                OrderStatus::Fulfilled(self.closed_at.unwrap_or(self.created_at))
+            }
+            (None, _) => OrderStatus::Queued(self.created_at),
+        }
+    }
+
+    fn payment(&self, order_id: &str) -> Payment {
+        let financial_status = text(&self.financial_status);
+
+        Payment {
+            id: Uuid::new_v4().to_string(),
+            payment_method: PaymentMethod::Other(match self.payment_gateway_names.is_empty() {
+                true => SHOPIFY_SOURCE.to_string(),
+                false => self.payment_gateway_names.join(", "),
+            }),
+            fulfillment_date: self.processed_at.unwrap_or(self.created_at),
+            amount: Price {
+                quantity: self.total_price,
+                currency: self.currency.clone(),
+            },
+            processing_fee: Price {
+                quantity: 0.0,
+                currency: self.currency.clone(),
+            },
+            status: match financial_status.as_str() {
+                "paid" | "partially_refunded" | "refunded" => {
+                    PaymentStatus::Complete(Processable::Anonymous(SHOPIFY_TRANSACTION.into()))
+                }
+                "pending" | "authorized" | "partially_paid" => {
+                    PaymentStatus::Pending(financial_status)
+                }
+                "voided" => {
+                    PaymentStatus::Failed(Processable::Anonymous(SHOPIFY_TRANSACTION.into()))
+                }
+                _ => PaymentStatus::Unfulfilled(financial_status),
+            },
+            processor: PaymentProcessor::anonymous(SHOPIFY_TRANSACTION.into()),
+            order_ids: vec![order_id.to_string()],
+            delay_action: PaymentAction::Complete,
+            delay_duration: "PT12H".to_string(),
+        }
+    }
+
+    fn notes(&self) -> Vec<Note> {
+        self.note
+            .clone()
+            .filter(|note| !note.trim().is_empty())
+            .map(|message| Note {
+                message,
+                author: SHOPIFY_SOURCE.to_string(),
+                timestamp: self.created_at,
+            })
+            .into_iter()
+            .collect()
+    }
+}
+
+#[cfg(feature = "methods")]
+impl ShopifyOrder {
+    /// Creates the order as a sale, or brings the status, payment and notes
+    /// of an order imported before up to date, yielding whether the order
+    /// and its customer (if any) were created. The order's items are those
+    /// given when it was first imported. Stock is not adjusted, as
+    /// Shopify's inventory levels already account for the sale.
+    pub async fn import(
+        &self,
+        store: Option<&Location>,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<(bool, Option<bool>), Error> {
+        let customer = match &self.customer {
+            Some(customer) => Some(customer.import(session.clone(), db).await?),
+            None => None,
+        };
+        let customer_created = customer.as_ref().map(|(_, created)| *created);
+
+        let status = self.status();
+
+        if let Some(id) = local_id(ORDER, &self.id, session.clone(), db).await? {
+            let mut transaction = Transaction::fetch_by_id(&id, session.clone(), db).await?;
+
+            if let Some(order) = transaction.products.first_mut() {
+                if order.status.status != status {
+                    order.status_history.push(History {
+                        item: order.status.clone(),
+                        reason: "Updated in Shopify".to_string(),
+                        timestamp: Utc::now(),
+                    });
+                    order.status = OrderStatusAssignment {
+                        status,
+                        assigned_products: order.products.iter().map(|p| p.id.clone()).collect(),
+                        timestamp: Utc::now(),
+                    };
+                }
+
+                let mut payment = self.payment(&order.id);
+                if let Some(previous) = transaction.payment.first() {
+                    payment.id = previous.id.clone();
+                }
+                transaction.payment = vec![payment];
+            }
+
+            transaction.order_notes = self.notes();
+
+            Transaction::update_value(transaction, session.clone(), &id, db).await?;
+            ExternalReference::record(SHOPIFY_SOURCE, ORDER, &self.id, &id, session, db).await?;
+
+            return Ok((false, customer_created));
+        }
+
+        let fulfilled = matches!(status, OrderStatus::Fulfilled(_));
+        let mut catalogue: HashMap<String, Option<Product>> = HashMap::new();
+        let mut purchases = vec![];
+
+        for item in &self.line_items {
+            let sku = match &item.product_id {
+                Some(product_id) => local_id(PRODUCT, product_id, session.clone(), db).await?,
+                None => None,
+            };
+            let variant_id = match &item.variant_id {
+                Some(variant_id) => local_id(VARIANT, variant_id, session.clone(), db).await?,
+                None => None,
+            };
+
+            let product = match &sku {
+                Some(sku) => {
+                    if !catalogue.contains_key(sku) {
+                        let product = fetch_product(sku, session.clone(), db).await?;
+                        catalogue.insert(sku.clone(), product);
+                    }
+                    catalogue[sku].as_ref()
+                }
+                None => None,
+            };
+            let variant = product.and_then(|product| {
+                product
+                    .variants
+                    .iter()
+                    .find(|v| Some(&v.id) == variant_id.as_ref())
+            });
+
+            purchases.push(ProductPurchase {
+                id: Uuid::new_v4().to_string(),
+                product_code: variant
+                    .map(|v| v.barcode.clone())
+                    .filter(|barcode| !barcode.is_empty())
+                    .unwrap_or_else(|| text(&item.sku)),
+                product_sku: sku.unwrap_or_else(|| text(&item.sku)),
+                // Absolute discounts are in whole units of the currency.
+                discount: DiscountValue::Absolute(item.total_discount.round() as u32),
+                product_name: item.title.clone(),
+                product_variant_name: text(&item.variant_title),
+                product_cost: item.price,
+                quantity: item.quantity,
+                tags: vec![],
+                transaction_type: TransactionType::Out,
+                instances: (0..item.quantity.max(0.0) as usize)
+                    .map(|_| ProductInstance {
+                        id: Uuid::new_v4().to_string(),
+                        fulfillment_status: FulfillmentStatus {
+                            pick_status: match fulfilled {
+                                true => PickStatus::Picked,
+                                false => PickStatus::Pending,
+                            },
+                            pick_history: vec![],
+                            last_updated: self.created_at,
+                            notes: vec![],
+                        },
+                        serial: None,
+                    })
+                    .collect(),
+                lot: None,
+                lots_drawn: vec![],
+            });
+        }
+
+        let origin = store.cloned().unwrap_or_else(|| Location {
+            store_code: String::new(),
+            store_id: String::new(),
+            contact: contact(SHOPIFY_SOURCE.to_string(), &None, &None),
+        });
+        let destination = match &self.shipping_address {
+            Some(address) => Location {
+                store_code: String::new(),
+                store_id: String::new(),
+                contact: contact(text(&address.name), &None, &Some(address.clone())),
+            },
+            None => origin.clone(),
+        };
+
+        let order = Order {
+            id: Uuid::new_v4().to_string(),
+            order_type: match self.shipping_address {
+                Some(_) => OrderType::Shipment,
+                None => OrderType::Direct,
+            },
+            destination,
+            origin,
+            status: OrderStatusAssignment {
+                status,
+                assigned_products: purchases.iter().map(|p| p.id.clone()).collect(),
+                timestamp: self.created_at,
+            },
+            products: purchases,
+            status_history: vec![],
+            order_history: vec![],
+            previous_failed_fulfillment_attempts: vec![],
+            packages: vec![],
+            stock_released: false,
+            collection: None,
+            order_notes: vec![],
+            reference: self.name.clone(),
+            creation_date: self.created_at,
+            discount: DiscountValue::Absolute(0),
+        };
+
+        let id = Uuid::new_v4().to_string();
+        let transaction = Transaction {
+            id: id.clone(),
+            customer: TransactionCustomer {
+                customer_type: CustomerType::Individual,
+                customer_id: customer.map(|(id, _)| id).unwrap_or_default(),
+            },
+            transaction_type: TransactionType::Out,
+            payment: vec![self.payment(&order.id)],
+            products: vec![order],
+            order_total: self.total_price.round() as i64,
+            order_date: self.created_at,
+            order_notes: self.notes(),
+            salesperson: session.employee.id.clone(),
+            kiosk: SHOPIFY_SOURCE.to_string(),
+            void: None,
+            quote: None,
+            created_at: self.created_at,
+            updated_at: Utc::now(),
+            issued_collections: vec![],
+        };
+
+        Transaction::insert_raw(transaction, session.clone(), db).await?;
+        ExternalReference::record(SHOPIFY_SOURCE, ORDER, &self.id, &id, session, db).await?;
+
+        Ok((true, customer_created))
+    }
+}
+
+#[cfg(feature = "process")]
+impl ImportCount {
+    fn count(&mut self, created: bool) {
+        match created {
+            true => self.created += 1,
+            false => self.updated += 1,
+        }
+    }
+}
+
+#[cfg(feature = "methods")]
+impl ShopifyExport {
+    /// Imports products, then customers, then orders, such that orders can
+    /// refer to the products and customers imported before them. Inventory
+    /// is recorded against the store with `store_code`, which is also the
+    /// origin of imported orders.
+    pub async fn import(
+        &self,
+        store_code: Option<&str>,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<ShopifyImport, Error> {
+        let store = match store_code {
+            Some(code) => {
+                let store = Store::fetch_by_code(code, session.clone(), db)
+                    .await
+                    .map_err(|_| {
+                        ErrorResponse::create_error(&format!("Unknown store code `{}`.", code))
+                    })?;
+
+                Some(Location {
+                    store_code: store.code,
+                    store_id: store.id,
+                    contact: store.contact,
+                })
+            }
+            None => None,
+        };
+
+        let mut result = ShopifyImport {
+            products: ImportCount::default(),
+            customers: ImportCount::default(),
+            orders: ImportCount::default(),
+            errors: vec![],
+        };
+
+        for (index, product) in self.products.iter().enumerate() {
+            match product.import(store.as_ref(), session.clone(), db).await {
+                Ok(created) => result.products.count(created),
+                Err(error) => result
+                    .errors
+                    .push(row_error("products", index, &product.id, error)),
+            }
+        }
+
+        for (index, customer) in self.customers.iter().enumerate() {
+            match customer.import(session.clone(), db).await {
+                Ok((_, created)) => result.customers.count(created),
+                Err(error) => {
+                    result
+                        .errors
+                        .push(row_error("customers", index, &customer.id, error))
+                }
+            }
+        }
+
+        for (index, order) in self.orders.iter().enumerate() {
+            match order.import(store.as_ref(), session.clone(), db).await {
+                Ok((created, customer_created)) => {
+                    result.orders.count(created);
+                    // Customers given only within their orders are counted
+                    // when first created.
+                    if customer_created == Some(true) {
+                        result.customers.count(true);
+                    }
+                }
+                Err(error) => result
+                    .errors
+                    .push(row_error("orders", index, &order.id, error)),
+            }
+        }
+
+        Ok(result)
+    }
+}
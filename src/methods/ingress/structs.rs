@@ -0,0 +1,547 @@
+#[cfg(feature = "process")]
+use crate::entities::prelude::{ExternalReferences, ImportJobs};
+#[cfg(feature = "process")]
+use crate::entities::{external_references, import_jobs};
+use crate::methods::Id;
+#[cfg(feature = "process")]
+use crate::methods::{Error, ErrorResponse};
+#[cfg(feature = "process")]
+use crate::{
+    example_employee, Customer, Kiosk, Product, Session, SessionVariant, Store, Transaction,
+};
+use chrono::{DateTime, Days, Utc};
+use schemars::JsonSchema;
+#[cfg(feature = "process")]
+use sea_orm::{ActiveModelTrait, ColumnTrait, DbConn, DbErr, EntityTrait, QueryFilter, Set};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+#[cfg(feature = "process")]
+use tracing::{info, warn};
+#[cfg(feature = "process")]
+use uuid::Uuid;
+use validator::Validate;
+
+/// The newest version of [`ImportManifest`] understood by the ingress
+/// worker.
+pub const IMPORT_FORMAT_VERSION: u32 = 1;
+
+/// Progress is saved after this many records, as well as after each
+/// collection.
+const PROGRESS_INTERVAL: usize = 100;
+
+/// The contents of a file uploaded for ingress. Each collection holds
+/// records in the form returned by the API, and is imported in the order
+/// below so that records may refer to those before them.
+///
+/// Files of the original, unversioned format - a tuple of products,
+/// customers, transactions, stores and kiosks - are read as version `0`.
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
+pub struct ImportManifest {
+    pub version: u32,
+    #[serde(default)]
+    pub stores: Vec<Value>,
+    #[serde(default)]
+    pub products: Vec<Value>,
+    #[serde(default)]
+    pub customers: Vec<Value>,
+    #[serde(default)]
+    pub transactions: Vec<Value>,
+    #[serde(default)]
+    pub kiosks: Vec<Value>,
+}
+
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub enum ImportStatus {
+    /// Awaiting the ingress worker.
+    Pending,
+    Running,
+    /// Every record was attempted, those which failed are listed in the
+    /// job's `errors`.
+    Completed,
+    /// The file could not be read, or the import was interrupted, the
+    /// reason being recorded in the job's `errors`. Records imported
+    /// before the interruption are kept.
+    Failed,
+}
+
+/// A record which could not be imported.
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct ImportRowError {
+    /// The manifest collection the record is in, i.e. `products`.
+    pub collection: String,
+    /// The record's position within its collection.
+    pub index: usize,
+    /// The record's `id` (or `sku`), where one could be read.
+    pub id: Option<String>,
+    pub message: String,
+}
+
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct ImportJob {
+    pub id: Id,
+    pub created_by: Id,
+    pub status: ImportStatus,
+    pub format_version: Option<u32>,
+
+    pub total_rows: u32,
+    pub processed_rows: u32,
+    pub succeeded_rows: u32,
+    pub failed_rows: u32,
+    /// File-level errors are recorded without a collection.
+    pub errors: Vec<ImportRowError>,
+
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[cfg(feature = "types")]
+impl ImportManifest {
+    /// Reads either a versioned manifest or the original tuple format.
+    pub fn parse(contents: &str) -> Result<ImportManifest, String> {
+        let value: Value = serde_json::from_str(contents)
+            .map_err(|e| format!("Unable to parse import file, {}", e))?;
+
+        let manifest = match value {
+            Value::Array(_) => {
+                let (products, customers, transactions, stores, kiosks) =
+                    serde_json::from_value(value)
+                        .map_err(|e| format!("Unable to read unversioned import, {}", e))?;
+
+                ImportManifest {
+                    version: 0,
+                    stores,
+                    products,
+                    customers,
+                    transactions,
+                    kiosks,
+                }
+            }
+            _ => serde_json::from_value(value)
+                .map_err(|e| format!("Unable to read import manifest, {}", e))?,
+        };
+
+        if manifest.version > IMPORT_FORMAT_VERSION {
+            return Err(format!(
+                "Unsupported import version {}, the newest supported is {}.",
+                manifest.version, IMPORT_FORMAT_VERSION
+            ));
+        }
+
+        Ok(manifest)
+    }
+
+    pub fn total_rows(&self) -> usize {
+        self.stores.len()
+            + self.products.len()
+            + self.customers.len()
+            + self.transactions.len()
+            + self.kiosks.len()
+    }
+}
+
+/// Reads and validates a single record, yielding the reason it was
+/// rejected otherwise.
+#[cfg(feature = "types")]
+fn read_row<T: DeserializeOwned + Validate>(row: &Value) -> Result<T, String> {
+    let record: T = serde_json::from_value(row.clone()).map_err(|e| e.to_string())?;
+    record.validate().map_err(|e| e.to_string())?;
+    Ok(record)
+}
+
+#[cfg(feature = "types")]
+fn row_id(row: &Value) -> Option<String> {
+    row.get("id")
+        .or_else(|| row.get("sku"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+#[cfg(feature = "process")]
+impl TryFrom<import_jobs::Model> for ImportJob {
+    type Error = Error;
+
+    fn try_from(val: import_jobs::Model) -> Result<Self, Self::Error> {
+        Ok(ImportJob {
+            id: val.id,
+            created_by: val.created_by,
+            status: serde_json::from_value(val.status)
+                .map_err(|e| ErrorResponse::create_error(&format!("Invalid import job, {}", e)))?,
+            format_version: val.format_version.map(|v| v as u32),
+            total_rows: val.total_rows as u32,
+            processed_rows: val.processed_rows as u32,
+            succeeded_rows: val.succeeded_rows as u32,
+            failed_rows: val.failed_rows as u32,
+            errors: serde_json::from_value(val.errors).unwrap_or_default(),
+            created_at: DateTime::from_naive_utc_and_offset(val.created_at, Utc),
+            started_at: val
+                .started_at
+                .map(|v| DateTime::from_naive_utc_and_offset(v, Utc)),
+            finished_at: val
+                .finished_at
+                .map(|v| DateTime::from_naive_utc_and_offset(v, Utc)),
+        })
+    }
+}
+
+#[cfg(feature = "methods")]
+impl ImportJob {
+    /// Records a pending job for a file which is about to be written to the
+    /// ingress directory, named by the job's id.
+    pub async fn create(session: Session, db: &DbConn) -> Result<ImportJob, Error> {
+        import_jobs::ActiveModel {
+            id: Set(Uuid::new_v4().to_string()),
+            tenant_id: Set(session.tenant_id),
+            created_by: Set(session.employee.id),
+            status: Set(json!(ImportStatus::Pending)),
+            format_version: Set(None),
+            total_rows: Set(0),
+            processed_rows: Set(0),
+            succeeded_rows: Set(0),
+            failed_rows: Set(0),
+            errors: Set(json!(Vec::<ImportRowError>::new())),
+            created_at: Set(Utc::now().naive_utc()),
+            started_at: Set(None),
+            finished_at: Set(None),
+        }
+        .insert(db)
+        .await?
+        .try_into()
+    }
+
+    pub async fn fetch_by_id(id: &str, session: Session, db: &DbConn) -> Result<ImportJob, Error> {
+        ImportJobs::find_by_id(id.to_string())
+            .filter(import_jobs::Column::TenantId.eq(session.tenant_id))
+            .one(db)
+            .await?
+            .ok_or_else(|| DbErr::RecordNotFound(id.to_string()))?
+            .try_into()
+    }
+
+    async fn save(&self, db: &DbConn) -> Result<(), Error> {
+        import_jobs::ActiveModel {
+            id: Set(self.id.clone()),
+            status: Set(json!(self.status)),
+            format_version: Set(self.format_version.map(|v| v as i32)),
+            total_rows: Set(self.total_rows as i32),
+            processed_rows: Set(self.processed_rows as i32),
+            succeeded_rows: Set(self.succeeded_rows as i32),
+            failed_rows: Set(self.failed_rows as i32),
+            errors: Set(json!(self.errors)),
+            started_at: Set(self.started_at.map(|v| v.naive_utc())),
+            finished_at: Set(self.finished_at.map(|v| v.naive_utc())),
+            ..Default::default()
+        }
+        .update(db)
+        .await?;
+
+        Ok(())
+    }
+
+    fn record(&mut self, collection: &str, index: usize, row: &Value, result: Result<(), String>) {
+        self.processed_rows += 1;
+
+        match result {
+            Ok(_) => self.succeeded_rows += 1,
+            Err(message) => {
+                self.failed_rows += 1;
+                self.errors.push(ImportRowError {
+                    collection: collection.to_string(),
+                    index,
+                    id: row_id(row),
+                    message,
+                });
+            }
+        }
+    }
+
+    /// Marks the job failed, recording `message` as a file-level error.
+    async fn fail(&mut self, message: String, db: &DbConn) -> Result<(), Error> {
+        self.status = ImportStatus::Failed;
+        self.finished_at = Some(Utc::now());
+        self.errors.push(ImportRowError {
+            collection: String::new(),
+            index: 0,
+            id: None,
+            message,
+        });
+        self.save(db).await
+    }
+
+    /// Marks a job which could not be run failed, i.e. when its file could
+    /// not be written or read.
+    pub async fn abandon(id: &str, message: String, db: &DbConn) -> Result<ImportJob, Error> {
+        let mut job: ImportJob = ImportJobs::find_by_id(id.to_string())
+            .one(db)
+            .await?
+            .ok_or_else(|| DbErr::RecordNotFound(id.to_string()))?
+            .try_into()?;
+
+        job.fail(message, db).await?;
+        Ok(job)
+    }
+
+    /// Marks failed every job left running, as only an import interrupted by
+    /// the server stopping can be. Called as the ingress worker starts, so
+    /// before any job is run. Returns the number of jobs failed.
+    pub async fn fail_interrupted(db: &DbConn) -> Result<usize, Error> {
+        let running = ImportJobs::find()
+            .filter(import_jobs::Column::Status.contains(json!(ImportStatus::Running).to_string()))
+            .all(db)
+            .await?;
+        let count = running.len();
+
+        for model in running {
+            let mut job: ImportJob = model.try_into()?;
+            warn!(job_id = %job.id, "import job interrupted by a restart");
+            job.fail("Import interrupted by a restart.".to_string(), db)
+                .await?;
+        }
+
+        Ok(count)
+    }
+
+    /// Imports the contents of the job's file, recording the outcome of
+    /// each record. The job's tenant is that of the employee who uploaded
+    /// the file. Should the import be interrupted, the job is marked failed.
+    pub async fn run(id: &str, contents: &str, db: &DbConn) -> Result<ImportJob, Error> {
+        let model = ImportJobs::find_by_id(id.to_string())
+            .one(db)
+            .await?
+            .ok_or_else(|| DbErr::RecordNotFound(id.to_string()))?;
+        let tenant_id = model.tenant_id.clone();
+        let mut job: ImportJob = model.try_into()?;
+
+        if job.status != ImportStatus::Pending {
+            return Err(ErrorResponse::create_error(
+                "The import job has already been run.",
+            ));
+        }
+
+        job.status = ImportStatus::Running;
+        job.started_at = Some(Utc::now());
+
+        let manifest = match ImportManifest::parse(contents) {
+            Ok(manifest) => manifest,
+            Err(message) => {
+                warn!(job_id = %job.id, %message, "import file rejected");
+                job.fail(message, db).await?;
+                return Ok(job);
+            }
+        };
+
+        if let Err(error) = job.import(manifest, tenant_id, db).await {
+            warn!(job_id = %job.id, error = ?error, "import job interrupted");
+            job.fail(format!("Import interrupted, {}", error.message()), db)
+                .await?;
+            return Err(error);
+        }
+
+        info!(
+            job_id = %job.id,
+            succeeded = job.succeeded_rows,
+            failed = job.failed_rows,
+            "import job completed"
+        );
+
+        Ok(job)
+    }
+
+    /// Imports each record of the manifest in turn, saving the job's
+    /// progress as it goes.
+    async fn import(
+        &mut self,
+        manifest: ImportManifest,
+        tenant_id: String,
+        db: &DbConn,
+    ) -> Result<(), Error> {
+        self.format_version = Some(manifest.version);
+        self.total_rows = manifest.total_rows() as u32;
+        self.save(db).await?;
+
+        let session = Session {
+            id: String::new(),
+            key: String::new(),
+            employee: example_employee().into(),
+            expiry: Utc::now().checked_add_days(Days::new(1)).unwrap(),
+            tenant_id,
+            variant: SessionVariant::AccessToken,
+        };
+
+        for (index, row) in manifest.stores.iter().enumerate() {
+            let result = match read_row::<Store>(row) {
+                Ok(store) => Store::insert(store, session.clone(), db)
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| e.message()),
+                Err(message) => Err(message),
+            };
+            self.record("stores", index, row, result);
+            self.checkpoint(index, db).await?;
+        }
+        self.save(db).await?;
+
+        for (index, row) in manifest.products.iter().enumerate() {
+            let result = match read_row::<Product>(row) {
+                Ok(product) => Product::insert(product, session.clone(), db)
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| e.message()),
+                Err(message) => Err(message),
+            };
+            self.record("products", index, row, result);
+            self.checkpoint(index, db).await?;
+        }
+        self.save(db).await?;
+
+        for (index, row) in manifest.customers.iter().enumerate() {
+            let result = match read_row::<Customer>(row) {
+                Ok(customer) => Customer::insert_raw(customer, session.clone(), db)
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| e.message()),
+                Err(message) => Err(message),
+            };
+            self.record("customers", index, row, result);
+            self.checkpoint(index, db).await?;
+        }
+        self.save(db).await?;
+
+        for (index, row) in manifest.transactions.iter().enumerate() {
+            let result = match read_row::<Transaction>(row) {
+                Ok(transaction) => Transaction::insert_raw(transaction, session.clone(), db)
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| e.message()),
+                Err(message) => Err(message),
+            };
+            self.record("transactions", index, row, result);
+            self.checkpoint(index, db).await?;
+        }
+        self.save(db).await?;
+
+        for (index, row) in manifest.kiosks.iter().enumerate() {
+            let result = match read_row::<Kiosk>(row) {
+                Ok(kiosk) => Kiosk::insert_raw(kiosk, session.clone(), db)
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| e.to_string()),
+                Err(message) => Err(message),
+            };
+            self.record("kiosks", index, row, result);
+            self.checkpoint(index, db).await?;
+        }
+
+        self.status = ImportStatus::Completed;
+        self.finished_at = Some(Utc::now());
+        self.save(db).await
+    }
+
+    async fn checkpoint(&self, index: usize, db: &DbConn) -> Result<(), Error> {
+        match (index + 1) % PROGRESS_INTERVAL {
+            0 => self.save(db).await,
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Links a record imported from another system, by its id there, to the
+/// record created for it. Importers look these up so that importing the
+/// same record again updates it instead of creating another.
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct ExternalReference {
+    pub id: Id,
+    /// The system the record was imported from, i.e. `shopify`.
+    pub source: String,
+    /// The kind of record, i.e. `product` or `order`.
+    pub kind: String,
+    pub external_id: String,
+    pub local_id: String,
+
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[cfg(feature = "process")]
+impl From<external_references::Model> for ExternalReference {
+    fn from(val: external_references::Model) -> Self {
+        ExternalReference {
+            id: val.id,
+            source: val.source,
+            kind: val.kind,
+            external_id: val.external_id,
+            local_id: val.local_id,
+            created_at: DateTime::from_naive_utc_and_offset(val.created_at, Utc),
+            updated_at: DateTime::from_naive_utc_and_offset(val.updated_at, Utc),
+        }
+    }
+}
+
+#[cfg(feature = "methods")]
+impl ExternalReference {
+    pub async fn fetch(
+        source: &str,
+        kind: &str,
+        external_id: &str,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<Option<ExternalReference>, Error> {
+        let reference = ExternalReferences::find()
+            .filter(external_references::Column::TenantId.eq(session.tenant_id))
+            .filter(external_references::Column::Source.eq(source))
+            .filter(external_references::Column::Kind.eq(kind))
+            .filter(external_references::Column::ExternalId.eq(external_id))
+            .one(db)
+            .await?;
+
+        Ok(reference.map(|r| r.into()))
+    }
+
+    /// Records that `external_id` was imported as `local_id`, or marks an
+    /// existing reference as updated.
+    pub async fn record(
+        source: &str,
+        kind: &str,
+        external_id: &str,
+        local_id: &str,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<(), Error> {
+        let now = Utc::now().naive_utc();
+
+        match Self::fetch(source, kind, external_id, session.clone(), db).await? {
+            Some(reference) => {
+                external_references::ActiveModel {
+                    id: Set(reference.id),
+                    local_id: Set(local_id.to_string()),
+                    updated_at: Set(now),
+                    ..Default::default()
+                }
+                .update(db)
+                .await?;
+            }
+            None => {
+                external_references::ActiveModel {
+                    id: Set(Uuid::new_v4().to_string()),
+                    tenant_id: Set(session.tenant_id),
+                    source: Set(source.to_string()),
+                    kind: Set(kind.to_string()),
+                    external_id: Set(external_id.to_string()),
+                    local_id: Set(local_id.to_string()),
+                    created_at: Set(now),
+                    updated_at: Set(now),
+                }
+                .insert(db)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
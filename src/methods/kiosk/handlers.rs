@@ -1,14 +1,18 @@
 use crate::catchers::Validated;
-use crate::guards::Convert;
+use crate::guards::{Convert, OverrideTokens};
 use crate::methods::Error;
 use crate::pool::InternalDb;
 use crate::{check_permissions, methods::Action};
-use crate::{AuthenticationLog, Kiosk, KioskInit, KioskPreferences, Session};
+use crate::{
+    AuthenticationLog, Kiosk, KioskInit, KioskPreferences, ManagerOverride, OverrideAction,
+    Session, Tenant,
+};
 use okapi::openapi3::OpenApi;
 use rocket::serde::json::Json;
 use rocket::{get, post};
 use rocket_okapi::settings::OpenApiSettings;
 use rocket_okapi::{openapi, openapi_get_routes_spec};
+use tracing::info;
 
 pub fn documented_routes(settings: &OpenApiSettings) -> (Vec<rocket::Route>, OpenApi) {
     openapi_get_routes_spec![
@@ -19,7 +23,8 @@ pub fn documented_routes(settings: &OpenApiSettings) -> (Vec<rocket::Route>, Ope
         update_preferences,
         update_online_status,
         delete,
-        auth_log
+        auth_log,
+        no_sale
     ]
 }
 
@@ -101,3 +106,35 @@ pub async fn auth_log(
         .await
         .map(|_| ())
 }
+
+/// Opens the kiosk's cash drawer outside of a sale, which may require a
+/// manager override.
+#[openapi(tag = "Kiosk")]
+#[post("/no_sale/<id>")]
+pub async fn no_sale(
+    db: InternalDb,
+    session: Session,
+    overrides: OverrideTokens,
+    id: &str,
+) -> Result<(), Error> {
+    check_permissions!(session.clone(), Action::CreateTransaction);
+
+    let kiosk = Kiosk::fetch_by_id(id, session.clone(), &db.0).await?;
+    let policy = Tenant::fetch_by_id(&session.tenant_id, &db.0)
+        .await?
+        .settings
+        .overrides;
+
+    ManagerOverride::authorise(
+        &policy.protected(&[OverrideAction::NoSale]),
+        Some(&kiosk.id),
+        0.0,
+        &overrides.0,
+        &session,
+        &db.0,
+    )
+    .await?;
+
+    info!(kiosk_id = %kiosk.id, employee_id = %session.employee.id, "opened cash drawer for no sale");
+    Ok(())
+}
@@ -1,7 +1,7 @@
 use crate::entities::kiosk::Model;
 #[cfg(feature = "process")]
 use crate::entities::prelude::Kiosk as Ksk;
-use crate::methods::Error;
+use crate::methods::{Error, OverrideAttempt};
 #[cfg(feature = "process")]
 use crate::{entities::authrecord::ActiveModel as AuthRecord, entities::kiosk::ActiveModel};
 #[cfg(feature = "process")]
@@ -29,6 +29,10 @@ pub struct KioskPreferences {
 pub struct AuthenticationLog {
     pub employee_id: String,
     pub successful: bool,
+    /// Present when the employee was authenticating to approve a manager
+    /// override, rather than to log in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub override_of: Option<OverrideAttempt>,
 }
 
 #[cfg(feature = "types")]
@@ -0,0 +1,155 @@
+use crate::catchers::Validated;
+use crate::guards::Convert;
+use crate::methods::{Action, Error, ErrorResponse};
+use crate::pool::InternalDb;
+use crate::{
+    check_permissions, Mfa, MfaCode, MfaEnrolment, MfaPolicy, RecoveryCodes, Session,
+    SessionVariant, Tenant,
+};
+use okapi::openapi3::OpenApi;
+use rocket::serde::json::Json;
+use rocket::{get, post};
+use rocket_okapi::settings::OpenApiSettings;
+use rocket_okapi::{openapi, openapi_get_routes_spec};
+
+pub fn documented_routes(settings: &OpenApiSettings) -> (Vec<rocket::Route>, OpenApi) {
+    openapi_get_routes_spec![
+        settings: get,
+        get_for,
+        enrol,
+        confirm,
+        regenerate_recovery_codes,
+        disable,
+        disable_for,
+        get_policy,
+        update_policy
+    ]
+}
+
+/// MFA is managed by the employee themselves, never through an API key.
+fn reject_api_keys(session: &Session) -> Result<(), Error> {
+    match session.variant {
+        SessionVariant::ApiKey(_) => Err(ErrorResponse::custom_unauthorized(
+            "MFA cannot be managed using an API key.",
+        )),
+        _ => Ok(()),
+    }
+}
+
+async fn require_code(code: &str, session: &Session, db: &InternalDb) -> Result<(), Error> {
+    match Mfa::verify(&session.employee, &session.tenant_id, code, &db.0).await? {
+        true => Ok(()),
+        false => Err(ErrorResponse::custom_unauthorized("Invalid MFA code.")),
+    }
+}
+
+#[openapi(tag = "Mfa")]
+#[get("/")]
+pub async fn get(db: InternalDb, session: Session) -> Convert<Mfa> {
+    let employee_id = session.employee.id.clone();
+    Mfa::fetch(&employee_id, session, &db.0).await.into()
+}
+
+#[openapi(tag = "Mfa")]
+#[get("/<employee_id>")]
+pub async fn get_for(db: InternalDb, session: Session, employee_id: &str) -> Convert<Mfa> {
+    check_permissions!(session.clone(), Action::AccessAdminPanel);
+    Mfa::fetch(employee_id, session, &db.0).await.into()
+}
+
+/// Starts enrolment, the returned secret must be confirmed through
+/// `/mfa/confirm` before it is required at login.
+#[openapi(tag = "Mfa")]
+#[post("/enrol")]
+pub async fn enrol(db: InternalDb, session: Session) -> Result<Json<MfaEnrolment>, Error> {
+    reject_api_keys(&session)?;
+    Mfa::enrol(session, &db.0).await.map(Json)
+}
+
+/// Completes enrolment, returning the recovery codes. Any session issued
+/// without the actions MFA protects must log in again to obtain them.
+#[openapi(tag = "Mfa")]
+#[post("/confirm", data = "<input_data>")]
+pub async fn confirm(
+    db: InternalDb,
+    session: Session,
+    input_data: Validated<Json<MfaCode>>,
+) -> Result<Json<RecoveryCodes>, Error> {
+    reject_api_keys(&session)?;
+    Mfa::confirm(&input_data.data().code, session, &db.0)
+        .await
+        .map(Json)
+}
+
+#[openapi(tag = "Mfa")]
+#[post("/recovery_codes", data = "<input_data>")]
+pub async fn regenerate_recovery_codes(
+    db: InternalDb,
+    session: Session,
+    input_data: Validated<Json<MfaCode>>,
+) -> Result<Json<RecoveryCodes>, Error> {
+    reject_api_keys(&session)?;
+    require_code(&input_data.data().code, &session, &db).await?;
+
+    Mfa::regenerate_recovery_codes(session, &db.0)
+        .await
+        .map(Json)
+}
+
+#[openapi(tag = "Mfa")]
+#[post("/disable", data = "<input_data>")]
+pub async fn disable(
+    db: InternalDb,
+    session: Session,
+    input_data: Validated<Json<MfaCode>>,
+) -> Result<(), Error> {
+    reject_api_keys(&session)?;
+    require_code(&input_data.data().code, &session, &db).await?;
+
+    let employee_id = session.employee.id.clone();
+    Mfa::disable(&employee_id, session, &db.0).await
+}
+
+/// Resets an employee's MFA, i.e. when their device has been lost.
+#[openapi(tag = "Mfa")]
+#[post("/disable/<employee_id>")]
+pub async fn disable_for(db: InternalDb, session: Session, employee_id: &str) -> Result<(), Error> {
+    check_permissions!(session.clone(), Action::AccessAdminPanel);
+    reject_api_keys(&session)?;
+    Mfa::disable(employee_id, session, &db.0).await
+}
+
+#[openapi(tag = "Mfa")]
+#[get("/policy")]
+pub async fn get_policy(db: InternalDb, session: Session) -> Convert<MfaPolicy> {
+    check_permissions!(session.clone(), Action::AccessAdminPanel);
+    Tenant::fetch_by_id(&session.tenant_id, &db.0)
+        .await
+        .map(|tenant| tenant.settings.mfa)
+        .into()
+}
+
+#[openapi(tag = "Mfa")]
+#[post("/policy", data = "<input_data>")]
+pub async fn update_policy(
+    db: InternalDb,
+    session: Session,
+    input_data: Validated<Json<MfaPolicy>>,
+) -> Convert<MfaPolicy> {
+    check_permissions!(session.clone(), Action::AccessAdminPanel);
+
+    if let Err(error) = reject_api_keys(&session) {
+        return Err(error).into();
+    }
+
+    let mut settings = match Tenant::fetch_by_id(&session.tenant_id, &db.0).await {
+        Ok(tenant) => tenant.settings,
+        Err(error) => return Err(error).into(),
+    };
+    settings.mfa = input_data.data();
+
+    Tenant::update_settings(&session.tenant_id, settings, &db.0)
+        .await
+        .map(|tenant| tenant.settings.mfa)
+        .into()
+}
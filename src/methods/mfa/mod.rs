@@ -0,0 +1,7 @@
+#[cfg(feature = "process")]
+pub(crate) mod handlers;
+mod structs;
+
+pub use self::structs::*;
+#[cfg(feature = "process")]
+pub use handlers::*;
@@ -0,0 +1,372 @@
+#[cfg(feature = "process")]
+use crate::entities::employee_mfa;
+#[cfg(feature = "process")]
+use crate::entities::prelude::EmployeeMfa;
+use crate::methods::{Action, Error, ErrorResponse, Id};
+#[cfg(feature = "process")]
+use crate::{Employee, Session, Tenant};
+use chrono::{DateTime, Utc};
+#[cfg(feature = "process")]
+use rand::distributions::{Alphanumeric, DistString};
+use schemars::JsonSchema;
+#[cfg(feature = "process")]
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, Condition, DbConn, DbErr, EntityTrait,
+    QueryFilter, Set,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+#[cfg(feature = "process")]
+use sha2::{Digest, Sha256};
+#[cfg(feature = "process")]
+use totp_rs::{Algorithm, Secret, TOTP};
+use validator::Validate;
+
+/// The issuer shown by authenticator apps.
+pub const MFA_ISSUER: &str = "OpenStock";
+
+const RECOVERY_CODE_COUNT: usize = 10;
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP: u64 = 30;
+/// Codes from one step either side of the current are accepted, allowing
+/// for clock drift.
+const TOTP_SKEW: u8 = 1;
+
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema, Validate)]
+pub struct Mfa {
+    pub employee_id: Id,
+    pub enabled: bool,
+    pub enabled_at: Option<DateTime<Utc>>,
+    pub recovery_codes_remaining: usize,
+    /// Whether the tenant's policy requires this employee to use MFA.
+    pub required: bool,
+}
+
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct MfaEnrolment {
+    /// The base32 encoded shared secret, for manual entry.
+    pub secret: String,
+    /// An `otpauth://` uri, typically presented as a QR code.
+    pub uri: String,
+}
+
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema, Validate)]
+pub struct MfaCode {
+    /// A current TOTP code or an unused recovery code.
+    #[validate(length(min = 6, max = 32))]
+    pub code: String,
+}
+
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct RecoveryCodes {
+    /// Each code may be used once in place of a TOTP code, these are only
+    /// ever returned when generated.
+    pub codes: Vec<String>,
+}
+
+#[cfg(feature = "process")]
+fn hash_code(code: &str) -> String {
+    hex::encode(Sha256::digest(code.trim().to_lowercase().as_bytes()))
+}
+
+#[cfg(feature = "process")]
+fn generate_recovery_codes() -> (RecoveryCodes, Vec<String>) {
+    let codes = (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let code = Alphanumeric
+                .sample_string(&mut rand::thread_rng(), 10)
+                .to_lowercase();
+            format!("{}-{}", &code[..5], &code[5..])
+        })
+        .collect::<Vec<String>>();
+
+    let hashes = codes.iter().map(|code| hash_code(code)).collect();
+
+    (RecoveryCodes { codes }, hashes)
+}
+
+#[cfg(feature = "process")]
+fn totp(secret: &str, account: &str) -> Result<TOTP, Error> {
+    let secret = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|e| ErrorResponse::create_error(&format!("Invalid MFA secret, {:?}", e)))?;
+
+    TOTP::new(
+        Algorithm::SHA1,
+        TOTP_DIGITS,
+        TOTP_SKEW,
+        TOTP_STEP,
+        secret,
+        Some(MFA_ISSUER.to_string()),
+        account.to_string(),
+    )
+    .map_err(|e| ErrorResponse::create_error(&format!("Unable to create TOTP, {:?}", e)))
+}
+
+/// Yields the time step `code` is valid for, within the permitted skew.
+#[cfg(feature = "process")]
+fn matching_step(totp: &TOTP, code: &str) -> Option<i64> {
+    let now = Utc::now().timestamp();
+    let current = now / TOTP_STEP as i64;
+
+    (current - TOTP_SKEW as i64..=current + TOTP_SKEW as i64).find(|step| {
+        let expected = totp.generate((*step as u64) * TOTP_STEP);
+        expected.len() == code.len()
+            && expected
+                .bytes()
+                .zip(code.bytes())
+                .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+                == 0
+    })
+}
+
+#[cfg(feature = "process")]
+impl From<employee_mfa::Model> for Mfa {
+    fn from(val: employee_mfa::Model) -> Self {
+        Mfa {
+            employee_id: val.employee_id,
+            enabled: val.enabled_at.is_some(),
+            enabled_at: val
+                .enabled_at
+                .map(|v| DateTime::from_naive_utc_and_offset(v, Utc)),
+            recovery_codes_remaining: serde_json::from_value::<Vec<String>>(val.recovery_codes)
+                .map(|codes| codes.len())
+                .unwrap_or_default(),
+            required: false,
+        }
+    }
+}
+
+#[cfg(feature = "methods")]
+impl Mfa {
+    async fn fetch_model(
+        employee_id: &str,
+        tenant_id: &str,
+        db: &DbConn,
+    ) -> Result<Option<employee_mfa::Model>, Error> {
+        Ok(EmployeeMfa::find_by_id(employee_id.to_string())
+            .filter(employee_mfa::Column::TenantId.eq(tenant_id))
+            .one(db)
+            .await?)
+    }
+
+    pub async fn fetch(employee_id: &str, session: Session, db: &DbConn) -> Result<Mfa, Error> {
+        let tenant = Tenant::fetch_by_id(&session.tenant_id, db).await?;
+        let employee = Employee::fetch_by_id(employee_id, session.clone(), db).await?;
+        let required = !tenant.settings.mfa.protected_actions(&employee).is_empty();
+
+        let mfa = match Mfa::fetch_model(employee_id, &session.tenant_id, db).await? {
+            Some(model) => model.into(),
+            None => Mfa {
+                employee_id: employee_id.to_string(),
+                enabled: false,
+                enabled_at: None,
+                recovery_codes_remaining: 0,
+                required: false,
+            },
+        };
+
+        Ok(Mfa { required, ..mfa })
+    }
+
+    /// Begins enrolment with a new secret, which only takes effect once
+    /// confirmed with a code from it. Enrolment may be restarted until then.
+    pub async fn enrol(session: Session, db: &DbConn) -> Result<MfaEnrolment, Error> {
+        let employee_id = session.employee.id.clone();
+
+        if let Some(existing) = Mfa::fetch_model(&employee_id, &session.tenant_id, db).await? {
+            if existing.enabled_at.is_some() {
+                return Err(ErrorResponse::create_error("MFA is already enabled."));
+            }
+
+            EmployeeMfa::delete_by_id(existing.employee_id)
+                .exec(db)
+                .await?;
+        }
+
+        let secret = Secret::generate_secret().to_encoded().to_string();
+        let totp = totp(&secret, &session.employee.rid)?;
+
+        employee_mfa::ActiveModel {
+            employee_id: Set(employee_id),
+            tenant_id: Set(session.tenant_id),
+            secret: Set(secret.clone()),
+            recovery_codes: Set(json!(Vec::<String>::new())),
+            last_used_step: Set(None),
+            created_at: Set(Utc::now().naive_utc()),
+            enabled_at: Set(None),
+        }
+        .insert(db)
+        .await?;
+
+        Ok(MfaEnrolment {
+            secret,
+            uri: totp.get_url(),
+        })
+    }
+
+    /// Completes enrolment, returning the recovery codes.
+    pub async fn confirm(
+        code: &str,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<RecoveryCodes, Error> {
+        let model = Mfa::fetch_model(&session.employee.id, &session.tenant_id, db)
+            .await?
+            .ok_or_else(|| ErrorResponse::create_error("MFA enrolment has not been started."))?;
+
+        if model.enabled_at.is_some() {
+            return Err(ErrorResponse::create_error("MFA is already enabled."));
+        }
+
+        let step = matching_step(&totp(&model.secret, &session.employee.rid)?, code.trim())
+            .ok_or_else(|| ErrorResponse::custom_unauthorized("Invalid MFA code."))?;
+
+        let (codes, hashes) = generate_recovery_codes();
+
+        employee_mfa::ActiveModel {
+            employee_id: Set(model.employee_id),
+            recovery_codes: Set(json!(hashes)),
+            last_used_step: Set(Some(step)),
+            enabled_at: Set(Some(Utc::now().naive_utc())),
+            ..Default::default()
+        }
+        .update(db)
+        .await?;
+
+        Ok(codes)
+    }
+
+    /// Checks a TOTP or recovery code for an enrolled employee. A TOTP code
+    /// is accepted once, and a recovery code is consumed when used. Each is
+    /// saved only if unchanged since it was read, so of concurrent uses of
+    /// a code, only one is accepted.
+    pub async fn verify(
+        employee: &Employee,
+        tenant_id: &str,
+        code: &str,
+        db: &DbConn,
+    ) -> Result<bool, Error> {
+        let code = code.trim();
+        let hash = hash_code(code);
+
+        loop {
+            let model = match Mfa::fetch_model(&employee.id, tenant_id, db).await? {
+                Some(model) if model.enabled_at.is_some() => model,
+                _ => return Ok(false),
+            };
+
+            if let Some(step) = matching_step(&totp(&model.secret, &employee.rid)?, code) {
+                if model.last_used_step.is_some_and(|last| step <= last) {
+                    return Ok(false);
+                }
+
+                let read = match model.last_used_step {
+                    Some(last) => employee_mfa::Column::LastUsedStep.eq(last),
+                    None => employee_mfa::Column::LastUsedStep.is_null(),
+                };
+
+                let result = EmployeeMfa::update_many()
+                    .col_expr(employee_mfa::Column::LastUsedStep, Expr::value(Some(step)))
+                    .filter(employee_mfa::Column::EmployeeId.eq(model.employee_id))
+                    .filter(read)
+                    .exec(db)
+                    .await?;
+
+                match result.rows_affected == 1 {
+                    true => return Ok(true),
+                    false => continue,
+                }
+            }
+
+            let mut hashes =
+                serde_json::from_value::<Vec<String>>(model.recovery_codes).unwrap_or_default();
+
+            let Some(index) = hashes.iter().position(|candidate| *candidate == hash) else {
+                return Ok(false);
+            };
+
+            // Codes are only removed, or replaced together, so the codes are
+            // unchanged while every one read remains.
+            let read = hashes.iter().fold(Condition::all(), |read, candidate| {
+                read.add(employee_mfa::Column::RecoveryCodes.contains(json!(candidate).to_string()))
+            });
+
+            hashes.remove(index);
+
+            let result = EmployeeMfa::update_many()
+                .col_expr(
+                    employee_mfa::Column::RecoveryCodes,
+                    Expr::value(json!(hashes)),
+                )
+                .filter(employee_mfa::Column::EmployeeId.eq(model.employee_id))
+                .filter(read)
+                .exec(db)
+                .await?;
+
+            if result.rows_affected == 1 {
+                return Ok(true);
+            }
+        }
+    }
+
+    /// Run after the password has been verified, yielding the actions to
+    /// withhold from the session. An enrolled employee must present a valid
+    /// code, otherwise the actions the tenant's policy protects are withheld.
+    pub async fn authorise(
+        employee: &Employee,
+        tenant_id: &str,
+        code: Option<&str>,
+        db: &DbConn,
+    ) -> Result<Vec<Action>, Error> {
+        let enrolled = Mfa::fetch_model(&employee.id, tenant_id, db)
+            .await?
+            .is_some_and(|model| model.enabled_at.is_some());
+
+        if enrolled {
+            return match code {
+                Some(code) if Mfa::verify(employee, tenant_id, code, db).await? => Ok(vec![]),
+                Some(_) => Err(ErrorResponse::custom_unauthorized("Invalid MFA code.")),
+                None => Err(ErrorResponse::custom_unauthorized("MFA code required.")),
+            };
+        }
+
+        let tenant = Tenant::fetch_by_id(tenant_id, db).await?;
+        Ok(tenant.settings.mfa.protected_actions(employee))
+    }
+
+    /// Replaces the recovery codes, invalidating those issued before.
+    pub async fn regenerate_recovery_codes(
+        session: Session,
+        db: &DbConn,
+    ) -> Result<RecoveryCodes, Error> {
+        let (codes, hashes) = generate_recovery_codes();
+
+        employee_mfa::ActiveModel {
+            employee_id: Set(session.employee.id),
+            recovery_codes: Set(json!(hashes)),
+            ..Default::default()
+        }
+        .update(db)
+        .await?;
+
+        Ok(codes)
+    }
+
+    pub async fn disable(employee_id: &str, session: Session, db: &DbConn) -> Result<(), Error> {
+        let result = EmployeeMfa::delete_many()
+            .filter(employee_mfa::Column::EmployeeId.eq(employee_id))
+            .filter(employee_mfa::Column::TenantId.eq(session.tenant_id))
+            .exec(db)
+            .await?;
+
+        match result.rows_affected {
+            0 => Err(DbErr::RecordNotFound(employee_id.to_string()).into()),
+            _ => Ok(()),
+        }
+    }
+}
@@ -2,25 +2,36 @@ pub(crate) mod common;
 mod payment;
 mod stml;
 
+pub mod api_key;
 pub mod customer;
 pub mod employee;
 pub mod helpers;
 pub mod ingress;
 pub mod kiosk;
 pub mod macros;
+pub mod mfa;
+pub mod overrides;
 pub mod product;
+pub mod serial;
+pub mod shipping;
 pub mod store;
 pub mod supplier;
 pub mod tenant;
 pub mod transaction;
 
+pub use self::api_key::*;
 pub use self::common::*;
 pub use self::customer::*;
 pub use self::employee::*;
 pub use self::helpers::*;
+pub use self::ingress::*;
 pub use self::kiosk::*;
+pub use self::mfa::*;
+pub use self::overrides::*;
 pub use self::payment::*;
 pub use self::product::*;
+pub use self::serial::*;
+pub use self::shipping::*;
 pub use self::stml::*;
 pub use self::store::*;
 pub use self::supplier::*;
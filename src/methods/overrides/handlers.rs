@@ -0,0 +1,60 @@
+use crate::catchers::Validated;
+use crate::guards::Convert;
+use crate::methods::{Action, Error};
+use crate::pool::InternalDb;
+use crate::{check_permissions, ManagerOverride, OverridePolicy, OverrideRequest, Session, Tenant};
+use okapi::openapi3::OpenApi;
+use rocket::serde::json::Json;
+use rocket::{get, post};
+use rocket_okapi::settings::OpenApiSettings;
+use rocket_okapi::{openapi, openapi_get_routes_spec};
+
+pub fn documented_routes(settings: &OpenApiSettings) -> (Vec<rocket::Route>, OpenApi) {
+    openapi_get_routes_spec![settings: issue, get_policy, update_policy]
+}
+
+/// Issues an override for the session's employee, once another employee
+/// has entered their credentials at the same kiosk. The returned token is
+/// presented in the `X-Override-Token` header of the approved request.
+#[openapi(tag = "Override")]
+#[post("/", data = "<input_data>")]
+pub async fn issue(
+    db: InternalDb,
+    session: Session,
+    input_data: Validated<Json<OverrideRequest>>,
+) -> Result<Json<ManagerOverride>, Error> {
+    ManagerOverride::issue(input_data.data(), session, &db.0)
+        .await
+        .map(Json)
+}
+
+#[openapi(tag = "Override")]
+#[get("/policy")]
+pub async fn get_policy(db: InternalDb, session: Session) -> Convert<OverridePolicy> {
+    check_permissions!(session.clone(), Action::AccessAdminPanel);
+    Tenant::fetch_by_id(&session.tenant_id, &db.0)
+        .await
+        .map(|tenant| tenant.settings.overrides)
+        .into()
+}
+
+#[openapi(tag = "Override")]
+#[post("/policy", data = "<input_data>")]
+pub async fn update_policy(
+    db: InternalDb,
+    session: Session,
+    input_data: Validated<Json<OverridePolicy>>,
+) -> Convert<OverridePolicy> {
+    check_permissions!(session.clone(), Action::AccessAdminPanel);
+
+    let mut settings = match Tenant::fetch_by_id(&session.tenant_id, &db.0).await {
+        Ok(tenant) => tenant.settings,
+        Err(error) => return Err(error).into(),
+    };
+    settings.overrides = input_data.data();
+
+    Tenant::update_settings(&session.tenant_id, settings, &db.0)
+        .await
+        .map(|tenant| tenant.settings.overrides)
+        .into()
+}
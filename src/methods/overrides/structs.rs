@@ -0,0 +1,389 @@
+#[cfg(feature = "process")]
+use crate::entities::manager_overrides;
+#[cfg(feature = "process")]
+use crate::entities::prelude::ManagerOverrides;
+use crate::methods::{Error, ErrorResponse, Id, Note, NoteList};
+#[cfg(feature = "process")]
+use crate::{Action, AuthenticationLog, Employee, Kiosk, Mfa, Session, SessionVariant, Tenant};
+use chrono::{DateTime, Duration, Utc};
+#[cfg(feature = "process")]
+use rand::distributions::{Alphanumeric, DistString};
+use schemars::JsonSchema;
+#[cfg(feature = "process")]
+use sea_orm::{ActiveModelTrait, ColumnTrait, DbConn, EntityTrait, QueryFilter, Set};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "process")]
+use serde_json::json;
+use std::fmt::Display;
+#[cfg(feature = "process")]
+use tracing::info;
+#[cfg(feature = "process")]
+use uuid::Uuid;
+use validator::Validate;
+
+/// The request header carrying override tokens, which may be repeated
+/// when an action requires more than one.
+pub const OVERRIDE_HEADER: &str = "X-Override-Token";
+
+/// A till action which may require a manager's approval.
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub enum OverrideAction {
+    /// A discount above the tenant's threshold.
+    Discount,
+    Void,
+    Refund,
+    /// Opening the cash drawer outside of a sale.
+    NoSale,
+}
+
+impl Display for OverrideAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let output = match self {
+            OverrideAction::Discount => "DISCOUNT",
+            OverrideAction::Void => "VOID",
+            OverrideAction::Refund => "REFUND",
+            OverrideAction::NoSale => "NO-SALE",
+        };
+
+        write!(f, "{}", output)
+    }
+}
+
+/// A second employee's credentials, entered on the requesting employee's
+/// till.
+#[cfg(feature = "types")]
+#[derive(Deserialize, Clone, JsonSchema, Validate)]
+pub struct OverrideRequest {
+    pub rid: String,
+    pub pass: String,
+    pub kiosk_id: Id,
+    pub action: OverrideAction,
+    /// Binds the override to a single record, i.e. the transaction to void.
+    /// Required of a discount override, naming the sale it is given on.
+    #[serde(default)]
+    pub reference: Option<String>,
+    /// The greatest discount approved, as a percentage. Required of a
+    /// discount override.
+    #[serde(default)]
+    #[validate(range(max = 100))]
+    pub discount: Option<u32>,
+    /// Required when the approving employee is enrolled in MFA.
+    #[serde(default)]
+    pub mfa_code: Option<String>,
+}
+
+/// Recorded against the kiosk's `AuthRecord` for each override attempt.
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct OverrideAttempt {
+    pub action: OverrideAction,
+    pub requested_by: Id,
+    pub reference: Option<String>,
+}
+
+/// A short-lived, single-use approval for one action. The token is only
+/// returned when issued, and is presented in the `X-Override-Token` header
+/// of the request performing the action.
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct ManagerOverride {
+    pub id: Id,
+    pub token: String,
+    pub kiosk_id: Id,
+    pub action: OverrideAction,
+    pub reference: Option<String>,
+    /// The greatest discount approved by a discount override.
+    pub discount: Option<u32>,
+    pub requested_by: Id,
+    pub authorised_by: Id,
+    pub issued_at: DateTime<Utc>,
+    pub expiry: DateTime<Utc>,
+}
+
+#[cfg(feature = "process")]
+impl TryFrom<manager_overrides::Model> for ManagerOverride {
+    type Error = Error;
+
+    fn try_from(val: manager_overrides::Model) -> Result<Self, Self::Error> {
+        Ok(ManagerOverride {
+            id: val.id,
+            token: val.token,
+            kiosk_id: val.kiosk_id,
+            action: serde_json::from_value(val.action)
+                .map_err(|e| ErrorResponse::create_error(&format!("Invalid override, {}", e)))?,
+            reference: val.reference,
+            discount: val.discount.map(|discount| discount as u32),
+            requested_by: val.requested_by,
+            authorised_by: val.authorised_by,
+            issued_at: DateTime::from_naive_utc_and_offset(val.issued_at, Utc),
+            expiry: DateTime::from_naive_utc_and_offset(val.expiry, Utc),
+        })
+    }
+}
+
+#[cfg(feature = "methods")]
+impl ManagerOverride {
+    /// The note recorded against the transaction the override was used on.
+    pub fn note(&self) -> Note {
+        Note {
+            message: format!("{} authorised by manager override {}", self.action, self.id),
+            author: self.authorised_by.clone(),
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn is_usable(
+        &self,
+        session: &Session,
+        action: &OverrideAction,
+        reference: Option<&str>,
+    ) -> bool {
+        self.action == *action
+            && self.requested_by == session.employee.id
+            && self.expiry > Utc::now()
+            && self
+                .reference
+                .as_deref()
+                .is_none_or(|bound| Some(bound) == reference)
+    }
+
+    /// Whether the override approves a `discount` percent, which only a
+    /// discount override limits.
+    fn approves(&self, discount: f32) -> bool {
+        match self.action {
+            OverrideAction::Discount => self
+                .discount
+                .is_some_and(|approved| approved as f32 >= discount),
+            _ => true,
+        }
+    }
+
+    /// Authenticates the approving employee at the requesting session's
+    /// kiosk, recording the attempt in the kiosk's `AuthRecord`.
+    pub async fn issue(
+        request: OverrideRequest,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<ManagerOverride, Error> {
+        if let SessionVariant::ApiKey(_) = session.variant {
+            return Err(ErrorResponse::custom_unauthorized(
+                "Overrides cannot be requested using an API key.",
+            ));
+        }
+
+        // Overrides are given in person, so must be entered at the till the
+        // requesting session was issued at.
+        let session_kiosk = session.kiosk_id(db).await?;
+
+        if session_kiosk.as_deref() != Some(request.kiosk_id.as_str()) {
+            return Err(ErrorResponse::custom_unauthorized(
+                "Overrides must be requested at the kiosk the session was issued at.",
+            ));
+        }
+
+        // A discount is approved up to an amount, on the one sale.
+        let discount = match request.action {
+            OverrideAction::Discount => match (&request.reference, request.discount) {
+                (Some(_), Some(discount)) => Some(discount),
+                _ => {
+                    return Err(ErrorResponse::create_error(
+                        "A discount override must give the sale and the discount it approves.",
+                    ))
+                }
+            },
+            _ => None,
+        };
+
+        let attempt = OverrideAttempt {
+            action: request.action.clone(),
+            requested_by: session.employee.id.clone(),
+            reference: request.reference.clone(),
+        };
+
+        let approved = ManagerOverride::approve(&request, &session, db).await;
+
+        let log = AuthenticationLog {
+            employee_id: match &approved {
+                Ok(employee) => employee.id.clone(),
+                Err(_) => request.rid.clone(),
+            },
+            successful: approved.is_ok(),
+            override_of: Some(attempt),
+        };
+        Kiosk::auth_log(&request.kiosk_id, session.clone(), log, db).await?;
+
+        let approver = approved?;
+        let tenant = Tenant::fetch_by_id(&session.tenant_id, db).await?;
+        let issued_at = Utc::now();
+        let expiry = issued_at + Duration::seconds(tenant.settings.overrides.lifetime_secs as i64);
+
+        let token = Alphanumeric.sample_string(&mut rand::thread_rng(), 32);
+
+        let model = manager_overrides::ActiveModel {
+            id: Set(Uuid::new_v4().to_string()),
+            token: Set(token),
+            tenant_id: Set(session.tenant_id.clone()),
+            kiosk_id: Set(request.kiosk_id),
+            action: Set(json!(request.action)),
+            reference: Set(request.reference),
+            discount: Set(discount.map(|discount| discount as i32)),
+            requested_by: Set(session.employee.id),
+            authorised_by: Set(approver.id),
+            issued_at: Set(issued_at.naive_utc()),
+            expiry: Set(expiry.naive_utc()),
+            consumed_at: Set(None),
+        }
+        .insert(db)
+        .await?;
+
+        info!(override_id = %model.id, action = %request.action, "issued manager override");
+
+        model.try_into()
+    }
+
+    /// Verifies the approving employee's credentials, yielding them when
+    /// they may authorise overrides.
+    async fn approve(
+        request: &OverrideRequest,
+        session: &Session,
+        db: &DbConn,
+    ) -> Result<Employee, Error> {
+        let mut approver =
+            Employee::verify_with_rid(&request.rid, session.clone(), &request.pass, db)
+                .await
+                .map_err(|_| ErrorResponse::custom_unauthorized("Invalid password or id."))?;
+
+        if approver.id == session.employee.id {
+            return Err(ErrorResponse::custom_unauthorized(
+                "An override must be authorised by another employee.",
+            ));
+        }
+
+        let restricted = Mfa::authorise(
+            &approver,
+            &session.tenant_id,
+            request.mfa_code.as_deref(),
+            db,
+        )
+        .await?;
+        approver.restrict(&restricted);
+
+        let permitted = approver
+            .level
+            .iter()
+            .any(|access| access.action == Action::AuthoriseOverride && access.authority >= 1);
+
+        match permitted {
+            true => Ok(approver),
+            false => Err(ErrorResponse::custom_unauthorized(
+                "Employee is not permitted to authorise overrides.",
+            )),
+        }
+    }
+
+    /// The overrides in `tokens` for `action` which are yet to be used,
+    /// without consuming them.
+    pub async fn held(
+        action: &OverrideAction,
+        reference: Option<&str>,
+        tokens: &[String],
+        session: &Session,
+        db: &DbConn,
+    ) -> Result<Vec<ManagerOverride>, Error> {
+        let mut held = vec![];
+
+        for token in tokens {
+            let model = ManagerOverrides::find()
+                .filter(manager_overrides::Column::Token.eq(token.as_str()))
+                .filter(manager_overrides::Column::TenantId.eq(session.tenant_id.as_str()))
+                .filter(manager_overrides::Column::ConsumedAt.is_null())
+                .one(db)
+                .await?;
+
+            if let Some(model) = model {
+                let candidate = ManagerOverride::try_from(model)?;
+
+                if candidate.is_usable(session, action, reference) {
+                    held.push(candidate);
+                }
+            }
+        }
+
+        Ok(held)
+    }
+
+    /// Consumes an override from `tokens` for each of `actions`, yielding
+    /// the notes to record against the action's transaction. A discount
+    /// override must approve the `discount` given. Nothing is required of
+    /// an employee permitted to authorise overrides themselves.
+    pub async fn authorise(
+        actions: &[OverrideAction],
+        reference: Option<&str>,
+        discount: f32,
+        tokens: &[String],
+        session: &Session,
+        db: &DbConn,
+    ) -> Result<NoteList, Error> {
+        if actions.is_empty() || session.clone().has_permission(Action::AuthoriseOverride) {
+            return Ok(vec![]);
+        }
+
+        let mut available = vec![];
+
+        for token in tokens {
+            let model = ManagerOverrides::find()
+                .filter(manager_overrides::Column::Token.eq(token.as_str()))
+                .filter(manager_overrides::Column::TenantId.eq(session.tenant_id.as_str()))
+                .filter(manager_overrides::Column::ConsumedAt.is_null())
+                .one(db)
+                .await?;
+
+            if let Some(model) = model {
+                available.push(ManagerOverride::try_from(model)?);
+            }
+        }
+
+        // Every action must be covered before any override is consumed, so
+        // that a partially approved request does not spend them.
+        let mut selected: Vec<ManagerOverride> = vec![];
+
+        for action in actions {
+            let position = available
+                .iter()
+                .position(|candidate| {
+                    candidate.is_usable(session, action, reference) && candidate.approves(discount)
+                })
+                .ok_or_else(|| {
+                    ErrorResponse::custom_unauthorized(&format!(
+                        "A manager override is required for {}.",
+                        action
+                    ))
+                })?;
+
+            selected.push(available.remove(position));
+        }
+
+        for used in &selected {
+            let result = ManagerOverrides::update_many()
+                .col_expr(
+                    manager_overrides::Column::ConsumedAt,
+                    sea_orm::sea_query::Expr::value(Utc::now().naive_utc()),
+                )
+                .filter(manager_overrides::Column::Id.eq(used.id.as_str()))
+                .filter(manager_overrides::Column::ConsumedAt.is_null())
+                .exec(db)
+                .await?;
+
+            if result.rows_affected == 0 {
+                return Err(ErrorResponse::custom_unauthorized(
+                    "The manager override has already been used.",
+                ));
+            }
+
+            info!(override_id = %used.id, action = %used.action, "consumed manager override");
+        }
+
+        Ok(selected.iter().map(ManagerOverride::note).collect())
+    }
+}
@@ -55,6 +55,16 @@ pub fn apply_discount(discount: DiscountValue, price: f32) -> f32 {
     }
 }
 
+/// The share of `price` the discount removes, as a percentage.
+pub fn discount_percentage(discount: &DiscountValue, price: f32) -> f32 {
+    match discount {
+        DiscountValue::Percentage(val) => *val as f32,
+        DiscountValue::Absolute(0) => 0.0,
+        DiscountValue::Absolute(_) if price <= 0.0 => 100.0,
+        DiscountValue::Absolute(val) => (*val as f32 / price) * 100.0,
+    }
+}
+
 impl FromStr for DiscountValue {
     type Err = String;
 
@@ -0,0 +1,810 @@
+//! CSV import and export of the product catalogue, with one row per variant.
+//!
+//! | Column                 | Maps to                                                 |
+//! |------------------------|---------------------------------------------------------|
+//! | `product_sku`          | `Product.sku`, rows sharing it form one product         |
+//! | `product_name`         | `Product.name`                                          |
+//! | `product_name_long`    | `Product.name_long`                                     |
+//! | `company`              | `Product.company`                                       |
+//! | `description`          | `Product.description`                                   |
+//! | `description_long`     | `Product.description_long`                              |
+//! | `tags`, `images`       | `Product.tags`, `Product.images`, separated by `\|`     |
+//! | `visible`              | `AlwaysShown`, `AlwaysHidden` or `ShowWhenInStock`      |
+//! | `product_ean`, `product_hs_code`, `product_article_code`, `product_isbn` | `Product.identification` |
+//! | `variant_id`           | `VariantInformation.id`                                 |
+//! | `variant_name`         | `VariantInformation.name`                               |
+//! | `variant_options`      | The variant's groups, as `Category=Name:CODE` separated by `\|`, i.e. `Colour=Black:02\|Size=Small:21` |
+//! | `barcode`              | `VariantInformation.barcode`                            |
+//! | `variant_sku`, `variant_ean`, `variant_hs_code`, `variant_article_code`, `variant_isbn` | `VariantInformation.identification` |
+//! | `retail_price`, `marginal_price` | `VariantInformation.retail_price`, `.marginal_price` |
+//! | `buy_min`, `buy_max`   | `VariantInformation.buy_min`, `.buy_max`                |
+//! | `stock_tracking`       | `VariantInformation.stock_tracking`, `true` or `false`  |
+//! | `stock:<store code>`   | The sellable quantity held at the store with that code  |
+//!
+//! Product columns are read from the first row of each product. Columns
+//! may be omitted, in which case existing values are kept. Products are
+//! matched to those existing by `product_sku`, falling back to a variant's
+//! `barcode`, and variants by `variant_id`, `barcode` and then their
+//! options. Neither products nor variants are removed by an import.
+
+#[cfg(feature = "process")]
+use crate::methods::{Error, ErrorResponse};
+use crate::methods::{ImportRowError, Location};
+#[cfg(feature = "process")]
+use crate::{
+    DiscountValue, Product, ProductIdentification, ProductVisibility, Quantity, Session, Stock,
+    StockInformation, Store, Variant, VariantCategory, VariantInformation,
+};
+#[cfg(feature = "process")]
+use chrono::Utc;
+use schemars::JsonSchema;
+#[cfg(feature = "process")]
+use sea_orm::DbConn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+#[cfg(feature = "process")]
+use std::collections::{BTreeSet, HashMap};
+#[cfg(feature = "process")]
+use uuid::Uuid;
+
+/// The columns written on export, followed by a `stock:<store code>`
+/// column for each store.
+pub const CATALOGUE_COLUMNS: [&str; 27] = [
+    "product_sku",
+    "product_name",
+    "product_name_long",
+    "company",
+    "description",
+    "description_long",
+    "tags",
+    "images",
+    "visible",
+    "product_ean",
+    "product_hs_code",
+    "product_article_code",
+    "product_isbn",
+    "variant_id",
+    "variant_name",
+    "variant_options",
+    "barcode",
+    "variant_sku",
+    "variant_ean",
+    "variant_hs_code",
+    "variant_article_code",
+    "variant_isbn",
+    "retail_price",
+    "marginal_price",
+    "buy_min",
+    "buy_max",
+    "stock_tracking",
+];
+
+pub const STOCK_COLUMN_PREFIX: &str = "stock:";
+
+const LIST_SEPARATOR: char = '|';
+
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub enum CatalogueAction {
+    Create,
+    Update,
+    Unchanged,
+}
+
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct FieldChange {
+    /// The changed field, variant fields are given as `variants.<id>.<field>`.
+    pub field: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct ProductDiff {
+    pub sku: String,
+    pub action: CatalogueAction,
+    pub changes: Vec<FieldChange>,
+}
+
+/// The outcome of a catalogue import. In a dry run nothing is saved, and
+/// the diff describes what the import would do.
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct CatalogueImport {
+    pub dry_run: bool,
+    pub products: Vec<ProductDiff>,
+    /// Rows which could not be read, `index` being the row's position
+    /// after the header. A product with any such row is not imported.
+    pub errors: Vec<ImportRowError>,
+}
+
+#[cfg(feature = "process")]
+type Row = HashMap<String, String>;
+
+#[cfg(feature = "process")]
+fn column<'a>(row: &'a Row, name: &str) -> Option<&'a str> {
+    row.get(name).map(|value| value.trim())
+}
+
+#[cfg(feature = "process")]
+fn list(value: &str) -> Vec<String> {
+    value
+        .split(LIST_SEPARATOR)
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(feature = "process")]
+fn number<T: std::str::FromStr>(value: &str, name: &str) -> Result<T, String> {
+    value
+        .parse::<T>()
+        .map_err(|_| format!("`{}` is not a number, found `{}`.", name, value))
+}
+
+#[cfg(feature = "process")]
+fn boolean(value: &str, name: &str) -> Result<bool, String> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "1" => Ok(true),
+        "false" | "no" | "0" => Ok(false),
+        _ => Err(format!(
+            "`{}` must be true or false, found `{}`.",
+            name, value
+        )),
+    }
+}
+
+/// Reads `Category=Name:CODE` options.
+#[cfg(feature = "process")]
+fn options(value: &str) -> Result<Vec<(String, String, String)>, String> {
+    list(value)
+        .iter()
+        .map(|option| {
+            option
+                .split_once('=')
+                .and_then(|(category, rest)| {
+                    rest.rsplit_once(':').map(|(name, code)| {
+                        (
+                            category.trim().to_string(),
+                            name.trim().to_string(),
+                            code.trim().to_string(),
+                        )
+                    })
+                })
+                .filter(|(category, _, code)| !category.is_empty() && !code.is_empty())
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid variant option `{}`, expected `Category=Name:CODE`.",
+                        option
+                    )
+                })
+        })
+        .collect()
+}
+
+#[cfg(feature = "process")]
+fn identification(row: &Row, prefix: &str, into: &mut ProductIdentification) {
+    let fields = [
+        ("sku", &mut into.sku),
+        ("ean", &mut into.ean),
+        ("hs_code", &mut into.hs_code),
+        ("article_code", &mut into.article_code),
+        ("isbn", &mut into.isbn),
+    ];
+
+    for (name, field) in fields {
+        if let Some(value) = column(row, &format!("{}_{}", prefix, name)) {
+            *field = value.to_string();
+        }
+    }
+}
+
+#[cfg(feature = "process")]
+pub(crate) fn new_product(sku: &str) -> Product {
+    Product {
+        name: String::new(),
+        name_long: String::new(),
+        company: String::new(),
+        variant_groups: vec![],
+        variants: vec![],
+        sku: sku.to_string(),
+        identification: ProductIdentification::default(),
+        images: vec![],
+        tags: vec![],
+        description: String::new(),
+        description_long: String::new(),
+        specifications: vec![],
+        visible: ProductVisibility::ShowWhenInStock,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    }
+}
+
+#[cfg(feature = "process")]
+pub(crate) fn new_variant(id: String) -> VariantInformation {
+    VariantInformation {
+        id,
+        name: String::new(),
+        stock: vec![],
+        stock_information: StockInformation {
+            stock_group: String::new(),
+            sales_group: String::new(),
+            value_stream: String::new(),
+            min_stock_before_alert: 0.0,
+            min_stock_level: 0.0,
+            brand: String::new(),
+            colli: String::new(),
+            size_x: 0.0,
+            size_y: 0.0,
+            size_z: 0.0,
+            size_x_unit: String::new(),
+            size_y_unit: String::new(),
+            size_z_unit: String::new(),
+            size_override_unit: String::new(),
+            tax_code: String::new(),
+            weight: String::new(),
+            volume: String::new(),
+            max_volume: String::new(),
+            back_order: false,
+            discontinued: false,
+            non_diminishing: false,
+            shippable: true,
+        },
+        images: vec![],
+        retail_price: 0.0,
+        marginal_price: 0.0,
+        buy_min: 1.0,
+        buy_max: -1.0,
+        loyalty_discount: DiscountValue::Absolute(0),
+        variant_code: vec![],
+        order_history: vec![],
+        barcode: String::new(),
+        identification: ProductIdentification::default(),
+        stock_tracking: true,
+    }
+}
+
+#[cfg(feature = "process")]
+fn apply_product_columns(row: &Row, product: &mut Product) -> Result<(), String> {
+    let text = [
+        ("product_name", &mut product.name),
+        ("product_name_long", &mut product.name_long),
+        ("company", &mut product.company),
+        ("description", &mut product.description),
+        ("description_long", &mut product.description_long),
+    ];
+
+    for (name, field) in text {
+        if let Some(value) = column(row, name) {
+            *field = value.to_string();
+        }
+    }
+
+    if let Some(value) = column(row, "tags") {
+        product.tags = list(value);
+    }
+    if let Some(value) = column(row, "images") {
+        product.images = list(value);
+    }
+    if let Some(value) = column(row, "visible").filter(|value| !value.is_empty()) {
+        product.visible = serde_json::from_value(Value::String(value.to_string()))
+            .map_err(|_| format!("Unknown visibility `{}`.", value))?;
+    }
+
+    identification(row, "product", &mut product.identification);
+
+    Ok(())
+}
+
+/// Applies a row to the product's matching variant, adding the variant
+/// (and any new options to the product's groups) where there is none.
+#[cfg(feature = "process")]
+fn apply_variant_columns(
+    row: &Row,
+    product: &mut Product,
+    stores: &HashMap<String, Location>,
+) -> Result<(), String> {
+    let id = column(row, "variant_id").filter(|id| !id.is_empty());
+    let barcode = column(row, "barcode").filter(|barcode| !barcode.is_empty());
+    let options = match column(row, "variant_options") {
+        Some(value) => Some(options(value)?),
+        None => None,
+    };
+    let codes = options
+        .as_ref()
+        .map(|options| options.iter().map(|(_, _, code)| code.clone()).collect());
+
+    let position = product.variants.iter().position(|variant| {
+        id.is_some_and(|id| variant.id == id)
+            || barcode.is_some_and(|barcode| variant.barcode == barcode)
+            || (id.is_none() && codes.as_ref() == Some(&variant.variant_code))
+    });
+
+    let position = match position {
+        Some(position) => position,
+        None => {
+            let id = id.map_or_else(|| Uuid::new_v4().to_string(), str::to_string);
+            product.variants.push(new_variant(id));
+            product.variants.len() - 1
+        }
+    };
+    let variant = &mut product.variants[position];
+
+    if let Some(value) = column(row, "variant_name") {
+        variant.name = value.to_string();
+    }
+    if let Some(value) = barcode {
+        variant.barcode = value.to_string();
+    }
+    identification(row, "variant", &mut variant.identification);
+
+    if let Some(value) = column(row, "retail_price").filter(|v| !v.is_empty()) {
+        variant.retail_price = number(value, "retail_price")?;
+    }
+    if let Some(value) = column(row, "marginal_price").filter(|v| !v.is_empty()) {
+        variant.marginal_price = number(value, "marginal_price")?;
+    }
+    if let Some(value) = column(row, "buy_min").filter(|v| !v.is_empty()) {
+        variant.buy_min = number(value, "buy_min")?;
+    }
+    if let Some(value) = column(row, "buy_max").filter(|v| !v.is_empty()) {
+        variant.buy_max = number(value, "buy_max")?;
+    }
+    if let Some(value) = column(row, "stock_tracking").filter(|v| !v.is_empty()) {
+        variant.stock_tracking = boolean(value, "stock_tracking")?;
+    }
+
+    let mut stock_columns = row
+        .iter()
+        .filter_map(|(name, value)| {
+            name.strip_prefix(STOCK_COLUMN_PREFIX)
+                .map(|code| (code.trim(), value.trim()))
+        })
+        .filter(|(_, value)| !value.is_empty())
+        .collect::<Vec<(&str, &str)>>();
+    stock_columns.sort();
+
+    for (code, value) in stock_columns {
+        let quantity: f32 = number(value, &format!("{}{}", STOCK_COLUMN_PREFIX, code))?;
+
+        match variant
+            .stock
+            .iter_mut()
+            .find(|stock| stock.store.store_code == code)
+        {
+            Some(stock) => stock.quantity.quantity_sellable = quantity,
+            None => {
+                let store = stores
+                    .get(code)
+                    .ok_or_else(|| format!("Unknown store code `{}`.", code))?;
+
+                variant.stock.push(Stock {
+                    store: store.clone(),
+                    quantity: Quantity {
+                        quantity_sellable: quantity,
+                        quantity_unsellable: 0.0,
+                        quantity_on_order: 0.0,
+                        quantity_allocated: 0.0,
+                    },
+                    lots: vec![],
+                });
+            }
+        }
+    }
+
+    if let Some(options) = options {
+        let marginal_price = variant.marginal_price;
+        variant.variant_code = codes.unwrap_or_default();
+
+        for (category, name, code) in options {
+            let group = match product
+                .variant_groups
+                .iter()
+                .position(|group| group.category == category)
+            {
+                Some(position) => &mut product.variant_groups[position],
+                None => {
+                    product.variant_groups.push(VariantCategory {
+                        category,
+                        variants: vec![],
+                    });
+                    product.variant_groups.last_mut().unwrap()
+                }
+            };
+
+            if !group.variants.iter().any(|v| v.variant_code == code) {
+                group.variants.push(Variant {
+                    name,
+                    images: vec![],
+                    marginal_price,
+                    variant_code: code,
+                    order_history: vec![],
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Lists the fields which differ between the existing and imported product.
+#[cfg(feature = "process")]
+fn diff(before: &Product, after: &Product) -> Vec<FieldChange> {
+    let (before, after) = (
+        serde_json::to_value(before).unwrap_or_default(),
+        serde_json::to_value(after).unwrap_or_default(),
+    );
+    let mut changes = vec![];
+
+    let fields = |value: &Value| value.as_object().cloned().unwrap_or_default();
+    let (before_fields, after_fields) = (fields(&before), fields(&after));
+
+    for (field, value) in after_fields.iter() {
+        if ["variants", "created_at", "updated_at"].contains(&field.as_str()) {
+            continue;
+        }
+
+        let previous = before_fields.get(field).cloned().unwrap_or(Value::Null);
+        if previous != *value {
+            changes.push(FieldChange {
+                field: field.clone(),
+                before: previous,
+                after: value.clone(),
+            });
+        }
+    }
+
+    let variants = |value: &Value| {
+        value["variants"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|variant| {
+                (
+                    variant["id"].as_str().unwrap_or_default().to_string(),
+                    variant,
+                )
+            })
+            .collect::<HashMap<String, Value>>()
+    };
+    let previous_variants = variants(&before);
+
+    for variant in after["variants"].as_array().cloned().unwrap_or_default() {
+        let id = variant["id"].as_str().unwrap_or_default().to_string();
+
+        match previous_variants.get(&id) {
+            None => changes.push(FieldChange {
+                field: format!("variants.{}", id),
+                before: Value::Null,
+                after: variant,
+            }),
+            Some(previous) => {
+                for (field, value) in fields(&variant) {
+                    if previous[&field] != value {
+                        changes.push(FieldChange {
+                            field: format!("variants.{}.{}", id, field),
+                            before: previous[&field].clone(),
+                            after: value,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    changes
+}
+
+#[cfg(feature = "process")]
+fn csv_error(error: csv::Error) -> Error {
+    ErrorResponse::create_error(&format!("Invalid catalogue CSV, {}", error))
+}
+
+#[cfg(feature = "methods")]
+impl CatalogueImport {
+    /// Imports the catalogue, or with `dry_run` only reports the changes it
+    /// would make.
+    pub async fn run(
+        contents: &str,
+        dry_run: bool,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<CatalogueImport, Error> {
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::Headers)
+            .from_reader(contents.as_bytes());
+
+        if !reader
+            .headers()
+            .map_err(csv_error)?
+            .iter()
+            .any(|header| header == "product_sku")
+        {
+            return Err(ErrorResponse::create_error(
+                "The catalogue must have a `product_sku` column.",
+            ));
+        }
+
+        let stores = Store::fetch_all(session.clone(), db)
+            .await?
+            .into_iter()
+            .map(|store| {
+                let location = Location {
+                    store_code: store.code.clone(),
+                    store_id: store.id,
+                    contact: store.contact,
+                };
+                (store.code, location)
+            })
+            .collect::<HashMap<String, Location>>();
+
+        let existing = Product::fetch_all(session.clone(), db).await?;
+
+        let mut errors = vec![];
+        // Rows are grouped by product, in the order each is first seen.
+        let mut groups: Vec<(String, Vec<(usize, Row)>)> = vec![];
+
+        for (index, record) in reader.deserialize::<Row>().enumerate() {
+            let row = match record {
+                Ok(row) => row,
+                Err(error) => {
+                    errors.push(ImportRowError {
+                        collection: "products".to_string(),
+                        index,
+                        id: None,
+                        message: error.to_string(),
+                    });
+                    continue;
+                }
+            };
+
+            let sku = column(&row, "product_sku").unwrap_or_default().to_string();
+
+            if sku.is_empty() {
+                errors.push(ImportRowError {
+                    collection: "products".to_string(),
+                    index,
+                    id: None,
+                    message: "`product_sku` is required.".to_string(),
+                });
+                continue;
+            }
+
+            match groups.iter_mut().find(|(group, _)| *group == sku) {
+                Some((_, rows)) => rows.push((index, row)),
+                None => groups.push((sku, vec![(index, row)])),
+            }
+        }
+
+        let mut products = vec![];
+
+        for (sku, rows) in groups {
+            let matched = existing
+                .iter()
+                .find(|product| product.sku == sku)
+                .or_else(|| {
+                    existing.iter().find(|product| {
+                        rows.iter().any(|(_, row)| {
+                            column(row, "barcode")
+                                .filter(|barcode| !barcode.is_empty())
+                                .is_some_and(|barcode| {
+                                    product.variants.iter().any(|v| v.barcode == barcode)
+                                })
+                        })
+                    })
+                });
+
+            let mut product = matched.cloned().unwrap_or_else(|| new_product(&sku));
+            let mut failed = false;
+
+            for (position, (index, row)) in rows.iter().enumerate() {
+                let applied = match position {
+                    0 => apply_product_columns(row, &mut product),
+                    _ => Ok(()),
+                }
+                .and_then(|_| apply_variant_columns(row, &mut product, &stores));
+
+                if let Err(message) = applied {
+                    failed = true;
+                    errors.push(ImportRowError {
+                        collection: "products".to_string(),
+                        index: *index,
+                        id: Some(sku.clone()),
+                        message,
+                    });
+                }
+            }
+
+            if matched.is_none() && product.name.is_empty() {
+                failed = true;
+                errors.push(ImportRowError {
+                    collection: "products".to_string(),
+                    index: rows[0].0,
+                    id: Some(sku.clone()),
+                    message: "`product_name` is required for a new product.".to_string(),
+                });
+            }
+
+            if failed {
+                continue;
+            }
+
+            let diff = match matched {
+                None => ProductDiff {
+                    sku: product.sku.clone(),
+                    action: CatalogueAction::Create,
+                    changes: diff(&new_product(&product.sku), &product),
+                },
+                Some(matched) => {
+                    let changes = diff(matched, &product);
+                    ProductDiff {
+                        sku: matched.sku.clone(),
+                        action: match changes.is_empty() {
+                            true => CatalogueAction::Unchanged,
+                            false => CatalogueAction::Update,
+                        },
+                        changes,
+                    }
+                }
+            };
+
+            if !dry_run {
+                match diff.action {
+                    CatalogueAction::Create => {
+                        Product::insert(product, session.clone(), db).await?;
+                    }
+                    CatalogueAction::Update => {
+                        let sku = product.sku.clone();
+                        product.updated_at = Utc::now();
+                        Product::update(product, session.clone(), &sku, db).await?;
+                    }
+                    CatalogueAction::Unchanged => {}
+                }
+            }
+
+            products.push(diff);
+        }
+
+        Ok(CatalogueImport {
+            dry_run,
+            products,
+            errors,
+        })
+    }
+}
+
+#[cfg(feature = "methods")]
+impl Product {
+    /// Writes the tenant's catalogue in the format read by
+    /// `CatalogueImport`, with a stock column for each store.
+    pub async fn export_csv(session: Session, db: &DbConn) -> Result<String, Error> {
+        let products = Product::fetch_all(session.clone(), db).await?;
+
+        let mut store_codes = Store::fetch_all(session, db)
+            .await?
+            .into_iter()
+            .map(|store| store.code)
+            .collect::<BTreeSet<String>>();
+        products
+            .iter()
+            .flat_map(|product| product.variants.iter())
+            .flat_map(|variant| variant.stock.iter())
+            .for_each(|stock| {
+                store_codes.insert(stock.store.store_code.clone());
+            });
+
+        let mut writer = csv::Writer::from_writer(vec![]);
+
+        let stock_headers = store_codes
+            .iter()
+            .map(|code| format!("{}{}", STOCK_COLUMN_PREFIX, code));
+        writer
+            .write_record(
+                CATALOGUE_COLUMNS
+                    .iter()
+                    .map(|column| column.to_string())
+                    .chain(stock_headers),
+            )
+            .map_err(csv_error)?;
+
+        for product in &products {
+            let visible = serde_json::to_value(&product.visible)
+                .ok()
+                .and_then(|value| value.as_str().map(str::to_string))
+                .unwrap_or_default();
+
+            let product_columns = vec![
+                product.sku.clone(),
+                product.name.clone(),
+                product.name_long.clone(),
+                product.company.clone(),
+                product.description.clone(),
+                product.description_long.clone(),
+                product.tags.join("|"),
+                product.images.join("|"),
+                visible,
+                product.identification.ean.clone(),
+                product.identification.hs_code.clone(),
+                product.identification.article_code.clone(),
+                product.identification.isbn.clone(),
+            ];
+
+            if product.variants.is_empty() {
+                let empty = CATALOGUE_COLUMNS.len() - product_columns.len() + store_codes.len();
+                writer
+                    .write_record(
+                        product_columns
+                            .iter()
+                            .cloned()
+                            .chain(vec![String::new(); empty]),
+                    )
+                    .map_err(csv_error)?;
+                continue;
+            }
+
+            for variant in &product.variants {
+                let options = variant
+                    .variant_code
+                    .iter()
+                    .filter_map(|code| {
+                        product.variant_groups.iter().find_map(|group| {
+                            group
+                                .variants
+                                .iter()
+                                .find(|v| v.variant_code == *code)
+                                .map(|v| format!("{}={}:{}", group.category, v.name, code))
+                        })
+                    })
+                    .collect::<Vec<String>>()
+                    .join("|");
+
+                let variant_columns = vec![
+                    variant.id.clone(),
+                    variant.name.clone(),
+                    options,
+                    variant.barcode.clone(),
+                    variant.identification.sku.clone(),
+                    variant.identification.ean.clone(),
+                    variant.identification.hs_code.clone(),
+                    variant.identification.article_code.clone(),
+                    variant.identification.isbn.clone(),
+                    variant.retail_price.to_string(),
+                    variant.marginal_price.to_string(),
+                    variant.buy_min.to_string(),
+                    variant.buy_max.to_string(),
+                    variant.stock_tracking.to_string(),
+                ];
+
+                let stock_columns = store_codes.iter().map(|code| {
+                    variant
+                        .stock
+                        .iter()
+                        .find(|stock| stock.store.store_code == *code)
+                        .map(|stock| stock.quantity.quantity_sellable.to_string())
+                        .unwrap_or_default()
+                });
+
+                writer
+                    .write_record(
+                        product_columns
+                            .iter()
+                            .cloned()
+                            .chain(variant_columns)
+                            .chain(stock_columns),
+                    )
+                    .map_err(csv_error)?;
+            }
+        }
+
+        let bytes = writer.into_inner().map_err(|e| {
+            ErrorResponse::create_error(&format!("Unable to write catalogue, {}", e))
+        })?;
+
+        String::from_utf8(bytes)
+            .map_err(|e| ErrorResponse::create_error(&format!("Unable to write catalogue, {}", e)))
+    }
+}
@@ -204,7 +204,8 @@ pub fn example_products() -> Vec<Product> {
                                 quantity_on_order: 0.0,
                                 quantity_unsellable: 0.0,
                                 quantity_allocated: 0.0
-                            }
+                            },
+                            lots: vec![]
                         },
                         Stock {
                             store: westfield.clone(),
@@ -213,7 +214,8 @@ pub fn example_products() -> Vec<Product> {
                                 quantity_on_order: 2.0,
                                 quantity_unsellable: 2.0,
                                 quantity_allocated: 0.0
-                            }
+                            },
+                            lots: vec![]
                         },
                         Stock {
                             store: albany.clone(),
@@ -222,7 +224,8 @@ pub fn example_products() -> Vec<Product> {
                                 quantity_on_order: 0.0,
                                 quantity_unsellable: 2.0,
                                 quantity_allocated: 0.0
-                            }
+                            },
+                            lots: vec![]
                         },
                     ],
                     images: vec![
@@ -274,7 +277,8 @@ pub fn example_products() -> Vec<Product> {
                                 quantity_unsellable: 2.0,
                                 quantity_on_order: 4.0,
                                 quantity_allocated: 0.0
-                            }
+                            },
+                            lots: vec![]
                         },
                         Stock {
                             store: westfield.clone(),
@@ -283,7 +287,8 @@ pub fn example_products() -> Vec<Product> {
                                 quantity_on_order: 1.0,
                                 quantity_unsellable: 0.0,
                                 quantity_allocated: 0.0
-                            }
+                            },
+                            lots: vec![]
                         },
                         Stock {
                             store: albany.clone(),
@@ -292,7 +297,8 @@ pub fn example_products() -> Vec<Product> {
                                 quantity_on_order: 0.0,
                                 quantity_unsellable: 2.0,
                                 quantity_allocated: 0.0
-                            }
+                            },
+                            lots: vec![]
                         },
                     ],
                     images: vec![
@@ -344,7 +350,8 @@ pub fn example_products() -> Vec<Product> {
                                 quantity_on_order: 0.0,
                                 quantity_unsellable: 2.0,
                                 quantity_allocated: 0.0
-                            }
+                            },
+                            lots: vec![]
                         },
                         Stock {
                             store: westfield.clone(),
@@ -353,7 +360,8 @@ pub fn example_products() -> Vec<Product> {
                                 quantity_on_order: 0.0,
                                 quantity_unsellable: 1.0,
                                 quantity_allocated: 0.0
-                            }
+                            },
+                            lots: vec![]
                         },
                         Stock {
                             store: albany.clone(),
@@ -362,7 +370,8 @@ pub fn example_products() -> Vec<Product> {
                                 quantity_on_order: 0.0,
                                 quantity_unsellable: 2.0,
                                 quantity_allocated: 0.0
-                            }
+                            },
+                            lots: vec![]
                         },
                     ],
                     images: vec![
@@ -480,7 +489,8 @@ pub fn example_products() -> Vec<Product> {
                                 quantity_on_order: 0.0,
                                 quantity_unsellable: 2.0,
                                 quantity_allocated: 0.0
-                            }
+                            },
+                            lots: vec![]
                         },
                         Stock {
                             store: westfield.clone(),
@@ -489,7 +499,8 @@ pub fn example_products() -> Vec<Product> {
                                 quantity_on_order: 1.0,
                                 quantity_unsellable: 0.0,
                                 quantity_allocated: 0.0
-                            }
+                            },
+                            lots: vec![]
                         },
                         Stock {
                             store: albany.clone(),
@@ -498,7 +509,8 @@ pub fn example_products() -> Vec<Product> {
                                 quantity_on_order: 2.0,
                                 quantity_unsellable: 1.0,
                                 quantity_allocated: 0.0
-                            }
+                            },
+                            lots: vec![]
                         },
                     ],
                     images: vec![
@@ -550,7 +562,8 @@ pub fn example_products() -> Vec<Product> {
                                 quantity_unsellable: 2.0,
                                 quantity_on_order: 4.0,
                                 quantity_allocated: 0.0
-                            }
+                            },
+                            lots: vec![]
                         },
                         Stock {
                             store: westfield.clone(),
@@ -559,7 +572,8 @@ pub fn example_products() -> Vec<Product> {
                                 quantity_on_order: 1.0,
                                 quantity_unsellable: 0.0,
                                 quantity_allocated: 0.0
-                            }
+                            },
+                            lots: vec![]
                         },
                         Stock {
                             store: albany.clone(),
@@ -568,7 +582,8 @@ pub fn example_products() -> Vec<Product> {
                                 quantity_on_order: 0.0,
                                 quantity_unsellable: 2.0,
                                 quantity_allocated: 0.0
-                            }
+                            },
+                            lots: vec![]
                         },
                     ],
                     images: vec![
@@ -698,7 +713,8 @@ pub fn example_products() -> Vec<Product> {
                                 quantity_on_order: 0.0,
                                 quantity_unsellable: 2.0,
                                 quantity_allocated: 0.0
-                            }
+                            },
+                            lots: vec![]
                         },
                         Stock {
                             store: westfield.clone(),
@@ -707,7 +723,8 @@ pub fn example_products() -> Vec<Product> {
                                 quantity_on_order: 1.0,
                                 quantity_unsellable: 0.0,
                                 quantity_allocated: 0.0
-                            }
+                            },
+                            lots: vec![]
                         },
                         Stock {
                             store: albany.clone(),
@@ -716,7 +733,8 @@ pub fn example_products() -> Vec<Product> {
                                 quantity_on_order: 2.0,
                                 quantity_unsellable: 1.0,
                                 quantity_allocated: 0.0
-                            }
+                            },
+                            lots: vec![]
                         },
                     ],
                     images: vec![
@@ -768,7 +786,8 @@ pub fn example_products() -> Vec<Product> {
                                 quantity_on_order: 0.0,
                                 quantity_unsellable: 2.0,
                                 quantity_allocated: 0.0
-                            }
+                            },
+                            lots: vec![]
                         },
                         Stock {
                             store: westfield.clone(),
@@ -777,7 +796,8 @@ pub fn example_products() -> Vec<Product> {
                                 quantity_on_order: 1.0,
                                 quantity_unsellable: 0.0,
                                 quantity_allocated: 0.0
-                            }
+                            },
+                            lots: vec![]
                         },
                         Stock {
                             store: albany.clone(),
@@ -786,7 +806,8 @@ pub fn example_products() -> Vec<Product> {
                                 quantity_on_order: 2.0,
                                 quantity_unsellable: 1.0,
                                 quantity_allocated: 0.0
-                            }
+                            },
+                            lots: vec![]
                         },
                     ],
                     images: vec![
@@ -838,7 +859,8 @@ pub fn example_products() -> Vec<Product> {
                                 quantity_on_order: 0.0,
                                 quantity_unsellable: 2.0,
                                 quantity_allocated: 0.0
-                            }
+                            },
+                            lots: vec![]
                         },
                         Stock {
                             store: westfield,
@@ -847,7 +869,8 @@ pub fn example_products() -> Vec<Product> {
                                 quantity_on_order: 1.0,
                                 quantity_unsellable: 0.0,
                                 quantity_allocated: 0.0
-                            }
+                            },
+                            lots: vec![]
                         },
                         Stock {
                             store: albany,
@@ -856,7 +879,8 @@ pub fn example_products() -> Vec<Product> {
                                 quantity_on_order: 2.0,
                                 quantity_unsellable: 1.0,
                                 quantity_allocated: 0.0
-                            }
+                            },
+                            lots: vec![]
                         },
                     ],
                     images: vec![
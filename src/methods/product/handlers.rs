@@ -1,11 +1,13 @@
-use super::{Product, ProductWPromotion, Promotion, PromotionInput};
+use super::{CatalogueImport, ExpiringLot, Product, ProductWPromotion, Promotion, PromotionInput};
 use crate::catchers::Validated;
 use crate::guards::Convert;
-use crate::methods::{Action, Error};
+use crate::methods::{Action, Error, ErrorResponse};
 use crate::pool::InternalDb;
 use crate::{check_permissions, Session};
 use okapi::openapi3::OpenApi;
+use rocket::data::{Data, ToByteUnit};
 use rocket::get;
+use rocket::http::ContentType;
 use rocket::post;
 use rocket::serde::json::Json;
 use rocket_okapi::settings::OpenApiSettings;
@@ -27,7 +29,11 @@ pub fn documented_routes(settings: &OpenApiSettings) -> (Vec<rocket::Route>, Ope
         create_promotion,
         update_promotion,
         generate_promotion,
-        search_with_associated_promotions
+        search_with_associated_promotions,
+        export_csv,
+        import_csv,
+        get_expiring_lots,
+        write_off_expired_lots
     ]
 }
 
@@ -191,3 +197,70 @@ async fn generate_promotion(db: InternalDb, session: Session) -> Convert<Vec<Pro
     check_permissions!(session.clone(), Action::GenerateTemplateContent);
     Promotion::generate(session, &db.0).await.into()
 }
+
+/// The largest catalogue accepted by `import_csv`.
+const CATALOGUE_LIMIT_MIB: usize = 16;
+
+/// Exports the catalogue as CSV, one row per variant. The columns are
+/// those read by `import_csv`.
+#[openapi(tag = "Product")]
+#[get("/export/csv")]
+pub async fn export_csv(session: Session, db: InternalDb) -> Result<(ContentType, String), Error> {
+    check_permissions!(session.clone(), Action::FetchProduct);
+    Product::export_csv(session, &db.0)
+        .await
+        .map(|csv| (ContentType::CSV, csv))
+}
+
+/// Creates and updates products from a CSV catalogue. With `dry_run` set,
+/// nothing is saved and the changes the import would make are returned.
+#[openapi(tag = "Product")]
+#[post("/import/csv?<dry_run>", data = "<data>")]
+pub async fn import_csv(
+    session: Session,
+    db: InternalDb,
+    dry_run: Option<bool>,
+    data: Data<'_>,
+) -> Result<Json<CatalogueImport>, Error> {
+    check_permissions!(session.clone(), Action::CreateProduct);
+    check_permissions!(session.clone(), Action::ModifyProduct);
+
+    let contents = data
+        .open(CATALOGUE_LIMIT_MIB.mebibytes())
+        .into_string()
+        .await
+        .map_err(|e| ErrorResponse::create_error(&format!("Unable to read catalogue, {}", e)))?;
+
+    if !contents.is_complete() {
+        return Err(ErrorResponse::create_error(&format!(
+            "Catalogue exceeds the {} MiB limit.",
+            CATALOGUE_LIMIT_MIB
+        )));
+    }
+
+    CatalogueImport::run(&contents, dry_run.unwrap_or(false), session, &db.0)
+        .await
+        .map(Json)
+}
+
+/// The lots expiring within the given number of days, including those
+/// already expired, soonest first.
+#[openapi(tag = "Product")]
+#[get("/lots/expiring/<days>")]
+pub async fn get_expiring_lots(
+    db: InternalDb,
+    session: Session,
+    days: i64,
+) -> Convert<Vec<ExpiringLot>> {
+    check_permissions!(session.clone(), Action::FetchProduct);
+    Product::fetch_expiring_lots(days, session, &db.0)
+        .await
+        .into()
+}
+
+#[openapi(tag = "Product")]
+#[post("/lots/write_off")]
+pub async fn write_off_expired_lots(db: InternalDb, session: Session) -> Convert<Vec<ExpiringLot>> {
+    check_permissions!(session.clone(), Action::CreateStockAdjustmentIntent);
+    Product::write_off_expired_lots(session, &db.0).await.into()
+}
@@ -0,0 +1,109 @@
+#[cfg(feature = "process")]
+use crate::methods::{Error, ErrorResponse};
+use crate::methods::{Location, ProductCode};
+use crate::StockLot;
+#[cfg(feature = "process")]
+use crate::{Product, Session};
+#[cfg(feature = "process")]
+use chrono::{DateTime, Duration, Utc};
+use schemars::JsonSchema;
+#[cfg(feature = "process")]
+use sea_orm::DbConn;
+use serde::{Deserialize, Serialize};
+
+/// A lot held at a store, as listed by the near-expiry report or written
+/// off once expired.
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct ExpiringLot {
+    pub product_sku: String,
+    pub product_name: String,
+    pub variant_code: ProductCode,
+    pub variant_name: String,
+    pub store: Location,
+    pub lot: StockLot,
+    pub expired: bool,
+}
+
+/// The tenant's lots expiring by `until`, soonest first.
+#[cfg(feature = "process")]
+fn lots_expiring(
+    products: &[Product],
+    until: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Vec<ExpiringLot> {
+    let mut lots = products
+        .iter()
+        .flat_map(|product| {
+            product.variants.iter().flat_map(move |variant| {
+                variant.stock.iter().flat_map(move |stock| {
+                    stock
+                        .lots
+                        .iter()
+                        .filter(move |lot| lot.expiry_date <= until)
+                        .map(move |lot| ExpiringLot {
+                            product_sku: product.sku.clone(),
+                            product_name: product.name.clone(),
+                            variant_code: variant.barcode.clone(),
+                            variant_name: variant.name.clone(),
+                            store: stock.store.clone(),
+                            lot: lot.clone(),
+                            expired: lot.expiry_date <= now,
+                        })
+                })
+            })
+        })
+        .collect::<Vec<ExpiringLot>>();
+
+    lots.sort_by_key(|expiring| expiring.lot.expiry_date);
+    lots
+}
+
+#[cfg(feature = "methods")]
+impl Product {
+    /// The lots expiring within `days`, including those already expired.
+    pub async fn fetch_expiring_lots(
+        days: i64,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<Vec<ExpiringLot>, Error> {
+        if days < 0 {
+            return Err(ErrorResponse::create_error(
+                "The number of days cannot be negative.",
+            ));
+        }
+
+        let products = Product::fetch_all(session, db).await?;
+        let now = Utc::now();
+
+        Ok(lots_expiring(&products, now + Duration::days(days), now))
+    }
+
+    /// Writes off every expired lot, moving its stock from sellable to
+    /// unsellable. The lots written off are returned.
+    pub async fn write_off_expired_lots(
+        session: Session,
+        db: &DbConn,
+    ) -> Result<Vec<ExpiringLot>, Error> {
+        let products = Product::fetch_all(session.clone(), db).await?;
+        let now = Utc::now();
+
+        let written_off = lots_expiring(&products, now, now);
+
+        for mut product in products {
+            let expired = product
+                .variants
+                .iter_mut()
+                .flat_map(|variant| variant.stock.iter_mut())
+                .map(|stock| stock.write_off_expired(now).len())
+                .sum::<usize>();
+
+            if expired > 0 {
+                let sku = product.sku.clone();
+                Product::update(product, session.clone(), &sku, db).await?;
+            }
+        }
+
+        Ok(written_off)
+    }
+}
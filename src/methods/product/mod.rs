@@ -1,11 +1,15 @@
+mod catalogue;
 mod conversions;
 mod example;
 #[cfg(feature = "process")]
 pub(crate) mod handlers;
+mod lots;
 mod structs;
 mod variant;
 
+pub use catalogue::*;
 #[cfg(feature = "process")]
 pub use handlers::*;
+pub use lots::*;
 pub use structs::*;
 pub use variant::*;
@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-use crate::{methods::Error, History, Session, TransactionType};
+use crate::{methods::Error, History, LotDetails, LotDraw, Session, TransactionType};
 use chrono::{DateTime, Utc};
 #[cfg(feature = "process")]
 use sea_orm::{
@@ -301,7 +301,7 @@ impl Product {
         db: &DbConn,
     ) -> Result<Vec<Product>, Error> {
         let res = products::Entity::find()
-            .having(products::Column::Name.contains(name))
+            .filter(products::Column::Name.contains(name))
             .filter(products::Column::TenantId.eq(session.tenant_id))
             .limit(25)
             .all(db)
@@ -318,7 +318,7 @@ impl Product {
         db: &DbConn,
     ) -> Result<Vec<Product>, Error> {
         let res = products::Entity::find()
-            .having(products::Column::Name.eq(name))
+            .filter(products::Column::Name.eq(name))
             .filter(products::Column::TenantId.eq(session.tenant_id))
             .limit(25)
             .all(db)
@@ -398,6 +398,12 @@ pub struct ProductPurchase {
 
     pub transaction_type: TransactionType,
     pub instances: Vec<ProductInstance>,
+    /// The lot the product is received into, or sold from. Sales without
+    /// one are taken from the lots first to expire.
+    pub lot: Option<LotDetails>,
+    /// The lots a sale without one was taken from, so they are restored
+    /// should its stock be returned.
+    pub lots_drawn: Vec<LotDraw>,
 }
 
 impl<'de> Deserialize<'de> for ProductPurchase {
@@ -433,6 +439,8 @@ impl<'de> Deserialize<'de> for ProductPurchase {
                 let mut transaction_type = None;
                 let mut quantity = None;
                 let mut instances: Option<Vec<ProductInstance>> = None;
+                let mut lot: Option<LotDetails> = None;
+                let mut lots_drawn: Option<Vec<LotDraw>> = None;
 
                 // pub transaction_type: TransactionType,
                 while let Some(key_s) = map.next_key::<String>()? {
@@ -507,6 +515,18 @@ impl<'de> Deserialize<'de> for ProductPurchase {
                             }
                             instances = Some(map.next_value()?);
                         }
+                        "lot" => {
+                            if lot.is_some() {
+                                return Err(serde::de::Error::duplicate_field("lot"));
+                            }
+                            lot = map.next_value()?;
+                        }
+                        "lots_drawn" => {
+                            if lots_drawn.is_some() {
+                                return Err(serde::de::Error::duplicate_field("lots_drawn"));
+                            }
+                            lots_drawn = Some(map.next_value()?);
+                        }
                         _ => {
                             return Err(serde::de::Error::unknown_field(
                                 key,
@@ -543,6 +563,7 @@ impl<'de> Deserialize<'de> for ProductPurchase {
                     instances.push(ProductInstance {
                         id: format!("{}-{}-{}", id, instances.len() + 1, Uuid::new_v4()),
                         fulfillment_status: default_fulfillment(),
+                        serial: None,
                     });
                 }
                 Ok(ProductPurchase {
@@ -557,6 +578,8 @@ impl<'de> Deserialize<'de> for ProductPurchase {
                     tags,
                     quantity,
                     instances,
+                    lot,
+                    lots_drawn: lots_drawn.unwrap_or_default(),
                 })
             }
         }
@@ -571,6 +594,10 @@ pub struct ProductInstance {
     pub id: String,
     #[serde(default = "default_fulfillment")]
     pub fulfillment_status: FulfillmentStatus,
+    /// The serial number of the instance, for products tracked
+    /// individually. Registered as the instance is received or sold.
+    #[serde(default)]
+    pub serial: Option<String>,
 }
 
 fn default_fulfillment() -> FulfillmentStatus {
@@ -591,11 +618,27 @@ pub struct FulfillmentStatus {
     pub notes: Vec<Note>,
 }
 
+#[cfg(feature = "types")]
+impl FulfillmentStatus {
+    /// Moves the instance to `status`, keeping its prior status in history.
+    pub fn transition(&mut self, status: PickStatus, reason: &str) {
+        self.pick_history.push(History {
+            item: self.pick_status.clone(),
+            reason: reason.to_string(),
+            timestamp: self.last_updated,
+        });
+        self.pick_status = status;
+        self.last_updated = Utc::now();
+    }
+}
+
 #[cfg(feature = "types")]
 #[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub enum PickStatus {
     Pending,
     Picked,
+    /// Picked, and packed into one of the order's packages.
+    Packed,
     Failed,
     Uncertain,
     Processing,
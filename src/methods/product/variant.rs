@@ -2,8 +2,8 @@ use chrono::{DateTime, Days, Utc};
 use rocket_okapi::JsonSchema;
 #[cfg(feature = "process")]
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DbConn, DbErr, EntityTrait, InsertResult, QueryFilter,
-    QuerySelect, Set,
+    ActiveModelTrait, ColumnTrait, Condition, DbConn, DbErr, EntityTrait, InsertResult,
+    QueryFilter, Set,
 };
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
@@ -14,8 +14,6 @@ use crate::entities::prelude::Promotion as Promotions;
 use crate::entities::promotion;
 use crate::methods::Error;
 use crate::methods::{DiscountValue, HistoryList, Id, StockList, Url};
-#[cfg(feature = "process")]
-use crate::products;
 use crate::{ProductIdentification, Session};
 use serde_json::json;
 use uuid::Uuid;
@@ -187,7 +185,7 @@ impl Promotion {
 
     pub async fn fetch_by_id(id: &str, session: Session, db: &DbConn) -> Result<Promotion, Error> {
         let pdt = Promotions::find_by_id(id.to_string())
-            .filter(products::Column::TenantId.eq(session.tenant_id))
+            .filter(promotion::Column::TenantId.eq(session.tenant_id))
             .one(db)
             .await?;
         let p = pdt.unwrap();
@@ -208,15 +206,18 @@ impl Promotion {
         db: &DbConn,
     ) -> Result<Vec<Promotion>, Error> {
         let res = Promotions::find()
-            .filter(products::Column::TenantId.eq(session.tenant_id))
-            // Is the bought product
-            .having(promotion::Column::Buy.contains(query))
-            // Is the promoted product
-            .having(promotion::Column::Get.contains(query))
-            // Meets the Any criterion
-            .having(promotion::Column::Buy.contains("Any"))
-            // Meets the Any criterion
-            .having(promotion::Column::Get.contains("Any"))
+            .filter(promotion::Column::TenantId.eq(session.tenant_id))
+            .filter(
+                Condition::any()
+                    // Is the bought product
+                    .add(promotion::Column::Buy.contains(query))
+                    // Is the promoted product
+                    .add(promotion::Column::Get.contains(query))
+                    // Meets the Any criterion, matched on its `"any` type tag
+                    .add(promotion::Column::Buy.contains("\"any"))
+                    // Meets the Any criterion
+                    .add(promotion::Column::Get.contains("\"any")),
+            )
             .all(db)
             .await?;
 
@@ -0,0 +1,53 @@
+use crate::catchers::Validated;
+use crate::check_permissions;
+use crate::guards::Convert;
+use crate::methods::{Action, Error};
+use crate::pool::InternalDb;
+use crate::{SerialNumber, Session, WarrantyClaimInput};
+use okapi::openapi3::OpenApi;
+use rocket::serde::json::Json;
+use rocket::{get, post};
+use rocket_okapi::settings::OpenApiSettings;
+use rocket_okapi::{openapi, openapi_get_routes_spec};
+
+pub fn documented_routes(settings: &OpenApiSettings) -> (Vec<rocket::Route>, OpenApi) {
+    openapi_get_routes_spec![settings: get, get_registry, claim_warranty]
+}
+
+/// The registered instances bearing the serial, each with its history.
+#[openapi(tag = "Serial")]
+#[get("/<serial>")]
+pub async fn get(db: InternalDb, session: Session, serial: &str) -> Convert<Vec<SerialNumber>> {
+    check_permissions!(session.clone(), Action::FetchProduct);
+    SerialNumber::fetch_by_serial(serial, session, &db.0)
+        .await
+        .into()
+}
+
+#[openapi(tag = "Serial")]
+#[get("/registry/<sku>/<variant_code>/<store_id>")]
+pub async fn get_registry(
+    db: InternalDb,
+    session: Session,
+    sku: &str,
+    variant_code: &str,
+    store_id: &str,
+) -> Convert<Vec<SerialNumber>> {
+    check_permissions!(session.clone(), Action::FetchProduct);
+    SerialNumber::fetch_registry(sku, variant_code, store_id, session, &db.0)
+        .await
+        .into()
+}
+
+#[openapi(tag = "Serial")]
+#[post("/warranty", data = "<input_data>")]
+pub async fn claim_warranty(
+    db: InternalDb,
+    session: Session,
+    input_data: Validated<Json<WarrantyClaimInput>>,
+) -> Result<Json<SerialNumber>, Error> {
+    check_permissions!(session.clone(), Action::ModifyProduct);
+    SerialNumber::claim_warranty(input_data.data(), session, &db.0)
+        .await
+        .map(Json)
+}
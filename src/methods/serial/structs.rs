@@ -0,0 +1,538 @@
+#[cfg(feature = "process")]
+use crate::entities::prelude::Serials;
+#[cfg(feature = "process")]
+use crate::entities::serials;
+#[cfg(feature = "process")]
+use crate::methods::{Error, ErrorResponse, Order, TransactionType};
+use crate::methods::{Id, ProductCode};
+#[cfg(feature = "process")]
+use crate::{Session, Transaction};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+#[cfg(feature = "process")]
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DbConn, EntityTrait, QueryFilter, Set,
+};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "process")]
+use serde_json::json;
+#[cfg(feature = "process")]
+use std::collections::HashSet;
+#[cfg(feature = "process")]
+use uuid::Uuid;
+use validator::Validate;
+
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub enum SerialStatus {
+    /// Held at the serial's store, awaiting sale.
+    InStock,
+    Sold,
+    /// Returned by a customer to the serial's store, it may be sold again.
+    Returned,
+}
+
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub enum SerialEventKind {
+    Received,
+    Sold,
+    Returned,
+    Warranty,
+    /// The transaction which last moved the serial was voided.
+    Voided,
+}
+
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct SerialEvent {
+    pub kind: SerialEventKind,
+    /// The status of the serial once the event took place.
+    pub status: SerialStatus,
+    pub transaction_id: Option<Id>,
+    pub store_id: Id,
+    pub note: Option<String>,
+    pub author: Id,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// **SerialNumber** <br />
+/// An individually tracked instance of a product variant, registered as
+/// it is first received or sold. A serial is unique within its variant.
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct SerialNumber {
+    pub id: Id,
+    pub serial: String,
+    pub product_sku: String,
+    pub variant_code: ProductCode,
+    /// The store holding the serial, or which last sold it.
+    pub store_id: Id,
+    pub status: SerialStatus,
+    pub history: Vec<SerialEvent>,
+
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema, Validate)]
+pub struct WarrantyClaimInput {
+    pub product_sku: String,
+    pub variant_code: ProductCode,
+    #[validate(length(min = 1))]
+    pub serial: String,
+    /// The store the claim was lodged at.
+    pub store_id: Id,
+    #[validate(length(min = 1))]
+    pub note: String,
+}
+
+/// A serial moved by a transaction, at the origin of its order.
+#[cfg(feature = "process")]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct SerialMovement {
+    serial: String,
+    product_sku: String,
+    variant_code: ProductCode,
+    store_id: Id,
+}
+
+/// Ensures the serial, as read, may move as the transaction would have it.
+#[cfg(feature = "process")]
+fn movable(
+    transaction_type: &TransactionType,
+    movement: &SerialMovement,
+    existing: Option<&SerialNumber>,
+) -> Result<(), Error> {
+    match (transaction_type, existing.map(|serial| &serial.status)) {
+        (TransactionType::Out, Some(SerialStatus::Sold)) => {
+            Err(ErrorResponse::create_error(&format!(
+                "Serial {} of product {} has already been sold.",
+                movement.serial, movement.product_sku
+            )))
+        }
+        (TransactionType::In, Some(SerialStatus::InStock | SerialStatus::Returned)) => {
+            Err(ErrorResponse::create_error(&format!(
+                "Serial {} of product {} is already in stock.",
+                movement.serial, movement.product_sku
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(feature = "process")]
+fn movements(orders: &[Order]) -> Vec<SerialMovement> {
+    orders
+        .iter()
+        .flat_map(|order| {
+            order.products.iter().flat_map(move |product| {
+                product.instances.iter().filter_map(move |instance| {
+                    instance.serial.as_ref().map(|serial| SerialMovement {
+                        serial: serial.clone(),
+                        product_sku: product.product_sku.clone(),
+                        variant_code: product.product_code.clone(),
+                        store_id: order.origin.store_id.clone(),
+                    })
+                })
+            })
+        })
+        .collect()
+}
+
+#[cfg(feature = "process")]
+impl From<serials::Model> for SerialNumber {
+    fn from(val: serials::Model) -> Self {
+        SerialNumber {
+            id: val.id,
+            serial: val.serial,
+            product_sku: val.product_sku,
+            variant_code: val.variant_code,
+            store_id: val.store_id,
+            status: serde_json::from_value(val.status).unwrap_or(SerialStatus::InStock),
+            history: serde_json::from_value(val.history).unwrap_or_default(),
+            created_at: DateTime::from_naive_utc_and_offset(val.created_at, Utc),
+            updated_at: DateTime::from_naive_utc_and_offset(val.updated_at, Utc),
+        }
+    }
+}
+
+#[cfg(feature = "methods")]
+impl SerialNumber {
+    pub async fn fetch<C: ConnectionTrait>(
+        product_sku: &str,
+        variant_code: &str,
+        serial: &str,
+        session: Session,
+        db: &C,
+    ) -> Result<Option<SerialNumber>, Error> {
+        let serial = Serials::find()
+            .filter(serials::Column::TenantId.eq(session.tenant_id))
+            .filter(serials::Column::ProductSku.eq(product_sku))
+            .filter(serials::Column::VariantCode.eq(variant_code))
+            .filter(serials::Column::Serial.eq(serial))
+            .one(db)
+            .await?;
+
+        Ok(serial.map(|s| s.into()))
+    }
+
+    /// Every registered instance bearing `serial`, of any product.
+    pub async fn fetch_by_serial(
+        serial: &str,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<Vec<SerialNumber>, Error> {
+        let serials = Serials::find()
+            .filter(serials::Column::TenantId.eq(session.tenant_id))
+            .filter(serials::Column::Serial.eq(serial))
+            .all(db)
+            .await?;
+
+        Ok(serials.into_iter().map(|s| s.into()).collect())
+    }
+
+    /// The serials of a variant held at the store, sold serials excluded.
+    pub async fn fetch_registry(
+        product_sku: &str,
+        variant_code: &str,
+        store_id: &str,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<Vec<SerialNumber>, Error> {
+        let serials = Serials::find()
+            .filter(serials::Column::TenantId.eq(session.tenant_id))
+            .filter(serials::Column::ProductSku.eq(product_sku))
+            .filter(serials::Column::VariantCode.eq(variant_code))
+            .filter(serials::Column::StoreId.eq(store_id))
+            .all(db)
+            .await?;
+
+        Ok(serials
+            .into_iter()
+            .map(SerialNumber::from)
+            .filter(|serial| serial.status != SerialStatus::Sold)
+            .collect())
+    }
+
+    /// Ensures the serials of a transaction about to be recorded may move
+    /// as it would have them. A serial cannot be sold whilst already sold,
+    /// nor received whilst already held.
+    pub async fn check_movements(
+        transaction_type: &TransactionType,
+        orders: &[Order],
+        session: Session,
+        db: &DbConn,
+    ) -> Result<(), Error> {
+        if !matches!(transaction_type, TransactionType::In | TransactionType::Out) {
+            return Ok(());
+        }
+
+        let mut seen = HashSet::new();
+
+        for movement in movements(orders) {
+            if !seen.insert((
+                movement.product_sku.clone(),
+                movement.variant_code.clone(),
+                movement.serial.clone(),
+            )) {
+                return Err(ErrorResponse::create_error(&format!(
+                    "Serial {} appears more than once in the transaction.",
+                    movement.serial
+                )));
+            }
+
+            let existing = Self::fetch(
+                &movement.product_sku,
+                &movement.variant_code,
+                &movement.serial,
+                session.clone(),
+                db,
+            )
+            .await?;
+
+            movable(transaction_type, &movement, existing.as_ref())?;
+        }
+
+        Ok(())
+    }
+
+    /// Serials are checked and moved as a transaction is recorded, so an
+    /// update may not change those of a recorded transaction, nor record
+    /// one with serials it has not already.
+    pub fn check_update(
+        existing: &Transaction,
+        transaction_type: &TransactionType,
+        orders: &[Order],
+    ) -> Result<(), Error> {
+        let recorded = |transaction_type: &TransactionType| {
+            matches!(transaction_type, TransactionType::In | TransactionType::Out)
+        };
+
+        if !recorded(&existing.transaction_type) && !recorded(transaction_type) {
+            return Ok(());
+        }
+
+        let before = movements(&existing.products)
+            .into_iter()
+            .collect::<HashSet<_>>();
+        let after = movements(orders).into_iter().collect::<HashSet<_>>();
+
+        if before != after {
+            return Err(ErrorResponse::create_error(
+                "The serials of a recorded transaction cannot be changed by an update.",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Registers the movement of each serial within a recorded transaction.
+    /// Sold serials are marked as such, and those received are placed in
+    /// stock, or returned should they have been sold. Each serial is moved
+    /// only from the status it was read in, so of two transactions moving
+    /// the same serial at once, only one moves it.
+    pub async fn record_movements<C: ConnectionTrait>(
+        transaction_id: &str,
+        transaction_type: &TransactionType,
+        orders: &[Order],
+        session: Session,
+        db: &C,
+    ) -> Result<(), Error> {
+        if !matches!(transaction_type, TransactionType::In | TransactionType::Out) {
+            return Ok(());
+        }
+
+        for movement in movements(orders) {
+            let existing = Self::fetch(
+                &movement.product_sku,
+                &movement.variant_code,
+                &movement.serial,
+                session.clone(),
+                db,
+            )
+            .await?;
+
+            // Checked again as read here, should another transaction have
+            // moved the serial since it was first checked.
+            movable(transaction_type, &movement, existing.as_ref())?;
+
+            let (kind, status) = match (transaction_type, &existing) {
+                (TransactionType::Out, _) => (SerialEventKind::Sold, SerialStatus::Sold),
+                (_, Some(_)) => (SerialEventKind::Returned, SerialStatus::Returned),
+                (_, None) => (SerialEventKind::Received, SerialStatus::InStock),
+            };
+
+            let event = SerialEvent {
+                kind,
+                status: status.clone(),
+                transaction_id: Some(transaction_id.to_string()),
+                store_id: movement.store_id.clone(),
+                note: None,
+                author: session.employee.id.clone(),
+                timestamp: Utc::now(),
+            };
+
+            match existing {
+                Some(mut serial) => {
+                    let read = std::mem::replace(&mut serial.status, status);
+                    serial.store_id = movement.store_id;
+                    serial.history.push(event);
+
+                    if !serial.save_from(&read, db).await? {
+                        return Err(ErrorResponse::create_error(&format!(
+                            "Serial {} of product {} was moved by another transaction.",
+                            movement.serial, movement.product_sku
+                        )));
+                    }
+                }
+                None => {
+                    let now = Utc::now();
+
+                    SerialNumber {
+                        id: Uuid::new_v4().to_string(),
+                        serial: movement.serial,
+                        product_sku: movement.product_sku,
+                        variant_code: movement.variant_code,
+                        store_id: movement.store_id,
+                        status,
+                        history: vec![event],
+                        created_at: now,
+                        updated_at: now,
+                    }
+                    .insert(session.clone(), db)
+                    .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the serials moved by a voided transaction to the state they
+    /// held before it. Serials it registered are removed from the registry,
+    /// and those moved again since are left as they are.
+    pub async fn revert_movements(
+        transaction: &Transaction,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<(), Error> {
+        if !matches!(
+            transaction.transaction_type,
+            TransactionType::In | TransactionType::Out
+        ) {
+            return Ok(());
+        }
+
+        for movement in movements(&transaction.products) {
+            let mut serial = match Self::fetch(
+                &movement.product_sku,
+                &movement.variant_code,
+                &movement.serial,
+                session.clone(),
+                db,
+            )
+            .await?
+            {
+                Some(serial) => serial,
+                None => continue,
+            };
+
+            let last_movement = serial.history.iter().rposition(|event| {
+                matches!(
+                    event.kind,
+                    SerialEventKind::Received | SerialEventKind::Sold | SerialEventKind::Returned
+                )
+            });
+
+            let index = match last_movement {
+                Some(index)
+                    if serial.history[index].transaction_id.as_deref()
+                        == Some(transaction.id.as_str()) =>
+                {
+                    index
+                }
+                _ => continue,
+            };
+
+            if index == 0 {
+                Serials::delete_by_id(serial.id).exec(db).await?;
+                continue;
+            }
+
+            let previous = serial.history[index - 1].clone();
+
+            serial.status = previous.status.clone();
+            serial.store_id = previous.store_id.clone();
+            serial.history.push(SerialEvent {
+                kind: SerialEventKind::Voided,
+                status: previous.status,
+                transaction_id: Some(transaction.id.clone()),
+                store_id: previous.store_id,
+                note: None,
+                author: session.employee.id.clone(),
+                timestamp: Utc::now(),
+            });
+            serial.save(db).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Records a warranty claim against a registered serial.
+    pub async fn claim_warranty(
+        input: WarrantyClaimInput,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<SerialNumber, Error> {
+        let mut serial = Self::fetch(
+            &input.product_sku,
+            &input.variant_code,
+            &input.serial,
+            session.clone(),
+            db,
+        )
+        .await?
+        .ok_or_else(|| {
+            ErrorResponse::create_error(&format!(
+                "Serial {} of product {} is not registered.",
+                input.serial, input.product_sku
+            ))
+        })?;
+
+        serial.history.push(SerialEvent {
+            kind: SerialEventKind::Warranty,
+            status: serial.status.clone(),
+            transaction_id: None,
+            store_id: input.store_id,
+            note: Some(input.note),
+            author: session.employee.id,
+            timestamp: Utc::now(),
+        });
+        serial.save(db).await?;
+
+        Ok(serial)
+    }
+
+    async fn insert<C: ConnectionTrait>(&self, session: Session, db: &C) -> Result<(), Error> {
+        serials::ActiveModel {
+            id: Set(self.id.clone()),
+            tenant_id: Set(session.tenant_id),
+            serial: Set(self.serial.clone()),
+            product_sku: Set(self.product_sku.clone()),
+            variant_code: Set(self.variant_code.clone()),
+            store_id: Set(self.store_id.clone()),
+            status: Set(json!(self.status)),
+            history: Set(json!(self.history)),
+            created_at: Set(self.created_at.naive_utc()),
+            updated_at: Set(self.updated_at.naive_utc()),
+        }
+        .insert(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Saves the serial, provided it is still in the status `read`,
+    /// returning whether it was.
+    async fn save_from<C: ConnectionTrait>(
+        &mut self,
+        read: &SerialStatus,
+        db: &C,
+    ) -> Result<bool, Error> {
+        self.updated_at = Utc::now();
+
+        let result = Serials::update_many()
+            .set(serials::ActiveModel {
+                store_id: Set(self.store_id.clone()),
+                status: Set(json!(self.status)),
+                history: Set(json!(self.history)),
+                updated_at: Set(self.updated_at.naive_utc()),
+                ..Default::default()
+            })
+            .filter(serials::Column::Id.eq(self.id.clone()))
+            .filter(serials::Column::Status.contains(json!(read).to_string()))
+            .exec(db)
+            .await?;
+
+        Ok(result.rows_affected == 1)
+    }
+
+    async fn save(&mut self, db: &DbConn) -> Result<(), Error> {
+        self.updated_at = Utc::now();
+
+        serials::ActiveModel {
+            id: Set(self.id.clone()),
+            store_id: Set(self.store_id.clone()),
+            status: Set(json!(self.status)),
+            history: Set(json!(self.history)),
+            updated_at: Set(self.updated_at.naive_utc()),
+            ..Default::default()
+        }
+        .update(db)
+        .await?;
+
+        Ok(())
+    }
+}
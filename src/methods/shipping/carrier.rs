@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::config::{ShippingConfig, ShippingProvider};
+use crate::methods::{
+    Error, ErrorResponse, RateQuote, ShipmentRequest, ShippingLabel, TrackingEvent,
+};
+use crate::methods::{KarrioCarrier, MockCarrier};
+
+/// A provider shipping labels are purchased from, and the tracking of
+/// dispatched orders polled from.
+#[async_trait]
+pub trait Carrier: Send + Sync {
+    /// Quotes the services able to carry the shipment.
+    async fn rates(&self, shipment: &ShipmentRequest) -> Result<Vec<RateQuote>, Error>;
+
+    /// Purchases a label for the shipment at the quoted rate.
+    async fn purchase_label(
+        &self,
+        shipment: &ShipmentRequest,
+        rate: &RateQuote,
+    ) -> Result<ShippingLabel, Error>;
+
+    /// The events recorded against a tracking code, oldest first.
+    async fn track(&self, carrier: &str, tracking_code: &str) -> Result<Vec<TrackingEvent>, Error>;
+}
+
+/// The configured carrier, managed as state. Holds nothing when shipping
+/// is disabled.
+#[derive(Clone)]
+pub struct ShippingCarrier(pub Option<Arc<dyn Carrier>>);
+
+impl ShippingCarrier {
+    pub fn from_config(config: &ShippingConfig) -> Self {
+        let carrier: Option<Arc<dyn Carrier>> = match config.provider {
+            ShippingProvider::None => None,
+            ShippingProvider::Karrio => Some(Arc::new(KarrioCarrier::new(
+                &config.karrio_url,
+                &config.karrio_api_key,
+            ))),
+            ShippingProvider::Mock => Some(Arc::new(MockCarrier::default())),
+        };
+
+        ShippingCarrier(carrier)
+    }
+
+    pub fn get(&self) -> Result<Arc<dyn Carrier>, Error> {
+        self.0
+            .clone()
+            .ok_or_else(|| ErrorResponse::create_error("No shipping provider is configured."))
+    }
+}
@@ -0,0 +1,86 @@
+use crate::catchers::Validated;
+use crate::guards::Convert;
+use crate::methods::{Action, Error, RateQuote, ShippingCarrier, Transaction};
+use crate::pool::InternalDb;
+use crate::{check_permissions, Session};
+use okapi::openapi3::OpenApi;
+use rocket::post;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket_okapi::settings::OpenApiSettings;
+use rocket_okapi::{openapi, openapi_get_routes_spec};
+
+pub fn documented_routes(settings: &OpenApiSettings) -> (Vec<rocket::Route>, OpenApi) {
+    openapi_get_routes_spec![settings: rates, purchase_label, track]
+}
+
+/// Quotes the configured carrier's services for a packed order.
+#[openapi(tag = "Shipping")]
+#[post("/rates/<id>/<order_id>")]
+pub async fn rates(
+    db: InternalDb,
+    session: Session,
+    carrier: &State<ShippingCarrier>,
+    id: &str,
+    order_id: &str,
+) -> Result<Json<Vec<RateQuote>>, Error> {
+    check_permissions!(session.clone(), Action::ModifyTransaction);
+
+    let carrier = carrier.get()?;
+    Transaction::shipping_rates(id, order_id, carrier.as_ref(), session, &db.0)
+        .await
+        .map(Json)
+}
+
+/// Purchases a label at one of the quoted rates, placing the order in
+/// transit with the carrier's tracking.
+#[openapi(tag = "Shipping")]
+#[post("/label/<id>/<order_id>", data = "<input_data>")]
+pub async fn purchase_label(
+    db: InternalDb,
+    session: Session,
+    carrier: &State<ShippingCarrier>,
+    input_data: Validated<Json<RateQuote>>,
+    id: &str,
+    order_id: &str,
+) -> Convert<Transaction> {
+    check_permissions!(session.clone(), Action::ModifyTransaction);
+
+    let carrier = match carrier.get() {
+        Ok(carrier) => carrier,
+        Err(error) => return Err(error).into(),
+    };
+
+    Transaction::purchase_label(
+        id,
+        order_id,
+        input_data.data(),
+        carrier.as_ref(),
+        session,
+        &db.0,
+    )
+    .await
+    .into()
+}
+
+/// Polls the tracking of the transaction's orders in transit, rather than
+/// awaiting the background worker.
+#[openapi(tag = "Shipping")]
+#[post("/track/<id>")]
+pub async fn track(
+    db: InternalDb,
+    session: Session,
+    carrier: &State<ShippingCarrier>,
+    id: &str,
+) -> Convert<Transaction> {
+    check_permissions!(session.clone(), Action::ModifyTransaction);
+
+    let carrier = match carrier.get() {
+        Ok(carrier) => carrier,
+        Err(error) => return Err(error).into(),
+    };
+
+    Transaction::track(id, carrier.as_ref(), session, &db.0)
+        .await
+        .into()
+}
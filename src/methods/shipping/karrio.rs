@@ -0,0 +1,243 @@
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use rocket::tokio::task::spawn_blocking;
+use serde_json::{json, Value};
+
+use crate::methods::{
+    Carrier, ContactInformation, Error, ErrorResponse, Package, Price, RateQuote, ShipmentRequest,
+    ShippingLabel, TrackingEvent, TrackingStatus,
+};
+
+/// A carrier reached through the proxy API of a Karrio instance, which
+/// fronts whichever carriers it is connected to.
+pub struct KarrioCarrier {
+    url: String,
+    api_key: String,
+}
+
+impl KarrioCarrier {
+    pub fn new(url: &str, api_key: &str) -> Self {
+        KarrioCarrier {
+            url: url.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+        }
+    }
+
+    async fn request(
+        &self,
+        method: &'static str,
+        path: String,
+        body: Option<Value>,
+    ) -> Result<Value, Error> {
+        let url = format!("{}{}", self.url, path);
+        let authorization = format!("Token {}", self.api_key);
+
+        spawn_blocking(move || {
+            let request = ureq::request(method, &url).set("Authorization", &authorization);
+
+            let response = match body {
+                Some(body) => request.send_json(body),
+                None => request.call(),
+            };
+
+            match response {
+                Ok(response) => response.into_json::<Value>().map_err(|error| {
+                    ErrorResponse::create_error(&format!("Malformed carrier response: {}", error))
+                }),
+                Err(ureq::Error::Status(code, response)) => {
+                    let message = response
+                        .into_json::<Value>()
+                        .ok()
+                        .and_then(|body| body["messages"][0]["message"].as_str().map(String::from))
+                        .unwrap_or_else(|| format!("status {}", code));
+
+                    Err(ErrorResponse::create_error(&format!(
+                        "Carrier request failed: {}",
+                        message
+                    )))
+                }
+                Err(error) => Err(ErrorResponse::create_error(&format!(
+                    "Carrier request failed: {}",
+                    error
+                ))),
+            }
+        })
+        .await
+        .map_err(|error| {
+            ErrorResponse::create_error(&format!("Carrier request failed: {}", error))
+        })?
+    }
+}
+
+fn address(contact: &ContactInformation) -> Value {
+    json!({
+        "person_name": contact.name,
+        "address_line1": contact.address.street,
+        "address_line2": contact.address.street2,
+        "city": contact.address.city,
+        "postal_code": contact.address.po_code,
+        "country_code": contact.address.country,
+        "phone_number": contact.mobile.number,
+        "email": contact.email.full,
+    })
+}
+
+fn parcel(package: &Package) -> Value {
+    json!({
+        "weight": package.weight_kg,
+        "weight_unit": "KG",
+        "length": package.length_cm,
+        "width": package.width_cm,
+        "height": package.height_cm,
+        "dimension_unit": "CM",
+    })
+}
+
+fn shipment(request: &ShipmentRequest) -> Value {
+    json!({
+        "shipper": address(&request.origin),
+        "recipient": address(&request.destination),
+        "parcels": request.packages.iter().map(parcel).collect::<Vec<Value>>(),
+    })
+}
+
+fn tracking_status(status: &str) -> TrackingStatus {
+    match status {
+        "delivered" => TrackingStatus::Delivered,
+        "out_for_delivery" => TrackingStatus::OutForDelivery,
+        "delivery_failed" | "return_to_sender" | "cancelled" => TrackingStatus::Failed,
+        "pending" | "unknown" => TrackingStatus::Pending,
+        _ => TrackingStatus::InTransit,
+    }
+}
+
+#[async_trait]
+impl Carrier for KarrioCarrier {
+    async fn rates(&self, shipment_request: &ShipmentRequest) -> Result<Vec<RateQuote>, Error> {
+        let response = self
+            .request(
+                "POST",
+                "/v1/proxy/rates".to_string(),
+                Some(shipment(shipment_request)),
+            )
+            .await?;
+
+        Ok(response["rates"]
+            .as_array()
+            .map(|rates| {
+                rates
+                    .iter()
+                    .map(|rate| RateQuote {
+                        id: rate["id"].as_str().unwrap_or_default().to_string(),
+                        carrier: rate["carrier_name"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string(),
+                        service: rate["service"].as_str().unwrap_or_default().to_string(),
+                        amount: Price {
+                            quantity: rate["total_charge"].as_f64().unwrap_or_default() as f32,
+                            currency: rate["currency"].as_str().unwrap_or_default().to_string(),
+                        },
+                        transit_days: rate["transit_days"].as_u64().map(|days| days as u32),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn purchase_label(
+        &self,
+        shipment_request: &ShipmentRequest,
+        rate: &RateQuote,
+    ) -> Result<ShippingLabel, Error> {
+        let mut body = shipment(shipment_request);
+        body["service"] = json!(rate.service);
+        body["selected_rate_id"] = json!(rate.id);
+        body["rates"] = json!([{
+            "id": rate.id,
+            "carrier_name": rate.carrier,
+            "carrier_id": rate.carrier,
+            "service": rate.service,
+            "total_charge": rate.amount.quantity,
+            "currency": rate.amount.currency,
+        }]);
+
+        let response = self
+            .request("POST", "/v1/proxy/shipping".to_string(), Some(body))
+            .await?;
+
+        let tracking_code = response["tracking_number"]
+            .as_str()
+            .ok_or_else(|| ErrorResponse::create_error("Carrier returned no tracking number."))?;
+
+        Ok(ShippingLabel {
+            carrier: response["carrier_name"]
+                .as_str()
+                .unwrap_or(&rate.carrier)
+                .to_string(),
+            tracking_code: tracking_code.to_string(),
+            tracking_url: response["meta"]["tracking_url"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            label: response["docs"]["label"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+        })
+    }
+
+    async fn track(&self, carrier: &str, tracking_code: &str) -> Result<Vec<TrackingEvent>, Error> {
+        let response = self
+            .request(
+                "GET",
+                format!("/v1/proxy/tracking/{}/{}", carrier, tracking_code),
+                None,
+            )
+            .await?;
+
+        let tracking = &response["tracking"];
+
+        // Karrio lists events newest first, only the tracking as a whole
+        // carries a normalised status, which is given to the latest event.
+        let mut events = tracking["events"]
+            .as_array()
+            .map(|events| {
+                events
+                    .iter()
+                    .rev()
+                    .map(|event| TrackingEvent {
+                        status: TrackingStatus::InTransit,
+                        description: event["description"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string(),
+                        timestamp: NaiveDateTime::parse_from_str(
+                            &format!(
+                                "{} {}",
+                                event["date"].as_str().unwrap_or_default(),
+                                event["time"].as_str().unwrap_or("00:00")
+                            ),
+                            "%Y-%m-%d %H:%M",
+                        )
+                        .map(|time| time.and_utc())
+                        .unwrap_or_else(|_| Utc::now()),
+                    })
+                    .collect::<Vec<TrackingEvent>>()
+            })
+            .unwrap_or_default();
+
+        let status = tracking_status(tracking["status"].as_str().unwrap_or_default());
+
+        match events.last_mut() {
+            Some(event) => event.status = status,
+            None => events.push(TrackingEvent {
+                status,
+                description: String::new(),
+                timestamp: Utc::now(),
+            }),
+        }
+
+        Ok(events)
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::methods::{
+    Carrier, Error, ErrorResponse, Price, RateQuote, ShipmentRequest, ShippingLabel, TrackingEvent,
+    TrackingStatus,
+};
+
+/// An in-memory carrier whose shipments are delivered on the second poll
+/// of their tracking, for development and testing.
+#[derive(Default)]
+pub struct MockCarrier {
+    polls: Mutex<HashMap<String, usize>>,
+}
+
+const MOCK_CARRIER: &str = "mock";
+
+#[async_trait]
+impl Carrier for MockCarrier {
+    async fn rates(&self, shipment: &ShipmentRequest) -> Result<Vec<RateQuote>, Error> {
+        let weight: f32 = shipment.packages.iter().map(|p| p.weight_kg).sum();
+        let standard = 5.0 + 2.0 * weight;
+
+        Ok(vec![
+            RateQuote {
+                id: format!("{}-standard", MOCK_CARRIER),
+                carrier: MOCK_CARRIER.to_string(),
+                service: "standard".to_string(),
+                amount: Price {
+                    quantity: standard,
+                    currency: "NZD".to_string(),
+                },
+                transit_days: Some(3),
+            },
+            RateQuote {
+                id: format!("{}-express", MOCK_CARRIER),
+                carrier: MOCK_CARRIER.to_string(),
+                service: "express".to_string(),
+                amount: Price {
+                    quantity: standard * 2.0,
+                    currency: "NZD".to_string(),
+                },
+                transit_days: Some(1),
+            },
+        ])
+    }
+
+    async fn purchase_label(
+        &self,
+        _shipment: &ShipmentRequest,
+        rate: &RateQuote,
+    ) -> Result<ShippingLabel, Error> {
+        if rate.carrier != MOCK_CARRIER {
+            return Err(ErrorResponse::create_error(&format!(
+                "Unknown rate `{}`.",
+                rate.id
+            )));
+        }
+
+        let tracking_code = format!("MOCK-{}", Uuid::new_v4().simple());
+
+        Ok(ShippingLabel {
+            carrier: MOCK_CARRIER.to_string(),
+            tracking_url: format!("https://track.example.com/{}", tracking_code),
+            tracking_code,
+            label: "JVBERi0xLjQKJcOkw7zDtsOfCg==".to_string(),
+        })
+    }
+
+    async fn track(
+        &self,
+        _carrier: &str,
+        tracking_code: &str,
+    ) -> Result<Vec<TrackingEvent>, Error> {
+        let polls = {
+            let mut polls = self.polls.lock().unwrap();
+            let count = polls.entry(tracking_code.to_string()).or_default();
+            *count += 1;
+            *count
+        };
+
+        let mut events = vec![TrackingEvent {
+            status: TrackingStatus::InTransit,
+            description: "Picked up by courier".to_string(),
+            timestamp: Utc::now(),
+        }];
+
+        if polls > 1 {
+            events.push(TrackingEvent {
+                status: TrackingStatus::Delivered,
+                description: "Delivered".to_string(),
+                timestamp: Utc::now(),
+            });
+        }
+
+        Ok(events)
+    }
+}
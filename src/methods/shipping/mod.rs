@@ -0,0 +1,19 @@
+#[cfg(feature = "process")]
+mod carrier;
+#[cfg(feature = "process")]
+pub(crate) mod handlers;
+#[cfg(feature = "process")]
+mod karrio;
+#[cfg(feature = "process")]
+mod mock;
+mod structs;
+
+#[cfg(feature = "process")]
+pub use carrier::*;
+#[cfg(feature = "process")]
+pub use handlers::*;
+#[cfg(feature = "process")]
+pub use karrio::*;
+#[cfg(feature = "process")]
+pub use mock::*;
+pub use structs::*;
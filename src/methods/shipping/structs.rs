@@ -0,0 +1,305 @@
+//! Purchasing shipping labels from a carrier, and following the tracking
+//! of orders once dispatched. An order is quoted and labelled once packed,
+//! after which its status follows the carrier's tracking events.
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[cfg(feature = "process")]
+use crate::entities::{prelude::Transactions, transactions};
+#[cfg(feature = "process")]
+use crate::methods::{find_order, Carrier, Session};
+use crate::methods::{ContactInformation, Id, Package, Price, Url};
+#[cfg(feature = "methods")]
+use crate::methods::{Error, ErrorResponse, Order, OrderStatus, OrderType, TransitInformation};
+#[cfg(feature = "methods")]
+use crate::{PickStatus, Transaction};
+#[cfg(feature = "process")]
+use sea_orm::{sea_query::Expr, ColumnTrait, DbConn, EntityTrait, QueryFilter};
+#[cfg(feature = "process")]
+use serde_json::json;
+
+/// The parcels of an order, as carried from its origin to its destination.
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct ShipmentRequest {
+    pub origin: ContactInformation,
+    pub destination: ContactInformation,
+    pub packages: Vec<Package>,
+}
+
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema, Validate)]
+pub struct RateQuote {
+    /// The carrier's identifier for the rate, presented when purchasing.
+    pub id: Id,
+    pub carrier: String,
+    pub service: String,
+    pub amount: Price,
+    pub transit_days: Option<u32>,
+}
+
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct ShippingLabel {
+    pub carrier: String,
+    pub tracking_code: String,
+    pub tracking_url: Url,
+    /// The printable label, base64 encoded.
+    pub label: String,
+}
+
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub enum TrackingStatus {
+    Pending,
+    InTransit,
+    OutForDelivery,
+    Delivered,
+    Failed,
+}
+
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct TrackingEvent {
+    pub status: TrackingStatus,
+    pub description: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[cfg(feature = "methods")]
+impl ShipmentRequest {
+    pub fn from_order(order: &Order) -> Result<Self, Error> {
+        if order.packages.is_empty() {
+            return Err(ErrorResponse::create_error(&format!(
+                "Order `{}` has not been packed.",
+                order.id
+            )));
+        }
+
+        Ok(ShipmentRequest {
+            origin: order.origin.contact.clone(),
+            destination: order.destination.contact.clone(),
+            packages: order.packages.clone(),
+        })
+    }
+}
+
+/// Whether any of the orders is in transit with a carrier, flagging its
+/// transaction to be polled for tracking.
+#[cfg(feature = "methods")]
+pub fn in_transit(orders: &[Order]) -> bool {
+    orders.iter().any(|order| order.carrier().is_some())
+}
+
+#[cfg(feature = "methods")]
+impl Order {
+    /// The carrier the order's label was purchased from, if in transit.
+    pub fn carrier(&self) -> Option<(String, String)> {
+        match &self.status.status {
+            OrderStatus::Transit(transit) => transit
+                .carrier
+                .clone()
+                .map(|carrier| (carrier, transit.tracking_code.clone())),
+            _ => None,
+        }
+    }
+
+    /// The status an order in transit progresses to from the latest of
+    /// its tracking events, and the reason for it. Only delivery, or a
+    /// failure to deliver, leaves transit. Orders for pickup are delivered
+    /// to their store, rather than to the customer.
+    pub fn tracked_status(&self, events: &[TrackingEvent]) -> Option<(OrderStatus, String)> {
+        if !matches!(self.status.status, OrderStatus::Transit(_)) {
+            return None;
+        }
+
+        let event = events.last()?;
+
+        match event.status {
+            TrackingStatus::Delivered => match self.order_type {
+                OrderType::Pickup => Some(OrderStatus::InStore(event.timestamp)),
+                _ => Some(OrderStatus::Fulfilled(event.timestamp)),
+            },
+            TrackingStatus::Failed => Some(OrderStatus::Failed(event.description.clone())),
+            _ => None,
+        }
+        .map(|status| (status, event.description.clone()))
+    }
+
+    /// Places the order in transit with the purchased label.
+    #[cfg(feature = "process")]
+    pub fn assign_label(&mut self, label: ShippingLabel) -> Result<(), Error> {
+        let transit = TransitInformation {
+            shipping_company: ContactInformation {
+                name: label.carrier.clone(),
+                ..ContactInformation::anonymised()
+            },
+            query_url: label.tracking_url,
+            tracking_code: label.tracking_code,
+            assigned_products: self.products.iter().map(|p| p.id.clone()).collect(),
+            carrier: Some(label.carrier),
+            label: Some(label.label),
+        };
+
+        self.transition(OrderStatus::Transit(Box::new(transit)), "Label Purchased")
+    }
+}
+
+#[cfg(feature = "process")]
+impl Transaction {
+    pub async fn shipping_rates(
+        id: &str,
+        order_id: &str,
+        carrier: &dyn Carrier,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<Vec<RateQuote>, Error> {
+        let mut transaction = Self::fetch_by_id(id, session, db).await?;
+        let order = find_order(&mut transaction, order_id)?;
+
+        carrier.rates(&ShipmentRequest::from_order(order)?).await
+    }
+
+    /// Purchases a label for a packed order at the quoted rate, placing it
+    /// in transit with the carrier's tracking.
+    pub async fn purchase_label(
+        id: &str,
+        order_id: &str,
+        rate: RateQuote,
+        carrier: &dyn Carrier,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<Transaction, Error> {
+        let mut transaction = Self::fetch_by_id(id, session.clone(), db).await?;
+
+        if transaction.void.is_some() {
+            return Err(ErrorResponse::create_error(
+                "A voided transaction cannot be shipped.",
+            ));
+        }
+
+        let order = find_order(&mut transaction, order_id)?;
+
+        let packed = order
+            .products
+            .iter()
+            .flat_map(|purchase| purchase.instances.iter())
+            .all(|instance| matches!(instance.fulfillment_status.pick_status, PickStatus::Packed));
+
+        if !packed {
+            return Err(ErrorResponse::create_error(&format!(
+                "Order `{}` has not been packed.",
+                order_id
+            )));
+        }
+
+        if order.carrier().is_some() {
+            return Err(ErrorResponse::create_error(&format!(
+                "A label has already been purchased for order `{}`.",
+                order_id
+            )));
+        }
+
+        let label = carrier
+            .purchase_label(&ShipmentRequest::from_order(order)?, &rate)
+            .await?;
+
+        order.assign_label(label)?;
+
+        Self::update_value(transaction, session, id, db).await
+    }
+
+    /// Polls the tracking of each of the transaction's orders in transit,
+    /// returning whether any progressed.
+    pub async fn poll_tracking(&mut self, carrier: &dyn Carrier) -> Result<bool, Error> {
+        let mut progressed = false;
+
+        for order in self.products.clone() {
+            if let Some((name, tracking_code)) = order.carrier() {
+                let events = carrier.track(&name, &tracking_code).await?;
+
+                if let Some((status, reason)) = order.tracked_status(&events) {
+                    // Stock has left the store by the time it is in transit,
+                    // so none is ever released.
+                    self.transition_order(&order.id, status, &reason, &name)?;
+                    progressed = true;
+                }
+            }
+        }
+
+        if progressed {
+            self.updated_at = Utc::now();
+        }
+
+        Ok(progressed)
+    }
+
+    pub async fn track(
+        id: &str,
+        carrier: &dyn Carrier,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<Transaction, Error> {
+        let mut transaction = Self::fetch_by_id(id, session.clone(), db).await?;
+
+        if transaction.poll_tracking(carrier).await? {
+            let issued = std::mem::take(&mut transaction.issued_collections);
+            transaction.save_tracking(&session.tenant_id, db).await?;
+
+            let mut transaction = Self::fetch_by_id(id, session, db).await?;
+            transaction.issued_collections = issued;
+            return Ok(transaction);
+        }
+
+        Ok(transaction)
+    }
+
+    /// Saves the orders progressed by polling their tracking. Polling waits
+    /// on the carrier, so only the orders are written, and only whilst the
+    /// transaction remains unvoided.
+    pub async fn save_tracking(self, tenant_id: &str, db: &DbConn) -> Result<(), Error> {
+        let in_transit = in_transit(&self.products);
+
+        Transactions::update_many()
+            .col_expr(
+                transactions::Column::Products,
+                Expr::value(json!(self.products)),
+            )
+            .col_expr(transactions::Column::InTransit, Expr::value(in_transit))
+            .col_expr(
+                transactions::Column::UpdatedAt,
+                Expr::value(self.updated_at.naive_utc()),
+            )
+            .filter(transactions::Column::Id.eq(self.id))
+            .filter(transactions::Column::TenantId.eq(tenant_id))
+            .filter(transactions::Column::Void.is_null())
+            .exec(db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Transactions across every tenant with an order in transit with the
+    /// carrier its label was purchased from, paired with their tenant.
+    pub async fn fetch_tracked(db: &DbConn) -> Result<Vec<(Id, Transaction)>, Error> {
+        let res = Transactions::find()
+            .filter(transactions::Column::Void.is_null())
+            .filter(transactions::Column::InTransit.eq(true))
+            .all(db)
+            .await?;
+
+        Ok(res
+            .into_iter()
+            .map(|model| (model.tenant_id.clone(), model.into()))
+            .filter(|(_, transaction): &(Id, Transaction)| {
+                transaction
+                    .products
+                    .iter()
+                    .any(|order| order.carrier().is_some())
+            })
+            .collect())
+    }
+}
@@ -1,9 +1,11 @@
 use std::fmt::Display;
 
 use crate::methods::{
-    ContactInformation, DiscountValue, History, HistoryList, Id, Location, NoteList,
+    Collection, ContactInformation, DiscountValue, History, HistoryList, Id, Location, NoteList,
     ProductPurchaseList, Store, Url,
 };
+#[cfg(feature = "process")]
+use crate::methods::{Error, ErrorResponse};
 use chrono::{DateTime, Utc};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -31,6 +33,70 @@ pub struct Order {
 
     pub discount: DiscountValue,
     pub order_type: OrderType,
+
+    /// The packages the order is dispatched in, once packed.
+    #[serde(default)]
+    pub packages: Vec<Package>,
+
+    /// Whether the order's stock was returned to its origin as it failed.
+    #[serde(default)]
+    pub stock_released: bool,
+
+    /// Issued once a pickup order is ready in store.
+    #[serde(default)]
+    pub collection: Option<Collection>,
+}
+
+#[cfg(feature = "types")]
+impl Order {
+    /// Assigns `status` to each of the order's products, recording it in
+    /// the order's status history.
+    pub fn assign_status(&mut self, status: OrderStatus, reason: &str) {
+        let assignment = OrderStatusAssignment {
+            status,
+            assigned_products: self.products.iter().map(|p| p.id.clone()).collect(),
+            timestamp: Utc::now(),
+        };
+
+        self.status = assignment.clone();
+        self.status_history.push(History {
+            item: assignment,
+            reason: reason.to_string(),
+            timestamp: self.status.timestamp,
+        });
+    }
+
+    /// Moves the order to `status`, should its type permit the transition
+    /// from the status it holds.
+    #[cfg(feature = "process")]
+    pub fn transition(&mut self, status: OrderStatus, reason: &str) -> Result<(), Error> {
+        if !self.order_type.permits(&self.status.status, &status) {
+            return Err(ErrorResponse::invalid_transition(
+                &self.order_type,
+                &self.status.status,
+                &status,
+            ));
+        }
+
+        self.assign_status(status, reason);
+        Ok(())
+    }
+}
+
+/// A parcel an order is dispatched in, holding the listed instances.
+#[cfg(feature = "types")]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Validate)]
+pub struct Package {
+    #[validate(range(min = 0.0))]
+    pub length_cm: f32,
+    #[validate(range(min = 0.0))]
+    pub width_cm: f32,
+    #[validate(range(min = 0.0))]
+    pub height_cm: f32,
+    #[validate(range(min = 0.0))]
+    pub weight_kg: f32,
+    #[validate(length(min = 1))]
+    pub instances: Vec<Id>,
 }
 
 #[cfg(feature = "types")]
@@ -43,6 +109,43 @@ pub enum OrderType {
     Quote,
 }
 
+impl OrderType {
+    /// Whether an order of this type may move between the two statuses.
+    /// Fulfilled and failed orders are final, any other may fail. Transit
+    /// may be re-entered as its tracking details change.
+    pub fn permits(&self, from: &OrderStatus, to: &OrderStatus) -> bool {
+        use OrderStatus::*;
+
+        match (from, to) {
+            (Fulfilled(_) | Failed(_), _) => false,
+            (_, Failed(_)) => true,
+            (from, to) => match self {
+                // Sold over the counter, perhaps fetched from the back first.
+                OrderType::Direct => matches!(
+                    (from, to),
+                    (Queued(_), Processing(_) | Fulfilled(_)) | (Processing(_), Fulfilled(_))
+                ),
+                OrderType::Shipment => matches!(
+                    (from, to),
+                    (Queued(_), Processing(_))
+                        | (Processing(_), Transit(_))
+                        | (Transit(_), Transit(_) | Fulfilled(_))
+                ),
+                // Readied at the store, or sent there from another first.
+                // Fulfilled only as collected, see `Transaction::collect`.
+                OrderType::Pickup => matches!(
+                    (from, to),
+                    (Queued(_), Processing(_))
+                        | (Processing(_), Transit(_) | InStore(_))
+                        | (Transit(_), Transit(_) | InStore(_))
+                ),
+                // Quoted orders are never fulfilled, only withdrawn.
+                OrderType::Quote => false,
+            },
+        }
+    }
+}
+
 impl ToString for Order {
     fn to_string(&self) -> String {
         match serde_json::to_string(self) {
@@ -144,4 +247,11 @@ pub struct TransitInformation {
     pub query_url: Url,
     pub tracking_code: String,
     pub assigned_products: Vec<Id>,
+    /// The carrier tracking is polled from, as named by the shipping
+    /// provider. Unset when the details were entered by hand.
+    #[serde(default)]
+    pub carrier: Option<String>,
+    /// The purchased shipping label, base64 encoded.
+    #[serde(default)]
+    pub label: Option<String>,
 }
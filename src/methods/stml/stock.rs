@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use rocket_okapi::JsonSchema;
 use serde::{Deserialize, Serialize};
 use validator::Validate;
@@ -12,6 +13,10 @@ pub type StockList = Vec<Stock>;
 pub struct Stock {
     pub store: Location,
     pub quantity: Quantity,
+    /// The sellable stock received with a batch code, part of
+    /// `quantity_sellable`. Any sellable stock beyond the lots is untracked.
+    #[serde(default)]
+    pub lots: Vec<StockLot>,
 }
 
 #[cfg(feature = "types")]
@@ -22,3 +27,124 @@ pub struct Quantity {
     pub quantity_on_order: f32,
     pub quantity_allocated: f32,
 }
+
+/// Stock received together, sharing a batch code and expiry date.
+#[cfg(feature = "types")]
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, Validate)]
+pub struct StockLot {
+    pub batch_code: String,
+    pub expiry_date: DateTime<Utc>,
+    pub quantity: f32,
+    pub received_at: DateTime<Utc>,
+}
+
+/// The lot a purchased product is received into, or taken from.
+#[cfg(feature = "types")]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, JsonSchema, Validate)]
+pub struct LotDetails {
+    #[validate(length(min = 1))]
+    pub batch_code: String,
+    pub expiry_date: DateTime<Utc>,
+}
+
+/// Stock taken from a lot, such as by a sale without one given.
+#[cfg(feature = "types")]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LotDraw {
+    pub lot: LotDetails,
+    pub quantity: f32,
+}
+
+#[cfg(feature = "types")]
+impl LotDraw {
+    fn from_lot(lot: &StockLot, quantity: f32) -> Self {
+        LotDraw {
+            lot: LotDetails {
+                batch_code: lot.batch_code.clone(),
+                expiry_date: lot.expiry_date,
+            },
+            quantity,
+        }
+    }
+}
+
+#[cfg(feature = "types")]
+impl Stock {
+    /// Moves `quantity` into the lot, or out of it when negative. A lot
+    /// is created as stock is first received into it, and removed once
+    /// emptied.
+    pub fn transact_lot(&mut self, lot: &LotDetails, quantity: f32) {
+        match self
+            .lots
+            .iter_mut()
+            .find(|held| held.batch_code == lot.batch_code && held.expiry_date == lot.expiry_date)
+        {
+            Some(held) => held.quantity += quantity,
+            None if quantity > 0.0 => self.lots.push(StockLot {
+                batch_code: lot.batch_code.clone(),
+                expiry_date: lot.expiry_date,
+                quantity,
+                received_at: Utc::now(),
+            }),
+            None => {}
+        }
+
+        self.lots.retain(|held| held.quantity > 0.0);
+    }
+
+    /// Takes `quantity` from the lots first to expire (FEFO), those
+    /// already expired aside, returning what was drawn from each. Whatever
+    /// the lots cannot cover is taken from untracked stock, and once that
+    /// runs out, from the expired lots, so they never hold more than is
+    /// sellable.
+    pub fn take_first_expiring(&mut self, quantity: f32, now: DateTime<Utc>) -> Vec<LotDraw> {
+        let mut drawn = vec![];
+        let mut remaining = quantity;
+
+        self.lots.sort_by_key(|lot| lot.expiry_date);
+
+        for lot in self.lots.iter_mut().filter(|lot| lot.expiry_date > now) {
+            if remaining <= 0.0 {
+                break;
+            }
+
+            let taken = lot.quantity.min(remaining);
+            lot.quantity -= taken;
+            remaining -= taken;
+            drawn.push(LotDraw::from_lot(lot, taken));
+        }
+
+        let mut excess = self.lots.iter().map(|lot| lot.quantity).sum::<f32>()
+            - self.quantity.quantity_sellable.max(0.0);
+
+        for lot in self.lots.iter_mut().filter(|lot| lot.expiry_date <= now) {
+            if excess <= 0.0 {
+                break;
+            }
+
+            let taken = lot.quantity.min(excess);
+            lot.quantity -= taken;
+            excess -= taken;
+            drawn.push(LotDraw::from_lot(lot, taken));
+        }
+
+        self.lots.retain(|lot| lot.quantity > 0.0);
+
+        drawn
+    }
+
+    /// Removes the lots expired by `now`, moving their stock from
+    /// sellable to unsellable.
+    pub fn write_off_expired(&mut self, now: DateTime<Utc>) -> Vec<StockLot> {
+        let (expired, held): (Vec<StockLot>, Vec<StockLot>) =
+            self.lots.drain(..).partition(|lot| lot.expiry_date <= now);
+
+        self.lots = held;
+
+        let quantity = expired.iter().map(|lot| lot.quantity).sum::<f32>();
+        self.quantity.quantity_sellable -= quantity;
+        self.quantity.quantity_unsellable += quantity;
+
+        expired
+    }
+}
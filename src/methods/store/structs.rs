@@ -4,7 +4,7 @@ use sea_orm::Set;
 #[cfg(feature = "process")]
 use sea_orm::{
     ActiveModelTrait, ColumnTrait, DbConn, DbErr, EntityTrait, InsertResult, QueryFilter,
-    QuerySelect, RuntimeErr,
+    RuntimeErr,
 };
 use serde::{Deserialize, Serialize};
 
@@ -85,7 +85,7 @@ impl Store {
     pub async fn fetch_by_code(code: &str, session: Session, db: &DbConn) -> Result<Store, Error> {
         let store = StoreEntity::find()
             .filter(store::Column::TenantId.eq(session.tenant_id))
-            .having(store::Column::Code.eq(code))
+            .filter(store::Column::Code.eq(code))
             .one(db)
             .await?;
 
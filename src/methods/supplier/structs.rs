@@ -78,7 +78,7 @@ impl Supplier {
     ) -> Result<Vec<Supplier>, Error> {
         let res = supplier::Entity::find()
             .filter(supplier::Column::TenantId.eq(session.tenant_id))
-            .having(supplier::Column::Name.contains(name))
+            .filter(supplier::Column::Name.contains(name))
             .limit(25)
             .all(db)
             .await?;
@@ -95,7 +95,7 @@ impl Supplier {
     ) -> Result<Vec<Supplier>, Error> {
         let res = supplier::Entity::find()
             .filter(supplier::Column::TenantId.eq(session.tenant_id))
-            .having(supplier::Column::Contact.contains(phone))
+            .filter(supplier::Column::Contact.contains(phone))
             .limit(25)
             .all(db)
             .await?;
@@ -112,7 +112,7 @@ impl Supplier {
     ) -> Result<Vec<Supplier>, Error> {
         let res = supplier::Entity::find()
             .filter(supplier::Column::TenantId.eq(session.tenant_id))
-            .having(supplier::Column::Contact.contains(addr))
+            .filter(supplier::Column::Contact.contains(addr))
             .limit(25)
             .all(db)
             .await?;
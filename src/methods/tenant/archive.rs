@@ -0,0 +1,219 @@
+#[cfg(feature = "process")]
+use crate::entities::{
+    api_keys, authrecord, customer, employee, employee_mfa, external_references, import_jobs,
+    kiosk, manager_overrides, products, promotion, revoked_tokens, serials, session, store,
+    supplier, tenants, transactions,
+};
+#[cfg(feature = "process")]
+use crate::methods::Error;
+#[cfg(feature = "process")]
+use crate::Tenant;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+#[cfg(feature = "process")]
+use sea_orm::{ColumnTrait, ConnectionTrait, DbConn, EntityTrait, QueryFilter, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+#[cfg(feature = "process")]
+use tracing::info;
+
+/// Incremented whenever the layout of an export changes, such that an
+/// archive can be interpreted by the version which produced it.
+pub const EXPORT_VERSION: u32 = 1;
+
+/// Every record held for a tenant, keyed by table. Records are exported
+/// as stored, less any credentials (password hashes, session keys and
+/// the like), which are never included.
+#[cfg(feature = "types")]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct TenantExport {
+    pub version: u32,
+    pub tenant_id: String,
+    pub exported_at: DateTime<Utc>,
+    pub tables: BTreeMap<String, Vec<Value>>,
+}
+
+/// Reads the tenant's rows of `E` as JSON, omitting the `redacted` columns.
+#[cfg(feature = "process")]
+async fn export_entity<E: EntityTrait>(
+    tenant_column: E::Column,
+    redacted: &[&str],
+    tenant_id: &str,
+    db: &DbConn,
+) -> Result<(String, Vec<Value>), Error> {
+    let mut rows = E::find()
+        .filter(tenant_column.eq(tenant_id))
+        .into_json()
+        .all(db)
+        .await?;
+
+    for row in rows.iter_mut() {
+        if let Some(columns) = row.as_object_mut() {
+            redacted.iter().for_each(|column| {
+                columns.remove(*column);
+            });
+        }
+    }
+
+    Ok((E::default().table_name().to_string(), rows))
+}
+
+#[cfg(feature = "process")]
+async fn purge_entity<E: EntityTrait, C: ConnectionTrait>(
+    tenant_column: E::Column,
+    tenant_id: &str,
+    db: &C,
+) -> Result<u64, Error> {
+    let result = E::delete_many()
+        .filter(tenant_column.eq(tenant_id))
+        .exec(db)
+        .await?;
+
+    Ok(result.rows_affected)
+}
+
+#[cfg(feature = "methods")]
+impl TenantExport {
+    /// Collects the tenant's records from every entity. An entity added
+    /// to `entities` must also be added here and to `Tenant::purge`.
+    pub async fn collect(tenant_id: &str, db: &DbConn) -> Result<TenantExport, Error> {
+        let tables = vec![
+            export_entity::<tenants::Entity>(tenants::Column::TenantId, &[], tenant_id, db).await?,
+            export_entity::<employee::Entity>(employee::Column::TenantId, &["auth"], tenant_id, db)
+                .await?,
+            export_entity::<employee_mfa::Entity>(
+                employee_mfa::Column::TenantId,
+                &["secret", "recovery_codes"],
+                tenant_id,
+                db,
+            )
+            .await?,
+            export_entity::<session::Entity>(session::Column::TenantId, &["key"], tenant_id, db)
+                .await?,
+            export_entity::<revoked_tokens::Entity>(
+                revoked_tokens::Column::TenantId,
+                &[],
+                tenant_id,
+                db,
+            )
+            .await?,
+            export_entity::<api_keys::Entity>(
+                api_keys::Column::TenantId,
+                &["key_hash"],
+                tenant_id,
+                db,
+            )
+            .await?,
+            export_entity::<kiosk::Entity>(kiosk::Column::TenantId, &[], tenant_id, db).await?,
+            export_entity::<authrecord::Entity>(authrecord::Column::TenantId, &[], tenant_id, db)
+                .await?,
+            export_entity::<manager_overrides::Entity>(
+                manager_overrides::Column::TenantId,
+                &["token"],
+                tenant_id,
+                db,
+            )
+            .await?,
+            export_entity::<store::Entity>(store::Column::TenantId, &[], tenant_id, db).await?,
+            export_entity::<products::Entity>(products::Column::TenantId, &[], tenant_id, db)
+                .await?,
+            export_entity::<promotion::Entity>(promotion::Column::TenantId, &[], tenant_id, db)
+                .await?,
+            export_entity::<supplier::Entity>(supplier::Column::TenantId, &[], tenant_id, db)
+                .await?,
+            export_entity::<customer::Entity>(customer::Column::TenantId, &[], tenant_id, db)
+                .await?,
+            export_entity::<transactions::Entity>(
+                transactions::Column::TenantId,
+                &[],
+                tenant_id,
+                db,
+            )
+            .await?,
+            export_entity::<import_jobs::Entity>(import_jobs::Column::TenantId, &[], tenant_id, db)
+                .await?,
+            export_entity::<external_references::Entity>(
+                external_references::Column::TenantId,
+                &[],
+                tenant_id,
+                db,
+            )
+            .await?,
+            export_entity::<serials::Entity>(serials::Column::TenantId, &[], tenant_id, db).await?,
+        ];
+
+        Ok(TenantExport {
+            version: EXPORT_VERSION,
+            tenant_id: tenant_id.to_string(),
+            exported_at: Utc::now(),
+            tables: tables.into_iter().collect(),
+        })
+    }
+}
+
+#[cfg(feature = "methods")]
+impl Tenant {
+    /// Deletes every record held for the tenant, and the tenant itself, in
+    /// a single database transaction. Token revocations are kept until
+    /// they expire, so that tokens issued to the tenant stay unusable.
+    pub async fn purge(tenant_id: &str, db: &DbConn) -> Result<u64, Error> {
+        let txn = db.begin().await?;
+
+        let deleted = [
+            purge_entity::<serials::Entity, _>(serials::Column::TenantId, tenant_id, &txn).await?,
+            purge_entity::<external_references::Entity, _>(
+                external_references::Column::TenantId,
+                tenant_id,
+                &txn,
+            )
+            .await?,
+            purge_entity::<import_jobs::Entity, _>(import_jobs::Column::TenantId, tenant_id, &txn)
+                .await?,
+            purge_entity::<transactions::Entity, _>(
+                transactions::Column::TenantId,
+                tenant_id,
+                &txn,
+            )
+            .await?,
+            purge_entity::<customer::Entity, _>(customer::Column::TenantId, tenant_id, &txn)
+                .await?,
+            purge_entity::<supplier::Entity, _>(supplier::Column::TenantId, tenant_id, &txn)
+                .await?,
+            purge_entity::<promotion::Entity, _>(promotion::Column::TenantId, tenant_id, &txn)
+                .await?,
+            purge_entity::<products::Entity, _>(products::Column::TenantId, tenant_id, &txn)
+                .await?,
+            purge_entity::<store::Entity, _>(store::Column::TenantId, tenant_id, &txn).await?,
+            purge_entity::<manager_overrides::Entity, _>(
+                manager_overrides::Column::TenantId,
+                tenant_id,
+                &txn,
+            )
+            .await?,
+            purge_entity::<authrecord::Entity, _>(authrecord::Column::TenantId, tenant_id, &txn)
+                .await?,
+            purge_entity::<kiosk::Entity, _>(kiosk::Column::TenantId, tenant_id, &txn).await?,
+            purge_entity::<api_keys::Entity, _>(api_keys::Column::TenantId, tenant_id, &txn)
+                .await?,
+            purge_entity::<session::Entity, _>(session::Column::TenantId, tenant_id, &txn).await?,
+            purge_entity::<employee_mfa::Entity, _>(
+                employee_mfa::Column::TenantId,
+                tenant_id,
+                &txn,
+            )
+            .await?,
+            purge_entity::<employee::Entity, _>(employee::Column::TenantId, tenant_id, &txn)
+                .await?,
+            purge_entity::<tenants::Entity, _>(tenants::Column::TenantId, tenant_id, &txn).await?,
+        ]
+        .iter()
+        .sum();
+
+        txn.commit().await?;
+
+        info!(%tenant_id, rows = deleted, "purged tenant");
+
+        Ok(deleted)
+    }
+}
@@ -1,7 +1,7 @@
 use crate::tenants::{ActiveModel, Model};
 use crate::{Tenant, TenantSettings};
 use chrono::{DateTime, Utc};
-use sea_orm::ActiveValue::Set;
+use sea_orm::ActiveValue::{NotSet, Set};
 use serde_json::json;
 
 impl From<Model> for Tenant {
@@ -13,6 +13,12 @@ impl From<Model> for Tenant {
 
             created_at: DateTime::from_naive_utc_and_offset(val.created_at, Utc),
             updated_at: DateTime::from_naive_utc_and_offset(val.updated_at, Utc),
+            suspended_at: val
+                .suspended_at
+                .map(|v| DateTime::from_naive_utc_and_offset(v, Utc)),
+            purge_after: val
+                .purge_after
+                .map(|v| DateTime::from_naive_utc_and_offset(v, Utc)),
         }
     }
 }
@@ -25,6 +31,9 @@ impl From<Tenant> for ActiveModel {
             settings: Set(json!(val.settings)),
             created_at: Set(val.created_at.naive_utc()),
             updated_at: Set(val.updated_at.naive_utc()),
+            suspended_at: Set(val.suspended_at.map(|v| v.naive_utc())),
+            purge_after: Set(val.purge_after.map(|v| v.naive_utc())),
+            quote_sequence: NotSet,
         }
     }
 }
@@ -1,15 +1,198 @@
-use rocket::get;
-use rocket::routes;
-
+use crate::catchers::Validated;
+use crate::config::AppConfig;
 use crate::guards::Convert;
+use crate::methods::{Action, Error, ErrorResponse};
 use crate::pool::InternalDb;
-use crate::Tenant;
+use crate::{check_permissions, Session, Tenant, TenantExport, TenantSettings};
+use okapi::openapi3::OpenApi;
+use rocket::serde::json::Json;
+use rocket::{get, post, State};
+use rocket_okapi::settings::OpenApiSettings;
+use rocket_okapi::{openapi, openapi_get_routes_spec};
+use tracing::info;
 
-pub fn routes() -> Vec<rocket::Route> {
-    routes![get]
+pub fn documented_routes(settings: &OpenApiSettings) -> (Vec<rocket::Route>, OpenApi) {
+    openapi_get_routes_spec![
+        settings: get,
+        get_settings,
+        update_settings,
+        get_all,
+        get_by_id,
+        suspend,
+        reactivate,
+        export,
+        export_by_id,
+        purge
+    ]
 }
 
+/// Administering other tenants is reserved for super users of the tenant
+/// named by `operator_tenant`.
+fn require_operator(session: &Session, config: &AppConfig) -> Result<(), Error> {
+    let operator = config.operator_tenant.as_deref() == Some(session.tenant_id.as_str());
+
+    match operator && session.clone().has_permission(Action::SuperUserDo) {
+        true => Ok(()),
+        false => Err(ErrorResponse::custom_unauthorized(
+            "User is unauthorized, may not have a valid session.",
+        )),
+    }
+}
+
+#[openapi(tag = "Tenant")]
+#[get("/")]
+pub async fn get(db: InternalDb, session: Session) -> Convert<Tenant> {
+    Tenant::fetch_by_id(&session.tenant_id, &db.0).await.into()
+}
+
+#[openapi(tag = "Tenant")]
+#[get("/settings")]
+pub async fn get_settings(db: InternalDb, session: Session) -> Convert<TenantSettings> {
+    check_permissions!(session.clone(), Action::AccessAdminPanel);
+    Tenant::fetch_by_id(&session.tenant_id, &db.0)
+        .await
+        .map(|tenant| tenant.settings)
+        .into()
+}
+
+/// Replaces the tenant's settings, other than the MFA and override
+/// policies, which are kept as saved and updated only on their own routes.
+#[openapi(tag = "Tenant")]
+#[post("/settings", data = "<input_data>")]
+pub async fn update_settings(
+    db: InternalDb,
+    session: Session,
+    input_data: Validated<Json<TenantSettings>>,
+) -> Convert<TenantSettings> {
+    check_permissions!(session.clone(), Action::AccessAdminPanel);
+
+    let saved = match Tenant::fetch_by_id(&session.tenant_id, &db.0).await {
+        Ok(tenant) => tenant.settings,
+        Err(error) => return Err(error).into(),
+    };
+
+    let settings = TenantSettings {
+        mfa: saved.mfa,
+        overrides: saved.overrides,
+        ..input_data.data()
+    };
+
+    Tenant::update_settings(&session.tenant_id, settings, &db.0)
+        .await
+        .map(|tenant| tenant.settings)
+        .into()
+}
+
+#[openapi(tag = "Tenant")]
+#[get("/all")]
+pub async fn get_all(
+    db: InternalDb,
+    session: Session,
+    config: &State<AppConfig>,
+) -> Result<Json<Vec<Tenant>>, Error> {
+    require_operator(&session, config)?;
+    Tenant::fetch_all(&db.0).await.map(Json)
+}
+
+#[openapi(tag = "Tenant")]
 #[get("/<id>")]
-pub async fn get(db: InternalDb, id: String) -> Convert<Tenant> {
-    Tenant::fetch_by_id(&id, &db.0).await.into()
+pub async fn get_by_id(
+    db: InternalDb,
+    session: Session,
+    config: &State<AppConfig>,
+    id: &str,
+) -> Result<Json<Tenant>, Error> {
+    require_operator(&session, config)?;
+    Tenant::fetch_by_id(id, &db.0).await.map(Json)
+}
+
+/// Suspends a tenant, ending its sessions. Its employees and API keys are
+/// unable to authenticate until it is reactivated.
+#[openapi(tag = "Tenant")]
+#[post("/suspend/<id>")]
+pub async fn suspend(
+    db: InternalDb,
+    session: Session,
+    config: &State<AppConfig>,
+    id: &str,
+) -> Result<Json<Tenant>, Error> {
+    require_operator(&session, config)?;
+
+    if id == session.tenant_id {
+        return Err(ErrorResponse::create_error(
+            "The operator tenant cannot be suspended.",
+        ));
+    }
+
+    let tenant = Tenant::suspend(id, &db.0).await?;
+    info!(tenant_id = %id, suspended_by = %session.employee.id, "suspended tenant");
+
+    Ok(Json(tenant))
+}
+
+#[openapi(tag = "Tenant")]
+#[post("/reactivate/<id>")]
+pub async fn reactivate(
+    db: InternalDb,
+    session: Session,
+    config: &State<AppConfig>,
+    id: &str,
+) -> Result<Json<Tenant>, Error> {
+    require_operator(&session, config)?;
+
+    let tenant = Tenant::reactivate(id, &db.0).await?;
+    info!(tenant_id = %id, reactivated_by = %session.employee.id, "reactivated tenant");
+
+    Ok(Json(tenant))
+}
+
+/// Exports every record held for the tenant as a versioned archive,
+/// credentials excluded.
+#[openapi(tag = "Tenant")]
+#[get("/export")]
+pub async fn export(db: InternalDb, session: Session) -> Convert<TenantExport> {
+    check_permissions!(session.clone(), Action::AccessAdminPanel);
+    info!(tenant_id = %session.tenant_id, exported_by = %session.employee.id, "exported tenant");
+    TenantExport::collect(&session.tenant_id, &db.0)
+        .await
+        .into()
+}
+
+#[openapi(tag = "Tenant")]
+#[get("/export/<id>")]
+pub async fn export_by_id(
+    db: InternalDb,
+    session: Session,
+    config: &State<AppConfig>,
+    id: &str,
+) -> Result<Json<TenantExport>, Error> {
+    require_operator(&session, config)?;
+    Tenant::fetch_by_id(id, &db.0).await?;
+
+    info!(tenant_id = %id, exported_by = %session.employee.id, "exported tenant");
+    TenantExport::collect(id, &db.0).await.map(Json)
+}
+
+/// Schedules a suspended tenant's data to be deleted once the configured
+/// grace period has passed. Reactivating the tenant cancels the purge.
+#[openapi(tag = "Tenant")]
+#[post("/purge/<id>")]
+pub async fn purge(
+    db: InternalDb,
+    session: Session,
+    config: &State<AppConfig>,
+    id: &str,
+) -> Result<Json<Tenant>, Error> {
+    require_operator(&session, config)?;
+
+    if id == session.tenant_id {
+        return Err(ErrorResponse::create_error(
+            "The operator tenant cannot be purged.",
+        ));
+    }
+
+    let tenant = Tenant::request_purge(id, config.gc.tenant_purge_grace(), &db.0).await?;
+    info!(tenant_id = %id, requested_by = %session.employee.id, "requested tenant purge");
+
+    Ok(Json(tenant))
 }
@@ -1,8 +1,10 @@
 mod conversions;
+mod archive;
 #[cfg(feature = "process")]
 pub(crate) mod handlers;
 mod structs;
 
+pub use archive::*;
 #[cfg(feature = "process")]
 pub use handlers::*;
 pub use structs::*;
@@ -1,17 +1,218 @@
 #[cfg(feature = "process")]
-use crate::{entities::prelude::Tenants, methods::Error, tenants};
-use chrono::{DateTime, Utc};
+use crate::{
+    entities::prelude::Tenants,
+    methods::{Error, ErrorResponse},
+    tenants, Session,
+};
+use chrono::{DateTime, Duration, Utc};
 use schemars::JsonSchema;
 #[cfg(feature = "process")]
-use sea_orm::{DbConn, DbErr, EntityTrait, InsertResult};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DbConn, DbErr, EntityTrait, InsertResult, QueryFilter,
+    QueryOrder, Set,
+};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use validator::Validate;
 
-use crate::Id;
+use crate::{AccountType, Action, Employee, Id, OverrideAction};
 
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema, Validate)]
+#[serde(default)]
+pub struct TenantSettings {
+    /// The trading name shown on receipts.
+    #[validate(length(max = 128))]
+    pub business_name: String,
+    /// An ISO 4217 currency code, i.e. `NZD`.
+    #[validate(length(equal = 3))]
+    pub default_currency: String,
+    /// An IANA time zone, i.e. `Pacific/Auckland`.
+    #[validate(length(min = 1, max = 64))]
+    pub timezone: String,
+    pub tax_mode: TaxMode,
+    #[validate(length(max = 512))]
+    pub receipt_footer: String,
+    #[validate]
+    pub sessions: SessionLifetimes,
+    pub mfa: MfaPolicy,
+    #[validate]
+    pub overrides: OverridePolicy,
+    pub pricing: PricingPolicy,
+    #[validate]
+    pub quotes: QuotePolicy,
+}
+
+#[cfg(feature = "types")]
+impl Default for TenantSettings {
+    fn default() -> Self {
+        TenantSettings {
+            business_name: String::new(),
+            default_currency: "NZD".to_string(),
+            timezone: "Pacific/Auckland".to_string(),
+            tax_mode: TaxMode::default(),
+            receipt_footer: String::new(),
+            sessions: SessionLifetimes::default(),
+            mfa: MfaPolicy::default(),
+            overrides: OverridePolicy::default(),
+            pricing: PricingPolicy::default(),
+            quotes: QuotePolicy::default(),
+        }
+    }
+}
+
+/// Whether prices are entered inclusive or exclusive of tax.
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, JsonSchema)]
+pub enum TaxMode {
+    #[default]
+    Inclusive,
+    Exclusive,
+}
+
+/// Overrides the server's `sessions` configuration for the tenant's
+/// employees, the server's values apply where these are unset.
 #[cfg(feature = "types")]
 #[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema, Validate)]
-pub struct TenantSettings {}
+#[serde(default)]
+pub struct SessionLifetimes {
+    #[validate(range(min = 1))]
+    pub access_ttl_minutes: Option<i64>,
+    #[validate(range(min = 1))]
+    pub refresh_ttl_days: Option<i64>,
+}
+
+#[cfg(feature = "types")]
+impl SessionLifetimes {
+    pub fn access_ttl(&self, default: Duration) -> Duration {
+        self.access_ttl_minutes.map_or(default, Duration::minutes)
+    }
+
+    pub fn refresh_ttl(&self, default: Duration) -> Duration {
+        self.refresh_ttl_days.map_or(default, Duration::days)
+    }
+}
+
+/// Determines when employees must complete multi-factor authentication.
+/// An employee who is required to, but has not enrolled, is only issued
+/// a session without the protected actions (enough to enrol).
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema, Validate)]
+#[serde(default)]
+pub struct MfaPolicy {
+    /// Every action of a `Managerial` account is protected.
+    pub require_for_managerial: bool,
+    /// Actions protected for any account.
+    pub required_actions: Vec<Action>,
+}
+
+#[cfg(feature = "types")]
+impl MfaPolicy {
+    /// The actions held by `employee` which require MFA under this policy.
+    pub fn protected_actions(&self, employee: &Employee) -> Vec<Action> {
+        let managerial =
+            self.require_for_managerial && matches!(employee.account_type, AccountType::Managerial);
+
+        employee
+            .level
+            .iter()
+            .map(|access| access.action.clone())
+            .filter(|action| managerial || self.required_actions.contains(action))
+            .collect()
+    }
+}
+
+/// Determines which till actions require a manager override, unless the
+/// employee performing them holds `AuthoriseOverride` themselves.
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema, Validate)]
+#[serde(default)]
+pub struct OverridePolicy {
+    /// Overrides are opt-in, none being required until a tenant lists the
+    /// actions to protect.
+    pub required_actions: Vec<OverrideAction>,
+    /// Discounts up to this percentage of the price they apply to may be
+    /// given without an override.
+    #[validate(range(max = 100))]
+    pub discount_threshold: u32,
+    /// How long an override may go unused before it lapses.
+    pub lifetime_secs: u64,
+}
+
+#[cfg(feature = "types")]
+impl Default for OverridePolicy {
+    fn default() -> Self {
+        OverridePolicy {
+            required_actions: vec![],
+            discount_threshold: 20,
+            lifetime_secs: 120,
+        }
+    }
+}
+
+#[cfg(feature = "types")]
+impl OverridePolicy {
+    /// The subset of `actions` which require an override under this policy.
+    pub fn protected(&self, actions: &[OverrideAction]) -> Vec<OverrideAction> {
+        actions
+            .iter()
+            .filter(|action| self.required_actions.contains(action))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Determines how a sale priced differently to the catalogue is handled.
+/// Discounts above an employee's ceiling are rejected regardless.
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
+#[serde(default)]
+pub struct PricingPolicy {
+    pub mismatch: PriceMismatchAction,
+}
+
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, JsonSchema)]
+pub enum PriceMismatchAction {
+    /// The sale is rejected, listing each difference.
+    #[default]
+    Reject,
+    /// The sale is repriced from the catalogue, and each difference noted
+    /// against it.
+    Flag,
+}
+
+/// Determines how long a quote is valid for, and the prices it is sold at.
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema, Validate)]
+#[serde(default)]
+pub struct QuotePolicy {
+    /// Days from issue (or revision) until the quote expires.
+    #[validate(range(min = 1))]
+    pub validity_days: i64,
+    pub conversion: QuoteConversion,
+}
+
+#[cfg(feature = "types")]
+impl Default for QuotePolicy {
+    fn default() -> Self {
+        QuotePolicy {
+            validity_days: 30,
+            conversion: QuoteConversion::default(),
+        }
+    }
+}
+
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, JsonSchema)]
+pub enum QuoteConversion {
+    /// The sale is made at the prices quoted.
+    #[default]
+    Honour,
+    /// The sale is repriced from the catalogue, each difference noted
+    /// against it.
+    Reprice,
+}
 
 #[cfg(feature = "types")]
 #[derive(Debug, Serialize, Deserialize, Clone, JsonSchema, Validate)]
@@ -23,6 +224,19 @@ pub struct Tenant {
 
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Set whilst the tenant is suspended, during which its employees and
+    /// API keys are unable to authenticate.
+    pub suspended_at: Option<DateTime<Utc>>,
+    /// Set once a purge has been requested, after which every record held
+    /// for the tenant is deleted.
+    pub purge_after: Option<DateTime<Utc>>,
+}
+
+#[cfg(feature = "types")]
+impl Tenant {
+    pub fn is_suspended(&self) -> bool {
+        self.suspended_at.is_some()
+    }
 }
 
 #[cfg(feature = "methods")]
@@ -51,6 +265,100 @@ impl Tenant {
         }
     }
 
+    pub async fn update_settings(
+        id: &str,
+        settings: TenantSettings,
+        db: &DbConn,
+    ) -> Result<Tenant, Error> {
+        tenants::ActiveModel {
+            tenant_id: Set(id.to_string()),
+            settings: Set(json!(settings)),
+            updated_at: Set(Utc::now().naive_utc()),
+            ..Default::default()
+        }
+        .update(db)
+        .await?;
+
+        Tenant::fetch_by_id(id, db).await
+    }
+
+    pub async fn fetch_all(db: &DbConn) -> Result<Vec<Tenant>, Error> {
+        let tenants = Tenants::find()
+            .order_by_asc(tenants::Column::RegistrationDate)
+            .all(db)
+            .await?;
+
+        Ok(tenants.into_iter().map(|t| t.into()).collect())
+    }
+
+    /// Suspends the tenant, ending every session issued to it.
+    pub async fn suspend(id: &str, db: &DbConn) -> Result<Tenant, Error> {
+        let tenant = Tenant::fetch_by_id(id, db).await?;
+
+        if !tenant.is_suspended() {
+            tenants::ActiveModel {
+                tenant_id: Set(id.to_string()),
+                suspended_at: Set(Some(Utc::now().naive_utc())),
+                updated_at: Set(Utc::now().naive_utc()),
+                ..Default::default()
+            }
+            .update(db)
+            .await?;
+        }
+
+        Session::revoke_tenant(id, db).await?;
+
+        Tenant::fetch_by_id(id, db).await
+    }
+
+    /// Schedules the suspended tenant to be purged once `grace` has passed,
+    /// reactivating the tenant beforehand cancels the purge.
+    pub async fn request_purge(id: &str, grace: Duration, db: &DbConn) -> Result<Tenant, Error> {
+        let tenant = Tenant::fetch_by_id(id, db).await?;
+
+        if !tenant.is_suspended() {
+            return Err(ErrorResponse::create_error(
+                "A tenant must be suspended before it is purged.",
+            ));
+        }
+
+        tenants::ActiveModel {
+            tenant_id: Set(id.to_string()),
+            purge_after: Set(Some((Utc::now() + grace).naive_utc())),
+            updated_at: Set(Utc::now().naive_utc()),
+            ..Default::default()
+        }
+        .update(db)
+        .await?;
+
+        Tenant::fetch_by_id(id, db).await
+    }
+
+    /// The tenants whose requested purge is now due.
+    pub async fn fetch_due_for_purge(db: &DbConn) -> Result<Vec<Tenant>, Error> {
+        let tenants = Tenants::find()
+            .filter(tenants::Column::SuspendedAt.is_not_null())
+            .filter(tenants::Column::PurgeAfter.lte(Utc::now().naive_utc()))
+            .all(db)
+            .await?;
+
+        Ok(tenants.into_iter().map(|t| t.into()).collect())
+    }
+
+    pub async fn reactivate(id: &str, db: &DbConn) -> Result<Tenant, Error> {
+        tenants::ActiveModel {
+            tenant_id: Set(id.to_string()),
+            suspended_at: Set(None),
+            purge_after: Set(None),
+            updated_at: Set(Utc::now().naive_utc()),
+            ..Default::default()
+        }
+        .update(db)
+        .await?;
+
+        Tenant::fetch_by_id(id, db).await
+    }
+
     pub async fn insert(
         tnt: Tenant,
         db: &DbConn,
@@ -66,8 +374,10 @@ pub fn example_tenant(tenant_id: &str) -> Tenant {
     Tenant {
         tenant_id: tenant_id.to_string(),
         registration_date: Utc::now(),
-        settings: TenantSettings {},
+        settings: TenantSettings::default(),
         created_at: Utc::now(),
         updated_at: Utc::now(),
+        suspended_at: None,
+        purge_after: None,
     }
 }
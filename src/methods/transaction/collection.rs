@@ -0,0 +1,295 @@
+//! Click-and-collect of pickup orders. Once an order is ready in store, a
+//! collection code is issued to the customer, who presents it (or its QR
+//! code) at a kiosk of the destination store to collect the order. Only a
+//! hash of the code is kept.
+
+use chrono::{DateTime, Utc};
+#[cfg(feature = "process")]
+use rand::{distributions::Slice, Rng};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "process")]
+use serde_json::json;
+use validator::Validate;
+
+#[cfg(feature = "process")]
+use crate::entities::{prelude::Transactions, transactions};
+use crate::methods::Id;
+#[cfg(feature = "process")]
+use crate::methods::{find_order, Error, ErrorResponse, Kiosk, OrderStatus, OrderType, Session};
+#[cfg(feature = "process")]
+use crate::Transaction;
+#[cfg(feature = "process")]
+use sea_orm::{ColumnTrait, DbConn, EntityTrait, QueryFilter};
+#[cfg(feature = "process")]
+use sha2::{Digest, Sha256};
+
+/// Characters of a collection code, omitting those easily mistaken for
+/// one another when read aloud or typed.
+#[cfg(feature = "process")]
+const CODE_ALPHABET: &[char] = &[
+    'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'J', 'K', 'L', 'M', 'N', 'P', 'Q', 'R', 'S', 'T', 'U',
+    'V', 'W', 'X', 'Y', 'Z', '2', '3', '4', '5', '6', '7', '8', '9',
+];
+
+#[cfg(feature = "process")]
+const CODE_LENGTH: usize = 8;
+
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct Collection {
+    /// A hash of the code issued to the customer, the code itself is only
+    /// returned as it is issued.
+    pub code_hash: String,
+    pub issued_at: DateTime<Utc>,
+    pub collected: Option<CollectionRecord>,
+}
+
+/// The code a pickup order is collected with, as issued to the customer.
+/// This is only ever returned as the order is readied, or its code reissued.
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct IssuedCollection {
+    pub order_id: Id,
+    pub code: String,
+    /// Encoded into the QR code given to the customer, and scanned at the
+    /// kiosk to fill in a collection.
+    pub qr_payload: String,
+}
+
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct CollectionRecord {
+    /// The name of whoever collected the order, as given at the counter.
+    pub collected_by: String,
+    /// The employee who handed the order over.
+    pub verified_by: Id,
+    pub kiosk_id: Id,
+    pub collected_at: DateTime<Utc>,
+}
+
+#[cfg(feature = "types")]
+#[derive(Deserialize, Clone, JsonSchema, Validate)]
+pub struct CollectInput {
+    pub transaction_id: Id,
+    pub order_id: Id,
+    pub code: String,
+    #[validate(length(min = 1))]
+    pub collected_by: String,
+}
+
+/// A pickup order ready in store, and how long it has waited.
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct AwaitingCollection {
+    pub transaction_id: Id,
+    pub order_id: Id,
+    pub reference: String,
+    pub customer_id: Id,
+    pub ready_since: DateTime<Utc>,
+    pub age_secs: i64,
+}
+
+/// Codes are hashed alongside their order, so equal codes of two orders
+/// differ once hashed.
+#[cfg(feature = "process")]
+fn hash_code(order_id: &str, code: &str) -> String {
+    hex::encode(Sha256::digest(
+        format!("{}:{}", order_id, code.trim().to_uppercase()).as_bytes(),
+    ))
+}
+
+#[cfg(feature = "process")]
+impl Collection {
+    /// Issues a new code for the order, returning the collection to keep
+    /// and the code to give to the customer.
+    pub fn issue(transaction_id: &str, order_id: &str) -> (Self, IssuedCollection) {
+        let code = rand::thread_rng()
+            .sample_iter(Slice::new(CODE_ALPHABET).unwrap())
+            .take(CODE_LENGTH)
+            .collect::<String>();
+
+        let collection = Collection {
+            code_hash: hash_code(order_id, &code),
+            issued_at: Utc::now(),
+            collected: None,
+        };
+
+        let issued = IssuedCollection {
+            order_id: order_id.to_string(),
+            qr_payload: json!({
+                "transaction_id": transaction_id,
+                "order_id": order_id,
+                "code": code,
+            })
+            .to_string(),
+            code,
+        };
+
+        (collection, issued)
+    }
+
+    /// Whether `code` is the one issued for the order, in any case.
+    pub fn matches(&self, order_id: &str, code: &str) -> bool {
+        self.code_hash == hash_code(order_id, code)
+    }
+}
+
+#[cfg(feature = "process")]
+impl Transaction {
+    /// Hands a pickup order over, once its collection code is presented at
+    /// a kiosk of its destination store, fulfilling it.
+    pub async fn collect(
+        input: CollectInput,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<Transaction, Error> {
+        let mut transaction = Self::fetch_by_id(&input.transaction_id, session.clone(), db).await?;
+
+        if transaction.void.is_some() {
+            return Err(ErrorResponse::create_error(
+                "A voided transaction cannot be collected.",
+            ));
+        }
+
+        // Orders are handed over in person, at the kiosk the session was
+        // issued at.
+        let kiosk = match session.kiosk_id(db).await? {
+            Some(kiosk_id) => Kiosk::fetch_by_id(&kiosk_id, session.clone(), db).await?,
+            None => {
+                return Err(ErrorResponse::custom_unauthorized(
+                    "Orders must be collected using a session issued at a kiosk.",
+                ))
+            }
+        };
+        let order = find_order(&mut transaction, &input.order_id)?;
+
+        let collection = match (&order.order_type, &order.status.status) {
+            (OrderType::Pickup, OrderStatus::InStore(_)) => order.collection.as_mut(),
+            _ => None,
+        }
+        .filter(|collection| collection.collected.is_none())
+        .ok_or_else(|| {
+            ErrorResponse::create_error(&format!(
+                "Order `{}` is not awaiting collection.",
+                input.order_id
+            ))
+        })?;
+
+        if kiosk.disabled || kiosk.store_id != order.destination.store_id {
+            return Err(ErrorResponse::create_error(
+                "Orders must be collected at a kiosk of their destination store.",
+            ));
+        }
+
+        if !collection.matches(&input.order_id, &input.code) {
+            return Err(ErrorResponse::create_error(
+                "The collection code does not match.",
+            ));
+        }
+
+        collection.collected = Some(CollectionRecord {
+            collected_by: input.collected_by.clone(),
+            verified_by: session.employee.id.clone(),
+            kiosk_id: kiosk.id,
+            collected_at: Utc::now(),
+        });
+
+        // Only collection fulfils a pickup order, so no transition permits it.
+        order.assign_status(
+            OrderStatus::Fulfilled(Utc::now()),
+            &format!("Collected by {}", input.collected_by),
+        );
+
+        // Saved only if unchanged since read, so while the order is still in
+        // store and uncollected. Of concurrent collections, one succeeds.
+        let id = transaction.id.clone();
+        Self::update_value(transaction, session, &id, db).await
+    }
+
+    /// Issues a new code for a pickup order awaiting collection, replacing
+    /// its last, i.e. once the customer has lost theirs.
+    pub async fn reissue_collection(
+        id: &str,
+        order_id: &str,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<IssuedCollection, Error> {
+        let mut transaction = Self::fetch_by_id(id, session.clone(), db).await?;
+
+        if transaction.void.is_some() {
+            return Err(ErrorResponse::create_error(
+                "A voided transaction cannot be collected.",
+            ));
+        }
+
+        let order = find_order(&mut transaction, order_id)?;
+
+        let awaiting = matches!(
+            (&order.order_type, &order.status.status, &order.collection),
+            (OrderType::Pickup, OrderStatus::InStore(_), Some(collection))
+                if collection.collected.is_none()
+        );
+
+        if !awaiting {
+            return Err(ErrorResponse::create_error(&format!(
+                "Order `{}` is not awaiting collection.",
+                order_id
+            )));
+        }
+
+        let (collection, issued) = Collection::issue(id, order_id);
+        order.collection = Some(collection);
+
+        Self::update_value(transaction, session, id, db).await?;
+
+        Ok(issued)
+    }
+
+    /// The pickup orders ready at the store, oldest first.
+    pub async fn fetch_awaiting_collection(
+        store_id: &str,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<Vec<AwaitingCollection>, Error> {
+        let res = Transactions::find()
+            .filter(transactions::Column::TenantId.eq(session.tenant_id))
+            .filter(transactions::Column::Void.is_null())
+            .filter(transactions::Column::Products.contains(store_id))
+            .all(db)
+            .await?;
+
+        let now = Utc::now();
+        let mut awaiting = res
+            .into_iter()
+            .map(Transaction::from)
+            .flat_map(|transaction| {
+                let customer_id = transaction.customer.customer_id.clone();
+
+                transaction
+                    .products
+                    .into_iter()
+                    .filter_map(|order| match order.status.status {
+                        OrderStatus::InStore(ready_since)
+                            if order.destination.store_id == store_id =>
+                        {
+                            Some(AwaitingCollection {
+                                transaction_id: transaction.id.clone(),
+                                order_id: order.id,
+                                reference: order.reference,
+                                customer_id: customer_id.clone(),
+                                ready_since,
+                                age_secs: (now - ready_since).num_seconds(),
+                            })
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<AwaitingCollection>>()
+            })
+            .collect::<Vec<AwaitingCollection>>();
+
+        awaiting.sort_by_key(|order| order.ready_since);
+
+        Ok(awaiting)
+    }
+}
@@ -2,8 +2,8 @@
 use crate::entities::sea_orm_active_enums::TransactionType as SeaORMTType;
 use crate::transactions::{ActiveModel, Model};
 use crate::{
-    NoteList, OrderList, Payment, Session, Transaction, TransactionCustomer, TransactionInit,
-    TransactionInput, TransactionType,
+    in_transit, NoteList, OrderList, Payment, QuoteDetails, Session, Transaction,
+    TransactionCustomer, TransactionInit, TransactionInput, TransactionType, TransactionVoid,
 };
 use chrono::{DateTime, Utc};
 use sea_orm::ActiveValue::Set;
@@ -41,6 +41,7 @@ impl TransactionInput {
             id: Set(id),
             customer: Set(json!(self.customer)),
             transaction_type: Set(self.transaction_type.into()),
+            in_transit: Set(in_transit(&self.products)),
             products: Set(json!(self.products)),
             order_total: Set(self.order_total),
             payment: Set(json!(self.payment)),
@@ -51,6 +52,7 @@ impl TransactionInput {
             tenant_id: Set(session.tenant_id),
             created_at: Set(Utc::now().naive_utc()),
             updated_at: Set(Utc::now().naive_utc()),
+            ..Default::default()
         }
     }
 }
@@ -61,6 +63,7 @@ impl TransactionInit {
             id: Set(id),
             customer: Set(json!(self.customer)),
             transaction_type: Set(self.transaction_type.into()),
+            in_transit: Set(in_transit(&self.products)),
             products: Set(json!(self.products)),
             order_total: Set(self.order_total),
             payment: Set(json!(self.payment)),
@@ -68,6 +71,8 @@ impl TransactionInit {
             order_notes: Set(json!(self.order_notes)),
             salesperson: Set(session.employee.id),
             kiosk: Set(self.kiosk),
+            void: Set(None),
+            quote: Set(self.quote.map(|quote| json!(quote))),
             tenant_id: Set(session.tenant_id),
             created_at: Set(Utc::now().naive_utc()),
             updated_at: Set(Utc::now().naive_utc()),
@@ -81,6 +86,7 @@ impl Transaction {
             id: Set(self.id),
             customer: Set(json!(self.customer)),
             transaction_type: Set(self.transaction_type.into()),
+            in_transit: Set(in_transit(&self.products)),
             products: Set(json!(self.products)),
             order_total: Set(self.order_total),
             payment: Set(json!(self.payment)),
@@ -88,6 +94,8 @@ impl Transaction {
             order_notes: Set(json!(self.order_notes)),
             salesperson: Set(self.salesperson),
             kiosk: Set(self.kiosk),
+            void: Set(self.void.map(|void| json!(void))),
+            quote: Set(self.quote.map(|quote| json!(quote))),
             tenant_id: Set(tenant_id),
             created_at: Set(self.created_at.naive_utc()),
             updated_at: Set(self.updated_at.naive_utc()),
@@ -112,9 +120,17 @@ impl From<Model> for Transaction {
 
             salesperson: val.salesperson,
             kiosk: val.kiosk,
+            void: val
+                .void
+                .map(|void| serde_json::from_value::<TransactionVoid>(void).unwrap()),
+            quote: val
+                .quote
+                .map(|quote| serde_json::from_value::<QuoteDetails>(quote).unwrap()),
 
             created_at: DateTime::from_naive_utc_and_offset(val.created_at, Utc),
             updated_at: DateTime::from_naive_utc_and_offset(val.updated_at, Utc),
+
+            issued_collections: vec![],
         }
     }
 }
@@ -63,7 +63,10 @@ pub fn example_transaction(customer_id: &str) -> TransactionInit {
                         last_updated: Utc::now(),
                         notes: vec![],
                     },
+                    serial: None,
                 }],
+                lot: None,
+                lots_drawn: vec![],
             },
             ProductPurchase {
                 product_name: "Torpedo7 Kids Voyager II Paddle Vest".to_string(),
@@ -84,10 +87,16 @@ pub fn example_transaction(customer_id: &str) -> TransactionInit {
                         last_updated: Utc::now(),
                         notes: vec![],
                     },
+                    serial: None,
                 }],
+                lot: None,
+                lots_drawn: vec![],
             },
         ],
         previous_failed_fulfillment_attempts: vec![],
+        packages: vec![],
+        stock_released: false,
+        collection: None,
         status: OrderStatusAssignment {
             // status: OrderStatus::Transit(
             //     TransitInformation {
@@ -138,6 +147,8 @@ pub fn example_transaction(customer_id: &str) -> TransactionInit {
                         query_url: "https://www.fedex.com/fedextrack/?trknbr=".into(),
                         tracking_code: "1523123".into(),
                         assigned_products: vec!["132522-22".to_string()],
+                        carrier: None,
+                        label: None,
                     })),
                     timestamp: Utc::now().checked_add_signed(Duration::hours(2)).unwrap(),
                     assigned_products: vec!["132522-22".to_string()],
@@ -201,5 +212,7 @@ pub fn example_transaction(customer_id: &str) -> TransactionInit {
         }],
         // order_history: vec![History { item: ProductExchange { method_type: TransactionType::Out, product_code: "132522".into(), variant: vec!["22".into()], quantity: 1 }, reason: "Faulty Product".into(), timestamp: Utc::now() }],
         kiosk: "...".into(),
+        quote: None,
+        reference: None,
     }
 }
@@ -0,0 +1,295 @@
+//! Warehouse fulfilment of the orders dispatched from a store. Instances
+//! awaiting picking are gathered into a pick list, picked in batches across
+//! orders, then packed into packages. Once every instance of an order is
+//! packed, the order is placed in transit, or handed over if sold directly.
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::methods::{ContactInformation, Id, Package, Url};
+#[cfg(feature = "methods")]
+use crate::methods::{
+    Error, ErrorResponse, Order, OrderStatus, OrderType, ProductInstance, Session,
+    TransitInformation,
+};
+#[cfg(feature = "methods")]
+use crate::{PickStatus, Transaction};
+#[cfg(feature = "methods")]
+use sea_orm::DbConn;
+
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct PickList {
+    pub store_id: Id,
+    pub generated_at: DateTime<Utc>,
+    pub items: Vec<PickItem>,
+}
+
+/// A variant to be picked, and each instance of it awaiting picking across
+/// the store's orders.
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct PickItem {
+    pub product_sku: String,
+    pub product_code: String,
+    pub product_name: String,
+    pub product_variant_name: String,
+    pub quantity: usize,
+    pub targets: Vec<PickTarget>,
+}
+
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema, Validate)]
+pub struct PickTarget {
+    pub transaction_id: Id,
+    pub order_id: Id,
+    pub instance_id: Id,
+}
+
+#[cfg(feature = "types")]
+#[derive(Deserialize, Clone, JsonSchema, Validate)]
+pub struct PickBatch {
+    #[validate(length(min = 1))]
+    pub picks: Vec<PickTarget>,
+}
+
+/// Packs picked instances of an order. Should this complete the order, it
+/// is placed in transit with the given shipment, or as dispatched by its
+/// origin store when none is given. A direct order is fulfilled instead.
+#[cfg(feature = "types")]
+#[derive(Deserialize, Clone, JsonSchema, Validate)]
+pub struct PackInput {
+    #[validate]
+    pub packages: Vec<Package>,
+    pub shipment: Option<Shipment>,
+}
+
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema, Validate)]
+pub struct Shipment {
+    pub shipping_company: ContactInformation,
+    pub query_url: Url,
+    pub tracking_code: String,
+}
+
+#[cfg(feature = "methods")]
+pub(crate) fn find_order<'a>(
+    transaction: &'a mut Transaction,
+    order_id: &str,
+) -> Result<&'a mut Order, Error> {
+    transaction
+        .products
+        .iter_mut()
+        .find(|order| order.id == order_id)
+        .ok_or_else(|| ErrorResponse::create_error(&format!("Unknown order `{}`.", order_id)))
+}
+
+#[cfg(feature = "methods")]
+fn find_instance<'a>(
+    order: &'a mut Order,
+    instance_id: &str,
+) -> Result<&'a mut ProductInstance, Error> {
+    order
+        .products
+        .iter_mut()
+        .flat_map(|purchase| purchase.instances.iter_mut())
+        .find(|instance| instance.id == instance_id)
+        .ok_or_else(|| {
+            ErrorResponse::create_error(&format!("Unknown product instance `{}`.", instance_id))
+        })
+}
+
+#[cfg(feature = "methods")]
+impl Transaction {
+    /// The instances awaiting picking at the store, grouped by variant so
+    /// each is walked to once for every order it is sold in.
+    pub async fn pick_list(
+        store_id: &str,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<PickList, Error> {
+        let jobs = Self::fetch_deliverable_orders(store_id, session, db).await?;
+        let mut items: Vec<PickItem> = vec![];
+
+        for (transaction_id, order) in jobs {
+            for purchase in order.products {
+                let targets = purchase
+                    .instances
+                    .iter()
+                    .filter(|instance| {
+                        matches!(instance.fulfillment_status.pick_status, PickStatus::Pending)
+                    })
+                    .map(|instance| PickTarget {
+                        transaction_id: transaction_id.clone(),
+                        order_id: order.id.clone(),
+                        instance_id: instance.id.clone(),
+                    })
+                    .collect::<Vec<PickTarget>>();
+
+                if targets.is_empty() {
+                    continue;
+                }
+
+                match items.iter_mut().find(|item| {
+                    item.product_sku == purchase.product_sku
+                        && item.product_code == purchase.product_code
+                }) {
+                    Some(item) => item.targets.extend(targets),
+                    None => items.push(PickItem {
+                        product_sku: purchase.product_sku,
+                        product_code: purchase.product_code,
+                        product_name: purchase.product_name,
+                        product_variant_name: purchase.product_variant_name,
+                        quantity: 0,
+                        targets,
+                    }),
+                }
+            }
+        }
+
+        items
+            .iter_mut()
+            .for_each(|item| item.quantity = item.targets.len());
+        items.sort_by(|a, b| {
+            (&a.product_sku, &a.product_code).cmp(&(&b.product_sku, &b.product_code))
+        });
+
+        Ok(PickList {
+            store_id: store_id.to_string(),
+            generated_at: Utc::now(),
+            items,
+        })
+    }
+
+    /// Marks each instance picked, across however many orders. Nothing is
+    /// picked unless every instance given is awaiting picking.
+    pub async fn pick(
+        batch: PickBatch,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<Vec<Transaction>, Error> {
+        let mut transactions: Vec<Transaction> = vec![];
+
+        for target in batch.picks.iter() {
+            if !transactions.iter().any(|t| t.id == target.transaction_id) {
+                let transaction =
+                    Self::fetch_by_id(&target.transaction_id, session.clone(), db).await?;
+
+                if transaction.void.is_some() {
+                    return Err(ErrorResponse::create_error(
+                        "A voided transaction cannot be picked.",
+                    ));
+                }
+
+                transactions.push(transaction);
+            }
+
+            let transaction = transactions
+                .iter_mut()
+                .find(|t| t.id == target.transaction_id)
+                .unwrap();
+            let order = find_order(transaction, &target.order_id)?;
+            let instance = find_instance(order, &target.instance_id)?;
+
+            if !matches!(instance.fulfillment_status.pick_status, PickStatus::Pending) {
+                return Err(ErrorResponse::create_error(&format!(
+                    "Product instance `{}` is not awaiting picking.",
+                    target.instance_id
+                )));
+            }
+
+            instance
+                .fulfillment_status
+                .transition(PickStatus::Picked, "Batch Pick");
+
+            if let OrderStatus::Queued(_) = order.status.status {
+                order.transition(OrderStatus::Processing(Utc::now()), "Picking Started")?;
+            }
+        }
+
+        let mut picked = vec![];
+        for transaction in transactions {
+            let id = transaction.id.clone();
+            picked.push(Self::update_value(transaction, session.clone(), &id, db).await?);
+        }
+
+        Ok(picked)
+    }
+
+    /// Packs picked instances of the order into the packages given. Once
+    /// every instance of the order is packed, it is placed in transit, or
+    /// fulfilled if it is a direct order.
+    pub async fn pack(
+        id: &str,
+        order_id: &str,
+        input: PackInput,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<Transaction, Error> {
+        let mut transaction = Self::fetch_by_id(id, session.clone(), db).await?;
+
+        if transaction.void.is_some() {
+            return Err(ErrorResponse::create_error(
+                "A voided transaction cannot be packed.",
+            ));
+        }
+
+        if input.packages.is_empty() {
+            return Err(ErrorResponse::create_error(
+                "At least one package must be packed.",
+            ));
+        }
+
+        let order = find_order(&mut transaction, order_id)?;
+
+        for instance_id in input.packages.iter().flat_map(|p| p.instances.iter()) {
+            let instance = find_instance(order, instance_id)?;
+
+            if !matches!(instance.fulfillment_status.pick_status, PickStatus::Picked) {
+                return Err(ErrorResponse::create_error(&format!(
+                    "Product instance `{}` has not been picked.",
+                    instance_id
+                )));
+            }
+
+            instance
+                .fulfillment_status
+                .transition(PickStatus::Packed, "Packed");
+        }
+
+        order.packages.extend(input.packages);
+
+        let packed = order
+            .products
+            .iter()
+            .flat_map(|purchase| purchase.instances.iter())
+            .all(|instance| matches!(instance.fulfillment_status.pick_status, PickStatus::Packed));
+
+        // Fetched from the back for a sale over the counter, so handed over
+        // once packed rather than shipped.
+        if packed && matches!(order.order_type, OrderType::Direct) {
+            order.transition(OrderStatus::Fulfilled(Utc::now()), "Picked and Packed")?;
+        } else if packed {
+            let shipment = input.shipment.unwrap_or_else(|| Shipment {
+                shipping_company: order.origin.contact.clone(),
+                query_url: String::new(),
+                tracking_code: String::new(),
+            });
+
+            let transit = TransitInformation {
+                shipping_company: shipment.shipping_company,
+                query_url: shipment.query_url,
+                tracking_code: shipment.tracking_code,
+                assigned_products: order.products.iter().map(|p| p.id.clone()).collect(),
+                carrier: None,
+                label: None,
+            };
+
+            order.transition(OrderStatus::Transit(Box::new(transit)), "Picked and Packed")?;
+        }
+
+        Self::update_value(transaction, session, id, db).await
+    }
+}
@@ -1,21 +1,27 @@
 use super::{Transaction, TransactionInit, TransactionInput};
 use crate::catchers::Validated;
-use crate::guards::Convert;
+use crate::guards::{Convert, OverrideTokens};
 use crate::methods::employee::Action;
-use crate::methods::{Error, ErrorResponse, QuantityAlterationIntent};
+use crate::methods::{Error, ErrorResponse, Id, QuantityAlterationIntent};
 use crate::pool::InternalDb;
 use crate::Session;
 use crate::{
-    apply_discount, check_permissions, Order, OrderStatus, ProductStatusUpdate, TransactionType,
-    VoidableResult,
+    apply_discount, check_permissions, AwaitingCollection, CollectInput, IssuedCollection,
+    ManagerOverride, Note, NoteList, Order, OrderList, OrderStatus, OverrideAction, PackInput,
+    Payment, PickBatch, PickList, PriceDiff, PriceMismatchAction, PricedField, ProductStatusUpdate,
+    QuoteConversion, QuoteConversionInput, QuoteDetails, SensitiveAmounts, SerialNumber, Tenant,
+    TransactionType, TransactionVoidInput,
 };
+use chrono::{Duration, Utc};
 use okapi::openapi3::OpenApi;
 use rocket::get;
 use rocket::post;
 use rocket::serde::json::Json;
 use rocket_okapi::settings::OpenApiSettings;
 use rocket_okapi::{openapi, openapi_get_routes_spec};
-use sea_orm::{DbErr, DeleteResult};
+use sea_orm::{DbErr, TransactionTrait};
+use tracing::debug;
+use uuid::Uuid;
 
 pub fn documented_routes(settings: &OpenApiSettings) -> (Vec<rocket::Route>, OpenApi) {
     openapi_get_routes_spec![
@@ -25,15 +31,94 @@ pub fn documented_routes(settings: &OpenApiSettings) -> (Vec<rocket::Route>, Ope
         get_all_saved,
         get_by_product_sku,
         create,
+        get_quotes,
+        convert_quote,
         update,
         generate,
-        delete,
+        void,
         deliverables_search,
+        pick_list,
+        pick,
+        pack,
+        reroute,
+        collect,
+        reissue_collection,
+        awaiting_collection,
         update_product_status,
         update_order_status
     ]
 }
 
+/// The greatest discount a sale may give: the employee's ceiling, or any
+/// greater discount already `approved` on it. A manager's discount
+/// override for the sale lifts the ceiling to the discount it approves.
+async fn discount_ceiling(
+    approved: &SensitiveAmounts,
+    reference: &str,
+    overrides: &OverrideTokens,
+    session: &Session,
+    db: &InternalDb,
+) -> Result<f32, Error> {
+    let overridden = ManagerOverride::held(
+        &OverrideAction::Discount,
+        Some(reference),
+        &overrides.0,
+        session,
+        &db.0,
+    )
+    .await?
+    .iter()
+    .filter_map(|held| held.discount)
+    .max()
+    .unwrap_or(0);
+
+    Ok((session.discount_ceiling() as f32)
+        .max(approved.discount)
+        .max(overridden as f32))
+}
+
+/// Consumes the manager overrides required by the discounts and refunds
+/// within a sale, under the tenant's policy. A discount beyond the
+/// employee's ceiling requires one under any policy. When updating, only
+/// amounts beyond those already `approved` require one.
+async fn authorise_sale(
+    products: &OrderList,
+    payment: &[Payment],
+    approved: &SensitiveAmounts,
+    reference: &str,
+    overrides: &OverrideTokens,
+    session: &Session,
+    db: &InternalDb,
+) -> Result<NoteList, Error> {
+    let policy = Tenant::fetch_by_id(&session.tenant_id, &db.0)
+        .await?
+        .settings
+        .overrides;
+
+    let amounts = SensitiveAmounts::of(products, payment);
+    let mut required =
+        policy.protected(&amounts.exceeding(approved, policy.discount_threshold as f32));
+
+    let ceiling = session.discount_ceiling() as f32;
+    if !required.contains(&OverrideAction::Discount)
+        && amounts
+            .exceeding(approved, ceiling)
+            .contains(&OverrideAction::Discount)
+    {
+        required.push(OverrideAction::Discount);
+    }
+
+    ManagerOverride::authorise(
+        &required,
+        Some(reference),
+        amounts.discount,
+        &overrides.0,
+        session,
+        &db.0,
+    )
+    .await
+}
+
 #[openapi(tag = "Transaction")]
 #[get("/<id>")]
 pub async fn get(db: InternalDb, session: Session, id: &str) -> Convert<Transaction> {
@@ -83,6 +168,102 @@ pub async fn deliverables_search(
         .into()
 }
 
+#[openapi(tag = "Transaction")]
+#[get("/picklist/<store_id>")]
+pub async fn pick_list(db: InternalDb, session: Session, store_id: &str) -> Convert<PickList> {
+    check_permissions!(session.clone(), Action::FetchTransaction);
+    Transaction::pick_list(store_id, session, &db.0)
+        .await
+        .into()
+}
+
+#[openapi(tag = "Transaction")]
+#[post("/pick", data = "<input_data>")]
+async fn pick(
+    db: InternalDb,
+    session: Session,
+    input_data: Validated<Json<PickBatch>>,
+) -> Convert<Vec<Transaction>> {
+    check_permissions!(session.clone(), Action::ModifyTransaction);
+    Transaction::pick(input_data.data(), session, &db.0)
+        .await
+        .into()
+}
+
+#[openapi(tag = "Transaction")]
+#[post("/pack/<id>/<order_id>", data = "<input_data>")]
+async fn pack(
+    db: InternalDb,
+    session: Session,
+    input_data: Validated<Json<PackInput>>,
+    id: &str,
+    order_id: &str,
+) -> Convert<Transaction> {
+    check_permissions!(session.clone(), Action::ModifyTransaction);
+    Transaction::pack(id, order_id, input_data.data(), session, &db.0)
+        .await
+        .into()
+}
+
+/// Hands a pickup order over once its collection code is verified at the
+/// kiosk the session was issued at, which must be of the destination store.
+#[openapi(tag = "Transaction")]
+#[post("/collect", data = "<input_data>")]
+async fn collect(
+    db: InternalDb,
+    session: Session,
+    input_data: Validated<Json<CollectInput>>,
+) -> Convert<Transaction> {
+    check_permissions!(session.clone(), Action::ModifyTransaction);
+    Transaction::collect(input_data.data(), session, &db.0)
+        .await
+        .into()
+}
+
+/// Issues a pickup order awaiting collection a new code, replacing its last.
+#[openapi(tag = "Transaction")]
+#[post("/collection/<id>/<order_id>")]
+async fn reissue_collection(
+    db: InternalDb,
+    session: Session,
+    id: &str,
+    order_id: &str,
+) -> Convert<IssuedCollection> {
+    check_permissions!(session.clone(), Action::ModifyTransaction);
+    Transaction::reissue_collection(id, order_id, session, &db.0)
+        .await
+        .into()
+}
+
+#[openapi(tag = "Transaction")]
+#[get("/collections/<store_id>")]
+pub async fn awaiting_collection(
+    db: InternalDb,
+    session: Session,
+    store_id: &str,
+) -> Convert<Vec<AwaitingCollection>> {
+    check_permissions!(session.clone(), Action::FetchTransaction);
+    Transaction::fetch_awaiting_collection(store_id, session, &db.0)
+        .await
+        .into()
+}
+
+/// Re-routes an order which failed at its origin store to the nearest
+/// stores holding its stock.
+#[openapi(tag = "Transaction")]
+#[post("/reroute/<id>/<order_id>")]
+async fn reroute(
+    db: InternalDb,
+    session: Session,
+    id: &str,
+    order_id: &str,
+) -> Convert<Transaction> {
+    check_permissions!(session.clone(), Action::ModifyTransaction);
+    Transaction::reroute(id, order_id, session, &db.0)
+        .await
+        .into()
+}
+
 #[openapi(tag = "Transaction")]
 #[get("/receivables/<store_id>")]
 pub async fn receivables_search(
@@ -101,11 +282,116 @@ pub async fn receivables_search(
 async fn update(
     db: InternalDb,
     session: Session,
+    overrides: OverrideTokens,
     input_data: Validated<Json<TransactionInput>>,
     id: &str,
 ) -> Convert<Transaction> {
     check_permissions!(session.clone(), Action::ModifyTransaction);
-    Transaction::update(input_data.data(), session, id, &db.0)
+
+    let mut transaction = input_data.data();
+
+    let existing = match Transaction::fetch_by_id(id, session.clone(), &db.0).await {
+        Ok(existing) => existing,
+        Err(error) => return Err(error).into(),
+    };
+
+    if existing.void.is_some() {
+        return Err(ErrorResponse::create_error(
+            "A voided transaction cannot be modified.",
+        ))
+        .into();
+    }
+
+    if let Err(error) = transaction.check_orders(&existing) {
+        return Err(error).into();
+    }
+
+    if let Err(error) = SerialNumber::check_update(
+        &existing,
+        &transaction.transaction_type,
+        &transaction.products,
+    ) {
+        return Err(error).into();
+    }
+
+    // A quote stays one until converted, so each revision is priced.
+    if existing.quote.is_some() && !matches!(transaction.transaction_type, TransactionType::Quote) {
+        return Err(ErrorResponse::create_error(
+            "A quote must be converted to be sold.",
+        ))
+        .into();
+    }
+
+    // The update is priced as a sale is when created, discounts approved
+    // before remaining so.
+    let approved = SensitiveAmounts::approved(&existing);
+
+    let priced = match discount_ceiling(&approved, id, &overrides, &session, &db).await {
+        Ok(ceiling) => transaction.price(ceiling, &session, &db.0).await,
+        Err(error) => Err(error),
+    };
+
+    let resolved = match priced {
+        Ok(differences) => {
+            resolve_prices(
+                differences,
+                &mut transaction.order_notes,
+                None,
+                &session,
+                &db,
+            )
+            .await
+        }
+        Err(error) => Err(error),
+    };
+
+    if let Err(error) = resolved {
+        return Err(error).into();
+    }
+
+    // The superseded content of a quote is kept as a revision.
+    if existing.quote.is_some() {
+        let policy = match Tenant::fetch_by_id(&session.tenant_id, &db.0).await {
+            Ok(tenant) => tenant.settings.quotes,
+            Err(error) => return Err(error).into(),
+        };
+
+        if let Err(error) = Transaction::revise_quote(
+            existing.clone(),
+            Duration::days(policy.validity_days),
+            session.clone(),
+            &db.0,
+        )
+        .await
+        {
+            return Err(error).into();
+        }
+    }
+
+    if !matches!(
+        transaction.transaction_type,
+        TransactionType::Saved | TransactionType::Quote
+    ) {
+        // Only discounts or refunds introduced by this update need approval.
+        let notes = match authorise_sale(
+            &transaction.products,
+            &transaction.payment,
+            &approved,
+            id,
+            &overrides,
+            &session,
+            &db,
+        )
+        .await
+        {
+            Ok(notes) => notes,
+            Err(error) => return Err(error).into(),
+        };
+
+        transaction.order_notes.extend(notes);
+    }
+
+    Transaction::update(transaction, session, id, &db.0)
         .await
         .into()
 }
@@ -167,17 +453,75 @@ async fn generate(db: InternalDb, session: Session, customer_id: &str) -> Conver
         .into()
 }
 
-#[openapi(tag = "Transaction")]
-#[post("/", data = "<input_data>")]
-pub async fn create(
-    db: InternalDb,
-    session: Session,
-    input_data: Validated<Json<TransactionInit>>,
-) -> Result<Json<Transaction>, Error> {
-    check_permissions!(session.clone(), Action::CreateTransaction);
+/// Settles the differences between a sale's submitted and catalogue
+/// prices. A sale priced differently is rejected or repriced under
+/// `mismatch`, or the tenant's policy when not given, the repricing being
+/// noted in `notes`. Excessive discounts are rejected regardless.
+async fn resolve_prices(
+    differences: Vec<PriceDiff>,
+    notes: &mut NoteList,
+    mismatch: Option<PriceMismatchAction>,
+    session: &Session,
+    db: &InternalDb,
+) -> Result<(), Error> {
+    if differences.is_empty() {
+        return Ok(());
+    }
+
+    let mismatch = match mismatch {
+        Some(mismatch) => mismatch,
+        None => {
+            Tenant::fetch_by_id(&session.tenant_id, &db.0)
+                .await?
+                .settings
+                .pricing
+                .mismatch
+        }
+    };
 
+    let discounted = differences
+        .iter()
+        .any(|diff| diff.field == PricedField::Discount);
+
+    if discounted || mismatch == PriceMismatchAction::Reject {
+        return Err(ErrorResponse::price_mismatch(differences));
+    }
+
+    notes.push(Note {
+        message: format!(
+            "Repriced from the catalogue: {}",
+            differences
+                .iter()
+                .map(|diff| diff.to_string())
+                .collect::<Vec<String>>()
+                .join("; ")
+        ),
+        author: session.employee.id.clone(),
+        timestamp: Utc::now(),
+    });
+
+    Ok(())
+}
+
+/// Inserts the priced transaction as `id`. Unless saved or quoted, it must
+/// be paid in full and authorised by overrides bound to `reference`, after
+/// which its stock is moved.
+async fn record_sale(
+    mut new_transaction: TransactionInit,
+    id: Id,
+    reference: &str,
+    overrides: &OverrideTokens,
+    session: &Session,
+    db: &InternalDb,
+) -> Result<Json<Transaction>, Error> {
     let mut quantity_alteration_intents: Vec<QuantityAlterationIntent> = vec![];
-    let new_transaction = input_data.data();
+
+    // The lots drawn are recorded once the stock is moved.
+    new_transaction
+        .products
+        .iter_mut()
+        .flat_map(|order| order.products.iter_mut())
+        .for_each(|product| product.lots_drawn.clear());
 
     // Make and modify the required changes to stock levels
     new_transaction.products.iter().for_each(|order| {
@@ -189,6 +533,8 @@ pub async fn create(
                 transaction_store_id: order.clone().origin.store_id,
                 transaction_type: new_transaction.clone().transaction_type,
                 quantity_to_transact: product.clone().quantity,
+                lot: product.clone().lot,
+                purchase_id: Some(product.id.clone()),
             });
         });
     });
@@ -219,12 +565,12 @@ pub async fn create(
         })
         .sum::<f32>();
 
-    println!("Paid: {}. Cost: {}", total_paid, total_cost);
+    debug!(total_paid, total_cost, "computed transaction totals");
 
     let insertion = match new_transaction.transaction_type {
-        TransactionType::Saved => {
+        TransactionType::Saved | TransactionType::Quote => {
             // We do not need to process intents. Simply save.
-            Transaction::insert(new_transaction, session.clone(), &db.0).await?
+            Transaction::insert_as(new_transaction, id, session.clone(), &db.0).await?
         }
         _ => {
             // As we are removing inventory via a purchase,
@@ -236,15 +582,54 @@ pub async fn create(
                 ));
             }
 
-            let data = Transaction::insert(new_transaction, session.clone(), &db.0).await?;
-            Transaction::process_intents(session.clone(), &db.0, quantity_alteration_intents).await;
+            let notes = authorise_sale(
+                &new_transaction.products,
+                &new_transaction.payment,
+                &SensitiveAmounts::default(),
+                reference,
+                overrides,
+                session,
+                db,
+            )
+            .await?;
+            new_transaction.order_notes.extend(notes);
+
+            SerialNumber::check_movements(
+                &new_transaction.transaction_type,
+                &new_transaction.products,
+                session.clone(),
+                &db.0,
+            )
+            .await?;
+
+            let transaction_type = new_transaction.transaction_type.clone();
+            let orders = new_transaction.products.clone();
+
+            // The sale is saved with its serials' movement, so a serial sold
+            // at once by another fails the sale, rather than selling twice.
+            let txn = db.0.begin().await?;
+            let data = Transaction::insert_as(new_transaction, id, session.clone(), &txn).await?;
+            SerialNumber::record_movements(
+                &data.last_insert_id,
+                &transaction_type,
+                &orders,
+                session.clone(),
+                &txn,
+            )
+            .await?;
+            txn.commit().await?;
+
+            let drawn =
+                Transaction::process_intents(session.clone(), &db.0, quantity_alteration_intents)
+                    .await;
+            Transaction::record_lots_drawn(&data.last_insert_id, drawn, session, &db.0).await?;
 
             data
         }
     };
 
     let converted: Convert<Transaction> =
-        Transaction::fetch_by_id(&insertion.last_insert_id, session, &db.0)
+        Transaction::fetch_by_id(&insertion.last_insert_id, session.clone(), &db.0)
             .await
             .into();
 
@@ -252,11 +637,158 @@ pub async fn create(
 }
 
 #[openapi(tag = "Transaction")]
-#[post("/delete/<id>")]
-// #[guard(Action::DeleteTransaction)]
-async fn delete(db: InternalDb, session: Session, id: &str) -> Convert<()> {
+#[post("/", data = "<input_data>")]
+pub async fn create(
+    db: InternalDb,
+    session: Session,
+    overrides: OverrideTokens,
+    input_data: Validated<Json<TransactionInit>>,
+) -> Result<Json<Transaction>, Error> {
+    check_permissions!(session.clone(), Action::CreateTransaction);
+
+    let mut new_transaction = input_data.data();
+
+    // Overrides for the sale are bound to its reference, so without one
+    // none may be used.
+    let reference = new_transaction
+        .reference
+        .clone()
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let ceiling = discount_ceiling(
+        &SensitiveAmounts::default(),
+        &reference,
+        &overrides,
+        &session,
+        &db,
+    )
+    .await?;
+    let differences = new_transaction.price(ceiling, &session, &db.0).await?;
+    resolve_prices(
+        differences,
+        &mut new_transaction.order_notes,
+        None,
+        &session,
+        &db,
+    )
+    .await?;
+
+    if let TransactionType::Quote = new_transaction.transaction_type {
+        let policy = Tenant::fetch_by_id(&session.tenant_id, &db.0)
+            .await?
+            .settings
+            .quotes;
+
+        new_transaction.quote = Some(QuoteDetails::new(
+            Transaction::next_quote_number(&session, &db.0).await?,
+            Duration::days(policy.validity_days),
+        ));
+    }
+
+    record_sale(
+        new_transaction,
+        Uuid::new_v4().to_string(),
+        &reference,
+        &overrides,
+        &session,
+        &db,
+    )
+    .await
+}
+
+#[openapi(tag = "Transaction")]
+#[get("/quotes/<customer_id>")]
+async fn get_quotes(
+    db: InternalDb,
+    session: Session,
+    customer_id: &str,
+) -> Convert<Vec<Transaction>> {
+    check_permissions!(session.clone(), Action::FetchTransaction);
+    Transaction::fetch_quotes_by_customer(customer_id, session, &db.0)
+        .await
+        .into()
+}
+
+#[openapi(tag = "Transaction")]
+#[post("/quote/<id>/convert", data = "<input_data>")]
+async fn convert_quote(
+    db: InternalDb,
+    session: Session,
+    overrides: OverrideTokens,
+    input_data: Validated<Json<QuoteConversionInput>>,
+    id: &str,
+) -> Result<Json<Transaction>, Error> {
+    check_permissions!(session.clone(), Action::CreateTransaction);
+
+    let quote = Transaction::fetch_by_id(id, session.clone(), &db.0).await?;
+    let mut sale = quote.accept_quote(input_data.data(), session.employee.id.clone())?;
+
+    let policy = Tenant::fetch_by_id(&session.tenant_id, &db.0)
+        .await?
+        .settings
+        .quotes;
+
+    // Overrides for the sale are bound to the quote it is converted from.
+    if policy.conversion == QuoteConversion::Reprice {
+        let ceiling =
+            discount_ceiling(&SensitiveAmounts::default(), id, &overrides, &session, &db).await?;
+        let differences = sale.price(ceiling, &session, &db.0).await?;
+        resolve_prices(
+            differences,
+            &mut sale.order_notes,
+            Some(PriceMismatchAction::Flag),
+            &session,
+            &db,
+        )
+        .await?;
+    }
+
+    // The quote is claimed first, so a repeated conversion makes no sale.
+    let sale_id = Uuid::new_v4().to_string();
+    let claimed_at = Transaction::claim_conversion(&quote, &sale_id, &session, &db.0).await?;
+
+    match record_sale(sale, sale_id.clone(), id, &overrides, &session, &db).await {
+        Ok(created) => Ok(created),
+        Err(error) => {
+            Transaction::release_conversion(&quote, &sale_id, claimed_at, &session, &db.0).await?;
+            Err(error)
+        }
+    }
+}
+
+#[openapi(tag = "Transaction")]
+#[post("/void/<id>", data = "<input_data>")]
+async fn void(
+    db: InternalDb,
+    session: Session,
+    overrides: OverrideTokens,
+    input_data: Validated<Json<TransactionVoidInput>>,
+    id: &str,
+) -> Result<Json<Transaction>, Error> {
     check_permissions!(session.clone(), Action::DeleteTransaction);
 
-    let voided: VoidableResult<DeleteResult> = Transaction::delete(id, session, &db.0).await.into();
-    voided.void().into()
+    let policy = Tenant::fetch_by_id(&session.tenant_id, &db.0)
+        .await?
+        .settings
+        .overrides;
+
+    let notes = ManagerOverride::authorise(
+        &policy.protected(&[OverrideAction::Void]),
+        Some(id),
+        0.0,
+        &overrides.0,
+        &session,
+        &db.0,
+    )
+    .await?;
+
+    // The override's notes are saved with the void, and only if it is.
+    let (voided, intents) =
+        Transaction::void(id, input_data.data().reason, notes, session.clone(), &db.0).await?;
+
+    // Return the stock and serials the transaction moved.
+    Transaction::process_intents(session.clone(), &db.0, intents).await;
+    SerialNumber::revert_movements(&voided, session, &db.0).await?;
+
+    Ok(Json(voided))
 }
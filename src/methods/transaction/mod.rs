@@ -1,9 +1,15 @@
+mod collection;
 mod conversions;
 mod example;
+mod fulfilment;
 #[cfg(feature = "process")]
 pub(crate) mod handlers;
+#[cfg(feature = "process")]
+mod routing;
 mod structs;
 
 #[cfg(feature = "process")]
 pub use handlers::*;
+pub use collection::*;
+pub use fulfilment::*;
 pub use structs::*;
@@ -0,0 +1,235 @@
+//! Re-routing of orders which failed at the store they were to be
+//! fulfilled from. The store nearest the order's destination holding
+//! sufficient sellable stock for every line takes the order over. Should
+//! no store hold it all, the order is split line by line between the
+//! nearest stores which hold each.
+
+use std::collections::HashMap;
+
+use chrono::Utc;
+use geo::{point, VincentyDistance};
+use sea_orm::DbConn;
+use uuid::Uuid;
+
+use crate::methods::{
+    find_order, DiscountValue, Error, ErrorResponse, History, Location, OrderStatus, OrderType,
+    Product, ProductPurchase, QuantityAlterationIntent, Session, Store,
+};
+use crate::{PickStatus, Transaction};
+
+impl Transaction {
+    /// Moves a failed order to the stores routed to, recording the store it
+    /// failed at. Returns the intents allocating its stock at those stores,
+    /// to be processed once the transaction is saved.
+    pub async fn route_order(
+        &mut self,
+        order_id: &str,
+        session: &Session,
+        db: &DbConn,
+    ) -> Result<Vec<QuantityAlterationIntent>, Error> {
+        let order = find_order(self, order_id)?.clone();
+
+        if !matches!(order.order_type, OrderType::Shipment | OrderType::Pickup) {
+            return Err(ErrorResponse::create_error(
+                "Only orders for shipment or pickup may be re-routed.",
+            ));
+        }
+
+        let reason = match &order.status.status {
+            OrderStatus::Failed(reason) if order.stock_released => reason.clone(),
+            _ => {
+                return Err(ErrorResponse::create_error(
+                    "Only orders which failed at their origin store may be re-routed.",
+                ))
+            }
+        };
+
+        let stores = Store::fetch_all(session.clone(), db).await?;
+        let origin = stores
+            .iter()
+            .find(|store| store.code == order.origin.store_code)
+            .cloned()
+            .ok_or_else(|| {
+                ErrorResponse::create_error(&format!(
+                    "Unknown store `{}`.",
+                    order.origin.store_code
+                ))
+            })?;
+
+        let attempted = order
+            .previous_failed_fulfillment_attempts
+            .iter()
+            .map(|attempt| attempt.item.code.clone())
+            .chain([origin.code.clone()])
+            .collect::<Vec<String>>();
+
+        let address = &order.destination.contact.address;
+        let destination = point!(x: address.lat, y: address.lon);
+
+        let mut candidates = stores
+            .into_iter()
+            .filter(|store| !attempted.contains(&store.code))
+            .map(|store| {
+                let location = point!(x: store.contact.address.lat, y: store.contact.address.lon);
+                let distance = location
+                    .vincenty_distance(&destination)
+                    .unwrap_or(12756000.01);
+
+                (distance, store)
+            })
+            .collect::<Vec<(f64, Store)>>();
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut catalogue: HashMap<String, Product> = HashMap::new();
+        for purchase in order.products.iter() {
+            if !catalogue.contains_key(&purchase.product_sku) {
+                let product =
+                    Product::fetch_by_id(&purchase.product_sku, session.clone(), db).await?;
+                catalogue.insert(purchase.product_sku.clone(), product);
+            }
+        }
+
+        let stocks = |purchase: &ProductPurchase, store: &Store| {
+            catalogue
+                .get(&purchase.product_sku)
+                .and_then(|product| {
+                    product
+                        .variants
+                        .iter()
+                        .find(|variant| variant.barcode == purchase.product_code)
+                })
+                .and_then(|variant| {
+                    variant
+                        .stock
+                        .iter()
+                        .find(|stock| stock.store.store_code == store.code)
+                })
+                .is_some_and(|stock| stock.quantity.quantity_sellable >= purchase.quantity)
+        };
+
+        let routes = match candidates.iter().find(|(_, store)| {
+            order
+                .products
+                .iter()
+                .all(|purchase| stocks(purchase, store))
+        }) {
+            Some((_, store)) => vec![(store.clone(), order.products.clone())],
+            None => {
+                let mut routes: Vec<(Store, Vec<ProductPurchase>)> = vec![];
+
+                for purchase in order.products.iter() {
+                    let (_, store) = candidates
+                        .iter()
+                        .find(|(_, store)| stocks(purchase, store))
+                        .ok_or_else(|| {
+                            ErrorResponse::create_error(&format!(
+                                "No store holds sufficient stock of `{}` to fulfil the order.",
+                                purchase.product_code
+                            ))
+                        })?;
+
+                    match routes
+                        .iter_mut()
+                        .find(|(routed, _)| routed.code == store.code)
+                    {
+                        Some((_, purchases)) => purchases.push(purchase.clone()),
+                        None => routes.push((store.clone(), vec![purchase.clone()])),
+                    }
+                }
+
+                routes
+            }
+        };
+
+        let mut intents = vec![];
+        let mut routed = vec![];
+
+        for (index, (store, purchases)) in routes.into_iter().enumerate() {
+            let mut split = order.clone();
+
+            // Orders split off are told apart by their reference, an absolute
+            // discount stays with the first so it is not given twice.
+            if index > 0 {
+                split.id = Uuid::new_v4().to_string();
+                split.reference = format!("{}-{}", order.reference, index + 1);
+
+                if let DiscountValue::Absolute(_) = split.discount {
+                    split.discount = DiscountValue::Absolute(0);
+                }
+            }
+
+            split.origin = Location {
+                store_code: store.code.clone(),
+                store_id: store.id.clone(),
+                contact: store.contact.clone(),
+            };
+            split.products = purchases;
+            // The lots drawn at the origin were restored as the order failed.
+            split
+                .products
+                .iter_mut()
+                .for_each(|purchase| purchase.lots_drawn.clear());
+            split.packages = vec![];
+            split.stock_released = false;
+            split.previous_failed_fulfillment_attempts.push(History {
+                item: origin.clone(),
+                reason: reason.clone(),
+                timestamp: Utc::now(),
+            });
+
+            for instance in split
+                .products
+                .iter_mut()
+                .flat_map(|purchase| purchase.instances.iter_mut())
+            {
+                if !matches!(instance.fulfillment_status.pick_status, PickStatus::Pending) {
+                    instance
+                        .fulfillment_status
+                        .transition(PickStatus::Pending, "Re-routed");
+                }
+            }
+
+            split.assign_status(
+                OrderStatus::Queued(Utc::now()),
+                &format!("Re-routed from store {}", origin.code),
+            );
+
+            intents.extend(self.allocating_intents(&split));
+            routed.push(split);
+        }
+
+        let position = self
+            .products
+            .iter()
+            .position(|existing| existing.id == order.id)
+            .unwrap();
+        self.products.splice(position..=position, routed);
+
+        Ok(intents)
+    }
+
+    /// Re-routes a failed order, such as once stock has arrived where
+    /// there was none when it failed.
+    pub async fn reroute(
+        id: &str,
+        order_id: &str,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<Transaction, Error> {
+        let mut transaction = Self::fetch_by_id(id, session.clone(), db).await?;
+
+        if transaction.void.is_some() {
+            return Err(ErrorResponse::create_error(
+                "A voided transaction cannot be re-routed.",
+            ));
+        }
+
+        let intents = transaction.route_order(order_id, &session, db).await?;
+
+        let transaction = Self::update_value(transaction, session.clone(), id, db).await?;
+        let drawn = Self::process_intents(session.clone(), db, intents).await;
+        Self::record_lots_drawn(id, drawn, &session, db).await?;
+
+        Ok(transaction)
+    }
+}
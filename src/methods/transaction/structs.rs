@@ -1,7 +1,9 @@
 use core::fmt;
+#[cfg(feature = "process")]
+use std::collections::HashMap;
 use std::fmt::Display;
 
-use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
 use schemars::JsonSchema;
 #[cfg(feature = "process")]
 use sea_orm::{
@@ -10,7 +12,8 @@ use sea_orm::{
 };
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "process")]
-use tokio::task::JoinError;
+use serde_json::json;
+use tracing::{debug, info};
 use uuid::Uuid;
 
 #[cfg(feature = "process")]
@@ -18,13 +21,17 @@ use sea_orm::FromQueryResult;
 
 #[cfg(feature = "process")]
 use crate::entities::{
-    prelude::Transactions, sea_orm_active_enums::TransactionType as SeaORMTType, transactions,
+    prelude::{Products, Tenants, Transactions},
+    products,
+    sea_orm_active_enums::TransactionType as SeaORMTType,
+    tenants, transactions,
 };
 use crate::transaction::example::example_transaction;
 use crate::{
     methods::{
-        Error, History, Id, NoteList, Order, OrderList, OrderStatus, OrderStatusAssignment,
-        Payment, Product, Session, Stock, VariantInformation,
+        apply_discount, find_order, Collection, Error, ErrorResponse, Id, IssuedCollection,
+        LotDetails, LotDraw, Note, NoteList, Order, OrderList, OrderStatus, OverrideAction,
+        Payment, PaymentStatus, Price, Processable, Product, Session, Stock,
     },
     PickStatus, ProductInstance,
 };
@@ -56,6 +63,48 @@ pub struct QuantityAlterationIntent {
     pub transaction_store_id: String,
     pub transaction_type: TransactionType,
     pub quantity_to_transact: f32,
+    /// The lot moved, otherwise stock is taken from the lots first to
+    /// expire.
+    #[serde(default)]
+    pub lot: Option<LotDetails>,
+    /// The purchase the stock is moved for, which records the lots drawn.
+    #[serde(default)]
+    pub purchase_id: Option<Id>,
+}
+
+/// Applies the intent to the stock, returning the lots it drew from.
+#[cfg(feature = "process")]
+fn apply_intent(
+    stock: &mut Stock,
+    intent: &QuantityAlterationIntent,
+    now: DateTime<Utc>,
+) -> Vec<LotDraw> {
+    let sellable = match intent.transaction_type {
+        TransactionType::In => intent.quantity_to_transact,
+        TransactionType::Out => -intent.quantity_to_transact,
+        TransactionType::PendingIn => {
+            stock.quantity.quantity_on_order += intent.quantity_to_transact;
+            return vec![];
+        }
+        TransactionType::PendingOut => {
+            stock.quantity.quantity_allocated += intent.quantity_to_transact;
+            return vec![];
+        }
+        // A saved transaction should not be processed, but should be shifted into a specified IN or OUT variant.
+        // As this should never happen, the modified changes are left alone.
+        TransactionType::Saved | TransactionType::Quote => return vec![],
+    };
+
+    stock.quantity.quantity_sellable += sellable;
+
+    match &intent.lot {
+        Some(lot) => {
+            stock.transact_lot(lot, sellable);
+            vec![]
+        }
+        None if sellable < 0.0 => stock.take_first_expiring(-sellable, now),
+        None => vec![],
+    }
 }
 
 #[cfg(feature = "types")]
@@ -101,9 +150,110 @@ pub struct Transaction {
 
     pub salesperson: Id,
     pub kiosk: Id,
+    /// Set once the transaction is voided, after which it may no longer be
+    /// modified.
+    #[serde(default)]
+    pub void: Option<TransactionVoid>,
+    /// Held by quotes, tracking their number, expiry and revisions.
+    #[serde(default)]
+    pub quote: Option<QuoteDetails>,
 
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+
+    /// The collection codes issued as the request readied pickup orders in
+    /// store. Codes are never saved, so are returned only by that request.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub issued_collections: Vec<IssuedCollection>,
+}
+
+/// **TransactionVoid** <br />
+/// Records the voiding of a transaction. The stock it moved is returned,
+/// and each of its payments either cancelled or, if already taken, owed
+/// back to the customer.
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct TransactionVoid {
+    pub reason: String,
+    pub employee: Id,
+    pub timestamp: DateTime<Utc>,
+    pub payments: Vec<PaymentReversal>,
+}
+
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct PaymentReversal {
+    pub payment_id: Id,
+    /// The amount to be returned, the opposite of that paid.
+    pub amount: Price,
+    pub outcome: ReversalOutcome,
+}
+
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub enum ReversalOutcome {
+    /// The payment was not yet complete, and has been failed.
+    Cancelled,
+    /// The payment was complete, and must be refunded by the till.
+    RefundRequired,
+}
+
+/// **QuoteDetails** <br />
+/// A quote's number is unique within the tenant, and kept across its
+/// revisions. Each revision supersedes the products and total quoted,
+/// and extends the quote's expiry.
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct QuoteDetails {
+    pub number: String,
+    pub revision: u32,
+    pub expires_at: DateTime<Utc>,
+    /// The superseded revisions, oldest first.
+    pub revisions: Vec<QuoteRevision>,
+    /// The sale the quote was converted to, once accepted.
+    pub converted_to: Option<Id>,
+}
+
+#[cfg(feature = "types")]
+impl QuoteDetails {
+    pub fn new(number: String, validity: Duration) -> Self {
+        QuoteDetails {
+            number,
+            revision: 1,
+            expires_at: Utc::now() + validity,
+            revisions: vec![],
+            converted_to: None,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at < Utc::now()
+    }
+}
+
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct QuoteRevision {
+    pub revision: u32,
+    pub products: OrderList,
+    pub order_total: i64,
+    pub revised_by: Id,
+    pub revised_at: DateTime<Utc>,
+}
+
+/// Accepts a quote, paying for it at the given kiosk.
+#[cfg(feature = "types")]
+#[derive(Deserialize, Clone, JsonSchema, Validate)]
+pub struct QuoteConversionInput {
+    pub payment: Vec<Payment>,
+    pub kiosk: Id,
+}
+
+#[cfg(feature = "types")]
+#[derive(Deserialize, Clone, JsonSchema, Validate)]
+pub struct TransactionVoidInput {
+    #[validate(length(min = 1))]
+    pub reason: String,
 }
 
 #[cfg(feature = "process")]
@@ -157,6 +307,14 @@ pub struct TransactionInit {
     pub order_notes: NoteList,
 
     pub kiosk: Id,
+    /// Assigned by the server when a quote is created.
+    #[serde(skip)]
+    pub quote: Option<QuoteDetails>,
+    /// Names the sale for the overrides approving it, which are bound to
+    /// it when issued, i.e. an id generated by the till.
+    #[serde(default)]
+    #[validate(length(min = 1, max = 64))]
+    pub reference: Option<String>,
 }
 
 #[cfg(feature = "types")]
@@ -168,6 +326,332 @@ pub struct ProductStatusUpdate {
     pub new_status: PickStatus,
 }
 
+/// The part of a sale's pricing which differs from the catalogue.
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub enum PricedField {
+    /// A product's unit price, expected to be its variant's retail price.
+    ProductCost,
+    /// A product's discount combined with its share of the order's, as a
+    /// percentage, which exceeds the employee's ceiling (given as the
+    /// expected value).
+    Discount,
+    /// The transaction's total, in whole units of the currency.
+    OrderTotal,
+}
+
+#[cfg(feature = "types")]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct PriceDiff {
+    pub order_id: Id,
+    /// The product purchase priced, absent for order-wide differences.
+    pub purchase_id: Option<Id>,
+    pub field: PricedField,
+    pub submitted: f32,
+    pub expected: f32,
+}
+
+#[cfg(feature = "types")]
+impl Display for PriceDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} of {} was {}, expected {}",
+            self.field,
+            self.purchase_id.as_deref().unwrap_or(&self.order_id),
+            self.submitted,
+            self.expected
+        )
+    }
+}
+
+/// The discount given on each product of the order, as a percentage of its
+/// price, combining the product's own discount with its share of the
+/// order's discount.
+#[cfg(feature = "types")]
+pub fn combined_discounts(order: &Order) -> Vec<f32> {
+    let discounted = order
+        .products
+        .iter()
+        .map(|product| {
+            apply_discount(
+                product.discount.clone(),
+                product.product_cost * product.quantity,
+            )
+        })
+        .collect::<Vec<f32>>();
+
+    let subtotal = discounted.iter().sum::<f32>();
+    let share = match subtotal > 0.0 {
+        true => apply_discount(order.discount.clone(), subtotal) / subtotal,
+        false => 0.0,
+    };
+
+    order
+        .products
+        .iter()
+        .zip(discounted)
+        .map(|(product, discounted)| {
+            let price = product.product_cost * product.quantity;
+
+            match price > 0.0 {
+                true => (1.0 - (discounted * share) / price) * 100.0,
+                false => 0.0,
+            }
+        })
+        .collect()
+}
+
+/// The amounts within a sale which a manager override may be required
+/// for: its greatest discount, as a percentage of the price it applies to,
+/// and the total paid out to the customer.
+#[cfg(feature = "types")]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SensitiveAmounts {
+    pub discount: f32,
+    pub refund: f32,
+}
+
+#[cfg(feature = "types")]
+impl SensitiveAmounts {
+    pub fn of(products: &OrderList, payment: &[Payment]) -> Self {
+        let discount = products
+            .iter()
+            .flat_map(combined_discounts)
+            .fold(0.0, f32::max);
+
+        let refund = payment
+            .iter()
+            .filter(|payment| payment.amount.quantity < 0.0)
+            .map(|payment| -payment.amount.quantity)
+            .sum::<f32>();
+
+        SensitiveAmounts { discount, refund }
+    }
+
+    /// The amounts the transaction was completed with, which have been
+    /// approved. Saved transactions and quotes are never authorised, so
+    /// nothing they hold has been.
+    pub fn approved(transaction: &Transaction) -> Self {
+        match transaction.transaction_type {
+            TransactionType::Saved | TransactionType::Quote => SensitiveAmounts::default(),
+            _ => SensitiveAmounts::of(&transaction.products, &transaction.payment),
+        }
+    }
+
+    /// The till actions requiring an override: any discount above
+    /// `discount_threshold` percent, and any refund, which exceed the
+    /// amounts already `approved`.
+    pub fn exceeding(
+        &self,
+        approved: &SensitiveAmounts,
+        discount_threshold: f32,
+    ) -> Vec<OverrideAction> {
+        let discounted = self.discount > discount_threshold && self.discount > approved.discount;
+        let refunded = self.refund > 0.0 && self.refund > approved.refund;
+
+        [
+            (discounted, OverrideAction::Discount),
+            (refunded, OverrideAction::Refund),
+        ]
+        .into_iter()
+        .filter_map(|(required, action)| required.then_some(action))
+        .collect()
+    }
+}
+
+/// Unit prices may differ from the catalogue by rounding alone.
+#[cfg(feature = "methods")]
+const PRICE_TOLERANCE: f32 = 0.005;
+
+/// Reprices each product sold from its variant's current retail price,
+/// recomputing the order total to match, and returns the differences from
+/// the prices submitted. Discounts above `ceiling`, combining each
+/// product's discount with its order's, are returned as differences too.
+/// Purchases are priced as submitted.
+#[cfg(feature = "methods")]
+async fn price_orders(
+    transaction_type: &TransactionType,
+    products: &mut OrderList,
+    order_total: &mut i64,
+    ceiling: f32,
+    session: &Session,
+    db: &DbConn,
+) -> Result<Vec<PriceDiff>, Error> {
+    if matches!(
+        transaction_type,
+        TransactionType::In | TransactionType::PendingIn
+    ) {
+        return Ok(vec![]);
+    }
+
+    let mut catalogue: HashMap<String, Product> = HashMap::new();
+    let mut differences = vec![];
+    let mut total = 0.0;
+
+    for order in products.iter_mut() {
+        let mut subtotal = 0.0;
+
+        for purchase in order.products.iter_mut() {
+            if !catalogue.contains_key(&purchase.product_sku) {
+                let product = Products::find_by_id(purchase.product_sku.clone())
+                    .filter(products::Column::TenantId.eq(session.tenant_id.clone()))
+                    .one(db)
+                    .await?
+                    .ok_or_else(|| {
+                        ErrorResponse::create_error(&format!(
+                            "Unknown product `{}`.",
+                            purchase.product_sku
+                        ))
+                    })?;
+                catalogue.insert(purchase.product_sku.clone(), product.into());
+            }
+
+            let variant = catalogue[&purchase.product_sku]
+                .variants
+                .iter()
+                .find(|variant| variant.barcode == purchase.product_code)
+                .ok_or_else(|| {
+                    ErrorResponse::create_error(&format!(
+                        "Unknown variant `{}` of product `{}`.",
+                        purchase.product_code, purchase.product_sku
+                    ))
+                })?;
+
+            if (purchase.product_cost - variant.retail_price).abs() > PRICE_TOLERANCE {
+                differences.push(PriceDiff {
+                    order_id: order.id.clone(),
+                    purchase_id: Some(purchase.id.clone()),
+                    field: PricedField::ProductCost,
+                    submitted: purchase.product_cost,
+                    expected: variant.retail_price,
+                });
+                purchase.product_cost = variant.retail_price;
+            }
+
+            subtotal += apply_discount(
+                purchase.discount.clone(),
+                purchase.product_cost * purchase.quantity,
+            );
+        }
+
+        for (purchase, discount) in order.products.iter().zip(combined_discounts(order)) {
+            if discount > ceiling {
+                differences.push(PriceDiff {
+                    order_id: order.id.clone(),
+                    purchase_id: Some(purchase.id.clone()),
+                    field: PricedField::Discount,
+                    submitted: discount,
+                    expected: ceiling,
+                });
+            }
+        }
+
+        total += apply_discount(order.discount.clone(), subtotal);
+    }
+
+    let priced_total = total.round() as i64;
+    if *order_total != priced_total {
+        differences.push(PriceDiff {
+            order_id: products
+                .first()
+                .map(|order| order.id.clone())
+                .unwrap_or_default(),
+            purchase_id: None,
+            field: PricedField::OrderTotal,
+            submitted: *order_total as f32,
+            expected: priced_total as f32,
+        });
+        *order_total = priced_total;
+    }
+
+    Ok(differences)
+}
+
+#[cfg(feature = "methods")]
+impl TransactionInit {
+    /// Prices the sale from the catalogue, see [`price_orders`].
+    pub async fn price(
+        &mut self,
+        ceiling: f32,
+        session: &Session,
+        db: &DbConn,
+    ) -> Result<Vec<PriceDiff>, Error> {
+        price_orders(
+            &self.transaction_type,
+            &mut self.products,
+            &mut self.order_total,
+            ceiling,
+            session,
+            db,
+        )
+        .await
+    }
+}
+
+#[cfg(feature = "methods")]
+impl TransactionInput {
+    /// Orders are moved only by transitioning them, and collected only with
+    /// their code, so an update keeps the status, released stock and
+    /// collection of each saved order, and the lots drawn for its purchases.
+    /// Orders it adds have none of these.
+    pub fn check_orders(&self, existing: &Transaction) -> Result<(), Error> {
+        for order in &self.products {
+            let saved = existing.products.iter().find(|saved| saved.id == order.id);
+
+            if saved.is_some_and(|saved| saved.status.status != order.status.status) {
+                return Err(ErrorResponse::create_error(
+                    "An order's status cannot be changed by an update.",
+                ));
+            }
+
+            if saved.is_some_and(|saved| saved.stock_released) != order.stock_released {
+                return Err(ErrorResponse::create_error(
+                    "An order's released stock cannot be changed by an update.",
+                ));
+            }
+
+            if saved.and_then(|saved| saved.collection.as_ref()) != order.collection.as_ref() {
+                return Err(ErrorResponse::create_error(
+                    "An order's collection cannot be changed by an update.",
+                ));
+            }
+
+            for purchase in &order.products {
+                let drawn = saved
+                    .and_then(|saved| saved.products.iter().find(|saved| saved.id == purchase.id))
+                    .map_or(&[][..], |saved| &saved.lots_drawn[..]);
+
+                if drawn != &purchase.lots_drawn[..] {
+                    return Err(ErrorResponse::create_error(
+                        "The lots drawn for a purchase cannot be changed by an update.",
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Prices the updated sale from the catalogue, see [`price_orders`].
+    pub async fn price(
+        &mut self,
+        ceiling: f32,
+        session: &Session,
+        db: &DbConn,
+    ) -> Result<Vec<PriceDiff>, Error> {
+        price_orders(
+            &self.transaction_type,
+            &mut self.products,
+            &mut self.order_total,
+            ceiling,
+            session,
+            db,
+        )
+        .await
+    }
+}
+
 #[cfg(feature = "methods")]
 impl Transaction {
     pub async fn insert(
@@ -175,8 +659,16 @@ impl Transaction {
         session: Session,
         db: &DbConn,
     ) -> Result<InsertResult<transactions::ActiveModel>, Error> {
-        let id = Uuid::new_v4().to_string();
+        Self::insert_as(tsn, Uuid::new_v4().to_string(), session, db).await
+    }
 
+    /// Inserts the transaction under an id chosen beforehand.
+    pub async fn insert_as<C: ConnectionTrait>(
+        tsn: TransactionInit,
+        id: Id,
+        session: Session,
+        db: &C,
+    ) -> Result<InsertResult<transactions::ActiveModel>, Error> {
         match Transactions::insert(tsn.into_active(id, session))
             .exec(db)
             .await
@@ -205,11 +697,23 @@ impl Transaction {
         session: Session,
         db: &DbConn,
     ) -> Result<Vec<Order>, Error> {
+        let jobs = Self::fetch_deliverable_orders(query, session, db).await?;
+
+        Ok(jobs.into_iter().map(|(_, order)| order).collect())
+    }
+
+    /// The orders to be dispatched from the store, each alongside the id of
+    /// the transaction it belongs to.
+    pub async fn fetch_deliverable_orders(
+        query: &str,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<Vec<(Id, Order)>, Error> {
         let as_str: Vec<DerivableTransaction> =
             DerivableTransaction::find_by_statement(Statement::from_sql_and_values(
                 DbBackend::MySql,
                 &format!(
-                    "SELECT * FROM Transactions WHERE Transactions.products LIKE '%{}%' AND Transactions.tenant_id = '{}'",
+                    "SELECT * FROM Transactions WHERE Transactions.products LIKE '%{}%' AND Transactions.tenant_id = '{}' AND Transactions.void IS NULL",
                     query, session.tenant_id
                 ),
                 vec![],
@@ -219,16 +723,20 @@ impl Transaction {
 
         let mapped = as_str
             .iter()
+            .filter(|t| !matches!(t.transaction_type, SeaORMTType::Saved | SeaORMTType::Quote))
             .flat_map(|t| {
                 // Conditions are:
                 // 1. Must be distributed from the query location
                 // 2. Must be an actively queued job
+                // 3. Must be sold, rather than saved or quoted (as above)
                 let products = serde_json::from_value::<OrderList>(t.products.clone()).unwrap();
                 let orders = products
-                    .iter()
+                    .into_iter()
                     .filter(|o| o.origin.store_id == query && o.status.status.is_queued());
 
-                orders.cloned().collect::<Vec<Order>>()
+                orders
+                    .map(|o| (t.id.clone(), o))
+                    .collect::<Vec<(Id, Order)>>()
             })
             .collect();
 
@@ -244,7 +752,7 @@ impl Transaction {
             DerivableTransaction::find_by_statement(Statement::from_sql_and_values(
                 DbBackend::MySql,
                 &format!(
-                    "SELECT * FROM Transactions WHERE Transactions.products LIKE '%{}%' AND Transactions.tenant_id = '{}'",
+                    "SELECT * FROM Transactions WHERE Transactions.products LIKE '%{}%' AND Transactions.tenant_id = '{}' AND Transactions.void IS NULL",
                     query, session.tenant_id
                 ),
                 vec![],
@@ -290,12 +798,13 @@ impl Transaction {
     pub async fn fetch_all_saved(session: Session, db: &DbConn) -> Result<Vec<Transaction>, Error> {
         let res = Transactions::find()
             .filter(transactions::Column::TenantId.eq(session.tenant_id))
-            .having(
+            .filter(
                 Expr::expr(Func::lower(Expr::col(
                     transactions::Column::TransactionType,
                 )))
                 .like("%saved%".to_string()),
             )
+            .filter(transactions::Column::Void.is_null())
             .limit(25)
             .all(db)
             .await?;
@@ -312,11 +821,11 @@ impl Transaction {
     ) -> Result<Vec<Transaction>, Error> {
         let res = Transactions::find()
             .filter(transactions::Column::TenantId.eq(session.tenant_id))
-            .having(
+            .filter(
                 Expr::expr(Func::lower(Expr::col(transactions::Column::Products)))
                     .like(format!("%{}%", reference.to_lowercase())),
             )
-            .having(transactions::Column::TransactionType.not_like("Saved"))
+            .filter(transactions::Column::TransactionType.not_like("Saved"))
             .limit(25)
             .all(db)
             .await?;
@@ -333,7 +842,7 @@ impl Transaction {
     ) -> Result<Vec<Transaction>, Error> {
         let tsn = Transactions::find()
             .filter(transactions::Column::TenantId.eq(session.tenant_id))
-            .having(transactions::Column::Customer.contains(id))
+            .filter(transactions::Column::Customer.contains(id))
             .all(db)
             .await?;
 
@@ -355,19 +864,48 @@ impl Transaction {
         Self::fetch_by_id(id, session, db).await
     }
 
+    /// Saves the transaction as read, unless it has since been changed or
+    /// voided, so a change made in between is never undone.
     pub async fn update_value(
-        tsn: Transaction,
+        mut tsn: Transaction,
         session: Session,
         id: &str,
         db: &DbConn,
     ) -> Result<Transaction, Error> {
-        tsn.into_active(session.tenant_id.clone())
-            .update(db)
+        let read_at = tsn.updated_at;
+        // Timestamps may be stored to the second, the transaction's must change.
+        tsn.updated_at = Utc::now().max(read_at + Duration::seconds(1));
+
+        let result = Transactions::update_many()
+            .set(tsn.into_active(session.tenant_id.clone()))
+            .filter(transactions::Column::Id.eq(id))
+            .filter(transactions::Column::TenantId.eq(session.tenant_id.clone()))
+            .filter(transactions::Column::Void.is_null())
+            .filter(transactions::Column::UpdatedAt.eq(read_at.naive_utc()))
+            .exec(db)
             .await?;
 
+        if result.rows_affected != 1 {
+            return Err(ErrorResponse::create_error(
+                "Transaction has been changed or voided since it was read.",
+            ));
+        }
+
         Self::fetch_by_id(id, session, db).await
     }
 
+    pub async fn append_notes(
+        id: &str,
+        notes: NoteList,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<Transaction, Error> {
+        let mut transaction = Transaction::fetch_by_id(id, session.clone(), db).await?;
+        transaction.order_notes.extend(notes);
+
+        Self::update_value(transaction, session, id, db).await
+    }
+
     pub async fn update_order_status(
         id: &str,
         refer: &str,
@@ -377,34 +915,43 @@ impl Transaction {
     ) -> Result<Transaction, Error> {
         let mut transaction = Transaction::fetch_by_id(id, session.clone(), db).await?;
 
-        let new_orders = transaction
-            .clone()
+        let order_ids = transaction
             .products
-            .into_iter()
-            .map(|mut v| {
-                if v.reference == refer {
-                    let new_status = OrderStatusAssignment {
-                        status: status.clone(),
-                        assigned_products: v.products.iter().map(|el| el.id.clone()).collect(),
-                        timestamp: Utc::now(),
-                    };
-
-                    v.status = new_status.clone();
-
-                    v.status_history.push(History {
-                        item: new_status,
-                        reason: "Supered Update".to_string(),
-                        timestamp: v.status.timestamp,
-                    });
+            .iter()
+            .filter(|order| order.reference == refer)
+            .map(|order| order.id.clone())
+            .collect::<Vec<Id>>();
+
+        let (mut released, mut allocated) = (vec![], vec![]);
+        for order_id in order_ids {
+            let intents = transaction.transition_order(
+                &order_id,
+                status.clone(),
+                "Supered Update",
+                &session.employee.id,
+            )?;
+
+            // An order failing at its origin is fulfilled from elsewhere,
+            // if anywhere else can.
+            if !intents.is_empty() {
+                released.extend(intents);
+
+                match transaction.route_order(&order_id, &session, db).await {
+                    Ok(intents) => allocated.extend(intents),
+                    Err(error) => debug!(%order_id, error = ?error, "order not re-routed"),
                 }
+            }
+        }
 
-                v
-            })
-            .collect::<Vec<Order>>();
+        let issued = std::mem::take(&mut transaction.issued_collections);
+        let mut transaction = Self::update_value(transaction, session.clone(), id, db).await?;
+        transaction.issued_collections = issued;
 
-        transaction.products = new_orders;
+        Self::process_intents(session.clone(), db, released).await;
+        let drawn = Self::process_intents(session.clone(), db, allocated).await;
+        Self::record_lots_drawn(id, drawn, &session, db).await?;
 
-        Self::update_value(transaction, session, id, db).await
+        Ok(transaction)
     }
 
     pub async fn update_product_status(
@@ -431,14 +978,10 @@ impl Transaction {
                                     .into_iter()
                                     .map(|mut i| {
                                         if i.id == update.product_instance_id {
-                                            i.fulfillment_status.pick_history.push(History {
-                                                item: i.fulfillment_status.pick_status,
-                                                reason: "Standard Update Bump".to_string(),
-                                                timestamp: i.fulfillment_status.last_updated,
-                                            });
-                                            i.fulfillment_status.last_updated = Utc::now();
-                                            i.fulfillment_status.pick_status =
-                                                update.new_status.clone();
+                                            i.fulfillment_status.transition(
+                                                update.new_status.clone(),
+                                                "Standard Update Bump",
+                                            );
                                         }
 
                                         i
@@ -480,88 +1023,61 @@ impl Transaction {
         }
     }
 
+    /// Alters stock levels as the intents describe. The intents of a
+    /// product are applied together, so that none are lost to another
+    /// updating the product at the same time. Returns the lots drawn for
+    /// each purchase, see [`Transaction::record_lots_drawn`].
     pub async fn process_intents(
         session: Session,
         db: &DbConn,
         intents: Vec<QuantityAlterationIntent>,
-    ) -> Vec<Result<Product, JoinError>> {
-        let intent_processor = intents
-            .iter()
-            .map(|intent| async {
-                let intent = intent.clone();
+    ) -> Vec<(Id, Vec<LotDraw>)> {
+        let mut grouped: Vec<(String, Vec<QuantityAlterationIntent>)> = vec![];
+
+        for intent in intents {
+            match grouped
+                .iter_mut()
+                .find(|(sku, _)| *sku == intent.product_sku)
+            {
+                Some((_, group)) => group.push(intent),
+                None => grouped.push((intent.product_sku.clone(), vec![intent])),
+            }
+        }
+
+        let intent_processor = grouped
+            .into_iter()
+            .map(|(product_sku, intents)| {
                 let database = db.clone();
                 let session = session.clone();
 
-                println!("{}", session.employee);
-
                 tokio::spawn(async move {
-                    let db_ = database.clone();
-                    let session_clone = session.clone();
-
-                    match Product::fetch_by_id(&intent.product_sku, session.clone(), &db_).await {
+                    match Product::fetch_by_id(&product_sku, session.clone(), &database).await {
                         Ok(mut val) => {
-                            let variants: Vec<VariantInformation> = val
-                                .variants
-                                .iter_mut()
-                                .map(|var| {
-                                    let stock_info: Vec<Stock> = if var.barcode
-                                        == intent.variant_code
-                                    {
-                                        var.stock
-                                            .iter_mut()
-                                            .map(|stock| {
-                                                if stock.store.store_code
-                                                    == intent.transaction_store_code
-                                                {
-                                                    match intent.transaction_type {
-                                                        TransactionType::In => {
-                                                            stock.quantity.quantity_sellable +=
-                                                                intent.quantity_to_transact
-                                                        }
-                                                        TransactionType::Out => {
-                                                            stock.quantity.quantity_sellable -=
-                                                                intent.quantity_to_transact
-                                                        }
-                                                        TransactionType::PendingIn => {
-                                                            stock.quantity.quantity_on_order +=
-                                                                intent.quantity_to_transact
-                                                        }
-                                                        TransactionType::PendingOut => {
-                                                            stock.quantity.quantity_allocated +=
-                                                                intent.quantity_to_transact
-                                                        }
-                                                        TransactionType::Saved => {
-                                                            // A saved transaction should not be processed, but should be shifted into a specified IN or OUT variant.
-                                                            // As this should never happen, the modified changes are left alone.
-                                                            stock.quantity.quantity_allocated += 0.0
-                                                        }
-                                                        TransactionType::Quote => {
-                                                            // A saved transaction should not be processed, but should be shifted into a specified IN or OUT variant.
-                                                            // As this should never happen, the modified changes are left alone.
-                                                            stock.quantity.quantity_allocated += 0.0
-                                                        }
-                                                    }
-                                                }
-
-                                                stock.clone()
-                                            })
-                                            .collect::<Vec<Stock>>()
-                                    } else {
-                                        var.stock.clone()
-                                    };
-
-                                    var.stock = stock_info;
-                                    var.clone()
-                                })
-                                .collect::<Vec<VariantInformation>>();
-
-                            val.variants = variants;
+                            let now = Utc::now();
+                            let mut drawn = vec![];
+
+                            for intent in intents.iter() {
+                                let draws = val
+                                    .variants
+                                    .iter_mut()
+                                    .filter(|var| var.barcode == intent.variant_code)
+                                    .flat_map(|var| var.stock.iter_mut())
+                                    .filter(|stock| {
+                                        stock.store.store_code == intent.transaction_store_code
+                                    })
+                                    .flat_map(|stock| apply_intent(stock, intent, now))
+                                    .collect::<Vec<LotDraw>>();
+
+                                if let Some(purchase_id) = &intent.purchase_id {
+                                    if !draws.is_empty() {
+                                        drawn.push((purchase_id.clone(), draws));
+                                    }
+                                }
+                            }
 
                             // Possible chance for an alternate client to have a modification during this time-frame, try implementing a queued solution.
-                            match Product::update(val, session_clone, &intent.product_sku, &db_)
-                                .await
-                            {
-                                Ok(val) => Ok(val),
+                            match Product::update(val, session, &product_sku, &database).await {
+                                Ok(_) => Ok(drawn),
                                 Err(_) => Err(DbErr::Custom(String::new())),
                             }
                         }
@@ -569,22 +1085,497 @@ impl Transaction {
                     }
                     .unwrap()
                 })
-                .await
             })
             .collect::<Vec<_>>();
 
-        futures::future::join_all(intent_processor).await
+        futures::future::join_all(intent_processor)
+            .await
+            .into_iter()
+            .filter_map(Result::ok)
+            .flatten()
+            .collect()
     }
 
-    pub async fn delete(id: &str, session: Session, db: &DbConn) -> Result<DeleteResult, Error> {
-        Transactions::delete(transactions::ActiveModel {
-            id: Set(id.to_string()),
-            tenant_id: Set(session.tenant_id),
-            ..Default::default()
+    /// Records the lots drawn for each purchase, once its stock is moved.
+    pub async fn record_lots_drawn(
+        id: &str,
+        drawn: Vec<(Id, Vec<LotDraw>)>,
+        session: &Session,
+        db: &DbConn,
+    ) -> Result<(), Error> {
+        if drawn.is_empty() {
+            return Ok(());
+        }
+
+        loop {
+            let mut transaction = Self::fetch_by_id(id, session.clone(), db).await?;
+
+            if transaction.void.is_some() {
+                return Ok(());
+            }
+
+            for purchase in transaction
+                .products
+                .iter_mut()
+                .flat_map(|order| order.products.iter_mut())
+            {
+                if let Some((_, draws)) = drawn
+                    .iter()
+                    .find(|(drawn_for, _)| *drawn_for == purchase.id)
+                {
+                    purchase.lots_drawn = draws.clone();
+                }
+            }
+
+            // Timestamps may be stored to the second, the transaction's must change.
+            let saved_at = Utc::now().max(transaction.updated_at + Duration::seconds(1));
+
+            let result = Transactions::update_many()
+                .col_expr(
+                    transactions::Column::Products,
+                    Expr::value(json!(transaction.products)),
+                )
+                .col_expr(
+                    transactions::Column::UpdatedAt,
+                    Expr::value(saved_at.naive_utc()),
+                )
+                .filter(transactions::Column::Id.eq(id))
+                .filter(transactions::Column::TenantId.eq(session.tenant_id.clone()))
+                .filter(transactions::Column::Void.is_null())
+                .filter(transactions::Column::UpdatedAt.eq(transaction.updated_at.naive_utc()))
+                .exec(db)
+                .await?;
+
+            if result.rows_affected == 1 {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Voids the transaction for `reason`, returning it alongside the intents
+    /// which return the stock it moved. Payments not yet complete are failed,
+    /// and those complete are recorded as owing a refund. The `notes` are
+    /// saved with the void, such as those of the override authorising it.
+    pub async fn void(
+        id: &str,
+        reason: String,
+        notes: NoteList,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<(Transaction, Vec<QuantityAlterationIntent>), Error> {
+        let mut transaction = Transaction::fetch_by_id(id, session.clone(), db).await?;
+
+        if transaction.void.is_some() {
+            return Err(ErrorResponse::create_error(
+                "Transaction has already been voided.",
+            ));
+        }
+
+        let intents = transaction.reversing_intents();
+
+        let payments = transaction
+            .payment
+            .iter_mut()
+            .filter_map(|payment| {
+                let outcome = match payment.status {
+                    PaymentStatus::Failed(_) => return None,
+                    PaymentStatus::Complete(_) => ReversalOutcome::RefundRequired,
+                    _ => {
+                        payment.status = PaymentStatus::Failed(Processable::Anonymous(format!(
+                            "Voided: {}",
+                            reason
+                        )));
+                        ReversalOutcome::Cancelled
+                    }
+                };
+
+                Some(PaymentReversal {
+                    payment_id: payment.id.clone(),
+                    amount: Price {
+                        quantity: -payment.amount.quantity,
+                        currency: payment.amount.currency.clone(),
+                    },
+                    outcome,
+                })
+            })
+            .collect();
+
+        transaction.void = Some(TransactionVoid {
+            reason,
+            employee: session.employee.id.clone(),
+            timestamp: Utc::now(),
+            payments,
+        });
+        transaction.order_notes.extend(notes);
+        transaction.updated_at = Utc::now().max(transaction.updated_at + Duration::seconds(1));
+
+        // Only one of any concurrent voids may return the stock moved.
+        let result = Transactions::update_many()
+            .set(transaction.into_active(session.tenant_id.clone()))
+            .filter(transactions::Column::Id.eq(id))
+            .filter(transactions::Column::TenantId.eq(session.tenant_id.clone()))
+            .filter(transactions::Column::Void.is_null())
+            .exec(db)
+            .await?;
+
+        if result.rows_affected != 1 {
+            return Err(ErrorResponse::create_error(
+                "Transaction has already been voided.",
+            ));
+        }
+
+        let voided = Self::fetch_by_id(id, session, db).await?;
+
+        Ok((voided, intents))
+    }
+
+    /// The next quote number within the tenant, i.e. `Q-000042`. Numbers
+    /// are issued from the tenant's sequence, so are never reissued, even
+    /// once the quote holding one is purged.
+    pub async fn next_quote_number(session: &Session, db: &DbConn) -> Result<String, Error> {
+        loop {
+            let issued = Tenants::find_by_id(session.tenant_id.clone())
+                .one(db)
+                .await?
+                .ok_or_else(|| ErrorResponse::create_error("Tenant does not exist."))?
+                .quote_sequence;
+
+            // Of any concurrent quotes, only one may advance the sequence
+            // from `issued`, the rest read it again.
+            let result = Tenants::update_many()
+                .col_expr(tenants::Column::QuoteSequence, Expr::value(issued + 1))
+                .filter(tenants::Column::TenantId.eq(session.tenant_id.clone()))
+                .filter(tenants::Column::QuoteSequence.eq(issued))
+                .exec(db)
+                .await?;
+
+            if result.rows_affected == 1 {
+                return Ok(format!("Q-{:06}", issued + 1));
+            }
+        }
+    }
+
+    pub async fn fetch_quotes_by_customer(
+        id: &str,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<Vec<Transaction>, Error> {
+        let res = Transactions::find()
+            .filter(transactions::Column::TenantId.eq(session.tenant_id))
+            .filter(transactions::Column::Quote.is_not_null())
+            .filter(transactions::Column::Customer.contains(id))
+            .order_by_desc(transactions::Column::CreatedAt)
+            .all(db)
+            .await?;
+
+        let mapped = res.iter().map(|t| t.clone().into()).collect();
+
+        Ok(mapped)
+    }
+
+    /// Keeps the quote's current products and total as a revision, before
+    /// they are superseded, and extends its expiry by `validity`.
+    pub async fn revise_quote(
+        mut quote: Transaction,
+        validity: Duration,
+        session: Session,
+        db: &DbConn,
+    ) -> Result<Transaction, Error> {
+        let details = match quote.quote.as_mut() {
+            Some(details) if details.converted_to.is_none() => details,
+            Some(_) => {
+                return Err(ErrorResponse::create_error(
+                    "A converted quote cannot be revised.",
+                ))
+            }
+            None => return Err(ErrorResponse::create_error("Transaction is not a quote.")),
+        };
+
+        details.revisions.push(QuoteRevision {
+            revision: details.revision,
+            products: quote.products.clone(),
+            order_total: quote.order_total,
+            revised_by: session.employee.id.clone(),
+            revised_at: Utc::now(),
+        });
+        details.revision += 1;
+        details.expires_at = Utc::now() + validity;
+
+        let details = details.clone();
+        if Self::save_quote(&quote, &details, &session, db)
+            .await?
+            .is_none()
+        {
+            return Err(ErrorResponse::create_error(
+                "Quote has changed since it was read.",
+            ));
+        }
+
+        Self::fetch_by_id(&quote.id, session, db).await
+    }
+
+    /// The sale made by accepting the quote, at the prices quoted. Only an
+    /// open quote, one neither expired, voided nor converted, is accepted.
+    pub fn accept_quote(
+        &self,
+        input: QuoteConversionInput,
+        author: Id,
+    ) -> Result<TransactionInit, Error> {
+        let details = match &self.quote {
+            Some(details) if self.void.is_none() => details,
+            _ => {
+                return Err(ErrorResponse::create_error(
+                    "Transaction is not an open quote.",
+                ))
+            }
+        };
+
+        if details.converted_to.is_some() {
+            return Err(ErrorResponse::create_error(
+                "Quote has already been converted.",
+            ));
+        }
+
+        if details.is_expired() {
+            return Err(ErrorResponse::create_error("Quote has expired."));
+        }
+
+        let mut order_notes = self.order_notes.clone();
+        order_notes.push(Note {
+            message: format!(
+                "Converted from quote {} (revision {}).",
+                details.number, details.revision
+            ),
+            author,
+            timestamp: Utc::now(),
+        });
+
+        Ok(TransactionInit {
+            customer: self.customer.clone(),
+            transaction_type: TransactionType::Out,
+            products: self.products.clone(),
+            order_total: self.order_total,
+            payment: input.payment,
+            order_date: Utc::now(),
+            order_notes,
+            kiosk: input.kiosk,
+            quote: None,
+            reference: None,
         })
-        .exec(db)
-        .await
-        .map_err(|e| e.into())
+    }
+
+    /// Saves `details` as the quote's, provided the quote is unchanged
+    /// since it was read, returning when it was saved. Returns `None` if
+    /// the quote was changed, i.e. by a concurrent revision or conversion.
+    async fn save_quote(
+        quote: &Transaction,
+        details: &QuoteDetails,
+        session: &Session,
+        db: &DbConn,
+    ) -> Result<Option<DateTime<Utc>>, Error> {
+        // Timestamps may be stored to the second, the quote's must change.
+        let saved_at = Utc::now().max(quote.updated_at + Duration::seconds(1));
+
+        let result = Transactions::update_many()
+            .col_expr(transactions::Column::Quote, Expr::value(json!(details)))
+            .col_expr(
+                transactions::Column::UpdatedAt,
+                Expr::value(saved_at.naive_utc()),
+            )
+            .filter(transactions::Column::Id.eq(quote.id.clone()))
+            .filter(transactions::Column::TenantId.eq(session.tenant_id.clone()))
+            .filter(transactions::Column::Void.is_null())
+            .filter(transactions::Column::UpdatedAt.eq(quote.updated_at.naive_utc()))
+            .exec(db)
+            .await?;
+
+        Ok((result.rows_affected == 1).then_some(saved_at))
+    }
+
+    /// Claims the quote for the sale `sale_id`, before the sale is made,
+    /// returning when it was claimed. Of any concurrent conversions of the
+    /// quote, only one claims it.
+    pub async fn claim_conversion(
+        quote: &Transaction,
+        sale_id: &str,
+        session: &Session,
+        db: &DbConn,
+    ) -> Result<DateTime<Utc>, Error> {
+        let mut details = match quote.quote.clone() {
+            Some(details) => details,
+            None => return Err(ErrorResponse::create_error("Transaction is not a quote.")),
+        };
+        details.converted_to = Some(sale_id.to_string());
+
+        Self::save_quote(quote, &details, session, db)
+            .await?
+            .ok_or_else(|| ErrorResponse::create_error("Quote has already been converted."))
+    }
+
+    /// Releases the quote claimed for `sale_id` by
+    /// [`Transaction::claim_conversion`] at `claimed_at` when its sale could
+    /// not be made, so it may be converted again. Nothing is released unless
+    /// the quote is still claimed for the sale.
+    pub async fn release_conversion(
+        quote: &Transaction,
+        sale_id: &str,
+        claimed_at: DateTime<Utc>,
+        session: &Session,
+        db: &DbConn,
+    ) -> Result<(), Error> {
+        let released_at = Utc::now().max(claimed_at + Duration::seconds(1));
+
+        let result = Transactions::update_many()
+            .col_expr(transactions::Column::Quote, Expr::value(json!(quote.quote)))
+            .col_expr(
+                transactions::Column::UpdatedAt,
+                Expr::value(released_at.naive_utc()),
+            )
+            .filter(transactions::Column::Id.eq(quote.id.clone()))
+            .filter(transactions::Column::TenantId.eq(session.tenant_id.clone()))
+            .filter(transactions::Column::Quote.contains(json!(sale_id).to_string()))
+            .exec(db)
+            .await?;
+
+        if result.rows_affected != 1 {
+            return Err(ErrorResponse::create_error(
+                "Quote is no longer claimed for the sale.",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Moves one of the transaction's orders to `status`, carrying out the
+    /// side-effects of the transition. Returns the stock released by it, to
+    /// be processed once the transaction is saved.
+    ///
+    /// An order failing while its stock is still held by the store releases
+    /// it, whereas one failing in transit does not. A customer is notified
+    /// once their order is in store, and issued a code to collect it with.
+    pub fn transition_order(
+        &mut self,
+        order_id: &str,
+        status: OrderStatus,
+        reason: &str,
+        author: &str,
+    ) -> Result<Vec<QuantityAlterationIntent>, Error> {
+        let (transaction_id, customer_id) = (self.id.clone(), self.customer.customer_id.clone());
+        let order = find_order(self, order_id)?;
+
+        let held = matches!(
+            order.status.status,
+            OrderStatus::Queued(_) | OrderStatus::Processing(_) | OrderStatus::InStore(_)
+        );
+
+        order.transition(status, reason)?;
+
+        match order.status.status {
+            OrderStatus::Failed(_) if held => {
+                order.stock_released = true;
+
+                let order = order.clone();
+                return Ok(self.releasing_intents(&order));
+            }
+            OrderStatus::InStore(_) => {
+                info!(
+                    customer_id = %customer_id,
+                    order_reference = %order.reference,
+                    store_code = %order.destination.store_code,
+                    "notifying customer of order ready for collection"
+                );
+
+                let (collection, issued) = Collection::issue(&transaction_id, &order.id);
+                order.collection = Some(collection);
+                order.order_notes.push(Note {
+                    message: format!(
+                        "Customer notified the order is ready for collection at {}.",
+                        order.destination.contact.name
+                    ),
+                    author: author.to_string(),
+                    timestamp: Utc::now(),
+                });
+                self.issued_collections.push(issued);
+            }
+            _ => {}
+        }
+
+        Ok(vec![])
+    }
+
+    /// The intents which undo the stock movements of the transaction. Saved
+    /// transactions and quotes never moved stock, so have none, nor do
+    /// orders whose stock was released when they failed.
+    fn reversing_intents(&self) -> Vec<QuantityAlterationIntent> {
+        self.products
+            .iter()
+            .filter(|order| !order.stock_released)
+            .flat_map(|order| self.releasing_intents(order))
+            .collect()
+    }
+
+    /// The intents which return the order's stock to its origin, restoring
+    /// the lots it was drawn from.
+    fn releasing_intents(&self, order: &Order) -> Vec<QuantityAlterationIntent> {
+        self.allocating_intents(order)
+            .into_iter()
+            .zip(order.products.iter())
+            .flat_map(|(intent, purchase)| {
+                let restored = purchase
+                    .lots_drawn
+                    .iter()
+                    .map(|draw| draw.quantity)
+                    .sum::<f32>();
+
+                let mut intents = purchase
+                    .lots_drawn
+                    .iter()
+                    .map(|draw| QuantityAlterationIntent {
+                        quantity_to_transact: draw.quantity,
+                        lot: Some(draw.lot.clone()),
+                        ..intent.clone()
+                    })
+                    .collect::<Vec<_>>();
+
+                if intent.quantity_to_transact > restored {
+                    intents.push(QuantityAlterationIntent {
+                        quantity_to_transact: intent.quantity_to_transact - restored,
+                        ..intent
+                    });
+                }
+
+                intents
+            })
+            .map(|mut intent| {
+                intent.quantity_to_transact = -intent.quantity_to_transact;
+                intent
+            })
+            .collect()
+    }
+
+    /// The intents which take the order's stock from its origin.
+    pub(crate) fn allocating_intents(&self, order: &Order) -> Vec<QuantityAlterationIntent> {
+        if matches!(
+            self.transaction_type,
+            TransactionType::Saved | TransactionType::Quote
+        ) {
+            return vec![];
+        }
+
+        order
+            .products
+            .iter()
+            .map(|product| QuantityAlterationIntent {
+                variant_code: product.product_code.clone(),
+                product_sku: product.product_sku.clone(),
+                transaction_store_code: order.origin.store_code.clone(),
+                transaction_store_id: order.origin.store_id.clone(),
+                transaction_type: self.transaction_type.clone(),
+                quantity_to_transact: product.quantity,
+                lot: product.lot.clone(),
+                purchase_id: Some(product.id.clone()),
+            })
+            .collect()
     }
 }
 
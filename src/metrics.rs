@@ -0,0 +1,189 @@
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram_vec, register_int_counter, register_int_counter_vec, register_int_gauge,
+    register_int_gauge_vec, Encoder, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec, TextEncoder,
+};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::ContentType;
+use rocket::{get, Data, Request, Response};
+use sea_orm::DatabaseConnection;
+
+use crate::pool::InternalDb;
+
+pub const SESSION_GARBAGE_COLLECTOR: &str = "session_garbage_collector";
+pub const SESSION_INGRESS_WORKER: &str = "session_ingress_worker";
+pub const SHIPPING_TRACKING_WORKER: &str = "shipping_tracking_worker";
+
+lazy_static! {
+    pub static ref HTTP_REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "http_request_duration_seconds",
+        "Latency of handled requests, by matched route.",
+        &["method", "route", "status"]
+    )
+    .unwrap();
+    pub static ref ERRORS: IntCounterVec = register_int_counter_vec!(
+        "errors_total",
+        "Error responses returned, by `Error` variant.",
+        &["variant"]
+    )
+    .unwrap();
+    pub static ref LOGIN_ATTEMPTS: IntCounterVec = register_int_counter_vec!(
+        "login_attempts_total",
+        "Employee authentication attempts, by outcome.",
+        &["outcome"]
+    )
+    .unwrap();
+    pub static ref INGEST_JOBS: IntCounterVec = register_int_counter_vec!(
+        "ingest_jobs_total",
+        "Files processed by the ingress worker, by outcome.",
+        &["outcome"]
+    )
+    .unwrap();
+    pub static ref SESSIONS_CULLED: IntCounter = register_int_counter!(
+        "sessions_culled_total",
+        "Expired sessions removed by the garbage collector."
+    )
+    .unwrap();
+    pub static ref TRANSACTIONS_CULLED: IntCounter = register_int_counter!(
+        "saved_transactions_culled_total",
+        "Stale saved transactions removed by the garbage collector."
+    )
+    .unwrap();
+    pub static ref TENANTS_PURGED: IntCounter = register_int_counter!(
+        "tenants_purged_total",
+        "Suspended tenants whose data was purged by the garbage collector."
+    )
+    .unwrap();
+    pub static ref BACKGROUND_TASK_LAST_RUN: IntGaugeVec = register_int_gauge_vec!(
+        "background_task_last_run_timestamp_seconds",
+        "Unix time at which a background task last completed an iteration.",
+        &["task"]
+    )
+    .unwrap();
+    pub static ref BACKGROUND_TASK_ERRORS: IntCounterVec = register_int_counter_vec!(
+        "background_task_errors_total",
+        "Errors encountered by background tasks.",
+        &["task"]
+    )
+    .unwrap();
+    pub static ref DB_QUERY_DURATION: HistogramVec = register_histogram_vec!(
+        "db_query_duration_seconds",
+        "Latency of statements executed against the database.",
+        &["outcome"]
+    )
+    .unwrap();
+    pub static ref DB_PING_DURATION: IntGauge = register_int_gauge!(
+        "db_ping_duration_microseconds",
+        "Round trip of a ping to the database, measured at scrape time."
+    )
+    .unwrap();
+    pub static ref DB_POOL_CONNECTIONS: IntGaugeVec = register_int_gauge_vec!(
+        "db_pool_connections",
+        "Connections held by the database pool, by state.",
+        &["state"]
+    )
+    .unwrap();
+}
+
+/// Marks an iteration of a background task as completed.
+pub fn task_heartbeat(task: &str) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default();
+
+    BACKGROUND_TASK_LAST_RUN.with_label_values(&[task]).set(now);
+}
+
+pub fn task_error(task: &str) {
+    BACKGROUND_TASK_ERRORS.with_label_values(&[task]).inc();
+}
+
+/// Installed as the connection's metric callback, invoked for every
+/// statement executed through the pool.
+pub fn observe_query(info: &sea_orm::metric::Info<'_>) {
+    let outcome = if info.failed { "failed" } else { "ok" };
+
+    DB_QUERY_DURATION
+        .with_label_values(&[outcome])
+        .observe(info.elapsed.as_secs_f64());
+}
+
+async fn record_database_stats(db: &DatabaseConnection) {
+    let start = Instant::now();
+
+    match db.ping().await {
+        Ok(_) => DB_PING_DURATION.set(start.elapsed().as_micros() as i64),
+        Err(_) => DB_PING_DURATION.set(-1),
+    }
+
+    if let DatabaseConnection::SqlxMySqlPoolConnection(_) = db {
+        let pool = db.get_mysql_connection_pool();
+        let idle = pool.num_idle() as i64;
+
+        DB_POOL_CONNECTIONS.with_label_values(&["idle"]).set(idle);
+        DB_POOL_CONNECTIONS
+            .with_label_values(&["active"])
+            .set(pool.size() as i64 - idle);
+    }
+}
+
+struct RequestTimer(Instant);
+
+/// Observes the latency of every request against the route it matched,
+/// using the route's uri template (i.e. `/api/product/<id>`) so label
+/// cardinality stays bounded.
+pub struct RequestMetrics;
+
+#[rocket::async_trait]
+impl Fairing for RequestMetrics {
+    fn info(&self) -> Info {
+        Info {
+            name: "Prometheus request metrics",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        request.local_cache(|| RequestTimer(Instant::now()));
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let elapsed = request
+            .local_cache(|| RequestTimer(Instant::now()))
+            .0
+            .elapsed();
+        let route = request
+            .route()
+            .map(|route| route.uri.to_string())
+            .unwrap_or_else(|| "unmatched".to_string());
+
+        HTTP_REQUEST_DURATION
+            .with_label_values(&[
+                request.method().as_str(),
+                &route,
+                &response.status().code.to_string(),
+            ])
+            .observe(elapsed.as_secs_f64());
+    }
+}
+
+#[get("/metrics")]
+pub async fn metrics(db: InternalDb) -> (ContentType, String) {
+    record_database_stats(&db.0).await;
+
+    let mut buffer = vec![];
+    let encoder = TextEncoder::new();
+
+    if let Err(error) = encoder.encode(&prometheus::gather(), &mut buffer) {
+        tracing::error!(%error, "unable to encode metrics");
+    }
+
+    (
+        ContentType::Plain,
+        String::from_utf8(buffer).unwrap_or_default(),
+    )
+}
@@ -1,5 +1,8 @@
+use sea_orm::DbBackend;
 use sea_orm_migration::prelude::*;
 
+use super::TableCreateBackendExt;
+
 pub struct Migration;
 
 impl MigrationName for Migration {
@@ -17,7 +20,7 @@ impl MigrationTrait for Migration {
             .create_table(
                 Table::create()
                     .table(Products::Table)
-                    .engine("InnoDB".to_string())
+                    .engine_if_supported(manager, "InnoDB")
                     .col(
                         ColumnDef::new(Products::Sku)
                             .string()
@@ -43,8 +46,11 @@ impl MigrationTrait for Migration {
             )
             .await?;
 
-        db.execute_unprepared("ALTER TABLE `Products` ADD FULLTEXT indx(`name`,`company`)")
-            .await?;
+        // Full-text search is only available (and only queried) on MySQL.
+        if manager.get_database_backend() == DbBackend::MySql {
+            db.execute_unprepared("ALTER TABLE `Products` ADD FULLTEXT indx(`name`,`company`)")
+                .await?;
+        }
 
         Ok(())
     }
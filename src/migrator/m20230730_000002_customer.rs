@@ -1,5 +1,7 @@
 use sea_orm_migration::prelude::*;
 
+use super::TableCreateBackendExt;
+
 pub struct Migration;
 
 impl MigrationName for Migration {
@@ -15,7 +17,7 @@ impl MigrationTrait for Migration {
             .create_table(
                 Table::create()
                     .table(Customer::Table)
-                    .engine("InnoDB".to_string())
+                    .engine_if_supported(manager, "InnoDB")
                     .col(
                         ColumnDef::new(Customer::Id)
                             .string()
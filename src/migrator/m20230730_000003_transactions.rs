@@ -1,6 +1,8 @@
 use sea_orm::{EnumIter, Iterable};
 use sea_orm_migration::prelude::*;
 
+use super::TableCreateBackendExt;
+
 pub struct Migration;
 
 impl MigrationName for Migration {
@@ -16,7 +18,7 @@ impl MigrationTrait for Migration {
             .create_table(
                 Table::create()
                     .table(Transactions::Table)
-                    .engine("InnoDB".to_string())
+                    .engine_if_supported(manager, "InnoDB")
                     .col(
                         ColumnDef::new(Transactions::Id)
                             .string()
@@ -1,5 +1,7 @@
 use sea_orm_migration::prelude::*;
 
+use super::TableCreateBackendExt;
+
 pub struct Migration;
 
 impl MigrationName for Migration {
@@ -15,7 +17,7 @@ impl MigrationTrait for Migration {
             .create_table(
                 Table::create()
                     .table(Store::Table)
-                    .engine("InnoDB".to_string())
+                    .engine_if_supported(manager, "InnoDB")
                     .col(ColumnDef::new(Store::TenantId).string().not_null())
                     .col(ColumnDef::new(Store::Id).string().not_null().primary_key())
                     .col(ColumnDef::new(Store::Name).text().not_null())
@@ -0,0 +1,75 @@
+use sea_orm_migration::prelude::*;
+
+use super::TableCreateBackendExt;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230730_000012_api_keys"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ApiKey::Table)
+                    .engine_if_supported(manager, "InnoDB")
+                    .col(ColumnDef::new(ApiKey::Id).string().not_null().primary_key())
+                    .col(ColumnDef::new(ApiKey::TenantId).string().not_null())
+                    .col(ColumnDef::new(ApiKey::Name).text().not_null())
+                    .col(
+                        ColumnDef::new(ApiKey::Prefix)
+                            .string_len(16)
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(ApiKey::KeyHash).string_len(64).not_null())
+                    .col(ColumnDef::new(ApiKey::Scopes).json().not_null())
+                    .col(ColumnDef::new(ApiKey::CreatedBy).string().not_null())
+                    .col(ColumnDef::new(ApiKey::CreatedAt).date_time().not_null())
+                    .col(ColumnDef::new(ApiKey::LastUsedAt).date_time().null())
+                    .col(ColumnDef::new(ApiKey::ExpiresAt).date_time().null())
+                    .col(ColumnDef::new(ApiKey::RevokedAt).date_time().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ApiKey::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum ApiKey {
+    #[iden = "ApiKey"]
+    Table,
+    #[iden = "id"]
+    Id,
+    #[iden = "tenant_id"]
+    TenantId,
+    #[iden = "name"]
+    Name,
+    #[iden = "prefix"]
+    Prefix,
+    #[iden = "key_hash"]
+    KeyHash,
+    #[iden = "scopes"]
+    Scopes,
+    #[iden = "created_by"]
+    CreatedBy,
+    #[iden = "created_at"]
+    CreatedAt,
+    #[iden = "last_used_at"]
+    LastUsedAt,
+    #[iden = "expires_at"]
+    ExpiresAt,
+    #[iden = "revoked_at"]
+    RevokedAt,
+}
@@ -0,0 +1,76 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230730_000013_session_metadata"
+    }
+}
+
+/// Records where and when a session was issued, and groups an access
+/// token with the refresh tokens derived from it (its family) so that a
+/// reused refresh token can revoke them all.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only accepts a single alteration per statement.
+        for mut column in [
+            ColumnDef::new(Session::KioskId).string().null().to_owned(),
+            ColumnDef::new(Session::CreatedAt)
+                .date_time()
+                .null()
+                .to_owned(),
+            ColumnDef::new(Session::FamilyId).string().null().to_owned(),
+            ColumnDef::new(Session::ConsumedAt)
+                .date_time()
+                .null()
+                .to_owned(),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Session::Table)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            Session::KioskId,
+            Session::CreatedAt,
+            Session::FamilyId,
+            Session::ConsumedAt,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Session::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum Session {
+    #[iden = "Session"]
+    Table,
+    #[iden = "kiosk_id"]
+    KioskId,
+    #[iden = "created_at"]
+    CreatedAt,
+    #[iden = "family_id"]
+    FamilyId,
+    #[iden = "consumed_at"]
+    ConsumedAt,
+}
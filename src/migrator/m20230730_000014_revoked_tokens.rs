@@ -0,0 +1,64 @@
+use sea_orm_migration::prelude::*;
+
+use super::TableCreateBackendExt;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230730_000014_revoked_tokens"
+    }
+}
+
+/// Signed access tokens are never looked up, so a revoked session (or
+/// employee) is recorded here until the tokens it covers have expired.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(RevokedToken::Table)
+                    .engine_if_supported(manager, "InnoDB")
+                    .col(
+                        ColumnDef::new(RevokedToken::Subject)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(RevokedToken::TenantId).string().not_null())
+                    .col(
+                        ColumnDef::new(RevokedToken::RevokedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RevokedToken::ExpiresAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RevokedToken::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum RevokedToken {
+    #[iden = "RevokedToken"]
+    Table,
+    #[iden = "subject"]
+    Subject,
+    #[iden = "tenant_id"]
+    TenantId,
+    #[iden = "revoked_at"]
+    RevokedAt,
+    #[iden = "expires_at"]
+    ExpiresAt,
+}
@@ -0,0 +1,99 @@
+use sea_orm_migration::prelude::*;
+
+use super::TableCreateBackendExt;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230730_000015_mfa"
+    }
+}
+
+/// Stores each employee's TOTP enrolment, and records on a session which
+/// actions were withheld because MFA was not satisfied when it was issued.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(EmployeeMfa::Table)
+                    .engine_if_supported(manager, "InnoDB")
+                    .col(
+                        ColumnDef::new(EmployeeMfa::EmployeeId)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(EmployeeMfa::TenantId).string().not_null())
+                    .col(ColumnDef::new(EmployeeMfa::Secret).string().not_null())
+                    .col(ColumnDef::new(EmployeeMfa::RecoveryCodes).json().not_null())
+                    .col(
+                        ColumnDef::new(EmployeeMfa::LastUsedStep)
+                            .big_integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(EmployeeMfa::CreatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(EmployeeMfa::EnabledAt).date_time().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Session::Table)
+                    .add_column(ColumnDef::new(Session::RestrictedActions).json().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Session::Table)
+                    .drop_column(Session::RestrictedActions)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(EmployeeMfa::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum EmployeeMfa {
+    #[iden = "EmployeeMfa"]
+    Table,
+    #[iden = "employee_id"]
+    EmployeeId,
+    #[iden = "tenant_id"]
+    TenantId,
+    #[iden = "secret"]
+    Secret,
+    #[iden = "recovery_codes"]
+    RecoveryCodes,
+    #[iden = "last_used_step"]
+    LastUsedStep,
+    #[iden = "created_at"]
+    CreatedAt,
+    #[iden = "enabled_at"]
+    EnabledAt,
+}
+
+#[derive(Iden)]
+pub enum Session {
+    #[iden = "Session"]
+    Table,
+    #[iden = "restricted_actions"]
+    RestrictedActions,
+}
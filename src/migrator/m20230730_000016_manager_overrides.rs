@@ -0,0 +1,106 @@
+use sea_orm_migration::prelude::*;
+
+use super::TableCreateBackendExt;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230730_000016_manager_overrides"
+    }
+}
+
+/// Stores the single-use tokens a manager issues to approve a sensitive
+/// action on another employee's till.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ManagerOverride::Table)
+                    .engine_if_supported(manager, "InnoDB")
+                    .col(
+                        ColumnDef::new(ManagerOverride::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ManagerOverride::Token)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ManagerOverride::TenantId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ManagerOverride::KioskId).string().not_null())
+                    .col(ColumnDef::new(ManagerOverride::Action).json().not_null())
+                    .col(ColumnDef::new(ManagerOverride::Reference).string().null())
+                    .col(
+                        ColumnDef::new(ManagerOverride::RequestedBy)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ManagerOverride::AuthorisedBy)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ManagerOverride::IssuedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ManagerOverride::Expiry)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ManagerOverride::ConsumedAt)
+                            .date_time()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ManagerOverride::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum ManagerOverride {
+    #[iden = "ManagerOverride"]
+    Table,
+    #[iden = "id"]
+    Id,
+    #[iden = "token"]
+    Token,
+    #[iden = "tenant_id"]
+    TenantId,
+    #[iden = "kiosk_id"]
+    KioskId,
+    #[iden = "action"]
+    Action,
+    #[iden = "reference"]
+    Reference,
+    #[iden = "requested_by"]
+    RequestedBy,
+    #[iden = "authorised_by"]
+    AuthorisedBy,
+    #[iden = "issued_at"]
+    IssuedAt,
+    #[iden = "expiry"]
+    Expiry,
+    #[iden = "consumed_at"]
+    ConsumedAt,
+}
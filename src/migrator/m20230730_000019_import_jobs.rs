@@ -0,0 +1,91 @@
+use sea_orm_migration::prelude::*;
+
+use super::TableCreateBackendExt;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230730_000019_import_jobs"
+    }
+}
+
+/// Tracks each file uploaded for ingress, its progress, and the errors of
+/// the records which could not be imported.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ImportJob::Table)
+                    .engine_if_supported(manager, "InnoDB")
+                    .col(
+                        ColumnDef::new(ImportJob::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ImportJob::TenantId).string().not_null())
+                    .col(ColumnDef::new(ImportJob::CreatedBy).string().not_null())
+                    .col(ColumnDef::new(ImportJob::Status).json().not_null())
+                    .col(ColumnDef::new(ImportJob::FormatVersion).integer().null())
+                    .col(ColumnDef::new(ImportJob::TotalRows).integer().not_null())
+                    .col(
+                        ColumnDef::new(ImportJob::ProcessedRows)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ImportJob::SucceededRows)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ImportJob::FailedRows).integer().not_null())
+                    .col(ColumnDef::new(ImportJob::Errors).json().not_null())
+                    .col(ColumnDef::new(ImportJob::CreatedAt).date_time().not_null())
+                    .col(ColumnDef::new(ImportJob::StartedAt).date_time().null())
+                    .col(ColumnDef::new(ImportJob::FinishedAt).date_time().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ImportJob::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum ImportJob {
+    #[iden = "ImportJob"]
+    Table,
+    #[iden = "id"]
+    Id,
+    #[iden = "tenant_id"]
+    TenantId,
+    #[iden = "created_by"]
+    CreatedBy,
+    #[iden = "status"]
+    Status,
+    #[iden = "format_version"]
+    FormatVersion,
+    #[iden = "total_rows"]
+    TotalRows,
+    #[iden = "processed_rows"]
+    ProcessedRows,
+    #[iden = "succeeded_rows"]
+    SucceededRows,
+    #[iden = "failed_rows"]
+    FailedRows,
+    #[iden = "errors"]
+    Errors,
+    #[iden = "created_at"]
+    CreatedAt,
+    #[iden = "started_at"]
+    StartedAt,
+    #[iden = "finished_at"]
+    FinishedAt,
+}
@@ -0,0 +1,107 @@
+use sea_orm_migration::prelude::*;
+
+use super::TableCreateBackendExt;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230730_000020_external_references"
+    }
+}
+
+/// Maps the ids of records imported from another system to the records
+/// created for them, so that importing the same records again updates
+/// rather than duplicates them.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ExternalReference::Table)
+                    .engine_if_supported(manager, "InnoDB")
+                    .col(
+                        ColumnDef::new(ExternalReference::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ExternalReference::TenantId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ExternalReference::Source)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ExternalReference::Kind).string().not_null())
+                    .col(
+                        ColumnDef::new(ExternalReference::ExternalId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ExternalReference::LocalId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ExternalReference::CreatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ExternalReference::UpdatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_external_reference")
+                    .table(ExternalReference::Table)
+                    .col(ExternalReference::TenantId)
+                    .col(ExternalReference::Source)
+                    .col(ExternalReference::Kind)
+                    .col(ExternalReference::ExternalId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ExternalReference::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum ExternalReference {
+    #[iden = "ExternalReference"]
+    Table,
+    #[iden = "id"]
+    Id,
+    #[iden = "tenant_id"]
+    TenantId,
+    #[iden = "source"]
+    Source,
+    #[iden = "kind"]
+    Kind,
+    #[iden = "external_id"]
+    ExternalId,
+    #[iden = "local_id"]
+    LocalId,
+    #[iden = "created_at"]
+    CreatedAt,
+    #[iden = "updated_at"]
+    UpdatedAt,
+}
@@ -0,0 +1,44 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230730_000021_transaction_void"
+    }
+}
+
+/// Records why, when and by whom a transaction was voided. A voided
+/// transaction is kept, rather than deleted, so it may still be queried.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Transactions::Table)
+                    .add_column(ColumnDef::new(Transactions::Void).json().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Transactions::Table)
+                    .drop_column(Transactions::Void)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Transactions {
+    #[iden = "Transactions"]
+    Table,
+    #[iden = "void"]
+    Void,
+}
@@ -0,0 +1,44 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230730_000022_transaction_quote"
+    }
+}
+
+/// Records the number, expiry and revisions of a quote, and the sale it
+/// was converted to.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Transactions::Table)
+                    .add_column(ColumnDef::new(Transactions::Quote).json().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Transactions::Table)
+                    .drop_column(Transactions::Quote)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Transactions {
+    #[iden = "Transactions"]
+    Table,
+    #[iden = "quote"]
+    Quote,
+}
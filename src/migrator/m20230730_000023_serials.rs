@@ -0,0 +1,101 @@
+use sea_orm_migration::prelude::*;
+
+use super::TableCreateBackendExt;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230730_000023_serials"
+    }
+}
+
+/// Registers the serial numbers of individually tracked product
+/// instances, the store each is held at, and their history of receipts,
+/// sales, returns and warranty claims.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SerialNumber::Table)
+                    .engine_if_supported(manager, "InnoDB")
+                    .col(
+                        ColumnDef::new(SerialNumber::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SerialNumber::TenantId).string().not_null())
+                    .col(ColumnDef::new(SerialNumber::Serial).string().not_null())
+                    .col(ColumnDef::new(SerialNumber::ProductSku).string().not_null())
+                    .col(
+                        ColumnDef::new(SerialNumber::VariantCode)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(SerialNumber::StoreId).string().not_null())
+                    .col(ColumnDef::new(SerialNumber::Status).json().not_null())
+                    .col(ColumnDef::new(SerialNumber::History).json().not_null())
+                    .col(
+                        ColumnDef::new(SerialNumber::CreatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SerialNumber::UpdatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_serial")
+                    .table(SerialNumber::Table)
+                    .col(SerialNumber::TenantId)
+                    .col(SerialNumber::ProductSku)
+                    .col(SerialNumber::VariantCode)
+                    .col(SerialNumber::Serial)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SerialNumber::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum SerialNumber {
+    #[iden = "Serial"]
+    Table,
+    #[iden = "id"]
+    Id,
+    #[iden = "tenant_id"]
+    TenantId,
+    #[iden = "serial"]
+    Serial,
+    #[iden = "product_sku"]
+    ProductSku,
+    #[iden = "variant_code"]
+    VariantCode,
+    #[iden = "store_id"]
+    StoreId,
+    #[iden = "status"]
+    Status,
+    #[iden = "history"]
+    History,
+    #[iden = "created_at"]
+    CreatedAt,
+    #[iden = "updated_at"]
+    UpdatedAt,
+}
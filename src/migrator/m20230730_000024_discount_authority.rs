@@ -0,0 +1,73 @@
+use sea_orm::ConnectionTrait;
+use sea_orm_migration::prelude::*;
+use serde_json::{json, Value};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230730_000024_discount_authority"
+    }
+}
+
+/// Grants `ApplyDiscount` to every employee whose level predates it. Until
+/// then discounts were unlimited, so existing employees keep the greatest
+/// authority (100%) and tenants lower it as they see fit. Employees given
+/// an authority already are left as they are.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+
+        let select = Query::select()
+            .columns([Employee::Id, Employee::Level])
+            .from(Employee::Table)
+            .to_owned();
+
+        for row in db.query_all(backend.build(&select)).await? {
+            let id: String = row.try_get("", "id")?;
+            let level: Value = row.try_get("", "level")?;
+
+            let Value::Array(mut level) = level else {
+                continue;
+            };
+
+            let granted = level
+                .iter()
+                .any(|access| access["action"] == json!("ApplyDiscount"));
+
+            if granted {
+                continue;
+            }
+
+            level.push(json!({ "action": "ApplyDiscount", "authority": 100 }));
+
+            let update = Query::update()
+                .table(Employee::Table)
+                .value(Employee::Level, Value::Array(level))
+                .and_where(Expr::col(Employee::Id).eq(id))
+                .to_owned();
+
+            db.execute(backend.build(&update)).await?;
+        }
+
+        Ok(())
+    }
+
+    /// The authority granted cannot be told apart from one given since,
+    /// so is kept.
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum Employee {
+    #[iden = "Employee"]
+    Table,
+    #[iden = "id"]
+    Id,
+    #[iden = "level"]
+    Level,
+}
@@ -0,0 +1,101 @@
+use sea_orm::ConnectionTrait;
+use sea_orm_migration::prelude::*;
+use serde_json::Value;
+use std::collections::HashMap;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230730_000025_quote_sequence"
+    }
+}
+
+/// Holds the last quote number issued by each tenant, so numbers are never
+/// reissued. Tenants continue from the greatest number already quoted.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Tenants::Table)
+                    .add_column(
+                        ColumnDef::new(Tenants::QuoteSequence)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+
+        let select = Query::select()
+            .columns([Transactions::TenantId, Transactions::Quote])
+            .from(Transactions::Table)
+            .and_where(Expr::col(Transactions::Quote).is_not_null())
+            .to_owned();
+
+        let mut issued: HashMap<String, i64> = HashMap::new();
+
+        for row in db.query_all(backend.build(&select)).await? {
+            let tenant_id: String = row.try_get("", "tenant_id")?;
+            let quote: Value = row.try_get("", "quote")?;
+
+            let number = quote["number"]
+                .as_str()
+                .and_then(|number| number.trim_start_matches("Q-").parse::<i64>().ok());
+
+            if let Some(number) = number {
+                let last = issued.entry(tenant_id).or_default();
+                *last = (*last).max(number);
+            }
+        }
+
+        for (tenant_id, last) in issued {
+            let update = Query::update()
+                .table(Tenants::Table)
+                .value(Tenants::QuoteSequence, last)
+                .and_where(Expr::col(Tenants::TenantId).eq(tenant_id))
+                .to_owned();
+
+            db.execute(backend.build(&update)).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Tenants::Table)
+                    .drop_column(Tenants::QuoteSequence)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Tenants {
+    #[iden = "Tenants"]
+    Table,
+    #[iden = "tenant_id"]
+    TenantId,
+    #[iden = "quote_sequence"]
+    QuoteSequence,
+}
+
+#[derive(Iden)]
+pub enum Transactions {
+    #[iden = "Transactions"]
+    Table,
+    #[iden = "tenant_id"]
+    TenantId,
+    #[iden = "quote"]
+    Quote,
+}
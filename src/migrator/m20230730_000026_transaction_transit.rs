@@ -0,0 +1,95 @@
+use sea_orm::ConnectionTrait;
+use sea_orm_migration::prelude::*;
+use serde_json::Value;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230730_000026_transaction_transit"
+    }
+}
+
+/// Flags the transactions with an order in transit with a carrier, being
+/// those whose tracking is polled. The flag is cleared once every order
+/// has left transit.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Transactions::Table)
+                    .add_column(
+                        ColumnDef::new(Transactions::InTransit)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+
+        let select = Query::select()
+            .columns([Transactions::Id, Transactions::Products])
+            .from(Transactions::Table)
+            .and_where(Expr::col(Transactions::Void).is_null())
+            .to_owned();
+
+        for row in db.query_all(backend.build(&select)).await? {
+            let id: String = row.try_get("", "id")?;
+            let products: Value = row.try_get("", "products")?;
+
+            let Value::Array(orders) = products else {
+                continue;
+            };
+
+            let in_transit = orders.iter().any(|order| {
+                let status = &order["status"]["status"];
+                status["type"] == "transit" && status["value"]["carrier"].is_string()
+            });
+
+            if !in_transit {
+                continue;
+            }
+
+            let update = Query::update()
+                .table(Transactions::Table)
+                .value(Transactions::InTransit, true)
+                .and_where(Expr::col(Transactions::Id).eq(id))
+                .to_owned();
+
+            db.execute(backend.build(&update)).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Transactions::Table)
+                    .drop_column(Transactions::InTransit)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Transactions {
+    #[iden = "Transactions"]
+    Table,
+    #[iden = "id"]
+    Id,
+    #[iden = "products"]
+    Products,
+    #[iden = "void"]
+    Void,
+    #[iden = "in_transit"]
+    InTransit,
+}
@@ -0,0 +1,85 @@
+use sea_orm::ConnectionTrait;
+use sea_orm_migration::prelude::*;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230730_000027_collection_codes"
+    }
+}
+
+/// Replaces the collection code held by each pickup order with its hash,
+/// dropping the QR payload encoding it. Codes already issued to customers
+/// remain valid.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+
+        let select = Query::select()
+            .columns([Transactions::Id, Transactions::Products])
+            .from(Transactions::Table)
+            .to_owned();
+
+        for row in db.query_all(backend.build(&select)).await? {
+            let id: String = row.try_get("", "id")?;
+            let products: Value = row.try_get("", "products")?;
+
+            let Value::Array(mut orders) = products else {
+                continue;
+            };
+
+            let mut hashed = false;
+
+            for order in orders.iter_mut() {
+                let order_id = order["id"].as_str().unwrap_or_default().to_string();
+
+                let Some(collection) = order["collection"].as_object_mut() else {
+                    continue;
+                };
+
+                let Some(Value::String(code)) = collection.remove("code") else {
+                    continue;
+                };
+
+                let hash = Sha256::digest(format!("{}:{}", order_id, code.to_uppercase()));
+                collection.insert("code_hash".to_string(), json!(hex::encode(hash)));
+                collection.remove("qr_payload");
+                hashed = true;
+            }
+
+            if !hashed {
+                continue;
+            }
+
+            let update = Query::update()
+                .table(Transactions::Table)
+                .value(Transactions::Products, Value::Array(orders))
+                .and_where(Expr::col(Transactions::Id).eq(id))
+                .to_owned();
+
+            db.execute(backend.build(&update)).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Codes cannot be recovered from their hashes, so are kept hashed.
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum Transactions {
+    #[iden = "Transactions"]
+    Table,
+    #[iden = "id"]
+    Id,
+    #[iden = "products"]
+    Products,
+}
@@ -0,0 +1,44 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230730_000028_override_discount"
+    }
+}
+
+/// Records the greatest discount a discount override approves. Those
+/// issued before approve none.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ManagerOverride::Table)
+                    .add_column(ColumnDef::new(ManagerOverride::Discount).integer().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ManagerOverride::Table)
+                    .drop_column(ManagerOverride::Discount)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum ManagerOverride {
+    #[iden = "ManagerOverride"]
+    Table,
+    #[iden = "discount"]
+    Discount,
+}
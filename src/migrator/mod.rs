@@ -1,3 +1,4 @@
+use sea_orm::DbBackend;
 use sea_orm_migration::prelude::*;
 
 mod m20230730_000001_products;
@@ -11,9 +12,41 @@ mod m20230730_000008_promotion;
 mod m20230730_000009_kiosk;
 mod m20230730_000010_authrec;
 mod m20230730_000011_tenants;
+mod m20230730_000012_api_keys;
+mod m20230730_000013_session_metadata;
+mod m20230730_000014_revoked_tokens;
+mod m20230730_000015_mfa;
+mod m20230730_000016_manager_overrides;
+mod m20230730_000017_tenant_status;
+mod m20230730_000018_tenant_purge;
+mod m20230730_000019_import_jobs;
+mod m20230730_000020_external_references;
+mod m20230730_000021_transaction_void;
+mod m20230730_000022_transaction_quote;
+mod m20230730_000023_serials;
+mod m20230730_000024_discount_authority;
+mod m20230730_000025_quote_sequence;
+mod m20230730_000026_transaction_transit;
+mod m20230730_000027_collection_codes;
+mod m20230730_000028_override_discount;
 
 pub struct Migrator;
 
+/// Table options which only MySQL understands, such as the storage engine,
+/// are skipped on other backends (i.e. the SQLite databases used in testing).
+pub(crate) trait TableCreateBackendExt {
+    fn engine_if_supported(&mut self, manager: &SchemaManager, engine: &str) -> &mut Self;
+}
+
+impl TableCreateBackendExt for TableCreateStatement {
+    fn engine_if_supported(&mut self, manager: &SchemaManager, engine: &str) -> &mut Self {
+        match manager.get_database_backend() {
+            DbBackend::MySql => self.engine(engine.to_string()),
+            _ => self,
+        }
+    }
+}
+
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
@@ -29,6 +62,23 @@ impl MigratorTrait for Migrator {
             Box::new(m20230730_000009_kiosk::Migration),
             Box::new(m20230730_000010_authrec::Migration),
             Box::new(m20230730_000011_tenants::Migration),
+            Box::new(m20230730_000012_api_keys::Migration),
+            Box::new(m20230730_000013_session_metadata::Migration),
+            Box::new(m20230730_000014_revoked_tokens::Migration),
+            Box::new(m20230730_000015_mfa::Migration),
+            Box::new(m20230730_000016_manager_overrides::Migration),
+            Box::new(m20230730_000017_tenant_status::Migration),
+            Box::new(m20230730_000018_tenant_purge::Migration),
+            Box::new(m20230730_000019_import_jobs::Migration),
+            Box::new(m20230730_000020_external_references::Migration),
+            Box::new(m20230730_000021_transaction_void::Migration),
+            Box::new(m20230730_000022_transaction_quote::Migration),
+            Box::new(m20230730_000023_serials::Migration),
+            Box::new(m20230730_000024_discount_authority::Migration),
+            Box::new(m20230730_000025_quote_sequence::Migration),
+            Box::new(m20230730_000026_transaction_transit::Migration),
+            Box::new(m20230730_000027_collection_codes::Migration),
+            Box::new(m20230730_000028_override_discount::Migration),
         ]
     }
 }
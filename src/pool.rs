@@ -1,12 +1,13 @@
 #[cfg(feature = "process")]
-use crate::entities::{session, transactions};
+use crate::entities::{manager_overrides, revoked_tokens, session, transactions};
 #[cfg(feature = "process")]
 use crate::migrator::Migrator;
-use crate::SessionVariant;
-use crate::{example_employee, Customer, Kiosk, Product, Session, Store, Transaction};
+use crate::{ImportJob, ImportStatus, Tenant};
+#[cfg(feature = "process")]
+use crate::{Carrier, Transaction};
 #[cfg(feature = "process")]
 use async_trait::async_trait;
-use chrono::{Days, Duration as ChronoDuration, Utc};
+use chrono::Utc;
 #[cfg(feature = "process")]
 use dotenv::dotenv;
 use rocket::request;
@@ -18,15 +19,26 @@ use rocket_okapi::gen::OpenApiGenerator;
 use rocket_okapi::request::{OpenApiFromRequest, RequestHeaderInput};
 use sea_orm::DatabaseConnection;
 #[cfg(feature = "process")]
-use sea_orm::{ColumnTrait, ConnectOptions, DbConn, EntityTrait, QuerySelect};
+use sea_orm::{ColumnTrait, ConnectOptions, DbConn, EntityTrait, QueryFilter};
 #[cfg(feature = "process")]
 use sea_orm_migration::prelude::*;
 #[cfg(feature = "process")]
 use sea_orm_rocket::rocket::figment::Figment;
-use std::{env, fs, sync::Arc, time::Duration};
+use std::{env, fs, path::Path, sync::Arc, time::Duration};
 use serde::de::Error;
 #[cfg(feature = "process")]
 use tokio::sync::Mutex;
+use tracing::{debug, error, info};
+
+#[cfg(feature = "process")]
+use crate::config::{GcConfig, IngressConfig, ShippingConfig};
+#[cfg(feature = "process")]
+use crate::logging::redact_url;
+#[cfg(feature = "process")]
+use crate::metrics::{
+    self, INGEST_JOBS, SESSIONS_CULLED, SESSION_GARBAGE_COLLECTOR, SESSION_INGRESS_WORKER,
+    SHIPPING_TRACKING_WORKER, TENANTS_PURGED, TRANSACTIONS_CULLED,
+};
 
 #[cfg(feature = "process")]
 #[derive(Database, Debug)]
@@ -89,42 +101,15 @@ impl rocket_db_pools::Pool for RocketDbPool {
 
     type Error = DbErr;
 
-    async fn init(_: &Figment) -> Result<Self, Self::Error> {
-        dotenv().ok();
-
-        let database_url = match env::var("DATABASE_URL") {
-            Ok(url) => url,
-            Err(err) => {
-                panic!(
-                    "Was unable to initialize, could not determine the database url. Reason: {}",
-                    err
-                )
-            }
-        };
-
-        println!("Database URL: {}", database_url);
-
-        let mut options = ConnectOptions::new(database_url);
-        options.idle_timeout(Duration::new(3600, 0));
-        options.acquire_timeout(Duration::new(3600, 0));
-        options.connect_timeout(Duration::new(3600, 0));
-        options.min_connections(1);
-        options.sqlx_logging(false);
+    async fn init(figment: &Figment) -> Result<Self, Self::Error> {
+        let mut conn = connect(figment).await?;
+        conn.set_metric_callback(metrics::observe_query);
 
-        let conn = sea_orm::Database::connect(options).await?;
-
-        // Perform all migrations to the DB
-        Migrator::up(&conn, None).await?;
-
-        let c2 = conn.clone();
-        tokio::spawn(async move {
-            session_garbage_collector(&c2).await;
-        });
-
-        let c3 = conn.clone();
-        tokio::spawn(async move {
-            session_ingress_worker(&c3).await;
-        });
+        // Migrations may instead be applied ahead of time through the
+        // `migrate` subcommand, in which case `run_migrations = false`.
+        if figment.extract_inner::<bool>("run_migrations").unwrap_or(true) {
+            Migrator::up(&conn, None).await?;
+        }
 
         Ok(RocketDbPool { conn })
     }
@@ -138,17 +123,60 @@ impl rocket_db_pools::Pool for RocketDbPool {
     }
 }
 
+/// Resolves the database url, an explicitly configured
+/// `databases.stock.url` takes precedence over the `DATABASE_URL`
+/// environment variable, allowing an ephemeral database to be used.
 #[cfg(feature = "process")]
-pub async fn session_ingress_worker(db: &DbConn) {
+pub fn database_url(figment: &Figment) -> Result<String, DbErr> {
+    dotenv().ok();
+
+    figment
+        .extract_inner::<String>("url")
+        .or_else(|_| env::var("DATABASE_URL"))
+        .map_err(|_| {
+            DbErr::Custom(
+                "Could not determine the database url, set `databases.stock.url` or `DATABASE_URL`."
+                    .to_string(),
+            )
+        })
+}
+
+/// Opens a connection for the `databases.stock` configuration.
+#[cfg(feature = "process")]
+pub async fn connect(figment: &Figment) -> Result<DbConn, DbErr> {
+    let database_url = database_url(figment)?;
+
+    info!(database_url = %redact_url(&database_url), "connecting to database");
+
+    let mut options = ConnectOptions::new(database_url);
+    options.idle_timeout(Duration::new(3600, 0));
+    options.acquire_timeout(Duration::new(3600, 0));
+    options.connect_timeout(Duration::new(3600, 0));
+    options.min_connections(1);
+    options.sqlx_logging(false);
+
+    sea_orm::Database::connect(options).await
+}
+
+#[cfg(feature = "process")]
+pub async fn session_ingress_worker(db: &DbConn, config: IngressConfig) {
     let currently_ingesting = Arc::new(Mutex::new(vec![]));
-    let mut interval = tokio::time::interval(Duration::from_secs(5));
+    let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs));
+
+    // Jobs interrupted by a restart would otherwise be left running, their
+    // files removed unread as the jobs have already been run.
+    if let Err(error) = ImportJob::fail_interrupted(db).await {
+        error!(error = ?error, "failed to mark interrupted import jobs failed");
+        metrics::task_error(SESSION_INGRESS_WORKER);
+    }
 
     loop {
         interval.tick().await;
 
-        if let Ok(dir) = fs::read_dir("./ingress/") {
+        if let Ok(dir) = fs::read_dir(&config.directory) {
             let found_files = dir
                 .map(|directory| directory.unwrap().path().to_str().unwrap().to_string())
+                .filter(|file| file.ends_with(".os"))
                 .collect::<Vec<String>>();
 
             let loop_ingest_clone = currently_ingesting.clone();
@@ -171,141 +199,207 @@ pub async fn session_ingress_worker(db: &DbConn) {
                                 // As we don't want to infinitely ingest the file,
                                 // if it cannot be deleted we shall preserve it as
                                 // continually being in the "currently_ingesting" state.
-                                println!("Failed to remove file after ingest; {}", error)
+                                error!(%file, %error, "failed to remove file after ingest");
+                                metrics::task_error(SESSION_INGRESS_WORKER);
                             }
                         }
                     });
                 }
             }
         }
+
+        metrics::task_heartbeat(SESSION_INGRESS_WORKER);
     }
 }
 
+/// Polls the carrier's tracking of every order in transit, progressing
+/// those delivered or which failed to be.
 #[cfg(feature = "process")]
-pub async fn ingest_file(db: &DbConn, file_path: String) {
-    // Read in the file to memory, hoping the memory is sufficient to do so.
-    let to_ingest = fs::read_to_string(file_path.clone());
+pub async fn shipping_tracking_worker(
+    db: &DbConn,
+    carrier: Arc<dyn Carrier>,
+    config: ShippingConfig,
+) {
+    let mut interval = tokio::time::interval(Duration::from_secs(config.poll_interval_secs));
 
-    if let Err(error) = to_ingest {
-        println!("Failed to ingest file, {}", error);
-        return;
-    }
+    loop {
+        interval.tick().await;
 
-    let objectified: (
-        Vec<Product>,
-        Vec<Customer>,
-        Vec<Transaction>,
-        Vec<Store>,
-        Vec<Kiosk>,
-    ) = match serde_json::from_str(&to_ingest.unwrap()) {
-        Ok(v) => v,
-        Err(e) => {
-            eprintln!("Caught error, {}", e);
-            return;
+        match Transaction::fetch_tracked(db).await {
+            Ok(tracked) => {
+                for (tenant_id, mut transaction) in tracked {
+                    match transaction.poll_tracking(carrier.as_ref()).await {
+                        Ok(false) => {}
+                        Ok(true) => {
+                            let id = transaction.id.clone();
+                            if let Err(err) = transaction.save_tracking(&tenant_id, db).await {
+                                error!(transaction_id = %id, error = ?err, "tracking update failed");
+                                metrics::task_error(SHIPPING_TRACKING_WORKER);
+                            }
+                        }
+                        Err(err) => {
+                            error!(transaction_id = %transaction.id, error = ?err, "tracking poll failed");
+                            metrics::task_error(SHIPPING_TRACKING_WORKER);
+                        }
+                    }
+                }
+            }
+            Err(err) => {
+                error!(error = ?err, "tracking poll failed");
+                metrics::task_error(SHIPPING_TRACKING_WORKER);
+            }
         }
-    };
-
-    let file_ending = file_path.split('/').last();
 
-    if file_ending.is_none() {
-        return;
+        metrics::task_heartbeat(SHIPPING_TRACKING_WORKER);
     }
+}
 
-    let (tenant_id, _date_saved) = file_ending.unwrap().split_once('_').unwrap();
-
-    let default_employee = example_employee();
-
-    let session = Session {
-        id: String::new(),
-        key: String::new(),
-        employee: default_employee.into(),
-        expiry: Utc::now().checked_add_days(Days::new(1)).unwrap(),
-        tenant_id: tenant_id.to_string().clone(),
-        variant: SessionVariant::AccessToken,
+/// Runs the import job the file was uploaded for, the file being named by
+/// the job's id.
+#[cfg(feature = "process")]
+pub async fn ingest_file(db: &DbConn, file_path: String) {
+    let Some(job_id) = Path::new(&file_path).file_stem().and_then(|stem| stem.to_str()) else {
+        return;
     };
 
-    for store in objectified.3 {
-        let _ = Store::insert(store, session.clone(), db).await;
-    }
-
-    for product in objectified.0 {
-        let _ = Product::insert(product, session.clone(), db).await;
-    }
-
-    for customer in objectified.1 {
-        let _ = Customer::insert_raw(customer, session.clone(), db).await;
-    }
-
-    for transaction in objectified.2 {
-        let _ = Transaction::insert_raw(transaction, session.clone(), db).await;
-    }
+    // Read in the file to memory, hoping the memory is sufficient to do so.
+    let to_ingest = match fs::read_to_string(file_path.clone()) {
+        Ok(contents) => contents,
+        Err(error) => {
+            error!(%file_path, %error, "failed to read ingest file");
+            INGEST_JOBS.with_label_values(&["failed"]).inc();
+
+            let message = format!("Unable to read import file, {}", error);
+            if let Err(error) = ImportJob::abandon(job_id, message, db).await {
+                error!(%file_path, error = ?error, "failed to mark import job failed");
+            }
+            return;
+        }
+    };
 
-    for kiosk in objectified.4 {
-        let _ = Kiosk::insert_raw(kiosk, session.clone(), db).await;
+    match ImportJob::run(job_id, &to_ingest, db).await {
+        Ok(job) if job.status == ImportStatus::Completed => {
+            INGEST_JOBS.with_label_values(&["completed"]).inc();
+        }
+        Ok(_) => {
+            INGEST_JOBS.with_label_values(&["failed"]).inc();
+        }
+        Err(error) => {
+            error!(%file_path, error = ?error, "failed to run import job");
+            INGEST_JOBS.with_label_values(&["failed"]).inc();
+        }
     }
 }
 
 #[cfg(feature = "process")]
-pub async fn session_garbage_collector(db: &DbConn) {
-    let mut interval = tokio::time::interval(Duration::from_secs(5));
+pub async fn session_garbage_collector(db: &DbConn, config: GcConfig) {
+    let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs));
 
     loop {
         interval.tick().await;
 
         match session::Entity::find()
-            .having(session::Column::Expiry.lte(Utc::now().naive_utc()))
+            .filter(session::Column::Expiry.lte(Utc::now().naive_utc()))
             .all(db)
             .await
         {
             Ok(data) => {
                 for model in data {
                     // Delete all model instances of sessions which have surpassed their existence time-frame.
-                    match session::Entity::delete_by_id(model.id).exec(db).await {
-                        Ok(data) => {
-                            println!("[log]: Culled session {:?}", data)
+                    match session::Entity::delete_by_id(model.id.clone()).exec(db).await {
+                        Ok(_) => {
+                            debug!(session_id = %model.id, "culled expired session");
+                            SESSIONS_CULLED.inc();
                         }
                         Err(err) => {
-                            println!("[err]: Error in scheduled cron task: {:?}", err)
+                            error!(error = %err, "session garbage collection failed");
+                            metrics::task_error(SESSION_GARBAGE_COLLECTOR);
                         }
                     }
                 }
             }
             Err(err) => {
-                println!("[err]: Error in scheduled cron task: {:?}", err)
+                error!(error = %err, "session garbage collection failed");
+                metrics::task_error(SESSION_GARBAGE_COLLECTOR);
             }
         };
 
-        let time = Utc::now().checked_sub_signed(ChronoDuration::seconds(3600));
+        // Revocations are only needed until the tokens they cover expire.
+        if let Err(err) = revoked_tokens::Entity::delete_many()
+            .filter(revoked_tokens::Column::ExpiresAt.lte(Utc::now().naive_utc()))
+            .exec(db)
+            .await
+        {
+            error!(error = %err, "token revocation culling failed");
+            metrics::task_error(SESSION_GARBAGE_COLLECTOR);
+        }
+
+        // Used overrides are kept on the transaction's notes and the kiosk's
+        // `AuthRecord`, so lapsed tokens need not be.
+        if let Err(err) = manager_overrides::Entity::delete_many()
+            .filter(manager_overrides::Column::Expiry.lte(Utc::now().naive_utc()))
+            .exec(db)
+            .await
+        {
+            error!(error = %err, "manager override culling failed");
+            metrics::task_error(SESSION_GARBAGE_COLLECTOR);
+        }
+
+        match Tenant::fetch_due_for_purge(db).await {
+            Ok(tenants) => {
+                for tenant in tenants {
+                    match Tenant::purge(&tenant.tenant_id, db).await {
+                        Ok(_) => TENANTS_PURGED.inc(),
+                        Err(err) => {
+                            error!(tenant_id = %tenant.tenant_id, error = ?err, "tenant purge failed");
+                            metrics::task_error(SESSION_GARBAGE_COLLECTOR);
+                        }
+                    }
+                }
+            }
+            Err(err) => {
+                error!(error = ?err, "tenant purge failed");
+                metrics::task_error(SESSION_GARBAGE_COLLECTOR);
+            }
+        }
+
+        let time = Utc::now().checked_sub_signed(config.saved_transaction_ttl());
 
         match time {
             Some(val) => {
                 match transactions::Entity::find()
-                    .having(transactions::Column::TransactionType.eq("saved"))
-                    .having(transactions::Column::OrderDate.lte(val.naive_utc()))
+                    .filter(transactions::Column::TransactionType.eq("saved"))
+                    .filter(transactions::Column::OrderDate.lte(val.naive_utc()))
                     .all(db)
                     .await
                 {
                     Ok(data) => {
                         for model in data {
                             // Delete all model instances of sessions which have surpassed their existence time-frame.
-                            match transactions::Entity::delete_by_id(model.id).exec(db).await {
+                            match transactions::Entity::delete_by_id(model.id.clone()).exec(db).await {
                                 Ok(_data) => {
-                                    println!("[log]: Culled transaction")
+                                    debug!(transaction_id = %model.id, "culled saved transaction");
+                                    TRANSACTIONS_CULLED.inc();
                                 }
                                 Err(err) => {
-                                    println!("[err]: Error in scheduled cron task: {:?}", err)
+                                    error!(error = %err, "saved transaction culling failed");
+                                    metrics::task_error(SESSION_GARBAGE_COLLECTOR);
                                 }
                             }
                         }
                     }
                     Err(err) => {
-                        println!("[err]: Error in scheduled cron task: {:?}", err)
+                        error!(error = %err, "saved transaction culling failed");
+                        metrics::task_error(SESSION_GARBAGE_COLLECTOR);
                     }
                 };
             }
             None => {
-                println!("[err]: Error in cron task: Unable to format DateTime")
+                error!("saved transaction culling failed, unable to format DateTime");
+                metrics::task_error(SESSION_GARBAGE_COLLECTOR);
             }
         };
+
+        metrics::task_heartbeat(SESSION_GARBAGE_COLLECTOR);
     }
 }
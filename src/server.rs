@@ -0,0 +1,168 @@
+use crate::catchers;
+use crate::config::AppConfig;
+use crate::health;
+use crate::logging::{self, LoggingConfig, RequestTracer};
+use crate::methods;
+use crate::methods::ShippingCarrier;
+use crate::metrics::{self, RequestMetrics};
+use crate::migrator::Migrator;
+use crate::pool::{
+    self, session_garbage_collector, session_ingress_worker, shipping_tracking_worker, Db,
+};
+use rocket::figment::Figment;
+use rocket::http::{Header, Method, Status};
+use rocket::{
+    catchers,
+    fairing::{AdHoc, Fairing, Info, Kind},
+    routes, Build, Request, Response, Rocket,
+};
+use rocket_db_pools::Database;
+use rocket_okapi::mount_endpoints_and_merged_docs;
+use rocket_okapi::swagger_ui::{make_swagger_ui, SwaggerUIConfig};
+use sea_orm::DbErr;
+use sea_orm_migration::MigratorTrait;
+use tracing::info;
+
+pub struct CORS;
+
+#[rocket::async_trait]
+impl Fairing for CORS {
+    fn info(&self) -> Info {
+        Info {
+            name: "Add CORS headers to responses",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let allowed = request.rocket().state::<AppConfig>().and_then(|config| {
+            config
+                .cors
+                .allow_origin(request.headers().get_one("Origin"))
+        });
+
+        if let Some(origin) = allowed {
+            response.set_header(Header::new("Access-Control-Allow-Origin", origin));
+        }
+
+        response.set_header(Header::new("Vary", "Origin"));
+        response.set_header(Header::new(
+            "Access-Control-Allow-Methods",
+            "POST, GET, PATCH, OPTIONS",
+        ));
+        response.set_header(Header::new("Access-Control-Allow-Headers", "*"));
+        response.set_header(Header::new("Access-Control-Expose-Headers", "*"));
+        response.set_header(Header::new("Access-Control-Allow-Credentials", "true"));
+
+        if request.method() == Method::Options {
+            response.set_status(Status::Ok);
+            response.set_header(Header::new(
+                "Access-Control-Allow-Headers",
+                "Content-Type, Referer, *",
+            ));
+        }
+    }
+}
+
+/// Builds the server from the default configuration sources
+/// (`Rocket.toml`, `ROCKET_*` environment variables).
+pub fn rocket() -> Rocket<Build> {
+    dotenv::dotenv().ok();
+    rocket_from_figment(rocket::Config::figment())
+}
+
+/// Applies any pending migrations and exits, used by the `migrate`
+/// subcommand so that schema changes can be rolled out ahead of the
+/// servers (which then run with `databases.stock.run_migrations = false`).
+pub async fn migrate() -> Result<(), DbErr> {
+    dotenv::dotenv().ok();
+
+    let figment = rocket::Config::figment();
+    logging::init(&LoggingConfig::from_figment(&figment));
+
+    let conn = pool::connect(&figment.focus("databases.stock")).await?;
+    let pending = Migrator::get_pending_migrations(&conn).await?;
+
+    info!(pending = pending.len(), "applying migrations");
+    Migrator::up(&conn, None).await?;
+
+    conn.close().await
+}
+
+/// Builds the server from a caller-provided configuration, this is
+/// what the integration tests use to point the pool at an ephemeral
+/// database through `databases.stock.url`.
+pub fn rocket_from_figment(figment: Figment) -> Rocket<Build> {
+    logging::init(&LoggingConfig::from_figment(&figment));
+
+    // All non-documented items attached here.
+    let mut launcher = rocket::custom(figment)
+        .register(
+            "/",
+            catchers![
+                catchers::not_authorized,
+                catchers::internal_server_error,
+                catchers::not_found,
+                catchers::unprocessable_entry,
+                catchers::general_catcher,
+            ],
+        )
+        .attach(AppConfig::fairing())
+        .attach(RequestTracer)
+        .attach(RequestMetrics)
+        .attach(Db::init())
+        .attach(AdHoc::on_liftoff("Background workers", |rocket| {
+            Box::pin(async move {
+                let (Some(db), Some(config)) = (Db::fetch(rocket), rocket.state::<AppConfig>())
+                else {
+                    return;
+                };
+
+                let (conn, gc) = (db.conn.clone(), config.gc.clone());
+                rocket::tokio::spawn(async move { session_garbage_collector(&conn, gc).await });
+
+                let (conn, ingress) = (db.conn.clone(), config.ingress.clone());
+                rocket::tokio::spawn(async move { session_ingress_worker(&conn, ingress).await });
+
+                if let Some(carrier) = rocket.state::<ShippingCarrier>().and_then(|c| c.0.clone()) {
+                    let (conn, shipping) = (db.conn.clone(), config.shipping.clone());
+                    rocket::tokio::spawn(async move {
+                        shipping_tracking_worker(&conn, carrier, shipping).await
+                    });
+                }
+            })
+        }))
+        .attach(CORS)
+        .mount("/", routes![metrics::metrics])
+        .mount("/health", health::routes())
+        .mount(
+            "/docs",
+            make_swagger_ui(&SwaggerUIConfig {
+                url: "../api/openapi.json".to_owned(),
+                ..Default::default()
+            }),
+        );
+
+    let openapi_settings = rocket_okapi::settings::OpenApiSettings::default();
+
+    mount_endpoints_and_merged_docs! {
+        launcher, "/api".to_owned(), openapi_settings,
+        "/api_key" => logging::traced(methods::api_key::handlers::documented_routes(&openapi_settings)),
+        "/store" => logging::traced(methods::store::handlers::documented_routes(&openapi_settings)),
+        "/kiosk" => logging::traced(methods::kiosk::handlers::documented_routes(&openapi_settings)),
+        "/ingress" => logging::traced(methods::ingress::handlers::documented_routes(&openapi_settings)),
+        "/mfa" => logging::traced(methods::mfa::handlers::documented_routes(&openapi_settings)),
+        "/tenant" => logging::traced(methods::tenant::handlers::documented_routes(&openapi_settings)),
+        "/override" => logging::traced(methods::overrides::handlers::documented_routes(&openapi_settings)),
+        "/shipping" => logging::traced(methods::shipping::handlers::documented_routes(&openapi_settings)),
+        "/product" => logging::traced(methods::product::handlers::documented_routes(&openapi_settings)),
+        "/serial" => logging::traced(methods::serial::handlers::documented_routes(&openapi_settings)),
+        "/customer" => logging::traced(methods::customer::handlers::documented_routes(&openapi_settings)),
+        "/employee" => logging::traced(methods::employee::handlers::documented_routes(&openapi_settings)),
+        "/supplier" => logging::traced(methods::supplier::handlers::documented_routes(&openapi_settings)),
+        "/helpers" => logging::traced(methods::helpers::handlers::documented_routes(&openapi_settings)),
+        "/transaction" => logging::traced(methods::transaction::handlers::documented_routes(&openapi_settings)),
+    }
+
+    launcher
+}
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use rocket::http::{Cookie, CookieJar, SameSite};
+use rocket::time::OffsetDateTime;
+use rocket::tokio::sync::RwLock;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{ColumnTrait, DbConn, DbErr, EntityTrait, QueryFilter, Set};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::entities::revoked_tokens;
+use crate::methods::{
+    Access, AccountType, Action, ContactInformation, Employee, EmployeeAuth, Name, Session,
+    SessionVariant,
+};
+
+pub const TOKEN_COOKIE: &str = "os-stock-token";
+
+/// The claims of a signed access token. These are stored in a private
+/// cookie, which Rocket encrypts and authenticates with its `secret_key`,
+/// so they can be trusted without a database lookup.
+#[derive(Serialize, Deserialize)]
+pub struct SignedSession {
+    /// The access session the token was issued alongside, which is also
+    /// the family any refresh tokens derived from it belong to.
+    pub sid: String,
+    pub tenant_id: String,
+    pub employee: EmployeeClaims,
+    /// Issued at, in seconds since the epoch.
+    pub iat: i64,
+    pub exp: i64,
+}
+
+/// A snapshot of the employee at issuance. The clock history and the
+/// password hash are not carried.
+#[derive(Serialize, Deserialize)]
+pub struct EmployeeClaims {
+    pub id: String,
+    pub rid: String,
+    pub name: Name,
+    pub contact: ContactInformation,
+    pub level: Vec<Access<Action>>,
+    pub account_type: AccountType,
+}
+
+impl SignedSession {
+    pub fn new(
+        session_id: &str,
+        tenant_id: &str,
+        employee: Employee,
+        expiry: DateTime<Utc>,
+    ) -> Self {
+        SignedSession {
+            sid: session_id.to_string(),
+            tenant_id: tenant_id.to_string(),
+            employee: EmployeeClaims {
+                id: employee.id,
+                rid: employee.rid,
+                name: employee.name,
+                contact: employee.contact,
+                level: employee.level,
+                account_type: employee.account_type,
+            },
+            iat: Utc::now().timestamp(),
+            exp: expiry.timestamp(),
+        }
+    }
+
+    /// Assigns the token as a private cookie, expiring with the access
+    /// session it was issued for.
+    pub fn assign(&self, cookies: &CookieJar<'_>) {
+        let value = match serde_json::to_string(self) {
+            Ok(value) => value,
+            Err(error) => {
+                warn!(%error, "unable to encode signed session");
+                return;
+            }
+        };
+
+        let expiry = OffsetDateTime::from_unix_timestamp(self.exp)
+            .unwrap_or_else(|_| OffsetDateTime::now_utc());
+
+        cookies.add_private(
+            Cookie::build((TOKEN_COOKIE, value))
+                .expires(expiry)
+                .path("/")
+                .secure(true)
+                .same_site(SameSite::None)
+                .http_only(true)
+                .build(),
+        );
+    }
+
+    pub fn remove(cookies: &CookieJar<'_>) {
+        cookies.remove_private(TOKEN_COOKIE);
+    }
+
+    /// Reads the token from the cookie jar, yielding `None` when it is
+    /// absent, has been tampered with or has expired.
+    pub fn from_cookies(cookies: &CookieJar<'_>) -> Option<Self> {
+        let cookie = cookies.get_private(TOKEN_COOKIE)?;
+        let claims = serde_json::from_str::<SignedSession>(cookie.value()).ok()?;
+
+        (claims.exp > Utc::now().timestamp()).then_some(claims)
+    }
+
+    fn subjects(&self) -> [&str; 3] {
+        [&self.sid, &self.employee.id, &self.tenant_id]
+    }
+}
+
+impl From<SignedSession> for Session {
+    fn from(value: SignedSession) -> Self {
+        Session {
+            id: value.sid,
+            // The session key is never placed in the token.
+            key: String::new(),
+            employee: Employee {
+                id: value.employee.id,
+                rid: value.employee.rid,
+                name: value.employee.name,
+                auth: EmployeeAuth {
+                    hash: String::new(),
+                },
+                contact: value.employee.contact,
+                clock_history: vec![],
+                level: value.employee.level,
+                account_type: value.employee.account_type,
+                created_at: Default::default(),
+                updated_at: Default::default(),
+            },
+            expiry: DateTime::from_timestamp(value.exp, 0).unwrap_or_default(),
+            tenant_id: value.tenant_id,
+            variant: SessionVariant::AccessToken,
+        }
+    }
+}
+
+/// Records that every signed token issued for `subject` (a session family,
+/// an employee or a tenant) up to now is revoked. The entry is kept until
+/// `expires_at`, after which any such token has expired regardless.
+pub async fn revoke_subject(
+    subject: &str,
+    tenant_id: &str,
+    expires_at: NaiveDateTime,
+    db: &DbConn,
+) -> Result<(), DbErr> {
+    revoked_tokens::Entity::insert(revoked_tokens::ActiveModel {
+        subject: Set(subject.to_string()),
+        tenant_id: Set(tenant_id.to_string()),
+        revoked_at: Set(Utc::now().naive_utc()),
+        expires_at: Set(expires_at),
+    })
+    .on_conflict(
+        OnConflict::column(revoked_tokens::Column::Subject)
+            .update_columns([
+                revoked_tokens::Column::RevokedAt,
+                revoked_tokens::Column::ExpiresAt,
+            ])
+            .to_owned(),
+    )
+    .exec(db)
+    .await
+    .map(|_| ())
+}
+
+#[derive(Default)]
+struct RevocationList {
+    synced_at: Option<Instant>,
+    revoked: HashMap<String, NaiveDateTime>,
+}
+
+/// An in-memory copy of the `RevokedToken` table, refreshed at most once
+/// per `sessions.revocation_sync_secs` so that verifying a signed token
+/// does not otherwise touch the database.
+pub struct Revocations {
+    interval: Duration,
+    list: RwLock<RevocationList>,
+}
+
+impl Revocations {
+    pub fn new(interval: Duration) -> Self {
+        Revocations {
+            interval,
+            list: RwLock::new(RevocationList::default()),
+        }
+    }
+
+    async fn sync(&self, db: &DbConn) -> Result<(), DbErr> {
+        let stale = |list: &RevocationList| {
+            list.synced_at
+                .is_none_or(|synced_at| synced_at.elapsed() >= self.interval)
+        };
+
+        if !stale(&*self.list.read().await) {
+            return Ok(());
+        }
+
+        let mut list = self.list.write().await;
+
+        // Another request may have synchronised whilst we awaited the lock.
+        if !stale(&list) {
+            return Ok(());
+        }
+
+        let revoked = revoked_tokens::Entity::find()
+            .filter(revoked_tokens::Column::ExpiresAt.gt(Utc::now().naive_utc()))
+            .all(db)
+            .await?;
+
+        debug!(entries = revoked.len(), "synchronised token revocations");
+
+        list.revoked = revoked
+            .into_iter()
+            .map(|entry| (entry.subject, entry.revoked_at))
+            .collect();
+        list.synced_at = Some(Instant::now());
+
+        Ok(())
+    }
+
+    /// A token is revoked when its session, employee or tenant was revoked
+    /// at or after the time it was issued.
+    pub async fn is_revoked(&self, claims: &SignedSession, db: &DbConn) -> Result<bool, DbErr> {
+        self.sync(db).await?;
+
+        let list = self.list.read().await;
+
+        Ok(claims.subjects().iter().any(|subject| {
+            list.revoked
+                .get(*subject)
+                .is_some_and(|revoked_at| revoked_at.and_utc().timestamp() >= claims.iat)
+        }))
+    }
+
+    /// Resolves the signed token presented with the request, if any, into
+    /// a session. `None` defers to the session cookie.
+    pub async fn verify(&self, cookies: &CookieJar<'_>, db: &DbConn) -> Option<Session> {
+        let claims = SignedSession::from_cookies(cookies)?;
+
+        match self.is_revoked(&claims, db).await {
+            Ok(false) => Some(claims.into()),
+            Ok(true) => None,
+            Err(error) => {
+                warn!(%error, "unable to synchronise token revocations");
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,2879 @@
+mod common;
+
+use chrono::{Duration, Utc};
+use common::{TenantFixture, TestApp, PASSWORD};
+use open_stock::entities::{customer, employee, import_jobs, transactions};
+use open_stock::server::rocket_from_figment;
+use open_stock::{
+    session, tenants, AwaitingCollection, ContactInformation, Customer, CustomerExport,
+    DiscountValue, Employee, ExpiringLot, ExternalReference, ImportCount, ImportJob, ImportStatus,
+    IssuedCollection, Kiosk, Migrator, OrderStatus, PaymentStatus, PickList, PickStatus,
+    PriceMismatchAction, Product, ProductVisibility, Promotion, RateQuote, ReversalOutcome,
+    SerialEventKind, SerialNumber, SerialStatus, ShopifyImport, TaxMode, Tenant, TenantExport,
+    TenantSettings, Transaction, TransactionType, EXPORT_VERSION, IMPORT_FORMAT_VERSION,
+    SHOPIFY_SOURCE, SHOPIFY_TRANSACTION,
+};
+use rocket::error::ErrorKind;
+use rocket::http::{ContentType, Header, Status};
+use rocket::local::asynchronous::Client;
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
+use sea_orm_migration::MigratorTrait;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+#[rocket::async_test]
+async fn unauthenticated_requests_are_rejected() {
+    let app = TestApp::new().await;
+
+    let response = app.client.get("/api/employee/").dispatch().await;
+
+    assert_eq!(response.status(), Status::Unauthorized);
+}
+
+#[rocket::async_test]
+async fn session_fixture_authenticates() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+
+    let response = app
+        .client
+        .get("/api/employee/")
+        .cookie(tenant.cookie())
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let employee: Employee = response.into_json().await.unwrap();
+    assert_eq!(employee.id, tenant.employee.id);
+}
+
+#[rocket::async_test]
+async fn password_login_issues_session_cookie() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+
+    let response = app
+        .client
+        .post(format!("/api/employee/auth/{}", tenant.employee.id))
+        .header(ContentType::JSON)
+        .body(
+            json!({
+                "pass": PASSWORD,
+                "kiosk_id": tenant.kiosk.id,
+                "tenant_id": tenant.tenant.tenant_id,
+            })
+            .to_string(),
+        )
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+    assert!(response.cookies().get(common::SESSION_COOKIE).is_some());
+
+    // The tracked client now carries the issued cookie.
+    let response = app.client.get("/api/employee/").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+}
+
+#[rocket::async_test]
+async fn wrong_password_is_rejected() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+
+    let response = app
+        .client
+        .post(format!("/api/employee/auth/{}", tenant.employee.id))
+        .header(ContentType::JSON)
+        .body(
+            json!({
+                "pass": "not-the-password",
+                "kiosk_id": tenant.kiosk.id,
+                "tenant_id": tenant.tenant.tenant_id,
+            })
+            .to_string(),
+        )
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Unauthorized);
+}
+
+#[rocket::async_test]
+async fn tenants_are_isolated() {
+    let app = TestApp::new().await;
+    let tenant_a = app.seed_tenant("TENANT_A").await;
+    let tenant_b = app.seed_tenant("TENANT_B").await;
+
+    let response = app
+        .client
+        .get(format!("/api/customer/{}", tenant_a.customer.id))
+        .cookie(tenant_a.cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let customer: Customer = response.into_json().await.unwrap();
+    assert_eq!(customer.id, tenant_a.customer.id);
+
+    let response = app
+        .client
+        .get(format!("/api/customer/{}", tenant_a.customer.id))
+        .cookie(tenant_b.cookie())
+        .dispatch()
+        .await;
+    assert_ne!(response.status(), Status::Ok);
+}
+
+#[rocket::async_test]
+async fn catalogue_is_seeded() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+    let (_, products) = app.seed_catalogue(&tenant).await;
+
+    let sku = &products.first().expect("products should be seeded").sku;
+
+    let response = app
+        .client
+        .get(format!("/api/product/{}", sku))
+        .cookie(tenant.cookie())
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+    let product: Product = response.into_json().await.unwrap();
+    assert_eq!(&product.sku, sku);
+}
+
+#[rocket::async_test]
+async fn responses_carry_request_id() {
+    let app = TestApp::new().await;
+
+    let response = app.client.get("/api/employee/").dispatch().await;
+    let assigned = response.headers().get_one("X-Request-Id").unwrap();
+    assert!(uuid::Uuid::parse_str(assigned).is_ok());
+
+    let provided = uuid::Uuid::new_v4().to_string();
+    let response = app
+        .client
+        .get("/api/employee/")
+        .header(Header::new("X-Request-Id", provided.clone()))
+        .dispatch()
+        .await;
+
+    assert_eq!(
+        response.headers().get_one("X-Request-Id"),
+        Some(provided.as_str())
+    );
+}
+
+#[rocket::async_test]
+async fn metrics_are_exposed() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+
+    app.client
+        .post(format!("/api/employee/auth/{}", tenant.employee.id))
+        .header(ContentType::JSON)
+        .body(
+            json!({
+                "pass": "not-the-password",
+                "kiosk_id": tenant.kiosk.id,
+                "tenant_id": tenant.tenant.tenant_id,
+            })
+            .to_string(),
+        )
+        .dispatch()
+        .await;
+
+    let response = app.client.get("/metrics").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_string().await.unwrap();
+    assert!(body.contains(r#"route="/api/employee/auth/<id>""#));
+    assert!(body.contains(r#"login_attempts_total{outcome="failure"}"#));
+    assert!(body.contains(r#"errors_total{variant="Unauthorized"}"#));
+    assert!(body.contains("db_query_duration_seconds"));
+}
+
+#[rocket::async_test]
+async fn health_reports_readiness() {
+    let app = TestApp::new().await;
+
+    let response = app.client.get("/health/live").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = app.client.get("/health/ready").dispatch().await;
+    let status = response.status();
+    let readiness: Value = response.into_json().await.unwrap();
+
+    assert_eq!(readiness["database"]["healthy"], true);
+    assert_eq!(readiness["migrations"]["healthy"], true);
+    assert_eq!(readiness["geocoder"]["critical"], false);
+    assert_eq!(status == Status::Ok, readiness["ready"] == true);
+}
+
+#[rocket::async_test]
+async fn pending_migrations_are_not_ready() {
+    let app =
+        TestApp::configured(|figment| figment.merge(("databases.stock.run_migrations", false)))
+            .await;
+
+    let response = app.client.get("/health/ready").dispatch().await;
+    assert_eq!(response.status(), Status::ServiceUnavailable);
+
+    let readiness: Value = response.into_json().await.unwrap();
+    assert_eq!(readiness["migrations"]["healthy"], false);
+}
+
+#[rocket::async_test]
+async fn cors_allows_configured_origins() {
+    let app = TestApp::configured(|figment| {
+        figment.merge((
+            "cors.allowed_origins",
+            ["https://pos.example.com", "https://admin.example.com"],
+        ))
+    })
+    .await;
+
+    let response = app
+        .client
+        .get("/health/live")
+        .header(Header::new("Origin", "https://admin.example.com"))
+        .dispatch()
+        .await;
+
+    assert_eq!(
+        response.headers().get_one("Access-Control-Allow-Origin"),
+        Some("https://admin.example.com")
+    );
+
+    let response = app
+        .client
+        .get("/health/live")
+        .header(Header::new("Origin", "https://elsewhere.example.com"))
+        .dispatch()
+        .await;
+
+    assert_eq!(
+        response.headers().get_one("Access-Control-Allow-Origin"),
+        None
+    );
+}
+
+#[rocket::async_test]
+async fn invalid_configuration_aborts_ignition() {
+    let figment = rocket::Config::figment()
+        .merge(("databases.stock.url", "sqlite::memory:"))
+        .merge(("log_level", "off"))
+        .merge(("logging.level", "off"))
+        .merge(("sessions.access_ttl_minutes", 0));
+
+    match Client::tracked(rocket_from_figment(figment)).await {
+        Err(error) => assert!(matches!(error.kind(), ErrorKind::FailedFairings(_))),
+        Ok(_) => panic!("an invalid configuration should not ignite"),
+    }
+}
+
+#[rocket::async_test]
+async fn generate_requires_demo_mode() {
+    let app = TestApp::configured(|figment| figment.merge(("demo", false))).await;
+
+    let response = app.client.post("/api/helpers/generate").dispatch().await;
+
+    assert_eq!(response.status(), Status::InternalServerError);
+}
+
+#[rocket::async_test]
+async fn tenant_settings_are_read_and_updated() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+
+    let fetch = |cookie| {
+        app.client
+            .get("/api/tenant/settings")
+            .cookie(cookie)
+            .dispatch()
+    };
+
+    let response = fetch(tenant.cookie()).await;
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = fetch(tenant.manager_cookie()).await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let mut settings: TenantSettings = response.into_json().await.unwrap();
+    assert_eq!(settings.default_currency, "NZD");
+    assert_eq!(settings.tax_mode, TaxMode::Inclusive);
+
+    settings.business_name = "Surf Shop".to_string();
+    settings.tax_mode = TaxMode::Exclusive;
+    settings.receipt_footer = "Thanks for shopping with us!".to_string();
+    settings.sessions.access_ttl_minutes = Some(30);
+    // The MFA policy is updated only on its own route.
+    settings.mfa.require_for_managerial = true;
+
+    let update = |cookie, settings: &TenantSettings| {
+        app.client
+            .post("/api/tenant/settings")
+            .header(ContentType::JSON)
+            .cookie(cookie)
+            .body(json!(settings).to_string())
+            .dispatch()
+    };
+
+    let response = update(tenant.cookie(), &settings).await;
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = update(tenant.manager_cookie(), &settings).await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = app
+        .client
+        .get("/api/tenant/")
+        .cookie(tenant.cookie())
+        .dispatch()
+        .await;
+    let updated: Tenant = response.into_json().await.unwrap();
+    assert_eq!(updated.settings.business_name, "Surf Shop");
+    assert_eq!(updated.settings.tax_mode, TaxMode::Exclusive);
+    assert!(!updated.settings.mfa.require_for_managerial);
+    assert!(!updated.is_suspended());
+
+    let mut invalid = settings.clone();
+    invalid.default_currency = "DOLLARS".to_string();
+    let response = update(tenant.manager_cookie(), &invalid).await;
+    assert_ne!(response.status(), Status::Ok);
+
+    // Sessions are issued with the tenant's lifetime.
+    let response = app
+        .client
+        .post(format!("/api/employee/auth/{}", tenant.employee.id))
+        .header(ContentType::JSON)
+        .body(
+            json!({
+                "pass": PASSWORD,
+                "kiosk_id": tenant.kiosk.id,
+                "tenant_id": tenant.tenant.tenant_id,
+            })
+            .to_string(),
+        )
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let issued = session::Entity::find()
+        .filter(session::Column::EmployeeId.eq(tenant.employee.id.clone()))
+        .filter(session::Column::KioskId.eq(tenant.kiosk.id.clone()))
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    let lifetime = issued.expiry - Utc::now().naive_utc();
+    assert!(lifetime > Duration::minutes(25) && lifetime <= Duration::minutes(30));
+}
+
+#[rocket::async_test]
+async fn operators_suspend_and_reactivate_tenants() {
+    let app = TestApp::configured(|figment| figment.merge(("operator_tenant", "TENANT_A"))).await;
+    let operator = app.seed_tenant("TENANT_A").await;
+    let tenant = app.seed_tenant("TENANT_B").await;
+
+    // Super users of other tenants may only administer their own.
+    let response = app
+        .client
+        .get("/api/tenant/all")
+        .cookie(tenant.manager_cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = app
+        .client
+        .get("/api/tenant/all")
+        .cookie(operator.manager_cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let tenants: Vec<Tenant> = response.into_json().await.unwrap();
+    assert_eq!(tenants.len(), 2);
+
+    let response = app
+        .client
+        .post("/api/tenant/suspend/TENANT_B")
+        .cookie(operator.manager_cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let suspended: Tenant = response.into_json().await.unwrap();
+    assert!(suspended.is_suspended());
+
+    let response = app
+        .client
+        .get("/api/employee/")
+        .cookie(tenant.manager_cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let login = || {
+        app.client
+            .post(format!("/api/employee/auth/{}", tenant.employee.id))
+            .header(ContentType::JSON)
+            .body(
+                json!({
+                    "pass": PASSWORD,
+                    "kiosk_id": tenant.kiosk.id,
+                    "tenant_id": tenant.tenant.tenant_id,
+                })
+                .to_string(),
+            )
+            .dispatch()
+    };
+    assert_eq!(login().await.status(), Status::Unauthorized);
+
+    let response = app
+        .client
+        .post("/api/tenant/reactivate/TENANT_B")
+        .cookie(operator.manager_cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(login().await.status(), Status::Ok);
+
+    let response = app
+        .client
+        .post("/api/tenant/suspend/TENANT_A")
+        .cookie(operator.manager_cookie())
+        .dispatch()
+        .await;
+    assert_ne!(response.status(), Status::Ok);
+}
+
+#[rocket::async_test]
+async fn customers_are_exported_and_anonymised() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+    let customer = &tenant.customer;
+
+    // A shipment to the customer records their contact information.
+    let mut transaction = Transaction::generate(&app.db, &customer.id, tenant.session.clone())
+        .await
+        .unwrap();
+    transaction.products[0].destination.store_id = String::new();
+    transaction.products[0].destination.contact = customer.contact.clone();
+    let transaction_id = transaction.id.clone();
+    Transaction::update_value(
+        transaction,
+        tenant.session.clone(),
+        &transaction_id,
+        &app.db,
+    )
+    .await
+    .unwrap();
+
+    let response = app
+        .client
+        .get(format!("/api/customer/export/{}", customer.id))
+        .cookie(tenant.manager_cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let export: CustomerExport = response.into_json().await.unwrap();
+    assert_eq!(export.version, EXPORT_VERSION);
+    assert_eq!(export.customer.contact, customer.contact);
+    assert_eq!(export.transactions.len(), 1);
+    let order_total = export.transactions[0].order_total;
+
+    let response = app
+        .client
+        .post(format!("/api/customer/anonymise/{}", customer.id))
+        .cookie(tenant.cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = app
+        .client
+        .post(format!("/api/customer/anonymise/{}", customer.id))
+        .cookie(tenant.manager_cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let anonymised: Customer = response.into_json().await.unwrap();
+    assert_eq!(anonymised.contact, ContactInformation::anonymised());
+    assert!(anonymised.name.is_empty());
+    assert!(!anonymised.accepts_marketing);
+
+    let transaction = Transaction::fetch_by_id(&transaction_id, tenant.session.clone(), &app.db)
+        .await
+        .unwrap();
+    assert_eq!(transaction.order_total, order_total);
+    assert_eq!(
+        transaction.products[0].destination.contact,
+        ContactInformation::anonymised()
+    );
+    // Store locations are left as they were.
+    assert_ne!(
+        transaction.products[0].origin.contact,
+        ContactInformation::anonymised()
+    );
+}
+
+#[rocket::async_test]
+async fn tenants_are_exported_and_purged() {
+    let app = TestApp::configured(|figment| {
+        figment
+            .merge(("operator_tenant", "TENANT_A"))
+            .merge(("gc.interval_secs", 1))
+            .merge(("gc.tenant_purge_grace_secs", 0))
+    })
+    .await;
+    let operator = app.seed_tenant("TENANT_A").await;
+    let tenant = app.seed_tenant("TENANT_B").await;
+
+    let response = app
+        .client
+        .get("/api/tenant/export")
+        .cookie(tenant.manager_cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let export: TenantExport = response.into_json().await.unwrap();
+    assert_eq!(export.version, EXPORT_VERSION);
+    assert_eq!(export.tenant_id, "TENANT_B");
+
+    let employees = &export.tables["Employee"];
+    assert_eq!(employees.len(), 2);
+    assert!(employees
+        .iter()
+        .all(|employee| employee.get("auth").is_none()));
+    assert!(export.tables["Session"]
+        .iter()
+        .all(|session| session.get("key").is_none()));
+    assert_eq!(export.tables["Customer"].len(), 1);
+    assert_eq!(
+        export.tables["Customer"][0]["id"],
+        json!(tenant.customer.id)
+    );
+
+    // Only suspended tenants may be purged.
+    let purge = || {
+        app.client
+            .post("/api/tenant/purge/TENANT_B")
+            .cookie(operator.manager_cookie())
+            .dispatch()
+    };
+    assert_ne!(purge().await.status(), Status::Ok);
+
+    let response = app
+        .client
+        .post("/api/tenant/suspend/TENANT_B")
+        .cookie(operator.manager_cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = purge().await;
+    assert_eq!(response.status(), Status::Ok);
+    let scheduled: Tenant = response.into_json().await.unwrap();
+    assert!(scheduled.purge_after.is_some());
+
+    let mut purged = false;
+    for _ in 0..50 {
+        if tenants::Entity::find_by_id("TENANT_B")
+            .one(&app.db)
+            .await
+            .unwrap()
+            .is_none()
+        {
+            purged = true;
+            break;
+        }
+        rocket::tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    }
+    assert!(purged);
+
+    let remaining = customer::Entity::find()
+        .filter(customer::Column::TenantId.eq("TENANT_B"))
+        .all(&app.db)
+        .await
+        .unwrap();
+    assert!(remaining.is_empty());
+
+    let response = app
+        .client
+        .get("/api/customer/recent")
+        .cookie(operator.manager_cookie())
+        .dispatch()
+        .await;
+    let customers: Vec<Customer> = response.into_json().await.unwrap();
+    assert_eq!(customers.len(), 1);
+}
+
+#[rocket::async_test]
+async fn interrupted_import_jobs_are_failed() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+
+    let pending = ImportJob::create(tenant.manager_session.clone(), &app.db)
+        .await
+        .unwrap();
+    let running = ImportJob::create(tenant.manager_session.clone(), &app.db)
+        .await
+        .unwrap();
+    import_jobs::ActiveModel {
+        id: Set(running.id.clone()),
+        status: Set(json!(ImportStatus::Running)),
+        ..Default::default()
+    }
+    .update(&app.db)
+    .await
+    .unwrap();
+
+    assert_eq!(ImportJob::fail_interrupted(&app.db).await.unwrap(), 1);
+
+    let failed = ImportJob::fetch_by_id(&running.id, tenant.session.clone(), &app.db)
+        .await
+        .unwrap();
+    assert_eq!(failed.status, ImportStatus::Failed);
+    assert!(failed.errors[0].message.contains("interrupted"));
+
+    let pending = ImportJob::fetch_by_id(&pending.id, tenant.session.clone(), &app.db)
+        .await
+        .unwrap();
+    assert_eq!(pending.status, ImportStatus::Pending);
+}
+
+/// Polls an import job until the ingress worker has finished with it.
+async fn await_import(app: &TestApp, tenant: &TenantFixture, id: &str) -> ImportJob {
+    for _ in 0..50 {
+        let response = app
+            .client
+            .get(format!("/api/ingress/jobs/{}", id))
+            .cookie(tenant.manager_cookie())
+            .dispatch()
+            .await;
+        let job: ImportJob = response.into_json().await.unwrap();
+
+        if matches!(job.status, ImportStatus::Completed | ImportStatus::Failed) {
+            return job;
+        }
+        rocket::tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    }
+
+    panic!("import job did not finish");
+}
+
+#[rocket::async_test]
+async fn import_jobs_report_progress_and_row_errors() {
+    let directory = std::env::temp_dir().join(format!("open-stock-ingress-{}", Uuid::new_v4()));
+    let ingress = directory.clone();
+    let app = TestApp::configured(move |figment| {
+        figment
+            .merge(("ingress.directory", ingress))
+            .merge(("ingress.interval_secs", 1))
+    })
+    .await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+    let other = app.seed_tenant("TENANT_B").await;
+
+    let mut customer = serde_json::to_value(&tenant.customer).unwrap();
+    let customer_id = Uuid::new_v4().to_string();
+    customer["id"] = json!(customer_id);
+
+    let upload = |manifest: Value| {
+        app.client
+            .post("/api/ingress/upload")
+            .header(ContentType::Plain)
+            .cookie(tenant.manager_cookie())
+            .body(manifest.to_string())
+            .dispatch()
+    };
+    let response = upload(json!({
+        "version": IMPORT_FORMAT_VERSION,
+        "customers": [customer, { "id": "broken" }],
+    }))
+    .await;
+    assert_eq!(response.status(), Status::Ok);
+    let job: ImportJob = response.into_json().await.unwrap();
+    assert_eq!(job.status, ImportStatus::Pending);
+
+    let job = await_import(&app, &tenant, &job.id).await;
+    assert_eq!(job.status, ImportStatus::Completed);
+    assert_eq!(job.format_version, Some(IMPORT_FORMAT_VERSION));
+    assert_eq!(job.total_rows, 2);
+    assert_eq!(job.processed_rows, 2);
+    assert_eq!(job.succeeded_rows, 1);
+    assert_eq!(job.failed_rows, 1);
+    assert_eq!(job.errors.len(), 1);
+    assert_eq!(job.errors[0].collection, "customers");
+    assert_eq!(job.errors[0].index, 1);
+    assert_eq!(job.errors[0].id.as_deref(), Some("broken"));
+
+    // Records are imported for the uploading employee's tenant.
+    let imported = Customer::fetch_by_id(&customer_id, tenant.session.clone(), &app.db).await;
+    assert!(imported.is_ok());
+
+    let response = app
+        .client
+        .get(format!("/api/ingress/jobs/{}", job.id))
+        .cookie(other.manager_cookie())
+        .dispatch()
+        .await;
+    assert_ne!(response.status(), Status::Ok);
+
+    let response = upload(json!({ "version": IMPORT_FORMAT_VERSION + 1 })).await;
+    let job: ImportJob = response.into_json().await.unwrap();
+    let job = await_import(&app, &tenant, &job.id).await;
+    assert_eq!(job.status, ImportStatus::Failed);
+    assert_eq!(job.processed_rows, 0);
+    assert!(job.errors[0].message.contains("Unsupported import version"));
+
+    let _ = std::fs::remove_dir_all(directory);
+}
+
+/// Lists the SKUs of the tenant's products.
+async fn product_skus(app: &TestApp, tenant: &TenantFixture) -> Vec<String> {
+    Product::fetch_all(tenant.session.clone(), &app.db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|product| product.sku)
+        .collect()
+}
+
+#[rocket::async_test]
+async fn catalogue_csv_is_exported_and_imported() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+    let (stores, products) = app.seed_catalogue(&tenant).await;
+    let store = &stores.first().expect("stores should be seeded").code;
+
+    let response = app
+        .client
+        .get("/api/product/export/csv")
+        .cookie(tenant.cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.content_type(), Some(ContentType::CSV));
+    let exported = response.into_string().await.unwrap();
+    let header = exported.lines().next().unwrap();
+    assert!(header.starts_with("product_sku,product_name,"));
+    assert!(header.contains(&format!("stock:{}", store)));
+    for product in &products {
+        assert!(exported.contains(&format!("\n{},", product.sku)));
+    }
+
+    // The existing product is matched by its variant's barcode.
+    let catalogue = format!(
+        "product_sku,product_name,variant_options,barcode,retail_price,stock:{store},stock:NOPE\n\
+         RENAMED,Renamed Product,,51890723908812,99.5,,\n\
+         CSV-1,Imported Shirt,Colour=Black:01|Size=Small:21,,20,4,\n\
+         CSV-1,,Colour=Black:01|Size=Large:23,,22,,\n\
+         CSV-2,Misplaced,,,10,,3\n"
+    );
+    let import = |dry_run: bool| {
+        app.client
+            .post(format!("/api/product/import/csv?dry_run={}", dry_run))
+            .header(ContentType::CSV)
+            .cookie(tenant.manager_cookie())
+            .body(catalogue.clone())
+            .dispatch()
+    };
+
+    let response = import(true).await;
+    assert_eq!(response.status(), Status::Ok);
+    let diff: Value = response.into_json().await.unwrap();
+    assert_eq!(diff["dry_run"], json!(true));
+    assert_eq!(diff["products"][0]["sku"], json!("123456"));
+    assert_eq!(diff["products"][0]["action"], json!("Update"));
+    let changes = diff["products"][0]["changes"].as_array().unwrap();
+    assert!(changes
+        .iter()
+        .any(|change| change["field"] == json!("name")
+            && change["after"] == json!("Renamed Product")));
+    assert!(changes.iter().any(|change| {
+        change["field"].as_str().unwrap().ends_with(".retail_price")
+            && change["after"] == json!(99.5)
+    }));
+    assert_eq!(diff["products"][1]["sku"], json!("CSV-1"));
+    assert_eq!(diff["products"][1]["action"], json!("Create"));
+    assert_eq!(diff["products"].as_array().unwrap().len(), 2);
+    assert_eq!(diff["errors"][0]["index"], json!(3));
+    assert_eq!(diff["errors"][0]["id"], json!("CSV-2"));
+    assert!(diff["errors"][0]["message"]
+        .as_str()
+        .unwrap()
+        .contains("Unknown store code `NOPE`"));
+
+    // A dry run saves nothing.
+    assert!(!product_skus(&app, &tenant)
+        .await
+        .contains(&"CSV-1".to_string()));
+    let existing = Product::fetch_by_id("123456", tenant.session.clone(), &app.db)
+        .await
+        .unwrap();
+    assert_ne!(existing.name, "Renamed Product");
+
+    let response = import(false).await;
+    assert_eq!(response.status(), Status::Ok);
+    let result: Value = response.into_json().await.unwrap();
+    assert_eq!(result["dry_run"], json!(false));
+
+    let updated = Product::fetch_by_id("123456", tenant.session.clone(), &app.db)
+        .await
+        .unwrap();
+    assert_eq!(updated.name, "Renamed Product");
+    assert_eq!(updated.variants.len(), existing.variants.len());
+    let variant = updated
+        .variants
+        .iter()
+        .find(|variant| variant.barcode == "51890723908812")
+        .unwrap();
+    assert_eq!(variant.retail_price, 99.5);
+
+    let created = Product::fetch_by_id("CSV-1", tenant.session.clone(), &app.db)
+        .await
+        .unwrap();
+    assert_eq!(created.name, "Imported Shirt");
+    assert_eq!(created.variants.len(), 2);
+    assert_eq!(created.variant_groups.len(), 2);
+    assert_eq!(created.variants[1].variant_code, vec!["01", "23"]);
+    assert_eq!(created.variants[0].stock[0].store.store_code, *store);
+    assert_eq!(created.variants[0].stock[0].quantity.quantity_sellable, 4.0);
+    assert!(!product_skus(&app, &tenant)
+        .await
+        .contains(&"CSV-2".to_string()));
+
+    // Importing the same catalogue again changes nothing.
+    let response = import(true).await;
+    let diff: Value = response.into_json().await.unwrap();
+    assert_eq!(diff["products"][0]["action"], json!("Unchanged"));
+}
+
+/// Merges the Shopify API responses in `tests/fixtures/shopify` into one
+/// export.
+fn shopify_export() -> Value {
+    let read = |contents: &str| serde_json::from_str::<Value>(contents).unwrap();
+    let products = read(include_str!("fixtures/shopify/products.json"));
+    let customers = read(include_str!("fixtures/shopify/customers.json"));
+    let orders = read(include_str!("fixtures/shopify/orders.json"));
+
+    json!({
+        "products": products["products"],
+        "customers": customers["customers"],
+        "orders": orders["orders"],
+    })
+}
+
+async fn shopify_local_id(app: &TestApp, tenant: &TenantFixture, kind: &str, id: &str) -> String {
+    ExternalReference::fetch(SHOPIFY_SOURCE, kind, id, tenant.session.clone(), &app.db)
+        .await
+        .unwrap()
+        .expect("record should be referenced")
+        .local_id
+}
+
+#[rocket::async_test]
+async fn shopify_exports_are_imported_idempotently() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+    let (stores, products) = app.seed_catalogue(&tenant).await;
+    let store = stores.first().expect("stores should be seeded");
+
+    let import = |export: Value, store: String| {
+        app.client
+            .post(format!("/api/ingress/shopify?store={}", store))
+            .header(ContentType::JSON)
+            .cookie(tenant.manager_cookie())
+            .body(export.to_string())
+            .dispatch()
+    };
+
+    let response = import(shopify_export(), store.code.clone()).await;
+    assert_eq!(response.status(), Status::Ok);
+    let result: ShopifyImport = response.into_json().await.unwrap();
+    assert!(result.errors.is_empty());
+    let created = |created| ImportCount {
+        created,
+        updated: 0,
+    };
+    assert_eq!(result.products, created(2));
+    // One customer is only given within their order.
+    assert_eq!(result.customers, created(2));
+    assert_eq!(result.orders, created(2));
+
+    let nano = Product::fetch_by_id("632910392", tenant.session.clone(), &app.db)
+        .await
+        .unwrap();
+    assert_eq!(nano.name, "IPod Nano - 8GB");
+    assert_eq!(nano.company, "Apple");
+    assert_eq!(nano.tags, vec!["Emotive", "Flash Memory", "MP3", "Music"]);
+    assert_eq!(nano.variant_groups.len(), 1);
+    assert_eq!(nano.variant_groups[0].category, "Color");
+    assert_eq!(nano.variant_groups[0].variants.len(), 2);
+    let red = nano
+        .variants
+        .iter()
+        .find(|variant| variant.barcode == "1234_red")
+        .unwrap();
+    assert_eq!(red.name, "Red");
+    assert_eq!(red.retail_price, 199.0);
+    assert_eq!(red.identification.sku, "IPOD2008RED");
+    assert_eq!(red.stock[0].store.store_code, store.code);
+    assert_eq!(red.stock[0].quantity.quantity_sellable, 20.0);
+
+    let touch = Product::fetch_by_id("921728736", tenant.session.clone(), &app.db)
+        .await
+        .unwrap();
+    assert!(matches!(touch.visible, ProductVisibility::AlwaysHidden));
+    assert!(touch.variant_groups.is_empty());
+    assert_eq!(touch.variants[0].name, "IPod Touch 8GB");
+
+    let bob = shopify_local_id(&app, &tenant, "customer", "207119551").await;
+    let bob = Customer::fetch_by_id(&bob, tenant.session.clone(), &app.db)
+        .await
+        .unwrap();
+    assert_eq!(bob.name, "Bob Norman");
+    assert_eq!(bob.contact.email.full, "bob.norman@mail.example.com");
+    assert_eq!(bob.contact.address.city, "Louisville");
+    assert_eq!(bob.customer_notes[0].message, "Prefers delivery after 5pm.");
+
+    let order = shopify_local_id(&app, &tenant, "order", "450789469").await;
+    let transaction = Transaction::fetch_by_id(&order, tenant.session.clone(), &app.db)
+        .await
+        .unwrap();
+    assert_eq!(transaction.customer.customer_id, bob.id);
+    assert_eq!(transaction.order_total, 599);
+    assert_eq!(
+        transaction.payment[0].processor.location,
+        SHOPIFY_TRANSACTION
+    );
+    assert_eq!(transaction.payment[0].amount.currency, "USD");
+    let sale = &transaction.products[0];
+    assert_eq!(sale.reference, "#1001");
+    assert_eq!(sale.origin.store_code, store.code);
+    assert_eq!(sale.destination.contact.name, "Bob Norman");
+    assert!(matches!(sale.status.status, OrderStatus::Queued(_)));
+    assert_eq!(sale.products.len(), 2);
+    assert_eq!(sale.products[0].product_code, "1234_red");
+    assert_eq!(sale.products[0].product_sku, "632910392");
+    assert_eq!(sale.products[1].instances.len(), 2);
+    assert!(matches!(
+        sale.products[1].discount,
+        DiscountValue::Absolute(10)
+    ));
+
+    let fulfilled = shopify_local_id(&app, &tenant, "order", "1073459963").await;
+    let fulfilled = Transaction::fetch_by_id(&fulfilled, tenant.session.clone(), &app.db)
+        .await
+        .unwrap();
+    assert!(matches!(
+        fulfilled.products[0].status.status,
+        OrderStatus::Fulfilled(_)
+    ));
+
+    // Importing the export again updates the records already imported.
+    let mut export = shopify_export();
+    export["orders"][0]["fulfillment_status"] = json!("fulfilled");
+    export["orders"][0]["closed_at"] = json!("2008-01-14T12:00:00-05:00");
+
+    let response = import(export, store.code.clone()).await;
+    assert_eq!(response.status(), Status::Ok);
+    let result: ShopifyImport = response.into_json().await.unwrap();
+    let updated = |updated| ImportCount {
+        created: 0,
+        updated,
+    };
+    assert_eq!(result.products, updated(2));
+    assert_eq!(result.customers, updated(1));
+    assert_eq!(result.orders, updated(2));
+
+    let all = Product::fetch_all(tenant.session.clone(), &app.db)
+        .await
+        .unwrap();
+    assert_eq!(all.len(), products.len() + 2);
+    let reimported = Product::fetch_by_id("632910392", tenant.session.clone(), &app.db)
+        .await
+        .unwrap();
+    let ids = |product: &Product| {
+        product
+            .variants
+            .iter()
+            .map(|variant| variant.id.clone())
+            .collect::<Vec<String>>()
+    };
+    assert_eq!(ids(&reimported), ids(&nano));
+
+    let transaction = Transaction::fetch_by_id(&order, tenant.session.clone(), &app.db)
+        .await
+        .unwrap();
+    assert_eq!(transaction.products[0].id, sale.id);
+    assert!(matches!(
+        transaction.products[0].status.status,
+        OrderStatus::Fulfilled(_)
+    ));
+    assert_eq!(transaction.products[0].status_history.len(), 1);
+
+    let response = import(shopify_export(), "UNKNOWN".to_string()).await;
+    assert_ne!(response.status(), Status::Ok);
+}
+
+#[rocket::async_test]
+async fn promotions_are_searched_by_product_or_any_criterion() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+    let other = app.seed_tenant("TENANT_B").await;
+
+    for fixture in [&tenant, &other] {
+        let response = app
+            .client
+            .post("/api/product/generate/promotion")
+            .cookie(fixture.cookie())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    let search = |query: &str| {
+        app.client
+            .get(format!("/api/product/promotion/search/{}", query))
+            .cookie(tenant.cookie())
+            .dispatch()
+    };
+
+    let names = |promotions: Vec<Promotion>| {
+        let mut names = promotions
+            .into_iter()
+            .map(|promotion| promotion.name)
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    };
+
+    let response = search("654321").await;
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        names(response.into_json().await.unwrap()),
+        vec![
+            "Buy 1 Get 1 10% off",
+            "Buy a Kayak, get a Life Jacket 50% off"
+        ]
+    );
+
+    let response = search("Tee").await;
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        names(response.into_json().await.unwrap()),
+        vec!["50% off T-shirts", "Buy 1 Get 1 10% off"]
+    );
+}
+
+#[rocket::async_test]
+async fn sales_are_priced_from_the_catalogue() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+    let (_, products) = app.seed_catalogue(&tenant).await;
+    let product = &products[0];
+    let variant = &product.variants[0];
+
+    let response = app
+        .client
+        .post(format!("/api/transaction/generate/{}", tenant.customer.id))
+        .cookie(tenant.cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let template: Transaction = response.into_json().await.unwrap();
+
+    // A single unit sold a dollar under its retail price.
+    let mut sale = json!(template);
+    let submitted = variant.retail_price - 1.0;
+    sale["transaction_type"] = json!("Out");
+    sale["kiosk"] = json!(tenant.kiosk.id);
+    sale["products"][0]["discount"] = json!({ "Absolute": 0 });
+    sale["products"][0]["products"] = json!([sale["products"][0]["products"][0].clone()]);
+    let purchase = &mut sale["products"][0]["products"][0];
+    purchase["product_sku"] = json!(product.sku);
+    purchase["product_code"] = json!(variant.barcode);
+    purchase["product_cost"] = json!(submitted);
+    sale["order_total"] = json!(submitted.round() as i64);
+    sale["payment"][0]["amount"]["quantity"] = json!(submitted);
+
+    let create = |sale: Value| {
+        app.client
+            .post("/api/transaction/")
+            .header(ContentType::JSON)
+            .cookie(tenant.cookie())
+            .body(sale.to_string())
+            .dispatch()
+    };
+
+    let response = create(sale.clone()).await;
+    assert_eq!(response.status(), Status::Conflict);
+    let error: Value = response.into_json().await.unwrap();
+    assert_eq!(error["code"], json!("error.transaction.price_mismatch"));
+    assert_eq!(error["differences"][0]["field"], json!("ProductCost"));
+    let amount = |value: &Value| value.as_f64().unwrap() as f32;
+    assert_eq!(amount(&error["differences"][0]["submitted"]), submitted);
+    assert_eq!(
+        amount(&error["differences"][0]["expected"]),
+        variant.retail_price
+    );
+    assert_eq!(error["differences"][1]["field"], json!("OrderTotal"));
+
+    // When flagged instead, the sale is repriced and paid in full.
+    let mut settings = Tenant::fetch_by_id("TENANT_A", &app.db)
+        .await
+        .unwrap()
+        .settings;
+    settings.pricing.mismatch = PriceMismatchAction::Flag;
+    let response = app
+        .client
+        .post("/api/tenant/settings")
+        .header(ContentType::JSON)
+        .cookie(tenant.manager_cookie())
+        .body(json!(settings).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    // The payment still covers only the submitted price.
+    let response = create(sale.clone()).await;
+    assert_eq!(response.status(), Status::InternalServerError);
+
+    sale["payment"][0]["amount"]["quantity"] = json!(variant.retail_price);
+    let response = create(sale.clone()).await;
+    assert_eq!(response.status(), Status::Ok);
+    let created: Transaction = response.into_json().await.unwrap();
+    assert_eq!(
+        created.products[0].products[0].product_cost,
+        variant.retail_price
+    );
+    assert_eq!(created.order_total, variant.retail_price.round() as i64);
+    assert!(created.order_notes.iter().any(|note| {
+        note.author == tenant.employee.id && note.message.starts_with("Repriced from the catalogue")
+    }));
+
+    // Discounts beyond the employee's ceiling are refused under any policy.
+    let price = variant.retail_price;
+    sale["products"][0]["products"][0]["product_cost"] = json!(price);
+    sale["products"][0]["discount"] = json!({ "Percentage": 15 });
+    sale["order_total"] = json!((price * 0.85).round() as i64);
+    sale["payment"][0]["amount"]["quantity"] = json!(price * 0.85);
+
+    let response = create(sale).await;
+    assert_eq!(response.status(), Status::Conflict);
+    let error: Value = response.into_json().await.unwrap();
+    let differences = error["differences"].as_array().unwrap();
+    assert_eq!(differences.len(), 1);
+    assert_eq!(differences[0]["field"], json!("Discount"));
+    assert_eq!(differences[0]["expected"], json!(10.0));
+}
+
+#[rocket::async_test]
+async fn updated_sales_are_priced_from_the_catalogue() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+    let (_, products) = app.seed_catalogue(&tenant).await;
+    let product = &products[0];
+    let variant = &product.variants[0];
+
+    let template = template(&app, &tenant).await;
+    let response = app
+        .client
+        .post("/api/transaction/")
+        .header(ContentType::JSON)
+        .cookie(tenant.cookie())
+        .body(sale_of(&template, &tenant, product, 1.0).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let created: Transaction = response.into_json().await.unwrap();
+
+    let update = |sale: Value| {
+        app.client
+            .post(format!("/api/transaction/{}", created.id))
+            .header(ContentType::JSON)
+            .cookie(tenant.cookie())
+            .body(sale.to_string())
+            .dispatch()
+    };
+
+    // The price cannot be lowered by an update.
+    let lowered = variant.retail_price - 1.0;
+    let mut sale = json!(created);
+    sale["products"][0]["products"][0]["product_cost"] = json!(lowered);
+    sale["order_total"] = json!(lowered.round() as i64);
+    sale["payment"][0]["amount"]["quantity"] = json!(lowered);
+
+    let response = update(sale).await;
+    assert_eq!(response.status(), Status::Conflict);
+    let error: Value = response.into_json().await.unwrap();
+    assert_eq!(error["differences"][0]["field"], json!("ProductCost"));
+
+    // Nor can discounts beyond the employee's ceiling be stacked.
+    let mut sale = json!(created);
+    sale["products"][0]["discount"] = json!({ "Percentage": 8 });
+    sale["products"][0]["products"][0]["discount"] = json!({ "Percentage": 8 });
+
+    let response = update(sale).await;
+    assert_eq!(response.status(), Status::Conflict);
+    let error: Value = response.into_json().await.unwrap();
+    assert!(error["differences"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|diff| diff["field"] == json!("Discount")));
+
+    let unchanged = Transaction::fetch_by_id(&created.id, tenant.session.clone(), &app.db)
+        .await
+        .unwrap();
+    assert_eq!(
+        unchanged.products[0].products[0].product_cost,
+        variant.retail_price
+    );
+}
+
+#[rocket::async_test]
+async fn existing_employees_are_granted_a_discount_authority() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+
+    // The manager's level as it was before `ApplyDiscount` existed.
+    let model = employee::Entity::find_by_id(tenant.manager.id.clone())
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    let level = model
+        .level
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|access| access["action"] != json!("ApplyDiscount"))
+        .cloned()
+        .collect::<Vec<Value>>();
+
+    let mut active: employee::ActiveModel = model.into();
+    active.level = Set(json!(level));
+    active.update(&app.db).await.unwrap();
+
+    // Migrations are re-run from the one granting the authority.
+    let since = Migrator::migrations()
+        .iter()
+        .rev()
+        .position(|migration| migration.name() == "m20230730_000024_discount_authority")
+        .unwrap()
+        + 1;
+    Migrator::down(&app.db, Some(since as u32)).await.unwrap();
+    Migrator::up(&app.db, None).await.unwrap();
+
+    let authority = |employee_id: String| {
+        let db = app.db.clone();
+
+        async move {
+            let model = employee::Entity::find_by_id(employee_id)
+                .one(&db)
+                .await
+                .unwrap()
+                .unwrap();
+
+            model
+                .level
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|access| access["action"] == json!("ApplyDiscount"))
+                .map(|access| access["authority"].clone())
+        }
+    };
+
+    assert_eq!(authority(tenant.manager.id.clone()).await, Some(json!(100)));
+    // Authorities already given are kept.
+    assert_eq!(authority(tenant.employee.id.clone()).await, Some(json!(10)));
+}
+
+/// A sale of the product's first variant from the template's origin store,
+/// at its retail price and paid in full.
+fn sale_of(
+    template: &Transaction,
+    tenant: &TenantFixture,
+    product: &Product,
+    quantity: f32,
+) -> Value {
+    let variant = &product.variants[0];
+    let price = variant.retail_price * quantity;
+
+    let mut sale = json!(template);
+    sale["transaction_type"] = json!("Out");
+    sale["kiosk"] = json!(tenant.kiosk.id);
+    sale["products"][0]["discount"] = json!({ "Absolute": 0 });
+    sale["products"][0]["products"] = json!([sale["products"][0]["products"][0].clone()]);
+    let purchase = &mut sale["products"][0]["products"][0];
+    purchase["product_sku"] = json!(product.sku);
+    purchase["product_code"] = json!(variant.barcode);
+    purchase["product_cost"] = json!(variant.retail_price);
+    purchase["quantity"] = json!(quantity);
+    purchase["instances"] = json!([]);
+    sale["order_total"] = json!(price.round() as i64);
+    sale["payment"][0]["amount"]["quantity"] = json!(price);
+
+    sale
+}
+
+async fn template(app: &TestApp, tenant: &TenantFixture) -> Transaction {
+    let response = app
+        .client
+        .post(format!("/api/transaction/generate/{}", tenant.customer.id))
+        .cookie(tenant.cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    response.into_json().await.unwrap()
+}
+
+#[rocket::async_test]
+async fn voided_sales_return_stock_and_are_kept() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+    let (_, products) = app.seed_catalogue(&tenant).await;
+    let product = &products[0];
+    let variant = &product.variants[0];
+
+    let template = template(&app, &tenant).await;
+    let sale = sale_of(&template, &tenant, product, 1.0);
+
+    let sellable = |product: Product| {
+        product.variants[0]
+            .stock
+            .iter()
+            .find(|stock| stock.store.store_code == "002")
+            .unwrap()
+            .quantity
+            .quantity_sellable
+    };
+    let before = sellable(product.clone());
+
+    let response = app
+        .client
+        .post("/api/transaction/")
+        .header(ContentType::JSON)
+        .cookie(tenant.cookie())
+        .body(sale.to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let created: Transaction = response.into_json().await.unwrap();
+
+    let fetch_product = || Product::fetch_by_id(&product.sku, tenant.session.clone(), &app.db);
+    assert_eq!(sellable(fetch_product().await.unwrap()), before - 1.0);
+
+    let void = || {
+        app.client
+            .post(format!("/api/transaction/void/{}", created.id))
+            .header(ContentType::JSON)
+            .cookie(tenant.manager_cookie())
+            .body(json!({ "reason": "Customer changed their mind" }).to_string())
+            .dispatch()
+    };
+
+    let response = void().await;
+    assert_eq!(response.status(), Status::Ok);
+    let voided: Transaction = response.into_json().await.unwrap();
+    let record = voided.void.unwrap();
+    assert_eq!(record.reason, "Customer changed their mind");
+    assert_eq!(record.employee, tenant.manager.id);
+    assert_eq!(record.payments.len(), 1);
+    assert_eq!(record.payments[0].outcome, ReversalOutcome::Cancelled);
+    assert_eq!(record.payments[0].amount.quantity, -variant.retail_price);
+    assert!(matches!(voided.payment[0].status, PaymentStatus::Failed(_)));
+
+    assert_eq!(sellable(fetch_product().await.unwrap()), before);
+
+    // The transaction is kept, but may not be voided again nor modified.
+    let response = app
+        .client
+        .get(format!("/api/transaction/{}", created.id))
+        .cookie(tenant.cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let fetched: Transaction = response.into_json().await.unwrap();
+    assert!(fetched.void.is_some());
+
+    // A copy read before the void is not saved over it.
+    let stale = Transaction::update_value(
+        created.clone(),
+        tenant.session.clone(),
+        &created.id,
+        &app.db,
+    )
+    .await;
+    assert!(stale.is_err());
+    let fetched = Transaction::fetch_by_id(&created.id, tenant.session.clone(), &app.db)
+        .await
+        .unwrap();
+    assert!(fetched.void.is_some());
+
+    assert_ne!(void().await.status(), Status::Ok);
+    assert_eq!(sellable(fetch_product().await.unwrap()), before);
+
+    let response = app
+        .client
+        .post(format!("/api/transaction/{}", created.id))
+        .header(ContentType::JSON)
+        .cookie(tenant.manager_cookie())
+        .body(json!(fetched).to_string())
+        .dispatch()
+        .await;
+    assert_ne!(response.status(), Status::Ok);
+}
+
+#[rocket::async_test]
+async fn concurrent_voids_return_stock_once() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+    let (_, products) = app.seed_catalogue(&tenant).await;
+    let product = &products[0];
+
+    let template = template(&app, &tenant).await;
+    let response = app
+        .client
+        .post("/api/transaction/")
+        .header(ContentType::JSON)
+        .cookie(tenant.cookie())
+        .body(sale_of(&template, &tenant, product, 1.0).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let created: Transaction = response.into_json().await.unwrap();
+
+    let void = || {
+        app.client
+            .post(format!("/api/transaction/void/{}", created.id))
+            .header(ContentType::JSON)
+            .cookie(tenant.manager_cookie())
+            .body(json!({ "reason": "Entered twice" }).to_string())
+            .dispatch()
+    };
+
+    let (first, second) = rocket::futures::join!(void(), void());
+    let voided = [first.status(), second.status()]
+        .iter()
+        .filter(|status| **status == Status::Ok)
+        .count();
+    assert_eq!(voided, 1);
+
+    let sellable = Product::fetch_by_id(&product.sku, tenant.session.clone(), &app.db)
+        .await
+        .unwrap()
+        .variants[0]
+        .stock
+        .iter()
+        .find(|stock| stock.store.store_code == "002")
+        .unwrap()
+        .quantity
+        .quantity_sellable;
+    assert_eq!(sellable, 4.0);
+}
+
+#[rocket::async_test]
+async fn quotes_are_revised_and_converted() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+    let (_, products) = app.seed_catalogue(&tenant).await;
+    let product = &products[0];
+    let variant = &product.variants[0];
+    let quoted = variant.retail_price;
+
+    let template = template(&app, &tenant).await;
+    let mut quote = sale_of(&template, &tenant, product, 1.0);
+    quote["transaction_type"] = json!("Quote");
+    quote["payment"] = json!([]);
+
+    let response = app
+        .client
+        .post("/api/transaction/")
+        .header(ContentType::JSON)
+        .cookie(tenant.cookie())
+        .body(quote.to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let created: Transaction = response.into_json().await.unwrap();
+    let details = created.quote.clone().unwrap();
+    assert_eq!(details.number, "Q-000001");
+    assert_eq!(details.revision, 1);
+    assert!(details.expires_at > Utc::now() + Duration::days(29));
+
+    // Quotes do not move stock.
+    let sellable = |product: Product| {
+        product.variants[0]
+            .stock
+            .iter()
+            .find(|stock| stock.store.store_code == "002")
+            .unwrap()
+            .quantity
+            .quantity_sellable
+    };
+    let fetch_product = || Product::fetch_by_id(&product.sku, tenant.session.clone(), &app.db);
+    let before = sellable(fetch_product().await.unwrap());
+    assert_eq!(sellable(product.clone()), before);
+
+    // A revision keeps the superseded content.
+    let mut revised = json!(created);
+    revised["order_notes"] = json!([]);
+    let response = app
+        .client
+        .post(format!("/api/transaction/{}", created.id))
+        .header(ContentType::JSON)
+        .cookie(tenant.cookie())
+        .body(revised.to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let revised: Transaction = response.into_json().await.unwrap();
+    let details = revised.quote.clone().unwrap();
+    assert_eq!(details.number, "Q-000001");
+    assert_eq!(details.revision, 2);
+    assert_eq!(details.revisions.len(), 1);
+    assert_eq!(details.revisions[0].revision, 1);
+    assert!(!details.revisions[0].products.is_empty());
+    assert!(revised.order_notes.is_empty());
+
+    let response = app
+        .client
+        .get(format!("/api/transaction/quotes/{}", tenant.customer.id))
+        .cookie(tenant.cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let quotes: Vec<Transaction> = response.into_json().await.unwrap();
+    assert_eq!(quotes.len(), 1);
+    assert_eq!(quotes[0].id, created.id);
+
+    // The quoted price is honoured though the catalogue has since changed.
+    let mut changed = fetch_product().await.unwrap();
+    changed.variants[0].retail_price = quoted + 5.0;
+    Product::update(changed, tenant.session.clone(), &product.sku, &app.db)
+        .await
+        .unwrap();
+
+    let mut payment = json!(template.payment[0]);
+    payment["amount"]["quantity"] = json!(quoted);
+    let convert = || {
+        app.client
+            .post(format!("/api/transaction/quote/{}/convert", created.id))
+            .header(ContentType::JSON)
+            .cookie(tenant.cookie())
+            .body(json!({ "payment": [payment], "kiosk": tenant.kiosk.id }).to_string())
+            .dispatch()
+    };
+
+    // A claim is released only by the conversion holding it.
+    let quote = Transaction::fetch_by_id(&created.id, tenant.session.clone(), &app.db)
+        .await
+        .unwrap();
+    let claimed_at = Transaction::claim_conversion(&quote, "SALE-1", &tenant.session, &app.db)
+        .await
+        .unwrap();
+    assert!(Transaction::release_conversion(
+        &quote,
+        "SALE-2",
+        claimed_at,
+        &tenant.session,
+        &app.db
+    )
+    .await
+    .is_err());
+    Transaction::release_conversion(&quote, "SALE-1", claimed_at, &tenant.session, &app.db)
+        .await
+        .unwrap();
+    let released = Transaction::fetch_by_id(&created.id, tenant.session.clone(), &app.db)
+        .await
+        .unwrap();
+    assert_eq!(released.quote.unwrap().converted_to, None);
+    assert!(released.updated_at > claimed_at);
+
+    let response = convert().await;
+    assert_eq!(response.status(), Status::Ok);
+    let sale: Transaction = response.into_json().await.unwrap();
+    assert!(matches!(sale.transaction_type, TransactionType::Out));
+    assert_eq!(sale.products[0].products[0].product_cost, quoted);
+    assert!(sale
+        .order_notes
+        .iter()
+        .any(|note| note.message.contains("Q-000001")));
+    assert_eq!(sellable(fetch_product().await.unwrap()), before - 1.0);
+
+    let converted = Transaction::fetch_by_id(&created.id, tenant.session.clone(), &app.db)
+        .await
+        .unwrap();
+    assert_eq!(converted.quote.unwrap().converted_to, Some(sale.id));
+
+    // A quote is only converted once.
+    assert_ne!(convert().await.status(), Status::Ok);
+}
+
+#[rocket::async_test]
+async fn quotes_are_numbered_and_converted_once() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+    let (_, products) = app.seed_catalogue(&tenant).await;
+    let product = &products[0];
+
+    let template = template(&app, &tenant).await;
+    let mut quote = sale_of(&template, &tenant, product, 1.0);
+    quote["transaction_type"] = json!("Quote");
+    quote["payment"] = json!([]);
+
+    let create = || {
+        app.client
+            .post("/api/transaction/")
+            .header(ContentType::JSON)
+            .cookie(tenant.cookie())
+            .body(quote.to_string())
+            .dispatch()
+    };
+
+    // A purged quote's number is not issued again.
+    let response = create().await;
+    assert_eq!(response.status(), Status::Ok);
+    let purged: Transaction = response.into_json().await.unwrap();
+    transactions::Entity::delete_by_id(purged.id)
+        .exec(&app.db)
+        .await
+        .unwrap();
+
+    let response = create().await;
+    assert_eq!(response.status(), Status::Ok);
+    let created: Transaction = response.into_json().await.unwrap();
+    assert_eq!(created.quote.clone().unwrap().number, "Q-000002");
+
+    // A quote is sold by converting it, rather than updating it.
+    let mut sold = json!(created);
+    sold["transaction_type"] = json!("Out");
+    let response = app
+        .client
+        .post(format!("/api/transaction/{}", created.id))
+        .header(ContentType::JSON)
+        .cookie(tenant.cookie())
+        .body(sold.to_string())
+        .dispatch()
+        .await;
+    assert_ne!(response.status(), Status::Ok);
+
+    let mut payment = json!(template.payment[0]);
+    payment["amount"]["quantity"] = json!(product.variants[0].retail_price);
+    let convert = || {
+        app.client
+            .post(format!("/api/transaction/quote/{}/convert", created.id))
+            .header(ContentType::JSON)
+            .cookie(tenant.cookie())
+            .body(json!({ "payment": [payment], "kiosk": tenant.kiosk.id }).to_string())
+            .dispatch()
+    };
+
+    let (first, second) = rocket::futures::join!(convert(), convert());
+    let converted = [first.status(), second.status()]
+        .iter()
+        .filter(|status| **status == Status::Ok)
+        .count();
+    assert_eq!(converted, 1);
+
+    let sellable = Product::fetch_by_id(&product.sku, tenant.session.clone(), &app.db)
+        .await
+        .unwrap()
+        .variants[0]
+        .stock
+        .iter()
+        .find(|stock| stock.store.store_code == "002")
+        .unwrap()
+        .quantity
+        .quantity_sellable;
+    assert_eq!(sellable, 3.0);
+}
+
+#[rocket::async_test]
+async fn orders_are_picked_packed_and_dispatched() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+    let (_, products) = app.seed_catalogue(&tenant).await;
+    let template = template(&app, &tenant).await;
+    let store_id = template.products[0].origin.store_id.clone();
+
+    // Two sales awaiting dispatch, of two and one units of the same variant.
+    let mut sales = vec![];
+    for quantity in [2.0, 1.0] {
+        let mut sale = sale_of(&template, &tenant, &products[0], quantity);
+        sale["products"][0]["status"]["status"] = json!({ "type": "queued", "value": Utc::now() });
+
+        let response = app
+            .client
+            .post("/api/transaction/")
+            .header(ContentType::JSON)
+            .cookie(tenant.cookie())
+            .body(sale.to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        sales.push(response.into_json::<Transaction>().await.unwrap());
+    }
+
+    let pick_list = || {
+        app.client
+            .get(format!("/api/transaction/picklist/{}", store_id))
+            .cookie(tenant.cookie())
+            .dispatch()
+    };
+
+    let response = pick_list().await;
+    assert_eq!(response.status(), Status::Ok);
+    let list: PickList = response.into_json().await.unwrap();
+    assert_eq!(list.items.len(), 1);
+    assert_eq!(list.items[0].product_sku, products[0].sku);
+    assert_eq!(list.items[0].quantity, 3);
+
+    // Everything is picked in one batch, across both orders.
+    let response = app
+        .client
+        .post("/api/transaction/pick")
+        .header(ContentType::JSON)
+        .cookie(tenant.cookie())
+        .body(json!({ "picks": list.items[0].targets }).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let picked: Vec<Transaction> = response.into_json().await.unwrap();
+    assert_eq!(picked.len(), 2);
+    assert!(picked.iter().all(|transaction| matches!(
+        transaction.products[0].status.status,
+        OrderStatus::Processing(_)
+    )));
+
+    let list: PickList = pick_list().await.into_json().await.unwrap();
+    assert!(list.items.is_empty());
+
+    // The larger order is packed into two parcels, one at a time.
+    let sale = &picked.iter().find(|t| t.id == sales[0].id).unwrap();
+    let order = &sale.products[0];
+    let instances = order.products[0]
+        .instances
+        .iter()
+        .map(|instance| instance.id.clone())
+        .collect::<Vec<String>>();
+
+    let pack = |instances: &[String]| {
+        app.client
+            .post(format!("/api/transaction/pack/{}/{}", sale.id, order.id))
+            .header(ContentType::JSON)
+            .cookie(tenant.cookie())
+            .body(
+                json!({
+                    "packages": [{
+                        "length_cm": 40.0,
+                        "width_cm": 30.0,
+                        "height_cm": 20.0,
+                        "weight_kg": 2.5,
+                        "instances": instances
+                    }]
+                })
+                .to_string(),
+            )
+            .dispatch()
+    };
+
+    let response = pack(&instances[..1]).await;
+    assert_eq!(response.status(), Status::Ok);
+    let packed: Transaction = response.into_json().await.unwrap();
+    assert_eq!(packed.products[0].packages.len(), 1);
+    assert!(matches!(
+        packed.products[0].status.status,
+        OrderStatus::Processing(_)
+    ));
+
+    // An instance is only packed once.
+    assert_ne!(pack(&instances[..1]).await.status(), Status::Ok);
+
+    let response = pack(&instances[1..]).await;
+    assert_eq!(response.status(), Status::Ok);
+    let packed: Transaction = response.into_json().await.unwrap();
+    let order = &packed.products[0];
+    assert_eq!(order.packages.len(), 2);
+    assert!(order.products[0]
+        .instances
+        .iter()
+        .all(|instance| matches!(instance.fulfillment_status.pick_status, PickStatus::Packed)));
+    match &order.status.status {
+        OrderStatus::Transit(transit) => {
+            assert_eq!(
+                transit.assigned_products,
+                vec![order.products[0].id.clone()]
+            )
+        }
+        status => panic!("order should be in transit, not {}", status),
+    }
+}
+
+#[rocket::async_test]
+async fn direct_orders_are_fulfilled_once_packed() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+    let (_, products) = app.seed_catalogue(&tenant).await;
+    let template = template(&app, &tenant).await;
+
+    let mut sale = sale_of(&template, &tenant, &products[0], 1.0);
+    sale["products"][0]["order_type"] = json!("direct");
+    sale["products"][0]["status"]["status"] = json!({ "type": "queued", "value": Utc::now() });
+
+    let response = app
+        .client
+        .post("/api/transaction/")
+        .header(ContentType::JSON)
+        .cookie(tenant.cookie())
+        .body(sale.to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let sale: Transaction = response.into_json().await.unwrap();
+    let order = &sale.products[0];
+    let instance = order.products[0].instances[0].id.clone();
+
+    let response = app
+        .client
+        .post("/api/transaction/pick")
+        .header(ContentType::JSON)
+        .cookie(tenant.cookie())
+        .body(
+            json!({ "picks": [{
+                "transaction_id": sale.id,
+                "order_id": order.id,
+                "instance_id": instance
+            }] })
+            .to_string(),
+        )
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = app
+        .client
+        .post(format!("/api/transaction/pack/{}/{}", sale.id, order.id))
+        .header(ContentType::JSON)
+        .cookie(tenant.cookie())
+        .body(
+            json!({
+                "packages": [{
+                    "length_cm": 40.0,
+                    "width_cm": 30.0,
+                    "height_cm": 20.0,
+                    "weight_kg": 2.5,
+                    "instances": [instance]
+                }]
+            })
+            .to_string(),
+        )
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let packed: Transaction = response.into_json().await.unwrap();
+    assert!(matches!(
+        packed.products[0].status.status,
+        OrderStatus::Fulfilled(_)
+    ));
+}
+
+#[rocket::async_test]
+async fn packed_orders_are_labelled_and_tracked_to_delivery() {
+    let app = TestApp::configured(|figment| {
+        figment
+            .merge(("shipping.provider", "mock"))
+            .merge(("shipping.poll_interval_secs", 3600))
+    })
+    .await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+    let (_, products) = app.seed_catalogue(&tenant).await;
+    let template = template(&app, &tenant).await;
+
+    let mut sale = sale_of(&template, &tenant, &products[0], 1.0);
+    sale["products"][0]["status"]["status"] = json!({ "type": "queued", "value": Utc::now() });
+
+    let response = app
+        .client
+        .post("/api/transaction/")
+        .header(ContentType::JSON)
+        .cookie(tenant.cookie())
+        .body(sale.to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let sale: Transaction = response.into_json().await.unwrap();
+    let order = &sale.products[0];
+    let instance = order.products[0].instances[0].id.clone();
+
+    let response = app
+        .client
+        .post("/api/transaction/pick")
+        .header(ContentType::JSON)
+        .cookie(tenant.cookie())
+        .body(
+            json!({ "picks": [{
+                "transaction_id": sale.id,
+                "order_id": order.id,
+                "instance_id": instance
+            }] })
+            .to_string(),
+        )
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let rates = || {
+        app.client
+            .post(format!("/api/shipping/rates/{}/{}", sale.id, order.id))
+            .cookie(tenant.cookie())
+            .dispatch()
+    };
+
+    // Nothing can be quoted until the order is packed.
+    assert_ne!(rates().await.status(), Status::Ok);
+
+    let response = app
+        .client
+        .post(format!("/api/transaction/pack/{}/{}", sale.id, order.id))
+        .header(ContentType::JSON)
+        .cookie(tenant.cookie())
+        .body(
+            json!({
+                "packages": [{
+                    "length_cm": 40.0,
+                    "width_cm": 30.0,
+                    "height_cm": 20.0,
+                    "weight_kg": 2.5,
+                    "instances": [instance]
+                }]
+            })
+            .to_string(),
+        )
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = rates().await;
+    assert_eq!(response.status(), Status::Ok);
+    let quotes: Vec<RateQuote> = response.into_json().await.unwrap();
+    assert_eq!(quotes.len(), 2);
+    let standard = quotes.iter().find(|q| q.service == "standard").unwrap();
+    assert_eq!(standard.amount.quantity, 10.0);
+
+    let label = || {
+        app.client
+            .post(format!("/api/shipping/label/{}/{}", sale.id, order.id))
+            .header(ContentType::JSON)
+            .cookie(tenant.cookie())
+            .body(serde_json::to_string(standard).unwrap())
+            .dispatch()
+    };
+
+    let response = label().await;
+    assert_eq!(response.status(), Status::Ok);
+    let labelled: Transaction = response.into_json().await.unwrap();
+    match &labelled.products[0].status.status {
+        OrderStatus::Transit(transit) => {
+            assert_eq!(transit.carrier.as_deref(), Some("mock"));
+            assert!(transit.tracking_code.starts_with("MOCK-"));
+            assert!(transit.label.is_some());
+        }
+        status => panic!("order should be in transit, not {}", status),
+    }
+
+    // A label is only purchased once.
+    assert_ne!(label().await.status(), Status::Ok);
+
+    // Only transactions with an order in transit are polled.
+    let polled = || async {
+        Transaction::fetch_tracked(&app.db)
+            .await
+            .unwrap()
+            .into_iter()
+            .any(|(_, transaction)| transaction.id == sale.id)
+    };
+    assert!(polled().await);
+
+    let track = || {
+        app.client
+            .post(format!("/api/shipping/track/{}", sale.id))
+            .cookie(tenant.cookie())
+            .dispatch()
+    };
+
+    // The mock carrier delivers on the second poll of its tracking.
+    let tracked: Transaction = track().await.into_json().await.unwrap();
+    assert!(matches!(
+        tracked.products[0].status.status,
+        OrderStatus::Transit(_)
+    ));
+
+    let tracked: Transaction = track().await.into_json().await.unwrap();
+    assert!(matches!(
+        tracked.products[0].status.status,
+        OrderStatus::Fulfilled(_)
+    ));
+    assert!(!polled().await);
+
+    // Saving a poll does not undo a void made whilst it waited.
+    let response = app
+        .client
+        .post(format!("/api/transaction/void/{}", sale.id))
+        .header(ContentType::JSON)
+        .cookie(tenant.manager_cookie())
+        .body(json!({ "reason": "Returned" }).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    tracked
+        .save_tracking(&tenant.session.tenant_id, &app.db)
+        .await
+        .unwrap();
+    let voided = Transaction::fetch_by_id(&sale.id, tenant.session.clone(), &app.db)
+        .await
+        .unwrap();
+    assert!(voided.void.is_some());
+}
+
+#[rocket::async_test]
+async fn shipping_requires_a_configured_provider() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+    let template = template(&app, &tenant).await;
+
+    let response = app
+        .client
+        .post(format!(
+            "/api/shipping/rates/{}/{}",
+            template.id, template.products[0].id
+        ))
+        .cookie(tenant.cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::InternalServerError);
+}
+
+#[rocket::async_test]
+async fn order_statuses_follow_their_type() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+    let (_, products) = app.seed_catalogue(&tenant).await;
+    let product = &products[0];
+    let template = template(&app, &tenant).await;
+
+    let sellable = |product: Product| {
+        product.variants[0]
+            .stock
+            .iter()
+            .find(|stock| stock.store.store_code == "002")
+            .unwrap()
+            .quantity
+            .quantity_sellable
+    };
+    let fetch_product = || Product::fetch_by_id(&product.sku, tenant.session.clone(), &app.db);
+    let before = sellable(product.clone());
+
+    let mut created = vec![];
+    for (reference, order_type) in [("PICKUP-1", "pickup"), ("DIRECT-1", "direct")] {
+        let mut sale = sale_of(&template, &tenant, product, 1.0);
+        sale["products"][0]["reference"] = json!(reference);
+        sale["products"][0]["order_type"] = json!(order_type);
+        sale["products"][0]["status"]["status"] = json!({ "type": "queued", "value": Utc::now() });
+
+        let response = app
+            .client
+            .post("/api/transaction/")
+            .header(ContentType::JSON)
+            .cookie(tenant.cookie())
+            .body(sale.to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        created.push(response.into_json::<Transaction>().await.unwrap());
+    }
+    assert_eq!(sellable(fetch_product().await.unwrap()), before - 2.0);
+
+    let update = |reference: &'static str, status: Value| {
+        app.client
+            .post(format!("/api/transaction/status/order/{}", reference))
+            .header(ContentType::JSON)
+            .cookie(tenant.cookie())
+            .body(status.to_string())
+            .dispatch()
+    };
+
+    // A pickup order is readied in store before it is collected.
+    let response = update(
+        "PICKUP-1",
+        json!({ "type": "fulfilled", "value": Utc::now() }),
+    )
+    .await;
+    assert_eq!(response.status(), Status::Conflict);
+    let error: Value = response.into_json().await.unwrap();
+    assert_eq!(error["code"], "error.order.invalid_transition");
+    assert_eq!(error["order_type"], "pickup");
+
+    let response = update(
+        "PICKUP-1",
+        json!({ "type": "processing", "value": Utc::now() }),
+    )
+    .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = update(
+        "PICKUP-1",
+        json!({ "type": "instore", "value": Utc::now() }),
+    )
+    .await;
+    assert_eq!(response.status(), Status::Ok);
+    let ready: Transaction = response.into_json().await.unwrap();
+    let order = &ready.products[0];
+    assert!(matches!(order.status.status, OrderStatus::InStore(_)));
+    assert_eq!(
+        order.status_history.len(),
+        created[0].products[0].status_history.len() + 2
+    );
+    assert!(order
+        .order_notes
+        .iter()
+        .any(|note| note.message.starts_with("Customer notified")));
+
+    let response = update("PICKUP-1", json!({ "type": "queued", "value": Utc::now() })).await;
+    assert_eq!(response.status(), Status::Conflict);
+
+    // Failing an order before dispatch releases its stock, once.
+    let response = update("DIRECT-1", json!({ "type": "failed", "value": "Damaged" })).await;
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(sellable(fetch_product().await.unwrap()), before - 1.0);
+
+    let response = update(
+        "DIRECT-1",
+        json!({ "type": "processing", "value": Utc::now() }),
+    )
+    .await;
+    assert_eq!(response.status(), Status::Conflict);
+
+    // Nor is it moved, or its stock held again, by updating the sale.
+    let failed = Transaction::fetch_by_id(&created[1].id, tenant.session.clone(), &app.db)
+        .await
+        .unwrap();
+    let mut requeued = json!(failed);
+    requeued["products"][0]["status"]["status"] = json!({ "type": "queued", "value": Utc::now() });
+    let mut unreleased = json!(failed);
+    unreleased["products"][0]["stock_released"] = json!(false);
+
+    for modified in [requeued, unreleased] {
+        let response = app
+            .client
+            .post(format!("/api/transaction/{}", failed.id))
+            .header(ContentType::JSON)
+            .cookie(tenant.cookie())
+            .body(modified.to_string())
+            .dispatch()
+            .await;
+        assert_ne!(response.status(), Status::Ok);
+    }
+
+    let response = app
+        .client
+        .post(format!("/api/transaction/void/{}", created[1].id))
+        .header(ContentType::JSON)
+        .cookie(tenant.manager_cookie())
+        .body(json!({ "reason": "Damaged in store" }).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(sellable(fetch_product().await.unwrap()), before - 1.0);
+}
+
+#[rocket::async_test]
+async fn failed_orders_are_rerouted_to_the_nearest_stocked_store() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+    let (_, products) = app.seed_catalogue(&tenant).await;
+    let product = products.iter().find(|p| p.sku == "123456").unwrap();
+    let (small, medium) = (&product.variants[0], &product.variants[1]);
+    let template = template(&app, &tenant).await;
+
+    // Store 001 is nearest the destination, but holds no small variant,
+    // whereas 003 holds only one of the medium.
+    let mut sale = sale_of(&template, &tenant, product, 1.0);
+    let mut line = sale["products"][0]["products"][0].clone();
+    line["id"] = json!("PDT-M-BLK-PURCHASE-ID-1");
+    line["product_code"] = json!(medium.barcode);
+    line["product_cost"] = json!(medium.retail_price);
+    line["quantity"] = json!(2.0);
+    sale["products"][0]["products"]
+        .as_array_mut()
+        .unwrap()
+        .push(line);
+    let price = small.retail_price + medium.retail_price * 2.0;
+    sale["order_total"] = json!(price.round() as i64);
+    sale["payment"][0]["amount"]["quantity"] = json!(price);
+    sale["products"][0]["reference"] = json!("ROUTE-1");
+    sale["products"][0]["status"]["status"] = json!({ "type": "queued", "value": Utc::now() });
+
+    let response = app
+        .client
+        .post("/api/transaction/")
+        .header(ContentType::JSON)
+        .cookie(tenant.cookie())
+        .body(sale.to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let created: Transaction = response.into_json().await.unwrap();
+
+    let sellable = || async {
+        let product = Product::fetch_by_id(&product.sku, tenant.session.clone(), &app.db)
+            .await
+            .unwrap();
+
+        product.variants[..2]
+            .iter()
+            .map(|variant| {
+                variant
+                    .stock
+                    .iter()
+                    .map(|stock| {
+                        (
+                            stock.store.store_code.clone(),
+                            stock.quantity.quantity_sellable,
+                        )
+                    })
+                    .collect::<Vec<(String, f32)>>()
+            })
+            .collect::<Vec<Vec<(String, f32)>>>()
+    };
+    let stock = |code: &str, levels: &[(String, f32)]| {
+        levels.iter().find(|(store, _)| store == code).unwrap().1
+    };
+    let before = sellable().await;
+
+    let update = |reference: &'static str| {
+        app.client
+            .post(format!("/api/transaction/status/order/{}", reference))
+            .header(ContentType::JSON)
+            .cookie(tenant.cookie())
+            .body(json!({ "type": "failed", "value": "Not found on shelf" }).to_string())
+            .dispatch()
+    };
+
+    let response = update("ROUTE-1").await;
+    assert_eq!(response.status(), Status::Ok);
+    let routed: Transaction = response.into_json().await.unwrap();
+    assert_eq!(routed.products.len(), 2);
+
+    // No store holds both, so each line goes to the nearest which holds it.
+    let (first, second) = (&routed.products[0], &routed.products[1]);
+    assert_eq!(first.id, created.products[0].id);
+    assert_eq!(first.origin.store_code, "003");
+    assert_eq!(first.products.len(), 1);
+    assert_eq!(first.products[0].product_code, small.barcode);
+    assert_eq!(second.reference, "ROUTE-1-2");
+    assert_eq!(second.origin.store_code, "001");
+    assert_eq!(second.products[0].product_code, medium.barcode);
+    for order in [first, second] {
+        assert!(matches!(order.status.status, OrderStatus::Queued(_)));
+        assert_eq!(order.previous_failed_fulfillment_attempts.len(), 1);
+        assert_eq!(
+            order.previous_failed_fulfillment_attempts[0].item.code,
+            "002"
+        );
+        assert_eq!(
+            order.previous_failed_fulfillment_attempts[0].reason,
+            "Not found on shelf"
+        );
+    }
+
+    let after = sellable().await;
+    assert_eq!(stock("002", &after[0]), stock("002", &before[0]) + 1.0);
+    assert_eq!(stock("002", &after[1]), stock("002", &before[1]) + 2.0);
+    assert_eq!(stock("003", &after[0]), stock("003", &before[0]) - 1.0);
+    assert_eq!(stock("001", &after[1]), stock("001", &before[1]) - 2.0);
+
+    // Should it fail again, no store remains with enough of the medium.
+    let response = update("ROUTE-1-2").await;
+    assert_eq!(response.status(), Status::Ok);
+    let failed: Transaction = response.into_json().await.unwrap();
+    assert!(matches!(
+        failed.products[1].status.status,
+        OrderStatus::Failed(_)
+    ));
+    assert_eq!(stock("001", &sellable().await[1]), stock("001", &before[1]));
+
+    let response = app
+        .client
+        .post(format!(
+            "/api/transaction/reroute/{}/{}",
+            created.id, failed.products[1].id
+        ))
+        .cookie(tenant.cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::InternalServerError);
+}
+
+#[rocket::async_test]
+async fn pickup_orders_are_collected_with_their_code() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+    let (stores, products) = app.seed_catalogue(&tenant).await;
+    let template = template(&app, &tenant).await;
+    let store = stores
+        .iter()
+        .find(|store| store.id == tenant.kiosk.store_id)
+        .unwrap();
+
+    let mut sale = sale_of(&template, &tenant, &products[0], 1.0);
+    sale["products"][0]["reference"] = json!("COLLECT-1");
+    sale["products"][0]["order_type"] = json!("pickup");
+    sale["products"][0]["destination"]["store_id"] = json!(store.id);
+    sale["products"][0]["destination"]["store_code"] = json!(store.code);
+    sale["products"][0]["status"]["status"] = json!({ "type": "queued", "value": Utc::now() });
+
+    let response = app
+        .client
+        .post("/api/transaction/")
+        .header(ContentType::JSON)
+        .cookie(tenant.cookie())
+        .body(sale.to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let created: Transaction = response.into_json().await.unwrap();
+    assert!(created.products[0].collection.is_none());
+
+    let mut ready = None;
+    for status in ["processing", "instore"] {
+        let response = app
+            .client
+            .post("/api/transaction/status/order/COLLECT-1")
+            .header(ContentType::JSON)
+            .cookie(tenant.cookie())
+            .body(json!({ "type": status, "value": Utc::now() }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        ready = Some(response.into_json::<Transaction>().await.unwrap());
+    }
+    let ready = ready.unwrap();
+    let order = ready.products[0].clone();
+    assert_eq!(ready.issued_collections.len(), 1);
+    let collection = ready.issued_collections[0].clone();
+    assert_eq!(collection.order_id, order.id);
+    assert_eq!(collection.code.len(), 8);
+    let payload: Value = serde_json::from_str(&collection.qr_payload).unwrap();
+    assert_eq!(payload["transaction_id"], json!(created.id));
+    assert_eq!(payload["order_id"], json!(order.id));
+    assert_eq!(payload["code"], json!(collection.code));
+
+    // The code is returned only as it is issued.
+    let response = app
+        .client
+        .get(format!("/api/transaction/{}", created.id))
+        .cookie(tenant.cookie())
+        .dispatch()
+        .await;
+    let body = response.into_string().await.unwrap();
+    assert!(!body.contains(&collection.code));
+    assert!(!body.contains("issued_collections"));
+
+    // Nor is the order fulfilled other than by collecting it.
+    let response = app
+        .client
+        .post("/api/transaction/status/order/COLLECT-1")
+        .header(ContentType::JSON)
+        .cookie(tenant.cookie())
+        .body(json!({ "type": "fulfilled", "value": Utc::now() }).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Conflict);
+
+    let awaiting = || {
+        app.client
+            .get(format!("/api/transaction/collections/{}", store.id))
+            .cookie(tenant.cookie())
+            .dispatch()
+    };
+
+    let response = awaiting().await;
+    assert_eq!(response.status(), Status::Ok);
+    let list: Vec<AwaitingCollection> = response.into_json().await.unwrap();
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0].order_id, order.id);
+    assert_eq!(list[0].reference, "COLLECT-1");
+    assert!(list[0].age_secs >= 0);
+
+    // Orders are collected at the kiosk the session was issued at, any
+    // given in the request is ignored.
+    let collect = |code: &str, kiosk_id: Option<&str>| {
+        let mut input = payload.clone();
+        input["code"] = json!(code);
+        input["kiosk_id"] = json!(tenant.kiosk.id);
+        input["collected_by"] = json!("Jane Doe");
+        let (app, tenant, kiosk_id) = (&app, &tenant, kiosk_id.map(str::to_string));
+
+        async move {
+            session::ActiveModel {
+                id: Set(tenant.session.id.clone()),
+                kiosk_id: Set(kiosk_id),
+                ..Default::default()
+            }
+            .update(&app.db)
+            .await
+            .unwrap();
+
+            app.client
+                .post("/api/transaction/collect")
+                .header(ContentType::JSON)
+                .cookie(tenant.cookie())
+                .body(input.to_string())
+                .dispatch()
+                .await
+        }
+    };
+
+    assert_eq!(
+        collect(&collection.code, None).await.status(),
+        Status::Unauthorized
+    );
+    assert_ne!(
+        collect("WRONG123", Some(&tenant.kiosk.id)).await.status(),
+        Status::Ok
+    );
+
+    // Orders are only handed over at their destination store.
+    let elsewhere = Kiosk {
+        id: Uuid::new_v4().to_string(),
+        store_id: stores
+            .iter()
+            .find(|other| other.id != store.id)
+            .unwrap()
+            .id
+            .clone(),
+        ..tenant.kiosk.clone()
+    };
+    Kiosk::insert_raw(elsewhere.clone(), tenant.session.clone(), &app.db)
+        .await
+        .unwrap();
+    assert_ne!(
+        collect(&collection.code, Some(&elsewhere.id))
+            .await
+            .status(),
+        Status::Ok
+    );
+
+    // A reissued code replaces the last.
+    let response = app
+        .client
+        .post(format!(
+            "/api/transaction/collection/{}/{}",
+            created.id, order.id
+        ))
+        .cookie(tenant.cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let reissued: IssuedCollection = response.into_json().await.unwrap();
+    assert_ne!(
+        collect(&collection.code, Some(&tenant.kiosk.id))
+            .await
+            .status(),
+        Status::Ok
+    );
+    let collection = reissued;
+
+    // Of concurrent collections, only one hands the order over.
+    let code = collection.code.to_lowercase();
+    let responses = rocket::futures::join!(
+        collect(&code, Some(&tenant.kiosk.id)),
+        collect(&code, Some(&tenant.kiosk.id))
+    );
+    let mut collected = None;
+    for response in [responses.0, responses.1] {
+        if response.status() == Status::Ok {
+            assert!(collected.is_none());
+            collected = Some(response.into_json::<Transaction>().await.unwrap());
+        }
+    }
+    let collected = collected.unwrap();
+    let order = &collected.products[0];
+    assert!(matches!(order.status.status, OrderStatus::Fulfilled(_)));
+    let record = order
+        .collection
+        .as_ref()
+        .unwrap()
+        .collected
+        .clone()
+        .unwrap();
+    assert_eq!(record.collected_by, "Jane Doe");
+    assert_eq!(record.verified_by, tenant.employee.id);
+    assert_eq!(record.kiosk_id, tenant.kiosk.id);
+
+    let list: Vec<AwaitingCollection> = awaiting().await.into_json().await.unwrap();
+    assert!(list.is_empty());
+    assert_ne!(
+        collect(&collection.code, Some(&tenant.kiosk.id))
+            .await
+            .status(),
+        Status::Ok
+    );
+}
+
+#[rocket::async_test]
+async fn serials_are_registered_and_sold_once() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+    let (_, products) = app.seed_catalogue(&tenant).await;
+    let product = &products[0];
+    let variant = &product.variants[0];
+    let template = template(&app, &tenant).await;
+
+    let record = |transaction_type: &str| {
+        let mut sale = sale_of(&template, &tenant, product, 1.0);
+        sale["transaction_type"] = json!(transaction_type);
+        sale["products"][0]["products"][0]["instances"] =
+            json!([{ "id": Uuid::new_v4().to_string(), "serial": "SN-0001" }]);
+
+        app.client
+            .post("/api/transaction/")
+            .header(ContentType::JSON)
+            .cookie(tenant.cookie())
+            .body(sale.to_string())
+            .dispatch()
+    };
+
+    let lookup = || async {
+        let response = app
+            .client
+            .get("/api/serial/SN-0001")
+            .cookie(tenant.cookie())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let mut serials: Vec<SerialNumber> = response.into_json().await.unwrap();
+        assert_eq!(serials.len(), 1);
+        serials.remove(0)
+    };
+
+    let response = record("Out").await;
+    assert_eq!(response.status(), Status::Ok);
+    let serial = lookup().await;
+    assert_eq!(serial.status, SerialStatus::Sold);
+    assert_eq!(serial.variant_code, variant.barcode);
+    assert_eq!(serial.store_id, tenant.kiosk.store_id);
+
+    // The same instance cannot be sold twice, though it may be returned.
+    assert_ne!(record("Out").await.status(), Status::Ok);
+    assert_eq!(record("In").await.status(), Status::Ok);
+    assert_ne!(record("In").await.status(), Status::Ok);
+
+    let registry = || {
+        app.client
+            .get(format!(
+                "/api/serial/registry/{}/{}/{}",
+                product.sku, variant.barcode, tenant.kiosk.store_id
+            ))
+            .cookie(tenant.cookie())
+            .dispatch()
+    };
+    let held: Vec<SerialNumber> = registry().await.into_json().await.unwrap();
+    assert_eq!(held.len(), 1);
+    assert_eq!(held[0].status, SerialStatus::Returned);
+
+    let response = app
+        .client
+        .post("/api/serial/warranty")
+        .header(ContentType::JSON)
+        .cookie(tenant.cookie())
+        .body(
+            json!({
+                "product_sku": product.sku,
+                "variant_code": variant.barcode,
+                "serial": "SN-0001",
+                "store_id": tenant.kiosk.store_id,
+                "note": "Cracked frame",
+            })
+            .to_string(),
+        )
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    // Once resold, voiding the sale returns the serial to the store.
+    let response = record("Out").await;
+    assert_eq!(response.status(), Status::Ok);
+    let resold: Transaction = response.into_json().await.unwrap();
+    assert!(registry()
+        .await
+        .into_json::<Vec<SerialNumber>>()
+        .await
+        .unwrap()
+        .is_empty());
+
+    let response = app
+        .client
+        .post(format!("/api/transaction/void/{}", resold.id))
+        .header(ContentType::JSON)
+        .cookie(tenant.manager_cookie())
+        .body(json!({ "reason": "Sold in error" }).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let serial = lookup().await;
+    assert_eq!(serial.status, SerialStatus::Returned);
+    assert_eq!(
+        serial
+            .history
+            .iter()
+            .map(|event| event.kind.clone())
+            .collect::<Vec<_>>(),
+        vec![
+            SerialEventKind::Sold,
+            SerialEventKind::Returned,
+            SerialEventKind::Warranty,
+            SerialEventKind::Sold,
+            SerialEventKind::Voided,
+        ]
+    );
+    assert_eq!(serial.history[2].note.as_deref(), Some("Cracked frame"));
+
+    // Of two sales of the serial at once, only one is recorded.
+    let (first, second) = rocket::futures::join!(record("Out"), record("Out"));
+    let mut sold = vec![];
+    for response in [first, second] {
+        if response.status() == Status::Ok {
+            sold.push(response.into_json::<Transaction>().await.unwrap());
+        }
+    }
+    assert_eq!(sold.len(), 1);
+    assert_eq!(lookup().await.status, SerialStatus::Sold);
+
+    // Nor is a serial added to a sale other than as it is recorded.
+    let mut updated = json!(sold[0]);
+    updated["products"][0]["products"][0]["instances"]
+        .as_array_mut()
+        .unwrap()
+        .push(json!({ "id": Uuid::new_v4().to_string(), "serial": "SN-0002" }));
+    let response = app
+        .client
+        .post(format!("/api/transaction/{}", sold[0].id))
+        .header(ContentType::JSON)
+        .cookie(tenant.cookie())
+        .body(updated.to_string())
+        .dispatch()
+        .await;
+    assert_ne!(response.status(), Status::Ok);
+}
+
+#[rocket::async_test]
+async fn concurrent_sales_sell_a_serial_once() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+    let (_, products) = app.seed_catalogue(&tenant).await;
+    let product = &products[0];
+
+    let template = template(&app, &tenant).await;
+    let sell = || {
+        let mut sale = sale_of(&template, &tenant, product, 1.0);
+        sale["products"][0]["products"][0]["instances"] =
+            json!([{ "id": Uuid::new_v4().to_string(), "serial": "SN-0001" }]);
+
+        app.client
+            .post("/api/transaction/")
+            .header(ContentType::JSON)
+            .cookie(tenant.cookie())
+            .body(sale.to_string())
+            .dispatch()
+    };
+
+    let (first, second) = rocket::futures::join!(sell(), sell());
+    let mut sold = vec![];
+    for response in [first, second] {
+        if response.status() == Status::Ok {
+            sold.push(response.into_json::<Transaction>().await.unwrap());
+        }
+    }
+    assert_eq!(sold.len(), 1);
+
+    let sellable = Product::fetch_by_id(&product.sku, tenant.session.clone(), &app.db)
+        .await
+        .unwrap()
+        .variants[0]
+        .stock
+        .iter()
+        .find(|stock| stock.store.store_code == "002")
+        .unwrap()
+        .quantity
+        .quantity_sellable;
+    assert_eq!(sellable, 3.0);
+
+    // A sale checked before the other was recorded is refused as it is.
+    let recorded = SerialNumber::record_movements(
+        &Uuid::new_v4().to_string(),
+        &TransactionType::Out,
+        &sold[0].products,
+        tenant.session.clone(),
+        &app.db,
+    )
+    .await;
+    assert!(recorded.is_err());
+
+    let serials = SerialNumber::fetch_by_serial("SN-0001", tenant.session.clone(), &app.db)
+        .await
+        .unwrap();
+    assert_eq!(serials[0].history.len(), 1);
+}
+
+#[rocket::async_test]
+async fn lots_are_sold_first_expiring_and_written_off() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+    let (_, products) = app.seed_catalogue(&tenant).await;
+    let product = &products[0];
+    let template = template(&app, &tenant).await;
+
+    let record = |transaction_type: &str, quantity: f32, lot: Value| {
+        let mut sale = sale_of(&template, &tenant, product, quantity);
+        sale["transaction_type"] = json!(transaction_type);
+        sale["products"][0]["products"][0]["lot"] = lot;
+
+        app.client
+            .post("/api/transaction/")
+            .header(ContentType::JSON)
+            .cookie(tenant.cookie())
+            .body(sale.to_string())
+            .dispatch()
+    };
+
+    let received = [
+        ("B-OLD", 1.0, Utc::now() - Duration::days(1)),
+        ("B-EARLY", 2.0, Utc::now() + Duration::days(2)),
+        ("B-LATE", 3.0, Utc::now() + Duration::days(30)),
+    ];
+    for (batch_code, quantity, expiry_date) in received {
+        let lot = json!({ "batch_code": batch_code, "expiry_date": expiry_date });
+        let response = record("In", quantity, lot).await;
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    // The expired lot is passed over, the earliest of the others emptied.
+    let response = record("Out", 3.0, Value::Null).await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let stock = || async {
+        Product::fetch_by_id(&product.sku, tenant.session.clone(), &app.db)
+            .await
+            .unwrap()
+            .variants[0]
+            .stock
+            .iter()
+            .find(|stock| stock.store.store_code == "002")
+            .unwrap()
+            .clone()
+    };
+    let held = stock().await;
+    assert_eq!(held.quantity.quantity_sellable, 4.0 + 6.0 - 3.0);
+    let lots = held
+        .lots
+        .iter()
+        .map(|lot| (lot.batch_code.as_str(), lot.quantity))
+        .collect::<Vec<_>>();
+    assert_eq!(lots, vec![("B-OLD", 1.0), ("B-LATE", 2.0)]);
+
+    let expiring = |days: i64| {
+        app.client
+            .get(format!("/api/product/lots/expiring/{}", days))
+            .cookie(tenant.cookie())
+            .dispatch()
+    };
+    let report: Vec<ExpiringLot> = expiring(7).await.into_json().await.unwrap();
+    assert_eq!(report.len(), 1);
+    assert_eq!(report[0].lot.batch_code, "B-OLD");
+    assert!(report[0].expired);
+    let report: Vec<ExpiringLot> = expiring(60).await.into_json().await.unwrap();
+    assert_eq!(report.len(), 2);
+    assert!(!report[1].expired);
+
+    let response = app
+        .client
+        .post("/api/product/lots/write_off")
+        .cookie(tenant.manager_cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let written_off: Vec<ExpiringLot> = response.into_json().await.unwrap();
+    assert_eq!(written_off.len(), 1);
+    assert_eq!(written_off[0].lot.batch_code, "B-OLD");
+    assert_eq!(written_off[0].store.store_code, "002");
+
+    let held = stock().await;
+    assert_eq!(held.quantity.quantity_sellable, 6.0);
+    assert_eq!(held.quantity.quantity_unsellable, 2.0 + 1.0);
+    assert_eq!(held.lots.len(), 1);
+    assert_eq!(held.lots[0].batch_code, "B-LATE");
+
+    // Once untracked stock runs out, the expired lot is drawn too, rather
+    // than holding more than is sellable and being written off twice.
+    let stale = json!({ "batch_code": "B-STALE", "expiry_date": Utc::now() - Duration::days(1) });
+    let response = record("In", 1.0, stale).await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = record("Out", 7.0, Value::Null).await;
+    assert_eq!(response.status(), Status::Ok);
+    let sale: Transaction = response.into_json().await.unwrap();
+    let drawn = sale.products[0].products[0]
+        .lots_drawn
+        .iter()
+        .map(|draw| (draw.lot.batch_code.as_str(), draw.quantity))
+        .collect::<Vec<_>>();
+    assert_eq!(drawn, vec![("B-LATE", 2.0), ("B-STALE", 1.0)]);
+
+    let held = stock().await;
+    assert_eq!(held.quantity.quantity_sellable, 0.0);
+    assert!(held.lots.is_empty());
+
+    // Voiding the sale restores the lots it was drawn from.
+    let response = app
+        .client
+        .post(format!("/api/transaction/void/{}", sale.id))
+        .header(ContentType::JSON)
+        .cookie(tenant.manager_cookie())
+        .body(json!({ "reason": "Sold from the wrong shelf" }).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let held = stock().await;
+    assert_eq!(held.quantity.quantity_sellable, 7.0);
+    let lots = held
+        .lots
+        .iter()
+        .map(|lot| (lot.batch_code.as_str(), lot.quantity))
+        .collect::<Vec<_>>();
+    assert_eq!(lots, vec![("B-LATE", 2.0), ("B-STALE", 1.0)]);
+
+    let response = app
+        .client
+        .post("/api/product/lots/write_off")
+        .cookie(tenant.manager_cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let held = stock().await;
+    assert_eq!(held.quantity.quantity_sellable, 6.0);
+    assert_eq!(held.quantity.quantity_unsellable, 2.0 + 1.0 + 1.0);
+}
@@ -0,0 +1,959 @@
+mod common;
+
+use chrono::{Duration, Utc};
+use common::{TenantFixture, TestApp};
+use open_stock::tokens::{self, TOKEN_COOKIE};
+use open_stock::{
+    authrecord, session, ApiKey, AuthenticationLog, ManagerOverride, Mfa, MfaEnrolment, NewApiKey,
+    OverrideAction, RecoveryCodes, RefreshedSession, SessionSummary, Transaction, MFA_ISSUER,
+    OVERRIDE_HEADER,
+};
+use rocket::http::{ContentType, Cookie, Header, Status};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde_json::json;
+use totp_rs::{Algorithm, Secret, TOTP};
+use uuid::Uuid;
+
+fn bearer(key: &str) -> Header<'static> {
+    Header::new("Authorization", format!("Bearer {}", key))
+}
+
+async fn issue_key(
+    app: &TestApp,
+    cookie: rocket::http::Cookie<'static>,
+    scopes: &[&str],
+) -> NewApiKey {
+    let response = app
+        .client
+        .post("/api/api_key/")
+        .header(ContentType::JSON)
+        .cookie(cookie)
+        .body(
+            json!({
+                "name": "Integration",
+                "scopes": scopes,
+                "expires_at": null,
+            })
+            .to_string(),
+        )
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+    response.into_json().await.unwrap()
+}
+
+#[rocket::async_test]
+async fn api_key_authenticates_within_its_scopes() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+
+    let issued = issue_key(&app, tenant.manager_cookie(), &["FetchEmployee"]).await;
+    assert!(issued.key.starts_with(&issued.api_key.prefix));
+
+    let response = app
+        .client
+        .get("/api/employee/")
+        .header(bearer(&issued.key))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    // The manager holds `FetchStore`, but the key was not granted it.
+    let response = app
+        .client
+        .get("/api/store/")
+        .header(bearer(&issued.key))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = app
+        .client
+        .get(format!("/api/api_key/{}", issued.api_key.id))
+        .cookie(tenant.manager_cookie())
+        .dispatch()
+        .await;
+    let key: ApiKey = response.into_json().await.unwrap();
+    assert!(key.last_used_at.is_some());
+}
+
+#[rocket::async_test]
+async fn revoked_and_unknown_api_keys_are_rejected() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+
+    let issued = issue_key(&app, tenant.manager_cookie(), &["FetchEmployee"]).await;
+
+    let response = app
+        .client
+        .post(format!("/api/api_key/revoke/{}", issued.api_key.id))
+        .cookie(tenant.manager_cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = app
+        .client
+        .get("/api/employee/")
+        .header(bearer(&issued.key))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let forged = format!("{}.not-the-secret", issued.api_key.prefix);
+    let response = app
+        .client
+        .get("/api/employee/")
+        .header(bearer(&forged))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Unauthorized);
+}
+
+#[rocket::async_test]
+async fn api_keys_cannot_exceed_their_creator() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+
+    let issued = issue_key(&app, tenant.manager_cookie(), &["AccessAdminPanel"]).await;
+
+    // A key may not be used to mint further keys.
+    let response = app
+        .client
+        .post("/api/api_key/")
+        .header(ContentType::JSON)
+        .header(bearer(&issued.key))
+        .body(json!({ "name": "Nested", "scopes": [], "expires_at": null }).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    // Keys are scoped to the tenant which issued them.
+    let other = app.seed_tenant("TENANT_B").await;
+    let response = app
+        .client
+        .get(format!("/api/api_key/{}", issued.api_key.id))
+        .cookie(other.manager_cookie())
+        .dispatch()
+        .await;
+    assert_ne!(response.status(), Status::Ok);
+}
+
+async fn issue_refresh_token(app: &TestApp, cookie: Cookie<'static>) -> String {
+    let response = app
+        .client
+        .get("/api/helpers/refresh_token")
+        .cookie(cookie)
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+    response.into_json().await.unwrap()
+}
+
+#[rocket::async_test]
+async fn sessions_are_listed_and_revoked() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+
+    let other = app
+        .create_session("TENANT_A", tenant.employee.clone())
+        .await;
+
+    let response = app
+        .client
+        .get("/api/employee/sessions")
+        .cookie(tenant.cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let sessions: Vec<SessionSummary> = response.into_json().await.unwrap();
+    assert_eq!(sessions.len(), 2);
+    assert!(sessions
+        .iter()
+        .any(|s| s.id == tenant.session.id && s.current));
+    assert!(sessions.iter().any(|s| s.id == other.id && !s.current));
+
+    // Another employee's sessions are not theirs to revoke.
+    let response = app
+        .client
+        .post(format!(
+            "/api/employee/sessions/revoke/{}",
+            tenant.manager_session.id
+        ))
+        .cookie(tenant.cookie())
+        .dispatch()
+        .await;
+    assert_ne!(response.status(), Status::Ok);
+
+    let response = app
+        .client
+        .post(format!("/api/employee/sessions/revoke/{}", other.id))
+        .cookie(tenant.cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = app
+        .client
+        .get("/api/employee/")
+        .cookie(Cookie::new(common::SESSION_COOKIE, other.key.clone()))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    // An administrator may list and revoke the sessions of others.
+    let response = app
+        .client
+        .get(format!("/api/employee/sessions/{}", tenant.employee.id))
+        .cookie(tenant.manager_cookie())
+        .dispatch()
+        .await;
+    let sessions: Vec<SessionSummary> = response.into_json().await.unwrap();
+    assert_eq!(sessions.len(), 1);
+
+    let response = app
+        .client
+        .post(format!(
+            "/api/employee/sessions/revoke_all/{}",
+            tenant.employee.id
+        ))
+        .cookie(tenant.manager_cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = app
+        .client
+        .get("/api/employee/")
+        .cookie(tenant.cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Unauthorized);
+}
+
+#[rocket::async_test]
+async fn logout_everywhere_revokes_refresh_tokens() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+
+    let refresh_key = issue_refresh_token(&app, tenant.cookie()).await;
+
+    let response = app
+        .client
+        .post("/api/employee/sessions/revoke_all")
+        .cookie(tenant.cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = app
+        .client
+        .get(format!("/api/helpers/refresh_token/{}", refresh_key))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    // The manager's sessions are left untouched.
+    let response = app
+        .client
+        .get("/api/employee/")
+        .cookie(tenant.manager_cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+}
+
+#[rocket::async_test]
+async fn refresh_tokens_rotate_and_detect_reuse() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+
+    let refresh_key = issue_refresh_token(&app, tenant.cookie()).await;
+
+    // A refresh token is not accepted in place of an access token.
+    let response = app
+        .client
+        .get("/api/employee/")
+        .cookie(Cookie::new(common::SESSION_COOKIE, refresh_key.clone()))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = app
+        .client
+        .get(format!("/api/helpers/refresh_token/{}", refresh_key))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let rotated: RefreshedSession = response.into_json().await.unwrap();
+    assert_ne!(rotated.refresh_key, refresh_key);
+
+    let access = Cookie::new(common::SESSION_COOKIE, rotated.access_key.clone());
+    let response = app
+        .client
+        .get("/api/employee/")
+        .cookie(access.clone())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    // Presenting the consumed token again revokes the whole family.
+    let response = app
+        .client
+        .get(format!("/api/helpers/refresh_token/{}", refresh_key))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = app
+        .client
+        .get("/api/employee/")
+        .cookie(access)
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = app
+        .client
+        .get(format!(
+            "/api/helpers/refresh_token/{}",
+            rotated.refresh_key
+        ))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Unauthorized);
+}
+
+#[rocket::async_test]
+async fn concurrent_refreshes_consume_a_token_once() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+
+    let refresh_key = issue_refresh_token(&app, tenant.cookie()).await;
+    let uri = format!("/api/helpers/refresh_token/{}", refresh_key);
+
+    let (first, second) = rocket::futures::join!(
+        app.client.get(uri.clone()).dispatch(),
+        app.client.get(uri.clone()).dispatch()
+    );
+
+    let statuses = [first.status(), second.status()];
+    let succeeded = statuses
+        .iter()
+        .filter(|status| **status == Status::Ok)
+        .count();
+    // The token is consumed at most once, any concurrent use of it being
+    // treated as reuse.
+    assert!(succeeded <= 1);
+    let permitted = [Status::Ok, Status::Unauthorized];
+    assert!(statuses.iter().all(|status| permitted.contains(status)));
+}
+
+#[rocket::async_test]
+async fn signed_tokens_authenticate_without_a_session() {
+    let app = TestApp::configured(|figment| {
+        figment
+            .merge(("sessions.stateless", true))
+            .merge(("sessions.revocation_sync_secs", 0))
+    })
+    .await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+
+    let response = app
+        .client
+        .post(format!("/api/employee/auth/{}", tenant.employee.id))
+        .header(ContentType::JSON)
+        .body(
+            json!({
+                "pass": common::PASSWORD,
+                "kiosk_id": tenant.kiosk.id,
+                "tenant_id": tenant.tenant.tenant_id,
+            })
+            .to_string(),
+        )
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    assert!(response.cookies().get(TOKEN_COOKIE).is_some());
+
+    // With the sessions gone, only the signed token can authenticate.
+    session::Entity::delete_many()
+        .filter(session::Column::EmployeeId.eq(tenant.employee.id.clone()))
+        .exec(&app.db)
+        .await
+        .unwrap();
+
+    let response = app.client.get("/api/employee/").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let expires_at = (Utc::now() + Duration::hours(1)).naive_utc();
+    tokens::revoke_subject(
+        &tenant.employee.id,
+        &tenant.tenant.tenant_id,
+        expires_at,
+        &app.db,
+    )
+    .await
+    .unwrap();
+
+    let response = app.client.get("/api/employee/").dispatch().await;
+    assert_eq!(response.status(), Status::Unauthorized);
+}
+
+async fn login(
+    app: &TestApp,
+    tenant: &TenantFixture,
+    employee_id: &str,
+    mfa_code: Option<&str>,
+) -> Status {
+    app.client
+        .post(format!("/api/employee/auth/{}", employee_id))
+        .header(ContentType::JSON)
+        .body(
+            json!({
+                "pass": common::PASSWORD,
+                "kiosk_id": tenant.kiosk.id,
+                "tenant_id": tenant.tenant.tenant_id,
+                "mfa_code": mfa_code,
+            })
+            .to_string(),
+        )
+        .dispatch()
+        .await
+        .status()
+}
+
+fn authenticator(enrolment: &MfaEnrolment, account: &str) -> TOTP {
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        30,
+        Secret::Encoded(enrolment.secret.clone())
+            .to_bytes()
+            .unwrap(),
+        Some(MFA_ISSUER.to_string()),
+        account.to_string(),
+    )
+    .unwrap()
+}
+
+#[rocket::async_test]
+async fn enrolled_employees_must_present_an_mfa_code() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+
+    let response = app
+        .client
+        .post("/api/mfa/enrol")
+        .cookie(tenant.manager_cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let enrolment: MfaEnrolment = response.into_json().await.unwrap();
+    assert!(enrolment.uri.starts_with("otpauth://totp/"));
+
+    let totp = authenticator(&enrolment, &tenant.manager.rid);
+    let confirmation = totp.generate_current().unwrap();
+
+    let response = app
+        .client
+        .post("/api/mfa/confirm")
+        .header(ContentType::JSON)
+        .cookie(tenant.manager_cookie())
+        .body(json!({ "code": confirmation }).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let recovery: RecoveryCodes = response.into_json().await.unwrap();
+    assert_eq!(recovery.codes.len(), 10);
+
+    assert_eq!(
+        login(&app, &tenant, &tenant.manager.id, None).await,
+        Status::Unauthorized
+    );
+    assert_eq!(
+        login(&app, &tenant, &tenant.manager.id, Some("000000")).await,
+        Status::Unauthorized
+    );
+
+    // The code used to confirm enrolment cannot be replayed, but the next
+    // one is accepted within the permitted skew.
+    assert_eq!(
+        login(&app, &tenant, &tenant.manager.id, Some(&confirmation)).await,
+        Status::Unauthorized
+    );
+
+    let next = totp.generate(Utc::now().timestamp() as u64 + 30);
+    assert_eq!(
+        login(&app, &tenant, &tenant.manager.id, Some(&next)).await,
+        Status::Ok
+    );
+    assert_eq!(
+        login(&app, &tenant, &tenant.manager.id, Some(&next)).await,
+        Status::Unauthorized
+    );
+
+    // Recovery codes may each be used once.
+    let code = &recovery.codes[0];
+    assert_eq!(
+        login(&app, &tenant, &tenant.manager.id, Some(code)).await,
+        Status::Ok
+    );
+    assert_eq!(
+        login(&app, &tenant, &tenant.manager.id, Some(code)).await,
+        Status::Unauthorized
+    );
+
+    // Concurrent logins presenting the same code are accepted only once.
+    let code = &recovery.codes[1];
+    let (first, second) = rocket::futures::join!(
+        login(&app, &tenant, &tenant.manager.id, Some(code)),
+        login(&app, &tenant, &tenant.manager.id, Some(code))
+    );
+    assert_eq!(
+        [first, second]
+            .iter()
+            .filter(|status| **status == Status::Ok)
+            .count(),
+        1
+    );
+
+    let response = app
+        .client
+        .get("/api/mfa/")
+        .cookie(tenant.manager_cookie())
+        .dispatch()
+        .await;
+    let mfa: Mfa = response.into_json().await.unwrap();
+    assert!(mfa.enabled);
+    assert_eq!(mfa.recovery_codes_remaining, 8);
+}
+
+#[rocket::async_test]
+async fn mfa_policy_withholds_protected_actions() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+
+    let response = app
+        .client
+        .post("/api/mfa/policy")
+        .header(ContentType::JSON)
+        .cookie(tenant.manager_cookie())
+        .body(json!({ "require_for_managerial": true, "required_actions": [] }).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    // The manager has not enrolled, so is only able to do so.
+    assert_eq!(
+        login(&app, &tenant, &tenant.manager.id, None).await,
+        Status::Ok
+    );
+
+    let response = app.client.get("/api/api_key/").dispatch().await;
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = app.client.get("/api/mfa/").dispatch().await;
+    let mfa: Mfa = response.into_json().await.unwrap();
+    assert!(mfa.required && !mfa.enabled);
+
+    let response = app.client.post("/api/mfa/enrol").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+
+    // Front-line employees are unaffected.
+    assert_eq!(
+        login(&app, &tenant, &tenant.employee.id, None).await,
+        Status::Ok
+    );
+
+    let response = app.client.get("/api/employee/").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+}
+
+#[rocket::async_test]
+async fn mfa_cannot_be_managed_using_an_api_key() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+
+    let issued = issue_key(&app, tenant.manager_cookie(), &["AccessAdminPanel"]).await;
+
+    let response = app
+        .client
+        .post(format!("/api/mfa/disable/{}", tenant.employee.id))
+        .header(bearer(&issued.key))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = app
+        .client
+        .post("/api/mfa/policy")
+        .header(ContentType::JSON)
+        .header(bearer(&issued.key))
+        .body(json!({ "require_for_managerial": false, "required_actions": [] }).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Unauthorized);
+}
+
+async fn request_override(
+    app: &TestApp,
+    tenant: &TenantFixture,
+    rid: &str,
+    action: OverrideAction,
+    reference: Option<&str>,
+    discount: Option<u32>,
+) -> (Status, Option<ManagerOverride>) {
+    let response = app
+        .client
+        .post("/api/override/")
+        .header(ContentType::JSON)
+        .body(
+            json!({
+                "rid": rid,
+                "pass": common::PASSWORD,
+                "kiosk_id": tenant.kiosk.id,
+                "action": action,
+                "reference": reference,
+                "discount": discount,
+            })
+            .to_string(),
+        )
+        .dispatch()
+        .await;
+
+    let status = response.status();
+    (status, response.into_json().await)
+}
+
+#[rocket::async_test]
+async fn sensitive_till_actions_require_a_manager_override() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+
+    // Overrides are required only once the tenant opts in.
+    let response = app
+        .client
+        .post("/api/override/policy")
+        .header(ContentType::JSON)
+        .cookie(tenant.manager_cookie())
+        .body(
+            json!({
+                "required_actions": ["Discount", "Void", "Refund", "NoSale"],
+                "discount_threshold": 20,
+                "lifetime_secs": 120,
+            })
+            .to_string(),
+        )
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    assert_eq!(
+        login(&app, &tenant, &tenant.employee.id, None).await,
+        Status::Ok
+    );
+
+    let response = app
+        .client
+        .post(format!("/api/transaction/generate/{}", tenant.customer.id))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let transaction: Transaction = response.into_json().await.unwrap();
+
+    // A discount above the threshold must be approved.
+    let mut discounted = json!(transaction);
+    discounted["products"][0]["products"][0]["discount"] = json!({ "Percentage": 50 });
+
+    let update = |override_token: Option<String>| {
+        let mut request = app
+            .client
+            .post(format!("/api/transaction/{}", transaction.id))
+            .header(ContentType::JSON)
+            .body(discounted.to_string());
+
+        if let Some(token) = override_token {
+            request = request.header(Header::new(OVERRIDE_HEADER, token));
+        }
+
+        request.dispatch()
+    };
+
+    assert_eq!(update(None).await.status(), Status::Unauthorized);
+
+    // The employee may not approve their own override.
+    let (status, _) = request_override(
+        &app,
+        &tenant,
+        &tenant.employee.rid,
+        OverrideAction::Discount,
+        Some(&transaction.id),
+        Some(50),
+    )
+    .await;
+    assert_eq!(status, Status::Unauthorized);
+
+    let (status, discount) = request_override(
+        &app,
+        &tenant,
+        &tenant.manager.rid,
+        OverrideAction::Discount,
+        Some(&transaction.id),
+        Some(50),
+    )
+    .await;
+    assert_eq!(status, Status::Ok);
+    let discount = discount.unwrap();
+    assert_eq!(discount.authorised_by, tenant.manager.id);
+
+    let response = update(Some(discount.token.clone())).await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let updated: Transaction = response.into_json().await.unwrap();
+    assert!(updated
+        .order_notes
+        .iter()
+        .any(|note| { note.author == tenant.manager.id && note.message.contains(&discount.id) }));
+
+    // The approval covers the discount given, not any greater one.
+    let mut raised = json!(updated);
+    raised["products"][0]["products"][0]["discount"] = json!({ "Percentage": 90 });
+
+    let response = app
+        .client
+        .post(format!("/api/transaction/{}", transaction.id))
+        .header(ContentType::JSON)
+        .body(raised.to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    // Overrides are single use, and only for the action approved.
+    let (_, void) = request_override(
+        &app,
+        &tenant,
+        &tenant.manager.rid,
+        OverrideAction::Void,
+        Some(&transaction.id),
+        None,
+    )
+    .await;
+    let void = void.unwrap();
+
+    let response = app
+        .client
+        .post(format!("/api/kiosk/no_sale/{}", tenant.kiosk.id))
+        .header(Header::new(OVERRIDE_HEADER, discount.token.clone()))
+        .header(Header::new(OVERRIDE_HEADER, void.token.clone()))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let void_transaction = |token: String| {
+        app.client
+            .post(format!("/api/transaction/void/{}", transaction.id))
+            .header(ContentType::JSON)
+            .header(Header::new(OVERRIDE_HEADER, token))
+            .body(json!({ "reason": "Entered in error" }).to_string())
+            .dispatch()
+    };
+
+    assert_eq!(
+        void_transaction(discount.token).await.status(),
+        Status::Unauthorized
+    );
+    assert_eq!(void_transaction(void.token).await.status(), Status::Ok);
+
+    // Each attempt is recorded against the kiosk.
+    let attempts = authrecord::Entity::find()
+        .filter(authrecord::Column::KioskId.eq(tenant.kiosk.id.clone()))
+        .all(&app.db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|record| serde_json::from_value::<AuthenticationLog>(record.attempt).unwrap())
+        .filter(|log| log.override_of.is_some())
+        .collect::<Vec<AuthenticationLog>>();
+
+    assert_eq!(attempts.len(), 3);
+    assert_eq!(attempts.iter().filter(|log| log.successful).count(), 2);
+
+    // An employee who may authorise overrides needs none themselves.
+    let response = app
+        .client
+        .post(format!("/api/kiosk/no_sale/{}", tenant.kiosk.id))
+        .cookie(tenant.manager_cookie())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+}
+
+#[rocket::async_test]
+async fn refreshed_sessions_remain_at_their_kiosk() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+
+    assert_eq!(
+        login(&app, &tenant, &tenant.employee.id, None).await,
+        Status::Ok
+    );
+
+    let response = app
+        .client
+        .get("/api/helpers/refresh_token")
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let refresh_key: String = response.into_json().await.unwrap();
+
+    // The access token has lapsed, so the refresh issues a new one.
+    let refresh = session::Entity::find()
+        .filter(session::Column::Key.eq(refresh_key.clone()))
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    session::Entity::delete_by_id(refresh.family_id.unwrap())
+        .exec(&app.db)
+        .await
+        .unwrap();
+
+    let response = app
+        .client
+        .get(format!("/api/helpers/refresh_token/{}", refresh_key))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let (status, _) = request_override(
+        &app,
+        &tenant,
+        &tenant.manager.rid,
+        OverrideAction::NoSale,
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(status, Status::Ok);
+}
+
+#[rocket::async_test]
+async fn a_discount_override_lifts_the_ceiling() {
+    let app = TestApp::new().await;
+    let tenant = app.seed_tenant("TENANT_A").await;
+    let (_, products) = app.seed_catalogue(&tenant).await;
+    let product = &products[0];
+    let variant = &product.variants[0];
+
+    assert_eq!(
+        login(&app, &tenant, &tenant.employee.id, None).await,
+        Status::Ok
+    );
+
+    let response = app
+        .client
+        .post(format!("/api/transaction/generate/{}", tenant.customer.id))
+        .dispatch()
+        .await;
+    let template: Transaction = response.into_json().await.unwrap();
+
+    // Line and order discounts of 8% each stack to beyond the employee's
+    // 10% ceiling.
+    let paid = variant.retail_price * 0.92 * 0.92;
+    let sale_id = Uuid::new_v4().to_string();
+    let mut sale = json!(template);
+    sale["reference"] = json!(sale_id);
+    sale["transaction_type"] = json!("Out");
+    sale["kiosk"] = json!(tenant.kiosk.id);
+    sale["products"][0]["discount"] = json!({ "Percentage": 8 });
+    sale["products"][0]["products"] = json!([sale["products"][0]["products"][0].clone()]);
+    let purchase = &mut sale["products"][0]["products"][0];
+    purchase["product_sku"] = json!(product.sku);
+    purchase["product_code"] = json!(variant.barcode);
+    purchase["product_cost"] = json!(variant.retail_price);
+    purchase["quantity"] = json!(1.0);
+    purchase["instances"] = json!([]);
+    purchase["discount"] = json!({ "Percentage": 8 });
+    sale["order_total"] = json!(paid.round() as i64);
+    sale["payment"][0]["amount"]["quantity"] = json!(paid);
+
+    let create = |override_token: Option<String>| {
+        let mut request = app
+            .client
+            .post("/api/transaction/")
+            .header(ContentType::JSON)
+            .body(sale.to_string());
+
+        if let Some(token) = override_token {
+            request = request.header(Header::new(OVERRIDE_HEADER, token));
+        }
+
+        request.dispatch()
+    };
+
+    assert_eq!(create(None).await.status(), Status::Conflict);
+
+    // A discount is approved up to an amount, on the one sale.
+    let (status, _) = request_override(
+        &app,
+        &tenant,
+        &tenant.manager.rid,
+        OverrideAction::Discount,
+        None,
+        Some(20),
+    )
+    .await;
+    assert_ne!(status, Status::Ok);
+
+    let request_discount = |reference: String, discount: u32| {
+        let (app, tenant) = (&app, &tenant);
+
+        async move {
+            let (status, issued) = request_override(
+                app,
+                tenant,
+                &tenant.manager.rid,
+                OverrideAction::Discount,
+                Some(&reference),
+                Some(discount),
+            )
+            .await;
+            assert_eq!(status, Status::Ok);
+            issued.unwrap()
+        }
+    };
+
+    let elsewhere = request_discount(Uuid::new_v4().to_string(), 20).await;
+    assert_eq!(
+        create(Some(elsewhere.token)).await.status(),
+        Status::Conflict
+    );
+
+    let short = request_discount(sale_id.clone(), 15).await;
+    assert_eq!(create(Some(short.token)).await.status(), Status::Conflict);
+
+    let discount = request_discount(sale_id.clone(), 16).await;
+
+    let response = create(Some(discount.token.clone())).await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let created: Transaction = response.into_json().await.unwrap();
+    assert!(created
+        .order_notes
+        .iter()
+        .any(|note| note.message.contains(&discount.id)));
+
+    // The override was consumed by the sale.
+    assert_eq!(
+        create(Some(discount.token)).await.status(),
+        Status::Conflict
+    );
+}
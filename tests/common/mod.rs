@@ -0,0 +1,183 @@
+//! In-process test harness for the Rocket API.
+//!
+//! Every [`TestApp`] boots the server with its own, throw-away SQLite
+//! database, so tests neither need a running MySQL instance nor share
+//! state with one another. Data is seeded with the same `generate`
+//! helpers used by `/helpers/generate`, and each seeded tenant carries
+//! ready-made sessions which can be attached to requests as cookies.
+#![allow(dead_code)]
+
+use std::env;
+use std::path::PathBuf;
+
+use chrono::{Days, Utc};
+use open_stock::server::rocket_from_figment;
+use open_stock::{
+    all_actions, example_employee, session, AccountType, Customer, Db, Employee, Kiosk, Product,
+    Session, SessionVariant, Store, Tenant,
+};
+use rocket::figment::Figment;
+use rocket::http::Cookie;
+use rocket::local::asynchronous::Client;
+use rocket_db_pools::Database;
+use sea_orm::{DbConn, EntityTrait};
+use uuid::Uuid;
+
+pub const SESSION_COOKIE: &str = "os-stock-key";
+
+/// The password assigned to every seeded employee.
+pub const PASSWORD: &str = "1232";
+
+pub struct TestApp {
+    pub client: Client,
+    pub db: DbConn,
+    database_path: PathBuf,
+}
+
+/// A seeded tenant along with an authenticated session for both a
+/// front-line employee (from `Employee::generate`) and a manager
+/// holding every permission.
+pub struct TenantFixture {
+    pub tenant: Tenant,
+    pub employee: Employee,
+    pub manager: Employee,
+    pub kiosk: Kiosk,
+    pub customer: Customer,
+    pub session: Session,
+    pub manager_session: Session,
+}
+
+impl TenantFixture {
+    pub fn cookie(&self) -> Cookie<'static> {
+        Cookie::new(SESSION_COOKIE, self.session.key.clone())
+    }
+
+    pub fn manager_cookie(&self) -> Cookie<'static> {
+        Cookie::new(SESSION_COOKIE, self.manager_session.key.clone())
+    }
+}
+
+impl TestApp {
+    pub async fn new() -> Self {
+        Self::configured(|figment| figment).await
+    }
+
+    /// Boots the server with additional configuration layered over the
+    /// test defaults, i.e. `("databases.stock.run_migrations", false)`.
+    pub async fn configured(configure: impl FnOnce(Figment) -> Figment) -> Self {
+        let database_path = env::temp_dir().join(format!("open-stock-{}.db", Uuid::new_v4()));
+
+        let figment = rocket::Config::figment()
+            .merge((
+                "databases.stock.url",
+                format!("sqlite://{}?mode=rwc", database_path.display()),
+            ))
+            .merge(("log_level", "off"))
+            .merge(("logging.level", "off"));
+
+        let client = Client::tracked(rocket_from_figment(configure(figment)))
+            .await
+            .expect("rocket instance should ignite");
+
+        let db = Db::fetch(client.rocket())
+            .expect("database pool should be attached")
+            .conn
+            .clone();
+
+        TestApp {
+            client,
+            db,
+            database_path,
+        }
+    }
+
+    /// Seeds a tenant, its employees, a kiosk and a customer.
+    pub async fn seed_tenant(&self, tenant_id: &str) -> TenantFixture {
+        let tenant = Tenant::generate(&self.db, tenant_id)
+            .await
+            .expect("tenant should be generated");
+
+        let seed_session = Session::default_with_tenant(tenant_id.to_string());
+
+        let employee = Employee::generate(&self.db, seed_session.clone())
+            .await
+            .expect("employee should be generated");
+
+        let manager_input = open_stock::EmployeeInput {
+            level: all_actions(),
+            account_type: AccountType::Managerial,
+            ..example_employee()
+        };
+        let manager_id =
+            Employee::insert(manager_input, &self.db, seed_session.clone(), None, None)
+                .await
+                .expect("manager should be inserted")
+                .last_insert_id;
+        let manager = Employee::fetch_by_id(&manager_id, seed_session.clone(), &self.db)
+            .await
+            .expect("manager should be fetched");
+
+        let kiosk = Kiosk::generate(&Uuid::new_v4().to_string(), seed_session.clone(), &self.db)
+            .await
+            .expect("kiosk should be generated");
+
+        let customer = Customer::generate(seed_session, &self.db)
+            .await
+            .expect("customer should be generated");
+
+        let session = self.create_session(tenant_id, employee.clone()).await;
+        let manager_session = self.create_session(tenant_id, manager.clone()).await;
+
+        TenantFixture {
+            tenant,
+            employee,
+            manager,
+            kiosk,
+            customer,
+            session,
+            manager_session,
+        }
+    }
+
+    /// Seeds the example stores and products for a tenant. As these carry
+    /// fixed identifiers, only one tenant per [`TestApp`] may be seeded.
+    pub async fn seed_catalogue(&self, fixture: &TenantFixture) -> (Vec<Store>, Vec<Product>) {
+        let stores = Store::generate(fixture.session.clone(), &self.db)
+            .await
+            .expect("stores should be generated");
+        let products = Product::generate(fixture.session.clone(), &self.db)
+            .await
+            .expect("products should be generated");
+
+        (stores, products)
+    }
+
+    /// Inserts an access token for the given employee directly.
+    pub async fn create_session(&self, tenant_id: &str, employee: Employee) -> Session {
+        let session = Session {
+            id: Uuid::new_v4().to_string(),
+            key: Uuid::new_v4().to_string(),
+            employee,
+            expiry: Utc::now().checked_add_days(Days::new(1)).unwrap(),
+            tenant_id: tenant_id.to_string(),
+            variant: SessionVariant::AccessToken,
+        };
+
+        session::Entity::insert::<session::ActiveModel>(session.clone().into())
+            .exec(&self.db)
+            .await
+            .expect("session should be inserted");
+
+        session
+    }
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        for suffix in ["", "-wal", "-shm", "-journal"] {
+            let mut path = self.database_path.clone().into_os_string();
+            path.push(suffix);
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}